@@ -0,0 +1,354 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+//! `agent` subcommands, used to run, validate and replay agents offline.
+
+use crate::{
+    agent_host::{ModelNameResolver, NodeAgentHost, DEFAULT_IPFS_API_URL},
+    ai_service::AiServiceOptions,
+};
+use pallet_uomi_engine::{
+    types::{AiModelKey, ExecutionBudget, ExecutionLogs, RequestStatus, WasmOutput},
+    wasm::{wasm_run, wasm_validate, AgentHost},
+    UomiEngineApi,
+};
+use sc_cli::{CliConfiguration, SharedParams};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{hexdisplay::HexDisplay, H256, U256};
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr, sync::Arc, time::Instant};
+use uomi_primitives::{AccountId, Block, BlockNumber};
+
+/// Run, validate and replay agents offline.
+#[derive(Debug, clap::Subcommand)]
+pub enum AgentSubcommand {
+    /// Execute the wasm of an agent with the host functions of the engine.
+    Run(AgentRunCmd),
+
+    /// Check that the wasm of an agent can be executed by the engine.
+    Validate(AgentValidateCmd),
+
+    /// Execute again a request stored on the chain database, with the inferences recorded by the
+    /// OPoC level 0.
+    Replay(AgentReplayCmd),
+}
+
+/// The `agent run` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AgentRunCmd {
+    /// Path of the wasm of the agent.
+    pub wasm: PathBuf,
+
+    /// Input data of the request.
+    #[clap(long, conflicts_with = "input_path")]
+    pub input: Option<String>,
+
+    /// Path of a file with the input data of the request.
+    #[clap(long)]
+    pub input_path: Option<PathBuf>,
+
+    /// Path of the input file of the request, returned by `get_input_file`.
+    #[clap(long)]
+    pub input_file: Option<PathBuf>,
+
+    /// File returned by `get_cid_file` for a CID, in the form `<cid>=<path>`. Can be repeated.
+    #[clap(long = "cid-file", value_name = "CID=PATH")]
+    pub cid_files: Vec<String>,
+
+    /// Name of an AI model on the AI service, in the form `<model key>=<name>`. Can be repeated.
+    /// The model 0 returns the input inverted and does not need a name.
+    #[clap(long = "model", value_name = "KEY=NAME")]
+    pub models: Vec<String>,
+
+    /// URL of the IPFS API used for the CIDs without a local file.
+    #[clap(long)]
+    pub ipfs_api_url: Option<String>,
+
+    /// Maximum execution time of the agent in milliseconds.
+    #[clap(long, default_value = "60000")]
+    pub timeout_ms: u64,
+
+    /// Maximum execution time of the request in blocks, used to derive the fuel of the agent.
+    /// The maximum fuel by default.
+    #[clap(long)]
+    pub execution_max_time: Option<u32>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub ai_service_options: AiServiceOptions,
+}
+
+impl AgentRunCmd {
+    /// Run the command.
+    pub fn run(&self) -> sc_cli::Result<()> {
+        let wasm = read_file(&self.wasm)?;
+        let input_data = match (&self.input, &self.input_path) {
+            (Some(input), _) => input.as_bytes().to_vec(),
+            (None, Some(path)) => read_file(path)?,
+            (None, None) => Vec::new(),
+        };
+
+        let models = self
+            .models
+            .iter()
+            .map(|model| {
+                let (key, name) = model
+                    .split_once('=')
+                    .ok_or_else(|| format!("`{}` is not a valid AI model, expected `KEY=NAME`", model))?;
+                let key = AiModelKey::from_dec_str(key.trim())
+                    .map_err(|_| format!("`{}` is not a valid AI model key", key))?;
+                Ok((key, name.trim().as_bytes().to_vec()))
+            })
+            .collect::<Result<BTreeMap<_, _>, String>>()?;
+        let model_name: ModelNameResolver = Box::new(move |model| models.get(&model).cloned());
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut host =
+            NodeAgentHost::new(self.ai_service_options.config()?, model_name, runtime.handle().clone());
+        if let Some(url) = &self.ipfs_api_url {
+            host = host.with_ipfs_api(url.clone());
+        }
+        if let Some(path) = &self.input_file {
+            host = host.with_input_file(read_file(path)?);
+        }
+        for cid_file in &self.cid_files {
+            let (cid, path) = cid_file
+                .split_once('=')
+                .ok_or_else(|| format!("`{}` is not a valid CID file, expected `CID=PATH`", cid_file))?;
+            host = host.with_file(cid.trim().as_bytes().to_vec(), read_file(&PathBuf::from(path.trim()))?);
+        }
+
+        let budget = ExecutionBudget::from_execution_max_time(
+            self.execution_max_time.map(U256::from).unwrap_or(U256::MAX),
+        );
+        let started = Instant::now();
+        let (result, logs) = wasm_run(&wasm, None, input_data, self.timeout_ms, budget, host);
+        print_execution(result, &logs, started)
+    }
+}
+
+/// The `agent validate` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AgentValidateCmd {
+    /// Path of the wasm of the agent.
+    pub wasm: PathBuf,
+}
+
+impl AgentValidateCmd {
+    /// Run the command.
+    pub fn run(&self) -> sc_cli::Result<()> {
+        let wasm = read_file(&self.wasm)?;
+        wasm_validate(&wasm).map_err(|e| format!("{} is not a valid agent: {}", self.wasm.display(), e))?;
+        println!("{} is a valid agent", self.wasm.display());
+        Ok(())
+    }
+}
+
+/// The `agent replay` command.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AgentReplayCmd {
+    /// Id of the request to replay.
+    pub request_id: String,
+
+    /// Hash of a block where the request is not completed yet, the best block by default.
+    #[clap(long)]
+    pub at: Option<String>,
+
+    /// Path of the wasm of the agent, read from IPFS by default.
+    #[clap(long)]
+    pub wasm: Option<PathBuf>,
+
+    /// URL of the IPFS API used to read the wasm and the files of the agent.
+    #[clap(long, default_value = DEFAULT_IPFS_API_URL)]
+    pub ipfs_api_url: String,
+
+    /// Maximum execution time of the agent in milliseconds.
+    #[clap(long, default_value = "60000")]
+    pub timeout_ms: u64,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub ai_service_options: AiServiceOptions,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+}
+
+impl AgentReplayCmd {
+    /// Run the command on the chain database of `client`.
+    pub async fn run<C>(&self, client: Arc<C>) -> sc_cli::Result<()>
+    where
+        C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+        C::Api: UomiEngineApi<Block, AccountId>,
+    {
+        let request_id = U256::from_dec_str(&self.request_id)
+            .map_err(|_| format!("`{}` is not a valid request id", self.request_id))?;
+        let best_hash = client.info().best_hash;
+        let at = match &self.at {
+            Some(at) => H256::from_str(at.trim_start_matches("0x"))
+                .map_err(|_| format!("`{}` is not a valid block hash", at))?,
+            None => best_hash,
+        };
+
+        let request = client
+            .runtime_api()
+            .get_request(at, request_id)
+            .map_err(|e| format!("fetch runtime get_request failed: {:?}", e))?
+            .ok_or_else(|| format!("request {} not found", request_id))?;
+        if !matches!(request.status, RequestStatus::Pending | RequestStatus::Running) {
+            return Err(format!(
+                "request {} is {:?} at block {:?}, use --at with a block where it is pending",
+                request_id, request.status, at
+            )
+            .into());
+        }
+        let inference_proofs = client
+            .runtime_api()
+            .opoc_l0_inferences(at, request_id)
+            .map_err(|e| format!("fetch runtime opoc_l0_inferences failed: {:?}", e))?
+            .into_iter()
+            .map(|(index, proof)| (index, proof.into_inner()))
+            .collect::<Vec<_>>();
+        log::info!(
+            "🧠 Replaying request {} of agent {} with {} recorded inferences",
+            request_id,
+            request.nft_id,
+            inference_proofs.len()
+        );
+
+        // The AI models are resolved with the names active on the block of the request, as the validators did
+        let request_block_number = BlockNumber::try_from(request.block_number)
+            .map_err(|_| format!("request {} has an invalid block number {}", request_id, request.block_number))?;
+        let request_hash = client
+            .hash(request_block_number)
+            .map_err(|e| format!("fetch block hash failed: {:?}", e))?
+            .ok_or_else(|| format!("block {} of request {} not found", request_block_number, request_id))?;
+        let model_client = client.clone();
+        let model_name: ModelNameResolver = Box::new(move |model| {
+            model_client
+                .runtime_api()
+                .ai_model_local_name(request_hash, model)
+                .ok()
+                .flatten()
+                .map(|name| name.into_inner())
+        });
+        let input_file_cid =
+            (!request.input_file_cid.is_empty()).then(|| request.input_file_cid.clone());
+        let mut host = NodeAgentHost::new(
+            self.ai_service_options.config()?,
+            model_name,
+            tokio::runtime::Handle::current(),
+        )
+        .with_ipfs_api(self.ipfs_api_url.clone())
+        .with_input_file_cid(input_file_cid)
+        .with_inference_proofs(inference_proofs);
+
+        let wasm_path = self.wasm.clone();
+        let input_data = request.input_data.into_inner();
+        let nft_file_cid = request.nft_file_cid;
+        let budget = ExecutionBudget::from_execution_max_time(request.nft_execution_max_time);
+        let timeout_ms = self.timeout_ms;
+        let (started, (result, logs)) = tokio::task::spawn_blocking(move || {
+            let (wasm, cid) = match wasm_path {
+                Some(path) => (read_file(&path)?, None),
+                None => {
+                    let wasm = host
+                        .get_cid_file(nft_file_cid.clone())
+                        .map_err(|e| format!("load agent wasm failed: {:?}", e))?;
+                    (wasm, Some(nft_file_cid))
+                },
+            };
+            let started = Instant::now();
+            Ok::<_, String>((started, wasm_run(&wasm, cid.as_ref(), input_data, timeout_ms, budget, host)))
+        })
+        .await
+        .map_err(|e| format!("replay task failed: {}", e))??;
+
+        // Compare the output with the consensus output, if the request is completed on the best block
+        let output = result.as_ref().ok().map(|output| output.output.clone());
+        print_execution(result, &logs, started)?;
+        let consensus_output = client
+            .runtime_api()
+            .get_request(best_hash, request_id)
+            .ok()
+            .flatten()
+            .and_then(|request| request.result)
+            .map(|result| result.output_data.into_inner());
+        match (output, consensus_output) {
+            (Some(output), Some(consensus_output)) if output == consensus_output => {
+                eprintln!("the output matches the consensus output")
+            },
+            (_, Some(_)) => eprintln!("the output does not match the consensus output"),
+            (_, None) => eprintln!("the request is not completed on the best block"),
+        }
+
+        Ok(())
+    }
+}
+
+impl CliConfiguration for AgentReplayCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+}
+
+fn read_file(path: &PathBuf) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+/// Print the output of an execution on stdout and the logs on stderr.
+fn print_execution<E: std::fmt::Display>(
+    result: Result<WasmOutput, E>,
+    logs: &ExecutionLogs,
+    started: Instant,
+) -> sc_cli::Result<()> {
+    for line in &logs.lines {
+        eprintln!("[console] {}", String::from_utf8_lossy(line));
+    }
+    for call in &logs.host_calls {
+        eprintln!(
+            "[{}] started at {} ms, took {} ms, {}",
+            String::from_utf8_lossy(&call.name),
+            call.started_at_ms,
+            call.duration_ms,
+            if call.success { "succeeded" } else { "failed" },
+        );
+    }
+    if logs.truncated {
+        eprintln!("[logs truncated]");
+    }
+    eprintln!("execution time: {} ms", started.elapsed().as_millis());
+
+    let output = result.map_err(|e| format!("the agent failed: {}", e))?;
+    eprintln!("abi version: {}", output.abi_version);
+    eprintln!("ai calls: {}", output.ai_calls);
+    eprintln!("fuel consumed: {}", output.fuel_consumed);
+    if output.out_of_fuel {
+        eprintln!("the agent exhausted its fuel, the output is empty");
+    }
+    if let Some(transaction) = &output.output_transaction {
+        eprintln!("output transaction: {:?}", transaction);
+    }
+    match String::from_utf8(output.output) {
+        Ok(output) => println!("{}", output),
+        Err(e) => println!("0x{}", HexDisplay::from(e.as_bytes())),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+//! Host of the agents executed by the tools of the node, outside of the offchain worker.
+//!
+//! The agents run with the same wasm runner and host ABI of the offchain worker, but the files
+//! are read directly from the IPFS API and the inferences are sent directly to the AI service,
+//! so nothing is submitted on chain.
+
+use pallet_ipfs::types::Cid;
+use pallet_uomi_engine::{
+    types::{AiModelKey, AiServiceConfig},
+    wasm::AgentHost,
+};
+use sp_runtime::DispatchError;
+use std::collections::BTreeMap;
+
+/// URL of the API of the IPFS daemon started by the node.
+pub const DEFAULT_IPFS_API_URL: &str = "http://127.0.0.1:5001/api/v0";
+
+/// Resolves the name of an AI model on the AI service, `None` if the model is not active.
+pub type ModelNameResolver = Box<dyn Fn(AiModelKey) -> Option<Vec<u8>> + Send + Sync>;
+
+/// Agent host reading the files from the IPFS API and running the inferences on the AI service.
+pub struct NodeAgentHost {
+    ai_service: AiServiceConfig,
+    model_name: ModelNameResolver,
+    runtime: tokio::runtime::Handle,
+    ipfs_api_url: Option<String>,
+    input_file_cid: Option<Cid>,
+    input_file: Option<Vec<u8>>,
+    files: BTreeMap<Vec<u8>, Vec<u8>>,
+    inference_proofs: Vec<(u32, Vec<u8>)>,
+}
+
+impl NodeAgentHost {
+    /// Create a new host without files. It must be used outside of the async context of
+    /// `runtime`, e.g. from a blocking task, because the HTTP requests block on it.
+    pub fn new(
+        ai_service: AiServiceConfig,
+        model_name: ModelNameResolver,
+        runtime: tokio::runtime::Handle,
+    ) -> Self {
+        Self {
+            ai_service,
+            model_name,
+            runtime,
+            ipfs_api_url: None,
+            input_file_cid: None,
+            input_file: None,
+            files: BTreeMap::new(),
+            inference_proofs: Vec::new(),
+        }
+    }
+
+    /// Read from the IPFS API the files without a local content.
+    pub fn with_ipfs_api(mut self, url: String) -> Self {
+        self.ipfs_api_url = Some(url);
+        self
+    }
+
+    /// Read the input file of the request from its CID.
+    pub fn with_input_file_cid(mut self, cid: Option<Cid>) -> Self {
+        self.input_file_cid = cid;
+        self
+    }
+
+    /// Use a local content as the input file of the request.
+    pub fn with_input_file(mut self, file: Vec<u8>) -> Self {
+        self.input_file = Some(file);
+        self
+    }
+
+    /// Use a local content for a CID.
+    pub fn with_file(mut self, cid: Vec<u8>, file: Vec<u8>) -> Self {
+        self.files.insert(cid, file);
+        self
+    }
+
+    /// Send to the AI service the proofs recorded by the OPoC level 0, as inference index and
+    /// proof, so the inferences are the same of the validators.
+    pub fn with_inference_proofs(mut self, proofs: Vec<(u32, Vec<u8>)>) -> Self {
+        self.inference_proofs = proofs;
+        self
+    }
+
+    fn post(
+        &self,
+        url: &str,
+        headers: &[(Vec<u8>, Vec<u8>)],
+        body: Vec<u8>,
+    ) -> Result<Vec<u8>, String> {
+        let mut request = hyper::Request::post(url)
+            .header("Accept", "application/json")
+            .header("Content-Type", "application/json");
+        for (name, value) in headers {
+            request = request.header(name.as_slice(), value.as_slice());
+        }
+        let request = request
+            .body(hyper::Body::from(body))
+            .map_err(|e| format!("invalid request to {}: {}", url, e))?;
+
+        self.runtime.block_on(async {
+            let response = hyper::Client::new()
+                .request(request)
+                .await
+                .map_err(|e| format!("request to {} failed: {}", url, e))?;
+            let status = response.status();
+            let body = hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(|e| format!("reading the response of {} failed: {}", url, e))?;
+            if !status.is_success() {
+                return Err(format!(
+                    "{} responded with status {}: {}",
+                    url,
+                    status,
+                    String::from_utf8_lossy(&body)
+                ));
+            }
+            Ok(body.to_vec())
+        })
+    }
+
+    fn cat(&self, cid: &Cid) -> Result<Vec<u8>, String> {
+        if let Some(file) = self.files.get(cid.as_slice()) {
+            return Ok(file.clone());
+        }
+        let ipfs_api_url = self
+            .ipfs_api_url
+            .as_ref()
+            .ok_or_else(|| "no local file for the CID and no IPFS API configured".to_string())?;
+        let cid = std::str::from_utf8(cid).map_err(|_| "the CID is not valid UTF-8".to_string())?;
+        let url = format!("{}/cat?arg={}", ipfs_api_url.trim_end_matches('/'), cid);
+        self.post(&url, &[], Vec::new())
+    }
+}
+
+impl AgentHost for NodeAgentHost {
+    fn get_input_file(&mut self) -> Vec<u8> {
+        if let Some(file) = &self.input_file {
+            return file.clone();
+        }
+        let Some(cid) = self.input_file_cid.clone() else {
+            return Vec::new();
+        };
+        self.cat(&cid).unwrap_or_else(|e| {
+            log::error!("🧠 Error getting the input file of the agent: {}", e);
+            Vec::new()
+        })
+    }
+
+    fn get_cid_file(&mut self, cid: Cid) -> Result<Vec<u8>, DispatchError> {
+        self.cat(&cid).map_err(|e| {
+            log::error!("🧠 Error getting the file of the agent: {}", e);
+            DispatchError::Other("Error getting the file from IPFS")
+        })
+    }
+
+    fn call_ai(
+        &mut self,
+        model: AiModelKey,
+        input: Vec<u8>,
+        counter: u32,
+    ) -> Result<Vec<u8>, DispatchError> {
+        // Model 0 is a deterministic model that returns the input data inverted, as on the offchain worker.
+        if model.is_zero() {
+            return Ok(input.into_iter().rev().collect());
+        }
+
+        let name = (self.model_name)(model)
+            .ok_or(DispatchError::Other("Model not registered or not active"))?;
+        let mut body = serde_json::json!({
+            "model": String::from_utf8_lossy(&name),
+            "input": String::from_utf8(input).map_err(|_| DispatchError::Other("Invalid UTF-8 in input data"))?,
+        });
+        if let Some((_, proof)) = self.inference_proofs.iter().find(|(index, _)| *index == counter) {
+            body["proof"] = String::from_utf8_lossy(proof).into();
+        }
+
+        let endpoint = self.ai_service.endpoint(&model);
+        let url = format!("{}/run", String::from_utf8_lossy(&endpoint.url).trim_end_matches('/'));
+        let output = self
+            .post(&url, &endpoint.headers, body.to_string().into_bytes())
+            .map_err(|e| {
+                log::error!("🧠 Error calling the AI service: {}", e);
+                DispatchError::Other("Error response from AI service")
+            })?;
+
+        // Keep only the response of the AI service, as the offchain worker does.
+        let output: serde_json::Value = serde_json::from_slice(&output)
+            .map_err(|_| DispatchError::Other("Error parsing output data to JSON"))?;
+        let response = output
+            .get("response")
+            .and_then(|response| response.as_str())
+            .ok_or(DispatchError::Other("Error parsing output data to JSON"))?;
+        Ok(serde_json::json!({ "response": response }).to_string().into_bytes())
+    }
+}
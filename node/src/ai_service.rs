@@ -0,0 +1,115 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+use pallet_uomi_engine::{
+    consts::{AI_SERVICE_CONFIG_STORAGE_KEY, MAX_WORKERS_STORAGE_KEY},
+    types::{AiModelKey, AiServiceConfig, AiServiceEndpoint},
+};
+use parity_scale_codec::Encode;
+use sp_core::offchain::OffchainStorage;
+
+/// AI service CLI options, used by the offchain worker of the uomi engine to run the inferences.
+#[derive(Debug, Clone, clap::Parser)]
+pub struct AiServiceOptions {
+    /// URL of the AI service used for the models without a specific endpoint.
+    #[clap(long, default_value = "http://127.0.0.1:8888")]
+    pub ai_service_url: String,
+
+    /// Header added to every request to the AI service, in the form `Name: value` (e.g. an
+    /// Authorization header). Can be repeated.
+    ///
+    /// WARNING: the headers are stored in plain text on the persistent offchain storage of the
+    /// node, where the offchain worker reads them, so they are readable by anyone calling the
+    /// unsafe `offchain_localStorageGet` RPC. Do not expose the unsafe RPC methods on a node
+    /// configured with secret headers.
+    #[clap(long = "ai-service-header", value_name = "NAME: VALUE")]
+    pub ai_service_headers: Vec<String>,
+
+    /// Endpoint of the AI service for a specific model, in the form `<model key>=<url>`.
+    /// Can be repeated to serve the models from different hosts.
+    #[clap(long = "ai-service-model-url", value_name = "KEY=URL")]
+    pub ai_service_model_urls: Vec<String>,
+}
+
+impl AiServiceOptions {
+    /// Build the AI service configuration read by the offchain worker.
+    pub fn config(&self) -> Result<AiServiceConfig, String> {
+        let headers = self
+            .ai_service_headers
+            .iter()
+            .map(|header| {
+                let (name, value) = header
+                    .split_once(':')
+                    .ok_or_else(|| format!("`{}` is not a valid AI service header, expected `Name: value`", header))?;
+                Ok((name.trim().as_bytes().to_vec(), value.trim().as_bytes().to_vec()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let endpoint = |url: &str| -> Result<AiServiceEndpoint, String> {
+            let url = url.trim();
+            url::Url::parse(url).map_err(|e| format!("`{}` is not a valid AI service URL: {}", url, e))?;
+            Ok(AiServiceEndpoint {
+                url: url.as_bytes().to_vec(),
+                headers: headers.clone(),
+            })
+        };
+
+        let models_endpoints = self
+            .ai_service_model_urls
+            .iter()
+            .map(|model_url| {
+                let (key, url) = model_url
+                    .split_once('=')
+                    .ok_or_else(|| format!("`{}` is not a valid AI model endpoint, expected `KEY=URL`", model_url))?;
+                let key = AiModelKey::from_dec_str(key.trim())
+                    .map_err(|_| format!("`{}` is not a valid AI model key", key))?;
+                Ok((key, endpoint(url)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(AiServiceConfig {
+            default_endpoint: endpoint(&self.ai_service_url)?,
+            models_endpoints,
+        })
+    }
+}
+
+/// Store the AI service configuration on the offchain local storage, where it is read by the
+/// offchain worker of the uomi engine.
+/// NOTE: The headers are stored in plain text, they are readable by the unsafe RPC methods of the
+/// offchain storage.
+pub fn persist_ai_service_config<S: OffchainStorage>(storage: &mut S, config: &AiServiceConfig) {
+    storage.set(sp_offchain::STORAGE_PREFIX, AI_SERVICE_CONFIG_STORAGE_KEY, &config.encode());
+    log::info!(
+        "🧠 AI service configured at {} with {} model specific endpoints",
+        String::from_utf8_lossy(&config.default_endpoint.url),
+        config.models_endpoints.len(),
+    );
+    if !config.default_endpoint.headers.is_empty() {
+        log::warn!(
+            "🧠 The AI service headers are stored in plain text on the offchain storage, do not expose the unsafe RPC methods of this node",
+        );
+    }
+}
+
+/// Store the maximum number of agents executed in parallel on the offchain local storage, where
+/// it is read by the offchain worker of the uomi engine.
+pub fn persist_agent_workers<S: OffchainStorage>(storage: &mut S, agent_workers: u32) {
+    storage.set(sp_offchain::STORAGE_PREFIX, MAX_WORKERS_STORAGE_KEY, &agent_workers.encode());
+    log::info!("🧠 Up to {} agents executed in parallel", agent_workers);
+}
@@ -19,6 +19,7 @@
 
 #[cfg(feature = "evm-tracing")]
 use crate::evm_tracing_types::EthApiOptions;
+use crate::ai_service::AiServiceOptions;
 use sc_cli::RunCmd;
 
 /// An overarching CLI command definition.
@@ -37,6 +38,20 @@ pub struct Cli {
     #[clap(flatten)]
     pub eth_api_options: EthApiOptions,
 
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub ai_service_options: AiServiceOptions,
+
+    /// Maximum size in MiB of the cache of the compiled agents, stored in the base path.
+    /// Set it to 0 to compile the agents on every execution.
+    #[clap(long, default_value = "512")]
+    pub agent_module_cache_size: u64,
+
+    /// Maximum number of agents executed in parallel by the offchain workers, every one of them
+    /// running a different assigned request by earliest deadline.
+    #[clap(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..=pallet_uomi_engine::consts::MAX_WORKERS as i64))]
+    pub agent_workers: u32,
+
     /// Enable Ethereum compatible JSON-RPC servers (disabled by default).
     #[clap(name = "enable-evm-rpc", long)]
     pub enable_evm_rpc: bool,
@@ -93,6 +108,10 @@ pub enum Subcommand {
     #[clap(subcommand)]
     Benchmark(frame_benchmarking_cli::BenchmarkCmd),
 
+    /// Run, validate and replay agents offline.
+    #[clap(subcommand)]
+    Agent(crate::agent::AgentSubcommand),
+
     /// Try some command against runtime state.
     /// No moved to separte cli and just a placeholder command here
     TryRuntime,
@@ -18,6 +18,7 @@
 
 //! Uomi CLI handlers.
 use crate::{
+    agent::AgentSubcommand,
     cli::{Cli, Subcommand},
     local::{self, development_config},
     finney::{self, testnet_config},
@@ -27,6 +28,7 @@ use crate::{
 use sc_cli::{
        Result, SubstrateCli,
 };
+use pallet_uomi_engine::wasm_cache::{wasm_cache_init, WasmModuleCache};
 use sc_service::PartialComponents;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -428,6 +430,29 @@ pub fn run() -> Result<()> {
                 }
             }
         }
+        Some(Subcommand::Agent(cmd)) => match cmd {
+            AgentSubcommand::Run(cmd) => cmd.run(),
+            AgentSubcommand::Validate(cmd) => cmd.run(),
+            AgentSubcommand::Replay(cmd) => {
+                let runner = cli.create_runner(cmd)?;
+                if runner.config().chain_spec.is_uomi() {
+                    runner.async_run(|config| {
+                        let PartialComponents { client, task_manager, .. } = uomi::new_partial(&config)?;
+                        Ok((cmd.run(client), task_manager))
+                    })
+                } else if runner.config().chain_spec.is_finney() {
+                    runner.async_run(|config| {
+                        let PartialComponents { client, task_manager, .. } = finney::new_partial(&config)?;
+                        Ok((cmd.run(client), task_manager))
+                    })
+                } else {
+                    runner.async_run(|config| {
+                        let PartialComponents { client, task_manager, .. } = local::new_partial(&config)?;
+                        Ok((cmd.run(client), task_manager))
+                    })
+                }
+            }
+        },
         Some(Subcommand::TryRuntime) => Err("The `try-runtime` subcommand has been migrated to a \
         standalone CLI (https://github.com/paritytech/try-runtime-cli). It is no longer \
         being maintained here and will be removed entirely some time after January 2024. \
@@ -449,14 +474,28 @@ pub fn run() -> Result<()> {
                 tracing_raw_max_memory_usage: cli.eth_api_options.tracing_raw_max_memory_usage,
             };
 
+            let ai_service_config = cli.ai_service_options.config()?;
+            let agent_module_cache_size = cli.agent_module_cache_size;
+            let agent_workers = cli.agent_workers;
+
             runner.run_node_until_exit(|config| async move {
                 log::info!("🧠 Uomi engine active, starting to process requests");
+                if agent_module_cache_size > 0 {
+                    let dir = config.base_path.path().join("uomi-engine").join("modules");
+                    match WasmModuleCache::new(dir.clone(), agent_module_cache_size.saturating_mul(1024 * 1024)) {
+                        Ok(cache) => {
+                            wasm_cache_init(cache);
+                            log::info!("🧠 Compiled agents cached in {:?} up to {} MiB", dir, agent_module_cache_size);
+                        },
+                        Err(e) => log::warn!("🧠 Compiled agents not cached, {:?} is not usable: {}", dir, e),
+                    }
+                }
                 if config.chain_spec.is_uomi() {
-                    return uomi::start_node(config, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
+                    return uomi::start_node(config, ai_service_config, agent_workers, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
                 } else if config.chain_spec.is_finney() {
-                    return finney::start_node(config, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
+                    return finney::start_node(config, ai_service_config, agent_workers, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
                 } else {
-                    return local::start_node(config, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
+                    return local::start_node(config, ai_service_config, agent_workers, #[cfg(feature = "evm-tracing")] evm_tracing_config).map_err(Into::into);
                 }
 
             })
@@ -33,6 +33,9 @@ pub mod uomi;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+mod agent;
+mod agent_host;
+mod ai_service;
 mod consensus_data_provider;
 mod cli;
 mod command;
@@ -62,6 +62,8 @@ use uomi_primitives::*;
 #[cfg(feature = "evm-tracing")]
 pub mod tracing;
 
+pub mod uomi_engine;
+
 #[cfg(feature = "evm-tracing")]
 #[derive(Clone)]
 pub struct EvmTracingConfig {
@@ -135,6 +137,8 @@ pub struct GrandpaDeps<B> {
 pub struct FullDeps<C, P, BE, A: ChainApi, SC, CIDP> {
     /// The client instance to use.
     pub client: Arc<C>,
+    /// The backend instance to use, to read the offchain storage of the node.
+    pub backend: Arc<BE>,
     /// Transaction pool instance.
     pub pool: Arc<P>,
     /// The SelectChain Strategy
@@ -207,6 +211,7 @@ where
         + fp_rpc::EthereumRuntimeRPCApi<Block>
         + BlockBuilder<Block>
         + BabeApi<Block>
+        + pallet_uomi_engine::UomiEngineApi<Block, AccountId>
         + moonbeam_rpc_primitives_debug::DebugRuntimeApi<Block>
         + moonbeam_rpc_primitives_txpool::TxPoolRuntimeApi<Block>,
     P: TransactionPool<Block = Block> + Sync + Send + 'static,
@@ -273,7 +278,8 @@ where
         + fp_rpc::ConvertTransactionRuntimeApi<Block>
         + fp_rpc::EthereumRuntimeRPCApi<Block>
         + BlockBuilder<Block>
-        + BabeApi<Block>,
+        + BabeApi<Block>
+        + pallet_uomi_engine::UomiEngineApi<Block, AccountId>,
     P: TransactionPool<Block = Block> + Sync + Send + 'static,
     BE: Backend<Block> + 'static,
     BE::State: StateBackend<BlakeTwo256>,
@@ -312,7 +318,8 @@ where
         + fp_rpc::ConvertTransactionRuntimeApi<Block>
         + fp_rpc::EthereumRuntimeRPCApi<Block>
         + BlockBuilder<Block>
-        + BabeApi<Block>,
+        + BabeApi<Block>
+        + pallet_uomi_engine::UomiEngineApi<Block, AccountId>,
     P: TransactionPool<Block = Block> + Sync + Send + 'static,
     BE: Backend<Block> + 'static,
     BE::State: StateBackend<BlakeTwo256>,
@@ -325,9 +332,11 @@ where
     use sc_consensus_babe_rpc::{Babe, BabeApiServer};
     use sc_consensus_grandpa_rpc::{Grandpa, GrandpaApiServer};
     use substrate_frame_rpc_system::{System, SystemApiServer};
+    use uomi_engine::{UomiEngine, UomiEngineApiServer};
     let mut io = RpcModule::new(());
     let FullDeps {
         client,
+        backend,
         pool,
         select_chain,
         graph,
@@ -362,6 +371,13 @@ where
     io.merge(TransactionPayment::new(client.clone()).into_rpc())?;
     io.merge(Babe::new(client.clone(), worker_handle, keystore, select_chain, deny_unsafe).into_rpc())?;
     io.merge(sc_rpc::dev::Dev::new(client.clone(), deny_unsafe).into_rpc())?;
+    io.merge(UomiEngineApiServer::<Hash, AccountId>::into_rpc(
+        UomiEngine::<Block, C, BE::OffchainStorage>::new(
+            client.clone(),
+            backend.offchain_storage(),
+            deny_unsafe,
+        ),
+    ))?;
     io.merge(
         Grandpa::new(
             subscription_executor,
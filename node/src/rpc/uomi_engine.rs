@@ -0,0 +1,478 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+///! Uomi engine RPC support, used to inspect the lifecycle of agent requests.
+use crate::agent_host::{ModelNameResolver, NodeAgentHost, DEFAULT_IPFS_API_URL};
+use fc_rpc::internal_err;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use pallet_ipfs::types::Cid;
+use pallet_uomi_engine::{
+    consts::AI_SERVICE_CONFIG_STORAGE_KEY,
+    types::{
+        AiModelKey, AiServiceConfig, ExecutionBudget, ExecutionLogs, HostCallTiming, RequestAssignment, RequestInfo,
+        RequestNodeOutput, RequestResult, RequestStatus,
+    },
+    wasm::{wasm_run, AgentHost},
+    UomiEngineApi as UomiEngineRuntimeApi,
+};
+use parity_scale_codec::{Decode, Encode};
+use sc_rpc::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+    offchain::{OffchainStorage, STORAGE_PREFIX},
+    Bytes, U256,
+};
+use sp_runtime::traits::Block as BlockT;
+use std::{marker::PhantomData, sync::Arc, time::Instant};
+
+/// Timeout of a dry run when the caller does not set one.
+const DRY_RUN_DEFAULT_TIMEOUT_MS: u64 = 60_000;
+
+/// Maximum timeout of a dry run.
+const DRY_RUN_MAX_TIMEOUT_MS: u64 = 300_000;
+
+/// Assignment of a request to a validator.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcRequestAssignment<AccountId> {
+    pub account_id: AccountId,
+    pub expiration_block_number: U256,
+    pub output_committed: bool,
+    pub output_received: bool,
+}
+
+impl<AccountId> From<RequestAssignment<AccountId>> for RpcRequestAssignment<AccountId> {
+    fn from(assignment: RequestAssignment<AccountId>) -> Self {
+        Self {
+            account_id: assignment.account_id,
+            expiration_block_number: assignment.expiration_block_number,
+            output_committed: assignment.output_committed,
+            output_received: assignment.output_received,
+        }
+    }
+}
+
+/// Output stored by a validator for a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcRequestNodeOutput<AccountId> {
+    pub account_id: AccountId,
+    pub output_data: Bytes,
+    pub out_of_fuel: bool,
+}
+
+impl<AccountId> From<RequestNodeOutput<AccountId>> for RpcRequestNodeOutput<AccountId> {
+    fn from(output: RequestNodeOutput<AccountId>) -> Self {
+        Self {
+            account_id: output.account_id,
+            output_data: output.output_data.into_inner().into(),
+            out_of_fuel: output.out_of_fuel,
+        }
+    }
+}
+
+/// Final result of a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcRequestResult {
+    pub output_data: Bytes,
+    pub total_executions: u32,
+    pub total_consensus: u32,
+    pub out_of_fuel: bool,
+}
+
+impl From<RequestResult> for RpcRequestResult {
+    fn from(result: RequestResult) -> Self {
+        Self {
+            output_data: result.output_data.into_inner().into(),
+            total_executions: result.total_executions,
+            total_consensus: result.total_consensus,
+            out_of_fuel: result.out_of_fuel,
+        }
+    }
+}
+
+/// Stage of the lifecycle of a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcRequestStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed { reason: String },
+    Cancelled,
+}
+
+impl From<RequestStatus> for RpcRequestStatus {
+    fn from(status: RequestStatus) -> Self {
+        match status {
+            RequestStatus::Pending => Self::Pending,
+            RequestStatus::Running => Self::Running,
+            RequestStatus::Completed => Self::Completed,
+            RequestStatus::Failed(reason) => Self::Failed { reason: format!("{:?}", reason) },
+            RequestStatus::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+/// Full view of the lifecycle of a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcRequestInfo<AccountId> {
+    pub request_id: U256,
+    pub status: RpcRequestStatus,
+    pub block_number: U256,
+    pub nft_id: U256,
+    pub nft_required_consensus: U256,
+    pub nft_execution_max_time: U256,
+    pub nft_file_cid: Bytes,
+    pub input_data: Bytes,
+    pub input_file_cid: Bytes,
+    pub opoc_level: Option<u8>,
+    pub assignments: Vec<RpcRequestAssignment<AccountId>>,
+    pub outputs: Vec<RpcRequestNodeOutput<AccountId>>,
+    pub result: Option<RpcRequestResult>,
+}
+
+impl<AccountId> From<RequestInfo<AccountId>> for RpcRequestInfo<AccountId> {
+    fn from(info: RequestInfo<AccountId>) -> Self {
+        Self {
+            request_id: info.request_id,
+            status: info.status.into(),
+            block_number: info.block_number,
+            nft_id: info.nft_id,
+            nft_required_consensus: info.nft_required_consensus,
+            nft_execution_max_time: info.nft_execution_max_time,
+            nft_file_cid: info.nft_file_cid.into_inner().into(),
+            input_data: info.input_data.into_inner().into(),
+            input_file_cid: info.input_file_cid.into_inner().into(),
+            opoc_level: info.opoc_level,
+            assignments: info.assignments.into_iter().map(Into::into).collect(),
+            outputs: info.outputs.into_iter().map(Into::into).collect(),
+            result: info.result.map(Into::into),
+        }
+    }
+}
+
+/// Timing of a host call made by an agent during its execution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcHostCallTiming {
+    pub name: String,
+    pub started_at_ms: u64,
+    pub duration_ms: u64,
+    pub success: bool,
+}
+
+impl From<HostCallTiming> for RpcHostCallTiming {
+    fn from(timing: HostCallTiming) -> Self {
+        Self {
+            name: String::from_utf8_lossy(&timing.name).into_owned(),
+            started_at_ms: timing.started_at_ms,
+            duration_ms: timing.duration_ms,
+            success: timing.success,
+        }
+    }
+}
+
+/// Logs collected by this node during the last execution of a request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcExecutionLogs {
+    pub lines: Vec<String>,
+    pub host_calls: Vec<RpcHostCallTiming>,
+    pub truncated: bool,
+}
+
+impl From<ExecutionLogs> for RpcExecutionLogs {
+    fn from(logs: ExecutionLogs) -> Self {
+        Self {
+            lines: logs
+                .lines
+                .iter()
+                .map(|line| String::from_utf8_lossy(line).into_owned())
+                .collect(),
+            host_calls: logs.host_calls.into_iter().map(Into::into).collect(),
+            truncated: logs.truncated,
+        }
+    }
+}
+
+/// Agent executed by a dry run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcDryRunAgent {
+    /// The wasm of the agent.
+    Wasm(Bytes),
+    /// The NFT ID of a deployed agent, its wasm is read from IPFS.
+    NftId(U256),
+}
+
+/// Result of a dry run of an agent.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcDryRunResult {
+    pub success: bool,
+    pub error: Option<String>,
+    pub output: Bytes,
+    /// SCALE encoding of the transaction emitted by the agent with `set_output_transaction`.
+    pub output_transaction: Option<Bytes>,
+    pub ai_calls: u32,
+    /// Version of the host ABI negotiated with the agent, `None` if the agent was not instantiated.
+    pub abi_version: Option<u32>,
+    /// Fuel consumed by the wasm instructions of the agent.
+    pub fuel_consumed: u64,
+    /// Whether the agent exhausted its fuel, in that case the output is empty as on the validators.
+    pub out_of_fuel: bool,
+    pub execution_time_ms: u64,
+    pub logs: RpcExecutionLogs,
+}
+
+#[rpc(server)]
+pub trait UomiEngineApi<BlockHash, AccountId> {
+    /// Returns the full view of a request, if it is known by the engine.
+    #[method(name = "uomi_getRequest")]
+    fn get_request(
+        &self,
+        request_id: U256,
+        at: Option<BlockHash>,
+    ) -> RpcResult<Option<RpcRequestInfo<AccountId>>>;
+
+    /// Returns the ids of the requests not completed yet.
+    #[method(name = "uomi_pendingRequests")]
+    fn pending_requests(&self, at: Option<BlockHash>) -> RpcResult<Vec<U256>>;
+
+    /// Returns the current assignments of a request.
+    #[method(name = "uomi_requestAssignments")]
+    fn request_assignments(
+        &self,
+        request_id: U256,
+        at: Option<BlockHash>,
+    ) -> RpcResult<Vec<RpcRequestAssignment<AccountId>>>;
+
+    /// Returns the logs collected by this node during its last execution of a request.
+    /// The logs are local to the node and they are never part of the consensus.
+    #[method(name = "uomi_getExecutionLogs")]
+    fn get_execution_logs(&self, request_id: U256) -> RpcResult<Option<RpcExecutionLogs>>;
+
+    /// Executes an agent on this node without submitting a request, with the same host ABI of
+    /// the offchain worker. The inferences run on the AI service configured on this node, the
+    /// model 0 returns the input inverted. The fuel of the agent derives from
+    /// `execution_max_time`, in blocks, as for a request; the maximum fuel by default.
+    #[method(name = "uomi_dryRunAgent", blocking)]
+    fn dry_run_agent(
+        &self,
+        agent: RpcDryRunAgent,
+        input_data: Bytes,
+        input_file_cid: Option<Bytes>,
+        timeout_ms: Option<u64>,
+        execution_max_time: Option<u32>,
+        at: Option<BlockHash>,
+    ) -> RpcResult<RpcDryRunResult>;
+}
+
+pub struct UomiEngine<B, C, S> {
+    client: Arc<C>,
+    offchain_storage: Option<S>,
+    deny_unsafe: DenyUnsafe,
+    _marker: PhantomData<B>,
+}
+
+impl<B, C, S> UomiEngine<B, C, S> {
+    pub fn new(client: Arc<C>, offchain_storage: Option<S>, deny_unsafe: DenyUnsafe) -> Self {
+        Self {
+            client,
+            offchain_storage,
+            deny_unsafe,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<B, C, S: OffchainStorage> UomiEngine<B, C, S> {
+    /// The AI service configuration stored on the offchain storage, or the default one.
+    fn ai_service_config(&self) -> AiServiceConfig {
+        let mut config = self
+            .offchain_storage
+            .as_ref()
+            .and_then(|storage| storage.get(STORAGE_PREFIX, AI_SERVICE_CONFIG_STORAGE_KEY))
+            .and_then(|encoded| AiServiceConfig::decode(&mut &encoded[..]).ok())
+            .unwrap_or_default();
+        if config.default_endpoint.url.is_empty() {
+            config.default_endpoint.url =
+                pallet_uomi_engine::consts::DEFAULT_AI_SERVICE_URL.to_vec();
+        }
+        config
+    }
+}
+
+impl<B, C, S, AccountId> UomiEngineApiServer<<B as BlockT>::Hash, AccountId>
+    for UomiEngine<B, C, S>
+where
+    B: BlockT,
+    S: OffchainStorage + 'static,
+    C: ProvideRuntimeApi<B> + HeaderBackend<B> + Send + Sync + 'static,
+    C::Api: UomiEngineRuntimeApi<B, AccountId>,
+    AccountId: parity_scale_codec::Codec + Clone + Serialize + Send + Sync + 'static,
+{
+    fn get_request(
+        &self,
+        request_id: U256,
+        at: Option<<B as BlockT>::Hash>,
+    ) -> RpcResult<Option<RpcRequestInfo<AccountId>>> {
+        let at = at.unwrap_or_else(|| self.client.info().best_hash);
+        let request = self
+            .client
+            .runtime_api()
+            .get_request(at, request_id)
+            .map_err(|err| internal_err(format!("fetch runtime get_request failed: {:?}", err)))?;
+
+        Ok(request.map(Into::into))
+    }
+
+    fn pending_requests(&self, at: Option<<B as BlockT>::Hash>) -> RpcResult<Vec<U256>> {
+        let at = at.unwrap_or_else(|| self.client.info().best_hash);
+        self.client
+            .runtime_api()
+            .pending_requests(at)
+            .map_err(|err| internal_err(format!("fetch runtime pending_requests failed: {:?}", err)))
+    }
+
+    fn request_assignments(
+        &self,
+        request_id: U256,
+        at: Option<<B as BlockT>::Hash>,
+    ) -> RpcResult<Vec<RpcRequestAssignment<AccountId>>> {
+        let at = at.unwrap_or_else(|| self.client.info().best_hash);
+        let assignments = self
+            .client
+            .runtime_api()
+            .request_assignments(at, request_id)
+            .map_err(|err| {
+                internal_err(format!("fetch runtime request_assignments failed: {:?}", err))
+            })?;
+
+        Ok(assignments.into_iter().map(Into::into).collect())
+    }
+
+    fn get_execution_logs(&self, request_id: U256) -> RpcResult<Option<RpcExecutionLogs>> {
+        let storage = self
+            .offchain_storage
+            .as_ref()
+            .ok_or_else(|| internal_err("offchain storage is not available on this node"))?;
+        let key = ExecutionLogs::storage_key(&request_id);
+        let Some(encoded) = storage.get(STORAGE_PREFIX, &key) else {
+            return Ok(None);
+        };
+        let logs = ExecutionLogs::decode(&mut &encoded[..])
+            .map_err(|err| internal_err(format!("decode execution logs failed: {:?}", err)))?;
+
+        Ok(Some(logs.into()))
+    }
+    fn dry_run_agent(
+        &self,
+        agent: RpcDryRunAgent,
+        input_data: Bytes,
+        input_file_cid: Option<Bytes>,
+        timeout_ms: Option<u64>,
+        execution_max_time: Option<u32>,
+        at: Option<<B as BlockT>::Hash>,
+    ) -> RpcResult<RpcDryRunResult> {
+        self.deny_unsafe.check_if_safe()?;
+
+        let at = at.unwrap_or_else(|| self.client.info().best_hash);
+        let runtime = tokio::runtime::Handle::current();
+        let input_file_cid = input_file_cid
+            .map(|cid| {
+                Cid::try_from(cid.0)
+                    .map_err(|_| internal_err("the input file CID exceeds the maximum length"))
+            })
+            .transpose()?;
+
+        let client = self.client.clone();
+        let model_name: ModelNameResolver = Box::new(move |model: AiModelKey| {
+            UomiEngineRuntimeApi::<B, AccountId>::ai_model_local_name(
+                &*client.runtime_api(),
+                at,
+                model,
+            )
+            .ok()
+            .flatten()
+            .map(|name| name.into_inner())
+        });
+        let mut host = NodeAgentHost::new(self.ai_service_config(), model_name, runtime)
+            .with_ipfs_api(DEFAULT_IPFS_API_URL.to_string())
+            .with_input_file_cid(input_file_cid);
+
+        let (wasm, cid) = match agent {
+            RpcDryRunAgent::Wasm(wasm) => (wasm.0, None),
+            RpcDryRunAgent::NftId(nft_id) => {
+                let cid = UomiEngineRuntimeApi::<B, AccountId>::agent_file_cid(
+                    &*self.client.runtime_api(),
+                    at,
+                    nft_id,
+                )
+                .map_err(|err| internal_err(format!("fetch runtime agent_file_cid failed: {:?}", err)))?
+                .ok_or_else(|| internal_err(format!("agent {} not found", nft_id)))?;
+                let wasm = host
+                    .get_cid_file(cid.clone())
+                    .map_err(|err| internal_err(format!("load agent wasm failed: {:?}", err)))?;
+                (wasm, Some(cid))
+            },
+        };
+
+        let timeout_ms = timeout_ms
+            .unwrap_or(DRY_RUN_DEFAULT_TIMEOUT_MS)
+            .min(DRY_RUN_MAX_TIMEOUT_MS);
+        let budget = ExecutionBudget::from_execution_max_time(
+            execution_max_time.map(U256::from).unwrap_or(U256::MAX),
+        );
+        let started = Instant::now();
+        let (result, logs) = wasm_run(&wasm, cid.as_ref(), input_data.0, timeout_ms, budget, host);
+        let execution_time_ms = started.elapsed().as_millis() as u64;
+
+        Ok(match result {
+            Ok(output) => RpcDryRunResult {
+                success: true,
+                error: None,
+                output: output.output.into(),
+                output_transaction: output.output_transaction.map(|transaction| transaction.encode().into()),
+                ai_calls: output.ai_calls,
+                abi_version: Some(output.abi_version),
+                fuel_consumed: output.fuel_consumed,
+                out_of_fuel: output.out_of_fuel,
+                execution_time_ms,
+                logs: logs.into(),
+            },
+            Err(err) => RpcDryRunResult {
+                success: false,
+                error: Some(err.to_string()),
+                output: Bytes::default(),
+                output_transaction: None,
+                ai_calls: logs.host_calls.iter().filter(|call| call.name == b"call_ai").count() as u32,
+                abi_version: None,
+                fuel_consumed: 0,
+                out_of_fuel: false,
+                execution_time_ms,
+                logs: logs.into(),
+            },
+        })
+    }
+}
@@ -24,6 +24,8 @@ use futures::{FutureExt, StreamExt};
 use sc_client_api::{Backend, BlockBackend, BlockchainEvents};
 use sc_consensus_grandpa::SharedVoterState;
 use sc_consensus::BoxBlockImport;
+use pallet_uomi_engine::types::AiServiceConfig;
+use crate::ai_service::{persist_agent_workers, persist_ai_service_config};
 use sc_executor::NativeElseWasmExecutor;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryHandle, TelemetryWorker};
@@ -249,6 +251,8 @@ pub fn build_babe_grandpa_import_queue(
 /// Builds a new service.
 pub fn start_node(
     config: Configuration,
+    ai_service_config: AiServiceConfig,
+    agent_workers: u32,
     #[cfg(feature = "evm-tracing")] evm_tracing_config: crate::evm_tracing_types::EvmTracingConfig,
 ) -> Result<TaskManager, ServiceError> {
 
@@ -321,6 +325,11 @@ pub fn start_node(
 
 
     if config.offchain_worker.enabled {
+        if let Some(mut offchain_storage) = backend.offchain_storage() {
+            persist_ai_service_config(&mut offchain_storage, &ai_service_config);
+            persist_agent_workers(&mut offchain_storage, agent_workers);
+        }
+
         task_manager.spawn_handle().spawn(
             "offchain-workers-runner",
             "offchain-work",
@@ -468,6 +477,7 @@ pub fn start_node(
 
     let rpc_extensions_builder = {
         let client = client.clone();
+        let backend = backend.clone();
         let network = network.clone();
         let transaction_pool = transaction_pool.clone();
         let sync = sync_service.clone();
@@ -485,6 +495,7 @@ pub fn start_node(
             let shared_voter_state = sc_consensus_grandpa::SharedVoterState::empty();
             let deps = crate::rpc::FullDeps {
                 client: client.clone(),
+                backend: backend.clone(),
                 select_chain: select_chain.clone(),
                 pool: transaction_pool.clone(),
                 graph: transaction_pool.pool().clone(),
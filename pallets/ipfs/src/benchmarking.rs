@@ -0,0 +1,122 @@
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use frame_benchmarking::v2::*;
+use frame_system::{ offchain::SigningTypes, RawOrigin };
+use sp_core::sr25519;
+
+// This is the number of validators that pinned every CID removed by the set_inherent_data benchmark.
+const BENCHMARK_CID_PINS: u32 = 10;
+
+// This function is used to build a CID unique for the index.
+fn benchmark_cid(index: u32) -> Cid {
+    let mut cid = b"bafkreibenchmark".to_vec();
+    cid.extend_from_slice(&index.to_le_bytes());
+    Cid::truncate_from(cid)
+}
+
+// This function is used to register a validator with a public key derived from the seed.
+fn benchmark_validator<T: Config>(seed: u32) -> T::Public
+    where <T as SigningTypes>::Public: From<sr25519::Public>
+{
+    let mut raw_public = [0u8; 32];
+    raw_public[0..4].copy_from_slice(&seed.to_le_bytes());
+    let public: T::Public = sr25519::Public::from_raw(raw_public).into();
+    Validators::<T>::insert(public.clone().into_account(), pallet_staking::ValidatorPrefs::default());
+    public
+}
+
+#[benchmarks(
+    where
+        <T as SigningTypes>::Public: From<sr25519::Public>,
+        <T as SigningTypes>::Signature: From<sr25519::Signature>,
+)]
+mod benchmarks {
+    use super::*;
+
+    #[benchmark]
+    fn pin_agent() {
+        let caller: T::AccountId = whitelisted_caller();
+        let nft_id = NftId::from(1);
+        // The agent is already pinned with another CID, that is scheduled for the expiration
+        AgentsPins::<T>::insert(nft_id, benchmark_cid(0));
+        CidsStatus::<T>::insert(benchmark_cid(0), (U256::zero(), U256::from(1)));
+
+        #[extrinsic_call]
+        pin_agent(RawOrigin::Signed(caller), benchmark_cid(1), nft_id);
+
+        assert_eq!(AgentsPins::<T>::get(nft_id), benchmark_cid(1));
+    }
+
+    #[benchmark]
+    fn pin_file() {
+        let caller: T::AccountId = whitelisted_caller();
+        // The file is already pinned, so its expiration is extended
+        CidsStatus::<T>::insert(benchmark_cid(0), (U256::from(1), U256::from(1)));
+
+        #[extrinsic_call]
+        pin_file(RawOrigin::Signed(caller), benchmark_cid(0), MinExpireDuration::get().into());
+
+        assert!(CidsStatus::<T>::get(benchmark_cid(0)).0 > U256::from(1));
+    }
+
+    #[benchmark]
+    fn submit_processed_pins(
+        s: Linear<0, { MaxPinsPerPayload::get() }>,
+        r: Linear<0, { MaxPinsPerPayload::get() }>
+    ) {
+        let public = benchmark_validator::<T>(0);
+        let account_id = public.clone().into_account();
+        let to_save = (0..s)
+            .map(|i| (benchmark_cid(i), (U256::zero(), U256::zero())))
+            .collect::<Vec<_>>();
+        let to_remove = (s..s + r)
+            .map(|i| (benchmark_cid(i), (U256::zero(), U256::zero())))
+            .collect::<Vec<_>>();
+        for (cid, _) in to_remove.iter() {
+            NodesPins::<T>::insert(cid, &account_id, true);
+        }
+        let payload = PinPayload { to_save, to_remove, public };
+        let signature: T::Signature = sr25519::Signature::from_raw([0u8; 64]).into();
+
+        #[extrinsic_call]
+        submit_processed_pins(RawOrigin::None, payload, signature);
+
+        assert_eq!(NodesPins::<T>::iter().count() as u32, s);
+    }
+
+    #[benchmark]
+    fn set_inherent_data(
+        u: Linear<0, { MaxPinsPerBlock::get() }>,
+        r: Linear<0, { MaxPinsPerBlock::get() }>
+    ) {
+        let usable = (0..u)
+            .map(|i| {
+                CidsStatus::<T>::insert(benchmark_cid(i), (U256::zero(), U256::zero()));
+                (benchmark_cid(i), (U256::zero(), U256::zero()))
+            })
+            .collect::<Vec<_>>();
+        let to_remove = (u..u + r)
+            .map(|i| {
+                CidsStatus::<T>::insert(benchmark_cid(i), (U256::from(1), U256::from(1)));
+                for j in 0..BENCHMARK_CID_PINS {
+                    let account_id: T::AccountId = account("validator", j, 0);
+                    NodesPins::<T>::insert(benchmark_cid(i), account_id, true);
+                }
+                (benchmark_cid(i), (U256::from(1), U256::from(1)))
+            })
+            .collect::<Vec<_>>();
+
+        #[extrinsic_call]
+        set_inherent_data(RawOrigin::None, (usable, to_remove));
+
+        assert!(InherentDidUpdate::<T>::get());
+        assert_eq!(CidsStatus::<T>::iter().count() as u32, u);
+    }
+
+    impl_benchmark_test_suite!(
+        Pallet,
+        crate::mock::new_test_ext(),
+        crate::mock::Test
+    );
+}
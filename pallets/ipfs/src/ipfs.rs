@@ -9,12 +9,16 @@ use crate::types::Cid;
 pub type IpfsReturn = Result<Vec<u8>, sp_runtime::offchain::http::Error>;
 
 fn call_endpoint(url: &str, body: Vec<u8>) -> IpfsReturn {
+    call_endpoint_with_content_type(url, body, "application/json")
+}
+
+fn call_endpoint_with_content_type(url: &str, body: Vec<u8>, content_type: &str) -> IpfsReturn {
     let deadline = sp_io::offchain
         ::timestamp()
         .add(sp_runtime::offchain::Duration::from_millis(10_000));
     let request = sp_runtime::offchain::http::Request
         ::post(url, vec![body])
-        .add_header("Content-Type", "application/json");
+        .add_header("Content-Type", content_type);
     let pending = request
         .deadline(deadline)
         .send()
@@ -66,6 +70,34 @@ pub fn offchain_pin_file<T: crate::Config>(cid: &Cid) -> IpfsReturn {
     }
 }
 
+// NOTE: The file is added with the CID version 1 and the default chunker, so every node gets the same CID for the same data.
+pub fn offchain_add_file<T: crate::Config>(data: Vec<u8>) -> Result<Cid, sp_runtime::offchain::http::Error> {
+    let url = format!("{}/add?cid-version=1&pin=true&quieter=true", T::IpfsApiUrl::get());
+
+    let boundary = "uomi-ipfs-add-boundary";
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"file\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+        boundary
+    ).into_bytes();
+    body.extend(data);
+    body.extend(format!("\r\n--{}--\r\n", boundary).into_bytes());
+
+    let content_type = format!("multipart/form-data; boundary={}", boundary);
+    let output = call_endpoint_with_content_type(&url, body, &content_type)?;
+
+    // The response is a JSON like {"Name":"file","Hash":"<cid>","Size":"<size>"}
+    let body_str = sp_std::str
+        ::from_utf8(&output)
+        .map_err(|_| sp_runtime::offchain::http::Error::Unknown)?;
+    let cid_str = body_str
+        .split("\"Hash\":\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .ok_or(sp_runtime::offchain::http::Error::Unknown)?;
+
+    Cid::try_from(cid_str.as_bytes().to_vec()).map_err(|_| sp_runtime::offchain::http::Error::Unknown)
+}
+
 pub fn offchain_unpin_file<T: crate::Config>(cid: &Cid) -> IpfsReturn {
     let cid_str = get_cid_str(cid)?;
 
@@ -5,11 +5,16 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
 mod ipfs;
 mod storages;
 pub mod types;
 
 pub use pallet::*;
+pub mod weights;
+pub use weights::*;
 
 use frame_support::pallet_prelude::DispatchClass;
 
@@ -153,6 +158,8 @@ pub mod pallet {
 
     parameter_types! {
         pub const MinExpireDuration: u32 = 28800; // bytes
+        pub const MaxPinsPerBlock: u32 = 100; // CIDs made usable or removed by a single inherent
+        pub const MaxPinsPerPayload: u32 = 100; // CIDs pinned or unpinned by a single submit_processed_pins
     }
 
     #[pallet::config]
@@ -172,6 +179,8 @@ pub mod pallet {
 
         #[pallet::constant]
         type TemporaryPinningCost: Get<Balance>;
+
+        type WeightInfo: WeightInfo;
     }
 
     #[pallet::event]
@@ -243,6 +252,7 @@ pub mod pallet {
     pub enum Error<T> {
         SomethingWentWrong,
         FundsUnavailable,
+        TooManyPins,
     }
 
     #[pallet::hooks]
@@ -276,16 +286,28 @@ pub mod pallet {
                         .build()
                 }
                 // Handle existing submit_processed_pins validation
-                Call::submit_processed_pins { .. } => {
+                Call::submit_processed_pins { payload, signature } => {
                     // Existing validation for submit_processed_pins
                     if source == TransactionSource::External && current_block_number < 510000.into() {  // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
                         log::info!("IPFS: Rejecting submit_processed_pins unsigned transaction from external origin");
                         return InvalidTransaction::BadSigner.into()
                     }
 
+                    if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+                        log::info!("IPFS: Rejecting submit_processed_pins with an invalid payload signature");
+                        return InvalidTransaction::BadProof.into()
+                    }
+
+                    let account_id = payload.public.clone().into_account();
+                    if !Self::is_active_validator(&account_id) {
+                        log::info!("IPFS: Rejecting submit_processed_pins from a not active validator");
+                        return InvalidTransaction::BadSigner.into()
+                    }
+
+                    // Every validator has a single pending submit_processed_pins, the ones sent while it is pending are rejected
                     ValidTransaction::with_tag_prefix("IpfsPallet")
                         .priority(TransactionPriority::MAX)
-                        .and_provides(&call)
+                        .and_provides(("submit_processed_pins", account_id))
                         .longevity(64)
                         .propagate(true)
                         .build()
@@ -300,7 +322,7 @@ pub mod pallet {
     #[pallet::call]
     impl<T: Config> Pallet<T> {
         #[pallet::call_index(0)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::pin_agent())]
         pub fn pin_agent(origin: OriginFor<T>, cid: Cid, nft_id: NftId) -> DispatchResult {
             let _who = ensure_signed(origin)?;
 
@@ -347,7 +369,7 @@ pub mod pallet {
         }
 
         #[pallet::call_index(1)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::pin_file())]
         pub fn pin_file(
             origin: OriginFor<T>,
             cid: Cid,
@@ -360,31 +382,13 @@ pub mod pallet {
             //check if duration is more than 28800 (number of blocks in a day)
             ensure!(duration >= min_duration, "Duration must be more than 28800 blocks");
 
-            let current_block = frame_system::Pallet::<T>::block_number();
-            let new_expires_at = current_block.saturating_add(duration);
-
-            if CidsStatus::<T>::contains_key(&cid) {
-                CidsStatus::<T>::mutate(&cid, |(expires_at, _usable_from)| {
-                    if (new_expires_at.into() > *expires_at) {
-                        *expires_at = new_expires_at.into();
-                    } else {
-                        *expires_at = expires_at.clone();
-                    }
-                });
-            } else {
-                CidsStatus::<T>::insert(&cid, (new_expires_at.into(), U256::zero()));
-            }
-
-            Self::deposit_event(Event::TemporaryPinCreated {
-                cid: cid.to_vec(),
-                expires_at: new_expires_at,
-            });
+            Self::temporary_pin(&cid, duration);
 
             Ok(())
         }
 
         #[pallet::call_index(2)]
-        #[pallet::weight(10_000)]
+        #[pallet::weight(T::WeightInfo::submit_processed_pins(payload.to_save.len() as u32, payload.to_remove.len() as u32))]
         pub fn submit_processed_pins(
             origin: OriginFor<T>,
             payload: PinPayload<T::Public>,
@@ -398,6 +402,11 @@ pub mod pallet {
                 return Err(DispatchError::Other("Only validators can call submit_processed_pins, ignore submit_processed_pins execution"));
             }
 
+            ensure!(
+                payload.to_save.len() as u32 <= MaxPinsPerPayload::get() && payload.to_remove.len() as u32 <= MaxPinsPerPayload::get(),
+                Error::<T>::TooManyPins
+            );
+
             for (cid, _expires_at) in payload.to_save {
                 let _ = add_node_pin::<T>(&cid.clone(), &payload.public.clone().into_account());
 
@@ -420,7 +429,7 @@ pub mod pallet {
         }
 
         #[pallet::call_index(3)]
-        #[pallet::weight((10_000, DispatchClass::Mandatory))]
+        #[pallet::weight((T::WeightInfo::set_inherent_data(operations.0.len() as u32, operations.1.len() as u32), DispatchClass::Mandatory))]
         pub fn set_inherent_data(
             origin: OriginFor<T>,
             operations: (
@@ -698,6 +707,10 @@ pub mod pallet {
                 }
             }
 
+            // the operations over MaxPinsPerBlock are applied by the inherents of the next blocks
+            usable.truncate(MaxPinsPerBlock::get() as usize);
+            to_remove.truncate(MaxPinsPerBlock::get() as usize);
+
             Ok((usable, to_remove))
         }
 
@@ -712,11 +725,18 @@ pub mod pallet {
             let public = Self::get_account_id()?;
 
             //iter cidsStatus
+            // NOTE: the pins over MaxPinsPerPayload are processed by the next offchain workers
             for (cid, (expires_at, usable_from)) in CidsStatus::<T>::iter() {
+                if to_save.len() as u32 >= MaxPinsPerPayload::get() {
+                    break;
+                }
                 Self::process_pin(cid, &public, (expires_at, usable_from), &mut to_save)?;
             }
 
             for (cid, (expires_at, usable_from)) in CidsStatus::<T>::iter() {
+                if to_remove.len() as u32 >= MaxPinsPerPayload::get() {
+                    break;
+                }
                 Self::process_unpin(cid, &public, (expires_at, usable_from), &mut to_remove)?;
             }
 
@@ -739,6 +759,44 @@ pub mod pallet {
             ipfs::offchain_pin_file::<T>(&cid).map(|_| true)
         }
 
+        // This function is used to pin a file on the validators for the given duration from the current block.
+        // If the file is already pinned, the expiration is only extended. It returns the expiration block number of the pin.
+        pub fn temporary_pin(cid: &Cid, duration: BlockNumber<T>) -> ExpirationBlockNumber {
+            let current_block = frame_system::Pallet::<T>::block_number();
+            let new_expires_at = current_block.saturating_add(duration);
+
+            let expiration_block_number = if CidsStatus::<T>::contains_key(cid) {
+                CidsStatus::<T>::mutate(cid, |(expires_at, _usable_from)| {
+                    if new_expires_at.into() > *expires_at {
+                        *expires_at = new_expires_at.into();
+                    }
+                    *expires_at
+                })
+            } else {
+                CidsStatus::<T>::insert(cid, (new_expires_at.into(), U256::zero()));
+                new_expires_at.into()
+            };
+
+            Self::deposit_event(Event::TemporaryPinCreated {
+                cid: cid.to_vec(),
+                expires_at: new_expires_at,
+            });
+
+            expiration_block_number
+        }
+
+        // This function is used by the offchain workers to add a file to the local IPFS node, it returns the CID of the file.
+        pub fn add_file(data: Vec<u8>) -> Result<Cid, sp_runtime::offchain::http::Error> {
+            let cid = ipfs::offchain_add_file::<T>(data)?;
+
+            Self::deposit_event(Event::IpfsOperationSuccess {
+                operation: IpfsOperation::Pin,
+                cid: cid.to_vec(),
+            });
+
+            Ok(cid)
+        }
+
         pub fn get_agent_cid(nft_id: NftId) -> Result<Cid, DispatchError> {
             let cid = AgentsPins::<T>::get(nft_id);
             if cid.is_empty() {
@@ -129,6 +129,7 @@ impl crate::Config for Test {
     type Currency = pallet_balances::Pallet<Test>;
     type BlockNumber = BlockNumber;
     type TemporaryPinningCost = IpfsPinningCost;
+    type WeightInfo = ();
 }
 
 // SIGNING TYPES
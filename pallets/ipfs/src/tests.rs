@@ -14,8 +14,15 @@ use crate::{
     InherentDidUpdate,
     Error,
     MinExpireDuration,
+    MaxPinsPerBlock,
+    MaxPinsPerPayload,
+    PinPayload,
     CRYPTO_KEY_TYPE,
 };
+use frame_support::inherent::{ InherentData, ProvideInherent };
+use frame_support::traits::Get;
+use frame_support::pallet_prelude::{ InvalidTransaction, TransactionSource, ValidateUnsigned };
+use codec::Encode;
 use std::{ io::Write, sync::{ Arc, Mutex } };
 use frame_support::assert_noop;
 use crate::types::MaxCidSize;
@@ -665,6 +672,125 @@ fn test_edge_case_pin_scenarios() {
         }
     });
 }
+#[test]
+fn test_submit_processed_pins_fails_with_too_many_pins() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let validators = create_test_validators(1, 1_000_000);
+
+        let to_save = (0..MaxPinsPerPayload::get() + 1)
+            .map(|i| (BoundedVec::try_from(i.to_le_bytes().to_vec()).expect("Vector exceeds the bound"), (U256::zero(), U256::zero())))
+            .collect::<Vec<_>>();
+        let payload = PinPayload { to_save, to_remove: Vec::new(), public: validators[0].clone() };
+
+        assert_noop!(
+            TestingPallet::submit_processed_pins(
+                RuntimeOrigin::none(),
+                payload,
+                sp_core::sr25519::Signature::from_raw([0u8; 64])
+            ),
+            Error::<Test>::TooManyPins
+        );
+    });
+}
+
+#[test]
+fn test_inherent_operations_are_bounded() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+
+        // Store more expired CIDs than the ones removed by a single inherent
+        let expired_count = MaxPinsPerBlock::get() + 5;
+        for i in 0..expired_count {
+            let cid: BoundedVec<u8, MaxCidSize> = BoundedVec::try_from(i.to_le_bytes().to_vec()).expect("Vector exceeds the bound");
+            CidsStatus::<Test>::insert(cid, (U256::from(1), U256::from(1)));
+        }
+
+        let call = <TestingPallet as ProvideInherent>::create_inherent(&InherentData::new()).expect("Inherent should be created");
+        let operations = match call {
+            crate::Call::set_inherent_data { operations } => operations,
+            _ => panic!("Unexpected inherent call"),
+        };
+        assert_eq!(operations.1.len() as u32, MaxPinsPerBlock::get());
+
+        // The remaining CIDs are removed by the inherent of the next block
+        assert_ok!(TestingPallet::set_inherent_data(RuntimeOrigin::none(), operations));
+        assert_eq!(CidsStatus::<Test>::iter().count() as u32, expired_count - MaxPinsPerBlock::get());
+    });
+}
+
+#[test]
+fn test_validate_unsigned_verifies_submit_processed_pins_signature() {
+    make_logger();
+
+    let mut ext = new_test_ext();
+
+    // Create and register keystore
+    let keystore = Arc::new(MemoryKeystore::new());
+    let public_key = keystore.sr25519_generate_new(CRYPTO_KEY_TYPE, None).unwrap();
+    let not_validator_public_key = keystore.sr25519_generate_new(CRYPTO_KEY_TYPE, None).unwrap();
+    ext.register_extension(KeystoreExt(keystore.clone()));
+
+    ext.execute_with(|| {
+        System::set_block_number(1);
+
+        // Set up the first key as validator
+        let account_id = AccountId::from_raw(public_key.0);
+        let stake = 1000;
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&account_id, stake);
+        assert_ok!(
+            Staking::bond(
+                RuntimeOrigin::signed(account_id.clone()),
+                stake,
+                pallet_staking::RewardDestination::Staked
+            )
+        );
+        assert_ok!(
+            Staking::validate(
+                RuntimeOrigin::signed(account_id.clone()),
+                pallet_staking::ValidatorPrefs {
+                    commission: sp_runtime::Perbill::from_percent(0),
+                    blocked: false,
+                }
+            )
+        );
+
+        let processed_pins_call = |public: sp_core::sr25519::Public, signer: sp_core::sr25519::Public| {
+            let payload = PinPayload { to_save: vec![(create_test_cid(), (U256::zero(), U256::zero()))], to_remove: Vec::new(), public };
+            let signature = keystore.sr25519_sign(CRYPTO_KEY_TYPE, &signer, &payload.encode()).unwrap().unwrap();
+            crate::Call::<Test>::submit_processed_pins { payload, signature }
+        };
+
+        // The payload signed by the validator is valid and provides a tag for the validator
+        let valid = <TestingPallet as ValidateUnsigned>::validate_unsigned(
+            TransactionSource::Local,
+            &processed_pins_call(public_key, public_key)
+        ).expect("Payload signed by the validator should be valid");
+        assert_eq!(valid.provides.len(), 1);
+
+        // The payload signed by another key is rejected
+        assert_eq!(
+            <TestingPallet as ValidateUnsigned>::validate_unsigned(
+                TransactionSource::Local,
+                &processed_pins_call(public_key, not_validator_public_key)
+            ),
+            InvalidTransaction::BadProof.into()
+        );
+
+        // The payload signed by a key that is not an active validator is rejected
+        assert_eq!(
+            <TestingPallet as ValidateUnsigned>::validate_unsigned(
+                TransactionSource::Local,
+                &processed_pins_call(not_validator_public_key, not_validator_public_key)
+            ),
+            InvalidTransaction::BadSigner.into()
+        );
+    });
+}
+
 // NOTE: Commented because it's not used for now
 // fn get_test_account() -> AccountId {
 //   let seed = [0u8; 32];
@@ -0,0 +1,146 @@
+//! Weights for pallet_ipfs
+//!
+//! ESTIMATED WEIGHTS: this file has not been generated by the benchmark CLI. The figures are estimated from the storage
+//! accesses of the benchmarks of pallets/ipfs/src/benchmarking.rs and are the same used by the runtimes, they are not measurements.
+//! Replace this file with the output of ./scripts/run-benchmarks.sh on the reference hardware before the release of the
+//! runtime, and every time a benchmarked call changes.
+
+// Command to generate this file:
+// ./target/release/uomi
+// benchmark
+// pallet
+// --chain=dev
+// --steps=50
+// --repeat=20
+// --pallet=pallet_ipfs
+// --extrinsic=*
+// --wasm-execution=compiled
+// --heap-pages=4096
+// --output=./pallets/ipfs/src/weights.rs
+// --template=./scripts/templates/pallet-weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weight functions needed for pallet_ipfs.
+pub trait WeightInfo {
+	fn pin_agent() -> Weight;
+	fn pin_file() -> Weight;
+	fn submit_processed_pins(s: u32, r: u32, ) -> Weight;
+	fn set_inherent_data(u: u32, r: u32, ) -> Weight;
+}
+
+/// Weights for pallet_ipfs using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: `Ipfs::AgentsPins` (r:1 w:1)
+	/// Proof: `Ipfs::AgentsPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:2)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_agent() -> Weight {
+		Weight::from_parts(28_105_000, 3606)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:1)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_file() -> Weight {
+		Weight::from_parts(18_290_000, 3606)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Staking::Validators` (r:1 w:0)
+	/// Proof: `Staking::Validators` (`max_values`: None, `max_size`: Some(45), added: 2520, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:0 w:200)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `s` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn submit_processed_pins(s: u32, r: u32, ) -> Weight {
+		Weight::from_parts(14_871_000, 3510)
+			.saturating_add(Weight::from_parts(6_482_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(6_117_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(s.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(r.into())))
+	}
+	/// Storage: `Ipfs::InherentDidUpdate` (r:1 w:1)
+	/// Proof: `Ipfs::InherentDidUpdate` (`max_values`: Some(1), `max_size`: Some(1), added: 496, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:100 w:200)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:1000 w:1000)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `u` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn set_inherent_data(u: u32, r: u32, ) -> Weight {
+		Weight::from_parts(8_412_000, 1486)
+			.saturating_add(Weight::from_parts(8_906_000, 0).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(41_735_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(u.into())))
+			.saturating_add(T::DbWeight::get().reads((10_u64).saturating_mul(r.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(u.into())))
+			.saturating_add(T::DbWeight::get().writes((11_u64).saturating_mul(r.into())))
+			.saturating_add(Weight::from_parts(0, 2616).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(0, 26000).saturating_mul(r.into()))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: `Ipfs::AgentsPins` (r:1 w:1)
+	/// Proof: `Ipfs::AgentsPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:2)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_agent() -> Weight {
+		Weight::from_parts(28_105_000, 3606)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(3_u64))
+	}
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:1)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_file() -> Weight {
+		Weight::from_parts(18_290_000, 3606)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Staking::Validators` (r:1 w:0)
+	/// Proof: `Staking::Validators` (`max_values`: None, `max_size`: Some(45), added: 2520, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:0 w:200)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `s` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn submit_processed_pins(s: u32, r: u32, ) -> Weight {
+		Weight::from_parts(14_871_000, 3510)
+			.saturating_add(Weight::from_parts(6_482_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(6_117_000, 0).saturating_mul(r.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(s.into())))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(r.into())))
+	}
+	/// Storage: `Ipfs::InherentDidUpdate` (r:1 w:1)
+	/// Proof: `Ipfs::InherentDidUpdate` (`max_values`: Some(1), `max_size`: Some(1), added: 496, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:100 w:200)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:1000 w:1000)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `u` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn set_inherent_data(u: u32, r: u32, ) -> Weight {
+		Weight::from_parts(8_412_000, 1486)
+			.saturating_add(Weight::from_parts(8_906_000, 0).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(41_735_000, 0).saturating_mul(r.into()))
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().reads((1_u64).saturating_mul(u.into())))
+			.saturating_add(RocksDbWeight::get().reads((10_u64).saturating_mul(r.into())))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(u.into())))
+			.saturating_add(RocksDbWeight::get().writes((11_u64).saturating_mul(r.into())))
+			.saturating_add(Weight::from_parts(0, 2616).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(0, 26000).saturating_mul(r.into()))
+	}
+}
@@ -221,6 +221,7 @@ impl pallet_ipfs::Config for Test {
     type Currency = pallet_balances::Pallet<Test>;
     type BlockNumber = u64;
     type TemporaryPinningCost = IpfsTemporaryPinningCost;
+    type WeightInfo = ();
 }
 
 impl pallet_babe::Config for Test {
@@ -1,32 +1,63 @@
 use crate::{
-    consts::{MAX_BLOCKS_TO_WAIT_NODE_UPDATE, PALLET_VERSION},
-    types::{AiModelKey, BlockNumber, Data, Version},
-    {AIModels, Config, NodesVersions, Pallet},
+    consts::MAX_BLOCKS_TO_WAIT_NODE_UPDATE,
+    types::{AiModel, AiModelKey, BlockNumber, Data},
+    {AIModels, Config, Error, Event, ModelsActivatedNames, ModelsPendingActivations, ModelsRegistry, NodesModels, Pallet},
 };
-use frame_support::pallet_prelude::DispatchError;
-use sp_std::collections::btree_map::BTreeMap;
+use frame_support::{
+    ensure,
+    pallet_prelude::{DispatchError, Weight},
+    traits::Get,
+};
+use sp_io::hashing::blake2_256;
+use sp_runtime::DispatchResult;
+use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
 
 impl<T: Config> Pallet<T> {
     // Aimodelscalc entry point
+    // The AI models are computed from the models registry managed by the governance and the models served by the nodes.
+    // A registered model is set as official name of its key only when it is served by the ModelActivationThreshold of the
+    // stake of the active validators, the previous name is kept for MAX_BLOCKS_TO_WAIT_NODE_UPDATE blocks to let the nodes
+    // complete the switch. A local name already activated in the past is never activated again, so a downgrade is not possible.
     pub fn aimodelscalc_run(current_block: BlockNumber) -> Result<BTreeMap<AiModelKey, (Data, Data, BlockNumber)>, DispatchError> {
         let mut aimodelscalc_operations = BTreeMap::<AiModelKey, (Data, Data, BlockNumber)>::new();
 
-        let most_used_version = Self::aimodelscalc_get_most_used_version();
-        let local_names_per_versions = Self::aimodelscalc_get_local_names_per_versions();
-        let local_names_per_most_used_version = local_names_per_versions.get(&most_used_version).unwrap();
+        let active_validators = Self::get_active_validators();
+        let total_stake = active_validators.iter()
+            .fold(0u128, |total, account_id| total.saturating_add(Self::get_active_validator_stake(account_id)));
+        let required_stake = T::ModelActivationThreshold::get().mul_ceil(total_stake);
+        let served_models_stakes = Self::aimodelscalc_get_served_models_stakes(&active_validators);
 
-        for (ai_model_key, local_name) in local_names_per_most_used_version {
+        for (ai_model_key, model) in ModelsRegistry::<T>::iter() {
             let (current_local_name, _previous_local_name, available_from) = AIModels::<T>::get(&ai_model_key);
-            if current_local_name == *local_name { // the local name is already set as official name for this ai model, nothing to do
+            if current_block < available_from { // the previous switch of the ai model is not completed yet
                 continue;
             }
 
-            if current_block < available_from { // we will not change the names if the ai model is not available yet
+            let local_name = if model.deprecated { // the deprecated ai models are removed without waiting the nodes
+                Data::default()
+            } else {
+                if current_block < model.activation_block_number { // the ai model can not be used yet
+                    continue;
+                }
+
+                let served_stake = served_models_stakes.get(&ai_model_key).cloned().unwrap_or(0);
+                if total_stake == 0 || served_stake < required_stake { // we wait the supermajority of the stake to serve the ai model
+                    continue;
+                }
+
+                if Self::aimodelscalc_is_downgrade(&ai_model_key, &model.backend_name) { // an older local name can not be activated again
+                    log::info!("UOMI-ENGINE: Skipped the downgrade of the AI model {:?}", ai_model_key);
+                    continue;
+                }
+
+                model.backend_name
+            };
+
+            if current_local_name == local_name { // the local name is already set as official name for this ai model, nothing to do
                 continue;
             }
 
-            let new_previous_local_name = Some(current_local_name).unwrap_or(local_name.clone());
-            aimodelscalc_operations.insert(ai_model_key.clone(), (local_name.clone(), new_previous_local_name, current_block + MAX_BLOCKS_TO_WAIT_NODE_UPDATE));
+            aimodelscalc_operations.insert(ai_model_key, (local_name, current_local_name, current_block + MAX_BLOCKS_TO_WAIT_NODE_UPDATE));
         }
 
         Ok(aimodelscalc_operations)
@@ -34,72 +65,87 @@ impl<T: Config> Pallet<T> {
 
     pub fn aimodelscalc_store_operations(aimodelscalc_operations: BTreeMap<AiModelKey, (Data, Data, BlockNumber)>) -> Result<(), DispatchError> {
         for (key, (local_name, previous_local_name, available_from_block_number)) in aimodelscalc_operations {
-            AIModels::<T>::insert(key, (local_name, previous_local_name, available_from_block_number));
+            AIModels::<T>::insert(key, (local_name.clone(), previous_local_name.clone(), available_from_block_number));
+            ModelsPendingActivations::<T>::insert(key, available_from_block_number);
+
+            Self::deposit_event(Event::ModelSwitchScheduled {
+                ai_model_key: key,
+                local_name,
+                previous_local_name,
+                available_from_block_number,
+            });
         }
 
         Ok(())
     }
 
-    // IMPORTANT: This algorithm is just a placeholder, it should be replaced by a more complex one
-    // The more complex should wait a better percentage of nodes to update their versions (like 80%)
-    // To be sure that is not possible to have a downgrade of the version.
-    // A downgrade of the version could be a security issue that permits to nodes to not have the requested version at a specific block.
-    fn aimodelscalc_get_most_used_version() -> Version {
-        // Check NodesVersions has at least one entry, if not return the PALLET_VERSION
-        if NodesVersions::<T>::iter().next().is_none() {
-            return PALLET_VERSION;
-        }
+    // This function is used on on_finalize to activate the switches of the AI models scheduled for the next block.
+    // NOTE: The activations are not run on on_initialize, so the inherent of the next block is checked on the same state where it is created.
+    pub fn aimodelscalc_activate(current_block: BlockNumber) -> Weight {
+        let activations = ModelsPendingActivations::<T>::iter()
+            .filter(|(_, available_from_block_number)| *available_from_block_number <= current_block)
+            .map(|(ai_model_key, _)| ai_model_key)
+            .collect::<Vec<AiModelKey>>();
 
-        // Count the number of nodes per version
-        let mut counters = BTreeMap::<Version, u32>::new();
-        for (_k, v) in NodesVersions::<T>::iter() {
-            *counters.entry(v).or_insert(0) += 1;
-        }
-    
-        // Find the version with the most nodes
-        let mut max = 0;
-        let mut version = PALLET_VERSION;
-        for (v, c) in counters {
-            if c > max {
-                max = c;
-                version = v;
+        for ai_model_key in activations.iter() {
+            ModelsPendingActivations::<T>::remove(ai_model_key);
+
+            let (local_name, _previous_local_name, _available_from) = AIModels::<T>::get(ai_model_key);
+            if !local_name.is_empty() {
+                ModelsActivatedNames::<T>::insert(ai_model_key, blake2_256(&local_name), current_block);
             }
+
+            Self::deposit_event(Event::ModelActivated { ai_model_key: *ai_model_key, local_name });
         }
 
-        version
+        let activated = activations.len() as u64;
+        T::DbWeight::get().reads_writes(1 + activated, activated * 2)
+    }
+
+    // This function returns the weight of the activations run on on_finalize for the given block, charged on on_initialize.
+    pub fn aimodelscalc_activate_weight(current_block: BlockNumber) -> Weight {
+        let activations = ModelsPendingActivations::<T>::iter_values()
+            .filter(|available_from_block_number| *available_from_block_number <= current_block)
+            .count() as u64;
+
+        T::DbWeight::get().reads_writes(2 + activations, activations * 2)
     }
 
-    fn aimodelscalc_get_local_names_per_versions() -> BTreeMap<Version, BTreeMap<AiModelKey, Data>> {
-        let mut map = BTreeMap::new();
-    
-        let qwen2_5: Data = b"Qwen/Qwen2.5-32B-Instruct-GPTQ-Int4".to_vec().try_into().unwrap();
-        let temp2_5: Data = b"Temp2.5/Temp".to_vec().try_into().unwrap();
-        let mistral_24b: Data = b"casperhansen/mistral-small-24b-instruct-2501-awq".to_vec().try_into().unwrap();
-        let qwen_qwq_32b: Data = b"Qwen/QwQ-32B-AWQ".to_vec().try_into().unwrap();
-        let dobby3_1_8b: Data = b"SentientAGI/Dobby-Mini-Unhinged-Llama-3.1-8B".to_vec().try_into().unwrap();
-        let sana_1600m: Data = b"Efficient-Large-Model/Sana_1600M_1024px_BF16_diffusers".to_vec().try_into().unwrap();
-
-        let mut version_2 = BTreeMap::new();
-        version_2.insert(AiModelKey::from(1), qwen2_5.clone());
-        map.insert(2 as u32, version_2);
-
-        let mut version_3 = BTreeMap::new();
-        version_3.insert(AiModelKey::from(1), temp2_5.clone());
-        map.insert(3 as u32, version_3);
-
-        let mut version_4 = BTreeMap::new();
-        version_4.insert(AiModelKey::from(1), qwen2_5.clone());
-        map.insert(4 as u32, version_4);
-
-        let mut version_5 = BTreeMap::new();
-        version_5.insert(AiModelKey::from(1), mistral_24b.clone());
-        version_5.insert(AiModelKey::from(2), qwen_qwq_32b.clone());
-        version_5.insert(AiModelKey::from(3), dobby3_1_8b.clone());
-        version_5.insert(AiModelKey::from(100), sana_1600m.clone());
-        map.insert(5 as u32, version_5);
-
-        // Free for future releases...
-    
-        map
+    // This function is used to check if a local name has already been activated for an AI model and it is not the current one.
+    pub fn aimodelscalc_is_downgrade(ai_model_key: &AiModelKey, local_name: &Data) -> bool {
+        let (current_local_name, _previous_local_name, _available_from) = AIModels::<T>::get(ai_model_key);
+
+        *local_name != current_local_name && ModelsActivatedNames::<T>::contains_key(ai_model_key, blake2_256(local_name))
+    }
+
+    // This function is used to validate a model before storing it on the models registry.
+    // NOTE: The model 0 is reserved to the test model that returns the input data inverted.
+    pub fn aimodelscalc_ensure_valid_model(ai_model_key: &AiModelKey, model: &AiModel) -> DispatchResult {
+        ensure!(!ai_model_key.is_zero(), Error::<T>::InvalidModelKey);
+        ensure!(!model.backend_name.is_empty(), Error::<T>::InvalidModelBackendName);
+        ensure!(model.min_consensus > 0 && model.min_consensus <= model.max_consensus, Error::<T>::InvalidModelConsensus);
+
+        Ok(())
+    }
+
+    // This function is used to check a request with the given nft_required_consensus is allowed to use a model.
+    pub fn aimodelscalc_is_allowed_consensus(ai_model_key: &AiModelKey, required_consensus: u32) -> bool {
+        match ModelsRegistry::<T>::get(ai_model_key) {
+            Some(model) => !model.deprecated && required_consensus >= model.min_consensus && required_consensus <= model.max_consensus,
+            None => false,
+        }
+    }
+
+    fn aimodelscalc_get_served_models_stakes(validators: &Vec<T::AccountId>) -> BTreeMap<AiModelKey, u128> {
+        let mut stakes = BTreeMap::<AiModelKey, u128>::new();
+        for validator in validators.iter() {
+            let stake = Self::get_active_validator_stake(validator);
+            for ai_model_key in NodesModels::<T>::get(validator).iter() {
+                let served_stake = stakes.entry(*ai_model_key).or_insert(0);
+                *served_stake = served_stake.saturating_add(stake);
+            }
+        }
+
+        stakes
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,128 @@
+use sp_std::vec::Vec;
+
+use pallet_ipfs::types::Cid;
+
+use crate::{
+    ipfs::IpfsInterface,
+    types::{AgentRequest, AgentResult, AiModelKey, BlockNumber, Data, NftId, RequestAssignment, RequestId, RequestInfo, RequestNodeOutput, RequestResult, RequestStatus},
+    AIModels, Config, Inputs, NodesOpocL0Inferences, NodesOutputs, NodesOutputsCids, NodesOutputsCommitments, NodesOutputsOutOfFuel, NodesOutputsTransactions, OpocAssignment, Outputs, Pallet,
+};
+
+impl<T: Config> Pallet<T> {
+    // This function is used by the runtime API to return the full view of a request.
+    // It returns None if the request has never been accepted.
+    pub fn api_get_request(request_id: RequestId) -> Option<RequestInfo<T::AccountId>> {
+        let status = Self::requests_status(&request_id)?;
+        let is_completed = status == RequestStatus::Completed;
+
+        let AgentRequest {
+            block_number,
+            nft_id,
+            nft_required_consensus,
+            nft_execution_max_time,
+            nft_file_cid,
+            input_data,
+            input_file_cid,
+        } = Inputs::<T>::get(request_id);
+
+        let assignments = Self::api_request_assignments(request_id);
+        let opoc_level = if assignments.is_empty() {
+            None
+        } else {
+            Some(Self::offchain_detect_opoc_level(&request_id, &nft_required_consensus))
+        };
+
+        let outputs = NodesOutputs::<T>::iter_prefix(request_id)
+            .map(|(account_id, output_data)| {
+                let output_cid = NodesOutputsCids::<T>::get(request_id, &account_id);
+                let output_transaction = NodesOutputsTransactions::<T>::get(request_id, &account_id);
+                let out_of_fuel = NodesOutputsOutOfFuel::<T>::get(request_id, &account_id);
+                RequestNodeOutput { account_id, output_data, output_cid, output_transaction, out_of_fuel }
+            })
+            .collect::<Vec<_>>();
+
+        let result = if is_completed {
+            let AgentResult {
+                output_data,
+                total_executions,
+                total_consensus,
+                output_cid,
+                output_cid_expiration_block_number,
+                output_transaction,
+                out_of_fuel,
+            } = Outputs::<T>::get(request_id);
+            Some(RequestResult {
+                output_data,
+                total_executions,
+                total_consensus,
+                output_cid,
+                output_cid_expiration_block_number,
+                output_transaction,
+                out_of_fuel,
+            })
+        } else {
+            None
+        };
+
+        Some(RequestInfo {
+            request_id,
+            status,
+            block_number,
+            nft_id,
+            nft_required_consensus,
+            nft_execution_max_time,
+            nft_file_cid,
+            input_data,
+            input_file_cid,
+            opoc_level,
+            assignments,
+            outputs,
+            result,
+        })
+    }
+
+    // This function is used by the runtime API to return the ids of the requests not completed yet.
+    pub fn api_pending_requests() -> Vec<RequestId> {
+        Inputs::<T>::iter_keys().collect()
+    }
+
+    // This function is used by the runtime API to return the current assignments of a request.
+    pub fn api_request_assignments(request_id: RequestId) -> Vec<RequestAssignment<T::AccountId>> {
+        OpocAssignment::<T>::iter_prefix(request_id)
+            .map(|(account_id, expiration_block_number)| {
+                let output_committed = NodesOutputsCommitments::<T>::contains_key(request_id, &account_id);
+                let output_received = NodesOutputs::<T>::contains_key(request_id, &account_id);
+                RequestAssignment { account_id, expiration_block_number, output_committed, output_received }
+            })
+            .collect()
+    }
+
+    // This function is used by the runtime API to return the CID of the wasm of an agent, used by the node to dry run the agent.
+    pub fn api_agent_file_cid(nft_id: NftId) -> Option<Cid> {
+        T::IpfsPallet::get_agent_cid(nft_id).ok().filter(|cid| !cid.is_empty())
+    }
+
+    // This function is used by the runtime API to return the name of an AI model on the AI service at the current block.
+    // It returns None if the model is not active, the model 0 is served by every node and it has no name.
+    pub fn api_ai_model_local_name(ai_model_key: AiModelKey) -> Option<Data> {
+        let (local_name, previous_local_name, available_from_block_number) = AIModels::<T>::get(&ai_model_key);
+        let block_number: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+        let name = if block_number < available_from_block_number {
+            previous_local_name
+        } else {
+            local_name
+        };
+
+        if name.is_empty() {
+            None
+        } else {
+            Some(name)
+        }
+    }
+
+    // This function is used by the runtime API to return the inferences recorded by the OPoC level 0 of a request, as inference index and proof.
+    // The proofs are used by the node to replay a request with the same inferences of the validators.
+    pub fn api_opoc_l0_inferences(request_id: RequestId) -> Vec<(u32, Data)> {
+        NodesOpocL0Inferences::<T>::iter_prefix_values(request_id).collect()
+    }
+}
@@ -2,25 +2,468 @@
 
 use super::*;
 use frame_benchmarking::v2::*;
-use frame_system::RawOrigin;
+use frame_system::{offchain::SigningTypes, RawOrigin};
+use sp_core::sr25519;
+use sp_runtime::traits::Bounded;
 
-#[benchmarks]
+use crate::{
+    consts::{MAX_INPUTS_MANAGED_PER_BLOCK, TEMP_BLOCK_FOR_NEW_OPOC},
+    payloads::{PayloadNodesModels, PayloadNodesOpocL0Inferences, PayloadNodesOutputs, PayloadNodesOutputsCommitments, PayloadNodesVersions},
+};
+
+// This is the number of validators assigned to every request completed or failed by the set_inherent_data benchmark.
+const BENCHMARK_REQUEST_VALIDATORS: u32 = 10;
+
+// This function is used to register an active validator with a public key derived from the seed.
+fn benchmark_validator<T: Config>(seed: u32) -> (T::Public, T::AccountId)
+where
+    <T as SigningTypes>::Public: From<sr25519::Public>,
+{
+    let mut raw_public = [0u8; 32];
+    raw_public[0..4].copy_from_slice(&seed.to_le_bytes());
+    let public: T::Public = sr25519::Public::from_raw(raw_public).into();
+    let account_id = public.clone().into_account();
+
+    pallet_staking::Validators::<T>::insert(&account_id, pallet_staking::ValidatorPrefs::default());
+    if let Ok(validator_id) = <T as pallet_session::Config>::ValidatorId::try_from(account_id.clone()) {
+        pallet_session::Validators::<T>::append(validator_id);
+    }
+
+    (public, account_id)
+}
+
+// This function is used to build the signature of the payloads, the signature is verified only by validate_unsigned.
+fn benchmark_signature<T: Config>() -> T::Signature
+where
+    <T as SigningTypes>::Signature: From<sr25519::Signature>,
+{
+    sr25519::Signature::from_raw([0u8; 64]).into()
+}
+
+// This function is used to build the transaction emitted by the agent of the requests of the benchmarks.
+fn benchmark_transaction() -> AgentTransaction {
+    AgentTransaction::Evm {
+        target: Address::zero(),
+        value: U256::zero(),
+        input: Data::default(),
+        gas_limit: 21_000,
+    }
+}
+
+// This function is used to build the AI models called by the agent of the requests of the benchmarks, as many as allowed.
+fn benchmark_ai_models() -> BoundedVec<AiModelKey, MaxModelsPerNode> {
+    BoundedVec::truncate_from((1..=MaxModelsPerNode::get()).map(AiModelKey::from).collect::<Vec<AiModelKey>>())
+}
+
+// This function is used to store a request assigned to all the validators, with its fee held in escrow, a callback registered
+// and the same output, with a transaction emitted by the agent and the AI models called, committed and revealed by every validator.
+fn benchmark_request<T: Config>(
+    request_id: RequestId,
+    payer: &T::AccountId,
+    validators: &Vec<T::AccountId>,
+    output_data: &Data
+) -> Result<(), BenchmarkError> {
+    let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+    let min_validators = U256::from(validators.len());
+
+    Inputs::<T>::insert(request_id, AgentRequest {
+        block_number: current_block,
+        nft_required_consensus: min_validators,
+        nft_execution_max_time: U256::from(10),
+        ..Default::default()
+    });
+    RequestsAddresses::<T>::insert(request_id, Address::zero());
+    Pallet::<T>::fees_reserve(&request_id, payer, &min_validators, &U256::from(10), 0)?;
+    Pallet::<T>::callbacks_register(&request_id, payer, Address::repeat_byte(1), [0u8; 4], T::MaxCallbackGas::get())?;
+
+    for account_id in validators.iter() {
+        OpocAssignment::<T>::insert(request_id, account_id, current_block + U256::from(10));
+        NodesWorks::<T>::insert(account_id, request_id, true);
+        NodesOutputsCommitments::<T>::insert(request_id, account_id, H256::zero());
+        NodesOutputs::<T>::insert(request_id, account_id, output_data.clone());
+        NodesOutputsTransactions::<T>::insert(request_id, account_id, benchmark_transaction());
+        NodesOutputsModels::<T>::insert(request_id, account_id, benchmark_ai_models());
+    }
+
+    Ok(())
+}
+
+#[benchmarks(
+    where
+        <T as SigningTypes>::Public: From<sr25519::Public>,
+        <T as SigningTypes>::Signature: From<sr25519::Signature>,
+)]
 mod benchmarks {
     use super::*;
 
     #[benchmark]
-    fn temporary_function() {
+    fn set_inherent_data(
+        p: Linear<0, 100>,
+        a: Linear<0, 1_000>,
+        w: Linear<0, 1_000>,
+        o: Linear<0, { MAX_INPUTS_MANAGED_PER_BLOCK as u32 }>,
+        f: Linear<0, { MAX_INPUTS_MANAGED_PER_BLOCK as u32 }>,
+        m: Linear<0, 100>,
+    ) -> Result<(), BenchmarkError> {
+        // The inferences of the OPoC level 0 are cleared only after TEMP_BLOCK_FOR_NEW_OPOC
+        frame_system::Pallet::<T>::set_block_number((TEMP_BLOCK_FOR_NEW_OPOC as u32).into());
+        let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+
+        let validators = (0..BENCHMARK_REQUEST_VALIDATORS)
+            .map(|seed| benchmark_validator::<T>(seed).1)
+            .collect::<Vec<T::AccountId>>();
+        let payer: T::AccountId = whitelisted_caller();
+        <T as Config>::Currency::make_free_balance_be(&payer, BalanceOf::<T>::max_value() / 2u32.into());
+        let output_data: Data = BoundedVec::try_from(vec![1u8; 32]).map_err(|_| BenchmarkError::Stop("Invalid output data"))?;
+        // The model fee is held in escrow and the fee of the models called is calculated on completion
+        ModelsFees::<T>::insert(AiModelKey::from(1), BalanceOf::<T>::from(1u32));
+
+        let mut opoc_operations = OpocOperations::<T::AccountId>::default();
+        for i in 0..p {
+            let account_id = validators[(i % BENCHMARK_REQUEST_VALIDATORS) as usize].clone();
+            opoc_operations.opoc_penalties_operations.entry(account_id).or_default().push(OpocOffenceKind::Timeout);
+        }
+        for i in 0..a {
+            let account_id = validators[(i % BENCHMARK_REQUEST_VALIDATORS) as usize].clone();
+            opoc_operations.opoc_assignment_operations.insert((RequestId::from(1_000_000 + i), account_id), current_block + U256::from(10));
+        }
+        for i in 0..w {
+            let account_id = validators[(i % BENCHMARK_REQUEST_VALIDATORS) as usize].clone();
+            opoc_operations.nodes_works_operations.entry(account_id).or_default().insert(RequestId::from(1_000_000 + i), true);
+        }
+        for i in 0..o {
+            let request_id = RequestId::from(i);
+            benchmark_request::<T>(request_id, &payer, &validators, &output_data)?;
+            opoc_operations.outputs_operations.insert(request_id, AgentResult {
+                output_data: output_data.clone(),
+                total_executions: BENCHMARK_REQUEST_VALIDATORS,
+                total_consensus: BENCHMARK_REQUEST_VALIDATORS,
+                output_transaction: Some(benchmark_transaction()),
+                ..Default::default()
+            });
+        }
+        for i in 0..f {
+            let request_id = RequestId::from(o + i);
+            benchmark_request::<T>(request_id, &payer, &validators, &output_data)?;
+            opoc_operations.failures_operations.insert(request_id, RequestFailureReason::Expired);
+        }
+
+        let mut aimodelscalc_operations = BTreeMap::<AiModelKey, (Data, Data, BlockNumber)>::new();
+        for i in 0..m {
+            let local_name: Data = BoundedVec::try_from(i.to_le_bytes().to_vec()).map_err(|_| BenchmarkError::Stop("Invalid local name"))?;
+            aimodelscalc_operations.insert(AiModelKey::from(i), (local_name, Data::default(), current_block + U256::from(10)));
+        }
+
+        #[extrinsic_call]
+        set_inherent_data(RawOrigin::None, opoc_operations, aimodelscalc_operations);
+
+        assert!(InherentDidUpdate::<T>::get());
+        assert_eq!(Inputs::<T>::iter().count(), 0);
+        // The transaction and the callback of every completed request are queued for on_idle
+        assert_eq!(NextExecutionId::<T>::get(), 2 * o as u64);
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn store_nodes_outputs(s: Linear<0, { MaxDataSize::get() }>) -> Result<(), BenchmarkError> {
+        let (public, account_id) = benchmark_validator::<T>(0);
+        let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+        let request_id = RequestId::from(1);
+        let node_output = NodeOutput {
+            output_data: BoundedVec::try_from(vec![1u8; s as usize]).map_err(|_| BenchmarkError::Stop("Invalid output data"))?,
+            output_cid: None,
+            output_transaction: Some(benchmark_transaction()),
+            out_of_fuel: false,
+            ai_models: benchmark_ai_models(),
+        };
+        let salt = H256::repeat_byte(1);
+        // The request is assigned only to the validator, so the reveal is open after its commitment
+        Inputs::<T>::insert(request_id, AgentRequest {
+            block_number: current_block,
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(10),
+            ..Default::default()
+        });
+        OpocAssignment::<T>::insert(request_id, &account_id, current_block + U256::from(10));
+        NodesOutputsCommitments::<T>::insert(request_id, &account_id, Pallet::<T>::opoc_output_hash(&node_output, &salt, &account_id));
+        let payload = PayloadNodesOutputs {
+            request_id,
+            output_data: node_output.output_data,
+            output_cid: node_output.output_cid,
+            output_transaction: node_output.output_transaction,
+            out_of_fuel: node_output.out_of_fuel,
+            ai_models: node_output.ai_models,
+            salt,
+            public,
+        };
+
+        #[extrinsic_call]
+        store_nodes_outputs(RawOrigin::None, payload, benchmark_signature::<T>());
+
+        assert!(NodesOutputs::<T>::contains_key(request_id, &account_id));
+        assert!(NodesOutputsTransactions::<T>::contains_key(request_id, &account_id));
+        assert!(NodesOutputsModels::<T>::contains_key(request_id, &account_id));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn commit_nodes_outputs() {
+        let (public, account_id) = benchmark_validator::<T>(0);
+        let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+        let request_id = RequestId::from(1);
+        OpocAssignment::<T>::insert(request_id, &account_id, current_block + U256::from(10));
+        let payload = PayloadNodesOutputsCommitments { request_id, output_hash: H256::repeat_byte(1), public };
+
+        #[extrinsic_call]
+        commit_nodes_outputs(RawOrigin::None, payload, benchmark_signature::<T>());
+
+        assert!(NodesOutputsCommitments::<T>::contains_key(request_id, &account_id));
+    }
+
+    #[benchmark]
+    fn store_nodes_versions() {
+        let (public, account_id) = benchmark_validator::<T>(0);
+        let payload = PayloadNodesVersions { version: 1, public };
+
+        #[extrinsic_call]
+        store_nodes_versions(RawOrigin::None, payload, benchmark_signature::<T>());
+
+        assert_eq!(NodesVersions::<T>::get(&account_id), 1);
+    }
+
+    #[benchmark]
+    fn store_nodes_opoc_l0_inferences(s: Linear<0, { MaxDataSize::get() }>) -> Result<(), BenchmarkError> {
+        let (public, account_id) = benchmark_validator::<T>(0);
+        let request_id = RequestId::from(1);
+        let payload = PayloadNodesOpocL0Inferences {
+            request_id,
+            inference_index: 0,
+            inference_proof: BoundedVec::try_from(vec![1u8; s as usize]).map_err(|_| BenchmarkError::Stop("Invalid inference proof"))?,
+            public,
+        };
+
+        #[extrinsic_call]
+        store_nodes_opoc_l0_inferences(RawOrigin::None, payload, benchmark_signature::<T>());
+
+        assert!(NodesOpocL0Inferences::<T>::contains_key(request_id, &account_id));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn store_nodes_models(m: Linear<1, { MaxModelsPerNode::get() }>) -> Result<(), BenchmarkError> {
+        let (public, account_id) = benchmark_validator::<T>(0);
+        let ai_model_keys = (1..=m).map(AiModelKey::from).collect::<Vec<AiModelKey>>();
+        let payload = PayloadNodesModels {
+            ai_model_keys: BoundedVec::try_from(ai_model_keys).map_err(|_| BenchmarkError::Stop("Invalid AI model keys"))?,
+            public,
+        };
+
+        #[extrinsic_call]
+        store_nodes_models(RawOrigin::None, payload, benchmark_signature::<T>());
+
+        assert_eq!(NodesModels::<T>::get(&account_id).len() as u32, m);
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn temporary_cleanup_inputs() {
         let caller: T::AccountId = whitelisted_caller();
 
+        #[extrinsic_call]
+        temporary_cleanup_inputs(
+            RawOrigin::Signed(caller)
+        );
+    }
+
+    #[benchmark]
+    fn temporary_function() {
         #[extrinsic_call]
         temporary_function(
             RawOrigin::None
         );
     }
 
+    #[benchmark]
+    fn set_model_fee() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let ai_model_key = AiModelKey::from(1);
+
+        // The worst case decreases the fee of the most expensive model, so the fees of the other models are read to find the new one
+        for model in 2..=100u32 {
+            ModelsFees::<T>::insert(AiModelKey::from(model), BalanceOf::<T>::from(2u32));
+        }
+        ModelsFees::<T>::insert(ai_model_key, BalanceOf::<T>::from(3u32));
+        ModelsMaxFee::<T>::put(BalanceOf::<T>::from(3u32));
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, ai_model_key, BalanceOf::<T>::from(1u32));
+
+        assert_eq!(ModelsFees::<T>::get(ai_model_key), BalanceOf::<T>::from(1u32));
+        assert_eq!(ModelsMaxFee::<T>::get(), BalanceOf::<T>::from(2u32));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn set_rewards_inflation() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let amount = BalanceOf::<T>::from(1_000u32);
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, amount);
+
+        assert_eq!(RewardsInflationPerEra::<T>::get(), amount);
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn claim_rewards() {
+        // The account is created by the claim, the rewards are above the existential deposit
+        let caller: T::AccountId = whitelisted_caller();
+        let amount = <T as Config>::Currency::minimum_balance() + BalanceOf::<T>::from(1_000u32);
+        ClaimableRewards::<T>::insert(&caller, amount);
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()));
+
+        assert_eq!(ClaimableRewards::<T>::get(&caller), BalanceOf::<T>::from(0u32));
+        assert_eq!(<T as Config>::Currency::free_balance(&caller), amount);
+    }
+
+    #[benchmark]
+    fn cancel_offence() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let (_, account_id) = benchmark_validator::<T>(0);
+        let offence_id: OffenceId = 0;
+        PendingOffences::<T>::insert(offence_id, PendingOffence {
+            account_id,
+            kind: OpocOffenceKind::InvalidOutput,
+            report_block_number: U256::from(10),
+        });
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, offence_id);
+
+        assert!(!PendingOffences::<T>::contains_key(offence_id));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn reset_reputation() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let (_, account_id) = benchmark_validator::<T>(0);
+        NodesReputations::<T>::insert(&account_id, NodeReputation {
+            penalty: 100,
+            updated_at_block_number: U256::from(1),
+        });
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, account_id.clone());
+
+        assert!(!NodesReputations::<T>::contains_key(&account_id));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn register_model() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let ai_model_key = AiModelKey::from(1);
+        let backend_name = Data::truncate_from(b"Qwen/QwQ-32B-AWQ".to_vec());
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, ai_model_key, backend_name, AiModelModality::Text, 1, u32::MAX, U256::zero());
+
+        assert!(ModelsRegistry::<T>::contains_key(ai_model_key));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn deprecate_model() -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let ai_model_key = AiModelKey::from(1);
+        ModelsRegistry::<T>::insert(ai_model_key, AiModel {
+            backend_name: Data::truncate_from(b"Qwen/QwQ-32B-AWQ".to_vec()),
+            modality: AiModelModality::Text,
+            min_consensus: 1,
+            max_consensus: u32::MAX,
+            activation_block_number: U256::zero(),
+            deprecated: false,
+        });
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, ai_model_key);
+
+        assert!(ModelsRegistry::<T>::get(ai_model_key).unwrap().deprecated);
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn replace_model(n: Linear<0, 1_000>) -> Result<(), BenchmarkError> {
+        let origin = T::GovernanceOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+        let ai_model_key = AiModelKey::from(1);
+        let backend_name = Data::truncate_from(b"Qwen/QwQ-32B-AWQ".to_vec());
+        ModelsRegistry::<T>::insert(ai_model_key, AiModel {
+            backend_name: backend_name.clone(),
+            modality: AiModelModality::Text,
+            min_consensus: 1,
+            max_consensus: u32::MAX,
+            activation_block_number: U256::zero(),
+            deprecated: false,
+        });
+        AIModels::<T>::insert(ai_model_key, (backend_name.clone(), Data::default(), U256::zero()));
+        for seed in 0..n {
+            let (_, account_id) = benchmark_validator::<T>(seed);
+            NodesModels::<T>::insert(&account_id, benchmark_ai_models());
+        }
+        let new_backend_name = Data::truncate_from(b"Qwen/QwQ-32B-AWQ-v2".to_vec());
+
+        #[extrinsic_call]
+        _(origin as T::RuntimeOrigin, ai_model_key, new_backend_name.clone(), AiModelModality::Text, 1, u32::MAX, U256::zero(), n);
+
+        assert_eq!(ModelsRegistry::<T>::get(ai_model_key).unwrap().backend_name, new_backend_name);
+        assert!(NodesModels::<T>::iter_values().all(|ai_model_keys| !ai_model_keys.contains(&ai_model_key)));
+
+        Ok(())
+    }
+
+    #[benchmark]
+    fn cancel_request() -> Result<(), BenchmarkError> {
+        // The request is not assigned yet, its fee is held in escrow and a callback is registered
+        let request_id = RequestId::from(1);
+        let address = Address::repeat_byte(0xAA);
+        let caller = T::AddressToAccountId::convert(address);
+        <T as Config>::Currency::make_free_balance_be(&caller, BalanceOf::<T>::max_value() / 2u32.into());
+        Inputs::<T>::insert(request_id, AgentRequest {
+            block_number: frame_system::Pallet::<T>::block_number().into(),
+            nft_required_consensus: U256::from(BENCHMARK_REQUEST_VALIDATORS),
+            nft_execution_max_time: U256::from(10),
+            ..Default::default()
+        });
+        RequestsAddresses::<T>::insert(request_id, address);
+        Pallet::<T>::fees_reserve(&request_id, &caller, &U256::from(BENCHMARK_REQUEST_VALIDATORS), &U256::from(10), 0)?;
+        Pallet::<T>::callbacks_register(&request_id, &caller, Address::repeat_byte(1), [0u8; 4], T::MaxCallbackGas::get())?;
+
+        #[extrinsic_call]
+        _(RawOrigin::Signed(caller.clone()), request_id);
+
+        assert_eq!(RequestsStatuses::<T>::get(request_id), Some(RequestStatus::Cancelled));
+        assert!(RequestsFees::<T>::get(request_id).is_none());
+
+        Ok(())
+    }
+
     impl_benchmark_test_suite!(
         Pallet,
         crate::mock::new_test_ext(),
         crate::mock::Test
     );
-}
\ No newline at end of file
+}
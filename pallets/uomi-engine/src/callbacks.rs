@@ -0,0 +1,171 @@
+use frame_support::{
+    ensure,
+    pallet_prelude::Weight,
+    traits::{Currency, Get, ReservableCurrency},
+};
+use sp_core::{H160, U256};
+use sp_runtime::{
+    traits::{SaturatedConversion, Saturating},
+    DispatchError, DispatchResult,
+};
+use sp_std::{vec, vec::Vec};
+
+use crate::{
+    types::{Address, AgentResult, RequestCallback, RequestCallbackResult, RequestId},
+    BalanceOf, CallbacksResults, Config, Error, Event, Outputs, Pallet, RequestsCallbacks,
+};
+
+// RequestCallbackExecutor is implemented by the runtime to execute the EVM callbacks of the completed requests.
+// It returns the gas used by the callback and the result of the callback, gas_to_weight converts the gas of the callbacks to weight.
+// NOTE: The callbacks are executed by on_idle, the weight of the gas limit is checked against the remaining weight of the block before the execution.
+pub trait RequestCallbackExecutor<T: Config> {
+    fn gas_to_weight(gas: u64) -> Weight;
+    fn execute(target: Address, input: Vec<u8>, gas_limit: u64) -> (u64, DispatchResult);
+}
+
+// The default executor rejects every callback without using gas.
+impl<T: Config> RequestCallbackExecutor<T> for () {
+    fn gas_to_weight(_gas: u64) -> Weight {
+        Weight::zero()
+    }
+
+    fn execute(_target: Address, _input: Vec<u8>, _gas_limit: u64) -> (u64, DispatchResult) {
+        (0, Err(DispatchError::Other("Callbacks are not supported")))
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    // This function is used to register the callback of a request and to reserve the fee of its gas limit from the payer.
+    pub fn callbacks_register(
+        request_id: &RequestId,
+        payer: &T::AccountId,
+        target: Address,
+        selector: [u8; 4],
+        gas_limit: u64
+    ) -> DispatchResult {
+        ensure!(target != H160::zero(), Error::<T>::InvalidCallback);
+        ensure!(gas_limit > 0 && gas_limit <= T::MaxCallbackGas::get(), Error::<T>::InvalidCallback);
+
+        let fee = T::CallbackFeePerGas::get().saturating_mul(gas_limit.saturated_into());
+        <T as Config>::Currency::reserve(payer, fee).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+        RequestsCallbacks::<T>::insert(request_id, RequestCallback {
+            target,
+            selector,
+            gas_limit,
+            payer: payer.clone(),
+            fee,
+        });
+
+        Self::deposit_event(Event::RequestCallbackRegistered {
+            request_id: *request_id,
+            target,
+            gas_limit,
+            fee,
+        });
+
+        Ok(())
+    }
+
+    // This function returns the maximum weight of the execution of a callback with the given gas limit.
+    pub fn callbacks_max_weight(gas_limit: u64) -> Weight {
+        T::CallbackExecutor::gas_to_weight(gas_limit).saturating_add(T::DbWeight::get().reads_writes(4, 4))
+    }
+
+    // This function is used to execute the callback of a completed request with its output stored on Outputs, it returns the weight used.
+    // The fee of the gas used by the callback is burned and the remainder is refunded to the payer.
+    // NOTE: A failed callback does not fail the completion of the request, the result is recorded on CallbacksResults.
+    pub fn callbacks_execute(request_id: &RequestId) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let callback = match RequestsCallbacks::<T>::take(request_id) {
+            Some(callback) => callback,
+            None => return db_weight.reads(1),
+        };
+        let result = match Outputs::<T>::try_get(request_id) {
+            Ok(result) => result,
+            Err(_) => { // The output has been pruned before the execution, the callback is not executed
+                Self::callbacks_reject(request_id, callback, Error::<T>::RequestNotFound.into());
+                return db_weight.reads_writes(4, 4);
+            }
+        };
+
+        let input = Self::callbacks_encode_input(&callback.selector, request_id, &result);
+        let (used_gas, callback_result) = T::CallbackExecutor::execute(callback.target, input, callback.gas_limit);
+        let used_gas = used_gas.min(callback.gas_limit);
+        if let Err(error) = callback_result {
+            log::info!("UOMI-ENGINE: Callback of request {:?} failed: {:?}", request_id, error);
+        }
+
+        let used_fee: BalanceOf<T> = T::CallbackFeePerGas::get()
+            .saturating_mul(used_gas.saturated_into())
+            .min(callback.fee);
+        let _ = <T as Config>::Currency::slash_reserved(&callback.payer, used_fee);
+        <T as Config>::Currency::unreserve(&callback.payer, callback.fee.saturating_sub(used_fee));
+
+        CallbacksResults::<T>::insert(request_id, RequestCallbackResult {
+            success: callback_result.is_ok(),
+            used_gas,
+        });
+
+        Self::deposit_event(Event::RequestCallbackExecuted {
+            request_id: *request_id,
+            target: callback.target,
+            used_gas,
+            result: callback_result,
+        });
+
+        T::CallbackExecutor::gas_to_weight(used_gas).saturating_add(db_weight.reads_writes(4, 4))
+    }
+
+    // This function is used to close the callback of a completed request without executing it, the fee of the gas is refunded to the payer.
+    pub fn callbacks_drop(request_id: &RequestId, error: DispatchError) {
+        if let Some(callback) = RequestsCallbacks::<T>::take(request_id) {
+            Self::callbacks_reject(request_id, callback, error);
+        }
+    }
+
+    fn callbacks_reject(request_id: &RequestId, callback: RequestCallback<T::AccountId, BalanceOf<T>>, error: DispatchError) {
+        <T as Config>::Currency::unreserve(&callback.payer, callback.fee);
+        CallbacksResults::<T>::insert(request_id, RequestCallbackResult {
+            success: false,
+            used_gas: 0,
+        });
+        Self::deposit_event(Event::RequestCallbackExecuted {
+            request_id: *request_id,
+            target: callback.target,
+            used_gas: 0,
+            result: Err(error),
+        });
+    }
+
+    // This function is used to refund the fee of the gas of the callback of a request closed without an output, the callback is not executed.
+    pub fn callbacks_refund(request_id: &RequestId) {
+        if let Some(callback) = RequestsCallbacks::<T>::take(request_id) {
+            <T as Config>::Currency::unreserve(&callback.payer, callback.fee);
+        }
+    }
+
+    // This function is used to build the ABI encoded input of a callback:
+    // selector ++ abi.encode(uint256 requestId, bytes output, uint256 totalExecutions, uint256 totalConsensus)
+    // NOTE: The output of a request offloaded to IPFS is empty, the contract can read its CID with get_agent_output_cid.
+    pub fn callbacks_encode_input(selector: &[u8; 4], request_id: &RequestId, result: &AgentResult) -> Vec<u8> {
+        let word = |value: U256| {
+            let mut word = [0u8; 32];
+            value.to_big_endian(&mut word);
+            word
+        };
+        let output = &result.output_data;
+        let padding = (32 - output.len() % 32) % 32;
+
+        let mut input = selector.to_vec();
+        input.extend_from_slice(&word(*request_id));
+        input.extend_from_slice(&word(U256::from(4 * 32))); // offset of the output
+        input.extend_from_slice(&word(U256::from(result.total_executions)));
+        input.extend_from_slice(&word(U256::from(result.total_consensus)));
+        input.extend_from_slice(&word(U256::from(output.len())));
+        input.extend_from_slice(output);
+        input.extend_from_slice(&vec![0u8; padding]);
+
+        input
+    }
+}
@@ -1,4 +1,5 @@
 use frame_support::inherent::InherentIdentifier;
+use sp_runtime::Perbill;
 use crate::types::Version;
 
 pub const PALLET_VERSION:Version = 5;
@@ -7,7 +8,85 @@ pub const PALLET_INHERENT_IDENTIFIER:InherentIdentifier = *b"uomiengi";
 // This is the maximum number of inputs that can be managed in a single block by OPoC and offchain workers.
 pub const MAX_INPUTS_MANAGED_PER_BLOCK:usize = 100;
 
+// This is the fraction of the maximum weight of a block that can be used by the operations computed by the OPoC in a single block.
+pub const MAX_OPOC_BLOCK_WEIGHT:Perbill = Perbill::from_percent(25);
+
+// This is the fraction of the maximum weight of a block that can be used by a single agent transaction or callback executed by on_idle.
+pub const MAX_EXECUTION_BLOCK_WEIGHT:Perbill = Perbill::from_percent(50);
+
 // This is the maximum number of blocks that a node have to complete an update of it's running version.
 pub const MAX_BLOCKS_TO_WAIT_NODE_UPDATE:u32 = 100;
 
-pub const TEMP_BLOCK_FOR_NEW_OPOC: i32 = 1950000; // For finney update. remove on turing
\ No newline at end of file
+// This is the maximum number of offences that can be reported to the offences pallet in a single block.
+pub const MAX_OFFENCES_REPORTED_PER_BLOCK:u32 = 10;
+
+pub const TEMP_BLOCK_FOR_NEW_OPOC: i32 = 1950000; // For finney update. remove on turing
+
+// This is the reputation score of a validator without penalties.
+pub const MAX_REPUTATION:u32 = 1000;
+
+// This is the penalty added to the reputation of a validator for an output that does not match the consensus.
+pub const REPUTATION_PENALTY_INVALID_OUTPUT:u32 = 600;
+
+// This is the penalty added to the reputation of a validator for an assignment in timeout.
+pub const REPUTATION_PENALTY_TIMEOUT:u32 = 250;
+
+// This is the reputation score under which a validator is excluded from the assignments, if there are enough other validators.
+pub const REPUTATION_EXCLUSION_THRESHOLD:u32 = 500;
+
+// This is the key of the offchain local storage where the node stores the AiServiceConfig.
+pub const AI_SERVICE_CONFIG_STORAGE_KEY:&[u8] = b"uomi-engine::ai-service-config";
+
+// This is the URL of the AI service used when the node does not store a different one.
+pub const DEFAULT_AI_SERVICE_URL:&[u8] = b"http://127.0.0.1:8888";
+
+// This is the key of the offchain local storage where the node stores the maximum number of agents executed in parallel.
+pub const MAX_WORKERS_STORAGE_KEY:&[u8] = b"uomi-engine::max-workers";
+
+// This is the number of agents executed in parallel when the node does not store a different one.
+pub const DEFAULT_MAX_WORKERS:u32 = 1;
+
+// This is the maximum number of agents executed in parallel by a node.
+pub const MAX_WORKERS:u32 = 32;
+
+// This is the key of the offchain local storage where the node stores the requests it has started to execute.
+pub const STARTED_REQUESTS_STORAGE_KEY:&[u8] = b"uomi-engine::started-requests";
+
+// This is the prefix hashed with the NFT ID of an agent to derive the address of the agent.
+pub const AGENT_ADDRESS_PREFIX:&[u8] = b"uomi-engine::agent";
+
+// This is the prefix of the keys of the offchain local storage where the node stores the ExecutionLogs of the requests.
+pub const EXECUTION_LOGS_STORAGE_PREFIX:&[u8] = b"uomi-engine::execution-logs::";
+
+// This is the maximum size in bytes of a single line logged by an agent with console_log.
+pub const MAX_EXECUTION_LOG_LINE_SIZE:usize = 1024;
+
+// This is the maximum total size in bytes of the lines logged by an agent in a single execution.
+pub const MAX_EXECUTION_LOGS_SIZE:usize = 64 * 1024;
+
+// This is the maximum number of host call timings recorded in a single execution.
+pub const MAX_EXECUTION_LOGS_HOST_CALLS:usize = 256;
+
+// This is the fuel given to an agent for every block of the maximum execution time of the request.
+pub const FUEL_PER_EXECUTION_BLOCK:u64 = 1_000_000_000;
+
+// This is the maximum fuel given to an agent in a single execution, whatever the maximum execution time of the request.
+pub const MAX_EXECUTION_FUEL:u64 = 100 * FUEL_PER_EXECUTION_BLOCK;
+
+// This is the maximum size in bytes of the linear memory of an agent.
+pub const MAX_AGENT_MEMORY_SIZE:usize = 512 * 1024 * 1024;
+
+// This is the maximum number of elements of a table of an agent.
+pub const MAX_AGENT_TABLE_ELEMENTS:usize = 100_000;
+
+// This is the number of blocks between the starts of two sweeps of the NodesWorks of the requests no more in Inputs.
+pub const NODES_WORKS_SWEEP_INTERVAL:u32 = 600;
+
+// This is the maximum size in bytes of the raw storage key saved as cursor of the NodesWorks sweep.
+pub const MAX_STORAGE_KEY_SIZE:u32 = 256;
+
+// This is the number of blocks the validators have to reveal their outputs after the expiration of the last assignment of the request.
+pub const OPOC_REVEAL_DURATION:u32 = 10;
+
+// This is the key of the offchain local storage where the node stores the outputs committed and not revealed yet, with their salts.
+pub const PENDING_REVEALS_STORAGE_KEY:&[u8] = b"uomi-engine::pending-reveals";
@@ -0,0 +1,106 @@
+use frame_support::{
+    pallet_prelude::Weight,
+    traits::Get,
+};
+
+use crate::{
+    consts::MAX_EXECUTION_BLOCK_WEIGHT,
+    types::{ExecutionId, PendingExecution},
+    Config, Error, Event, NextExecutionId, NextExecutionToRun, Pallet, PendingExecutions,
+};
+
+impl<T: Config> Pallet<T> {
+    // This function is used to queue an execution of a completed request, the execution is run by on_idle out of the inherent.
+    pub fn executions_schedule(execution: PendingExecution) {
+        let execution_id = NextExecutionId::<T>::get();
+        PendingExecutions::<T>::insert(execution_id, execution);
+        NextExecutionId::<T>::put(execution_id + 1);
+    }
+
+    // Executions entry point, called by on_idle to run the pending executions in the same order they are scheduled.
+    // An execution is run only if the remaining weight covers its maximum weight, the weight reported is the weight actually used.
+    // NOTE: The executions heavier than MAX_EXECUTION_BLOCK_WEIGHT of a block are dropped, otherwise they would stop the queue forever.
+    pub fn executions_run(remaining_weight: Weight) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let step_weight = db_weight.reads_writes(1, 1); // The read and the removal of a pending execution
+        let mut used_weight = db_weight.reads_writes(2, 1); // The read of the ids and the write of the cursor
+        if used_weight.any_gt(remaining_weight) {
+            return Weight::zero();
+        }
+
+        let max_execution_weight = MAX_EXECUTION_BLOCK_WEIGHT * T::BlockWeights::get().max_block;
+        let next_execution_id = NextExecutionId::<T>::get();
+        let mut execution_id = NextExecutionToRun::<T>::get();
+
+        while execution_id < next_execution_id {
+            if used_weight.saturating_add(step_weight).any_gt(remaining_weight) {
+                break;
+            }
+            let execution = match PendingExecutions::<T>::get(execution_id) {
+                Some(execution) => execution,
+                None => {
+                    used_weight = used_weight.saturating_add(db_weight.reads(1));
+                    execution_id += 1;
+                    continue;
+                }
+            };
+
+            let max_weight = Self::executions_max_weight(&execution);
+            if max_weight.any_gt(max_execution_weight) {
+                PendingExecutions::<T>::remove(execution_id);
+                Self::executions_drop(execution_id, execution);
+                used_weight = used_weight.saturating_add(step_weight);
+                execution_id += 1;
+                continue;
+            }
+            if used_weight.saturating_add(step_weight).saturating_add(max_weight).any_gt(remaining_weight) {
+                break;
+            }
+
+            PendingExecutions::<T>::remove(execution_id);
+            let execution_weight = Self::executions_execute(execution);
+            used_weight = used_weight.saturating_add(step_weight).saturating_add(execution_weight.min(max_weight));
+            execution_id += 1;
+        }
+
+        NextExecutionToRun::<T>::put(execution_id);
+
+        used_weight
+    }
+
+    // This function returns the maximum weight of an execution, used to check the remaining weight before running it.
+    fn executions_max_weight(execution: &PendingExecution) -> Weight {
+        match execution {
+            PendingExecution::AgentTransaction { transaction, .. } => Self::transactions_max_weight(transaction),
+            PendingExecution::Callback { gas_limit, .. } => Self::callbacks_max_weight(*gas_limit),
+        }
+    }
+
+    // This function is used to run an execution, it returns the weight used.
+    fn executions_execute(execution: PendingExecution) -> Weight {
+        match execution {
+            PendingExecution::AgentTransaction { request_id, nft_id, transaction } => {
+                Self::transactions_execute(&request_id, &nft_id, &transaction)
+            },
+            PendingExecution::Callback { request_id, .. } => Self::callbacks_execute(&request_id),
+        }
+    }
+
+    // This function is used to close an execution heavier than the maximum weight without running it.
+    fn executions_drop(execution_id: ExecutionId, execution: PendingExecution) {
+        log::info!("UOMI-ENGINE: Execution {:?} dropped, its weight exceeds the maximum weight of an execution", execution_id);
+        match execution {
+            PendingExecution::AgentTransaction { request_id, nft_id, .. } => {
+                Self::deposit_event(Event::AgentTransactionExecuted {
+                    request_id,
+                    nft_id,
+                    address: Self::transactions_agent_address(&nft_id),
+                    result: Err(Error::<T>::ExecutionWeightExceeded.into()),
+                });
+            },
+            PendingExecution::Callback { request_id, .. } => {
+                Self::callbacks_drop(&request_id, Error::<T>::ExecutionWeightExceeded.into());
+            },
+        }
+    }
+}
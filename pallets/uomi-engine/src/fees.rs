@@ -0,0 +1,183 @@
+use frame_support::{
+    pallet_prelude::{DispatchError, DispatchResult},
+    traits::{BalanceStatus, Currency, Get, ReservableCurrency},
+};
+use sp_core::U256;
+use sp_runtime::traits::{Saturating, Zero};
+use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
+
+use crate::{
+    types::{AiModelKey, RequestFee, RequestId},
+    BalanceOf, Config, Error, Event, ModelsFees, ModelsMaxFee, NodesOutputsModels, Pallet, RequestsFees, RequestsModelsFees,
+};
+
+impl<T: Config> Pallet<T> {
+    // This function is used to calculate the fee of a request.
+    // It returns the total amount to reserve, the amount reserved for every execution and the model fee included in it.
+    // NOTE: The AI models used by an agent are known only after the execution, so the model fee is an upper-bound escrow calculated
+    // with the most expensive model, the difference with the fee of the models actually called is refunded on completion by fees_pay.
+    pub fn fees_calculate(
+        min_validators: &U256,
+        min_blocks: &U256,
+        input_size: usize
+    ) -> Result<(BalanceOf<T>, BalanceOf<T>, BalanceOf<T>), DispatchError> {
+        let min_validators: u32 = (*min_validators).try_into().map_err(|_| Error::<T>::InvalidFeeParameters)?;
+        let min_blocks: u32 = (*min_blocks).try_into().map_err(|_| Error::<T>::InvalidFeeParameters)?;
+        let input_size: u32 = input_size.try_into().map_err(|_| Error::<T>::InvalidFeeParameters)?;
+
+        let model_fee = ModelsMaxFee::<T>::get();
+
+        let execution_fee = T::RequestFeePerValidator::get()
+            .saturating_add(model_fee)
+            .saturating_add(T::RequestFeePerBlock::get().saturating_mul(min_blocks.into()));
+
+        let amount = T::RequestBaseFee::get()
+            .saturating_add(T::RequestFeePerByte::get().saturating_mul(input_size.into()))
+            .saturating_add(execution_fee.saturating_mul(min_validators.into()));
+
+        Ok((amount, execution_fee, model_fee))
+    }
+
+    // This function is used to reserve the fee of a request from the payer and hold it in escrow.
+    pub fn fees_reserve(
+        request_id: &RequestId,
+        payer: &T::AccountId,
+        min_validators: &U256,
+        min_blocks: &U256,
+        input_size: usize
+    ) -> DispatchResult {
+        let (amount, execution_fee, model_fee) = Self::fees_calculate(min_validators, min_blocks, input_size)?;
+        if amount.is_zero() { // Nothing to hold in escrow if the request is free
+            return Ok(());
+        }
+
+        <T as Config>::Currency::reserve(payer, amount).map_err(|_| Error::<T>::InsufficientBalance)?;
+
+        RequestsFees::<T>::insert(request_id, RequestFee {
+            payer: payer.clone(),
+            amount,
+            execution_fee,
+            min_validators: min_validators.low_u32(),
+        });
+        if !model_fee.is_zero() {
+            RequestsModelsFees::<T>::insert(request_id, model_fee);
+        }
+
+        Self::deposit_event(Event::RequestFeeReserved {
+            request_id: request_id.clone(),
+            account_id: payer.clone(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // This function is used to pay the fee held in escrow to the validators that produced the consensus output of a request.
+    // Every validator receives an equal share of the base fee, of the input fee and of the executions fees up to min_validators executions.
+    // The model fee of every execution is the fee of the models actually called, capped by the model fee held in escrow.
+    // Everything not paid to the validators (missing executions, unused model fee, rounding, failed transfers) is refunded to the payer.
+    pub fn fees_pay(request_id: &RequestId, validators: &Vec<T::AccountId>) -> DispatchResult {
+        let fee = match RequestsFees::<T>::take(request_id) {
+            Some(fee) => fee,
+            None => { // Nothing to pay if the request is free
+                return Ok(());
+            }
+        };
+        let execution_fee = match RequestsModelsFees::<T>::take(request_id) {
+            Some(model_fee) => {
+                let used_model_fee = Self::fees_used_model_fee(request_id, validators).min(model_fee);
+                fee.execution_fee.saturating_sub(model_fee).saturating_add(used_model_fee)
+            },
+            None => fee.execution_fee,
+        };
+
+        let mut paid = BalanceOf::<T>::zero();
+
+        if !validators.is_empty() {
+            let validators_count = validators.len() as u32;
+            let executions_paid = validators_count.min(fee.min_validators);
+            let executions_fee = fee.execution_fee.saturating_mul(fee.min_validators.into());
+            let request_fee = fee.amount.saturating_sub(executions_fee);
+            let total = request_fee.saturating_add(execution_fee.saturating_mul(executions_paid.into()));
+            let amount_per_validator = total / validators_count.into();
+
+            if !amount_per_validator.is_zero() {
+                for validator in validators.iter() {
+                    match <T as Config>::Currency::repatriate_reserved(
+                        &fee.payer,
+                        validator,
+                        amount_per_validator,
+                        BalanceStatus::Free
+                    ) {
+                        Ok(missing) => {
+                            let amount = amount_per_validator.saturating_sub(missing);
+                            paid = paid.saturating_add(amount);
+                            Self::deposit_event(Event::RequestFeePaid {
+                                request_id: request_id.clone(),
+                                account_id: validator.clone(),
+                                amount,
+                            });
+                        }
+                        Err(error) => {
+                            log::error!(
+                                "UOMI-ENGINE: Failed to pay fee of request {:?} to validator {:?}. error: {:?}",
+                                request_id,
+                                validator,
+                                error
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        let remainder = fee.amount.saturating_sub(paid);
+        if !remainder.is_zero() {
+            let not_refunded = <T as Config>::Currency::unreserve(&fee.payer, remainder);
+            Self::deposit_event(Event::RequestFeeRefunded {
+                request_id: request_id.clone(),
+                account_id: fee.payer.clone(),
+                amount: remainder.saturating_sub(not_refunded),
+            });
+        }
+
+        Ok(())
+    }
+
+    // This function is used to calculate the model fee of an execution from the AI models called by the validators of the consensus.
+    // Every validator reports the models called with its output, the lowest fee reported is used so a validator can not raise it.
+    // NOTE: The validators of the consensus usually report the same models, so the fee of every model is read only once.
+    fn fees_used_model_fee(request_id: &RequestId, validators: &Vec<T::AccountId>) -> BalanceOf<T> {
+        let mut models_fees = BTreeMap::<AiModelKey, BalanceOf<T>>::new();
+        validators
+            .iter()
+            .map(|validator| {
+                NodesOutputsModels::<T>::get(request_id, validator)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|ai_model_key| *models_fees.entry(ai_model_key).or_insert_with(|| ModelsFees::<T>::get(ai_model_key)))
+                    .max()
+                    .unwrap_or_else(Zero::zero)
+            })
+            .min()
+            .unwrap_or_else(Zero::zero)
+    }
+
+    // This function is used to refund to the payer the fee held in escrow for a request closed without an output.
+    pub fn fees_refund(request_id: &RequestId) {
+        RequestsModelsFees::<T>::remove(request_id);
+        let fee = match RequestsFees::<T>::take(request_id) {
+            Some(fee) => fee,
+            None => { // Nothing to refund if the request is free
+                return;
+            }
+        };
+
+        let not_refunded = <T as Config>::Currency::unreserve(&fee.payer, fee.amount);
+        Self::deposit_event(Event::RequestFeeRefunded {
+            request_id: request_id.clone(),
+            account_id: fee.payer.clone(),
+            amount: fee.amount.saturating_sub(not_refunded),
+        });
+    }
+}
@@ -9,4 +9,6 @@ pub trait IpfsInterface<T: frame_system::Config> {
     fn get_cid_status(cid: &Cid) -> Result<(ExpirationBlockNumber, UsableFromBlockNumber), DispatchError>;
     fn get_file(cid: &Cid) -> Result<Vec<u8>, sp_runtime::offchain::http::Error>;
     fn pin_file(origin: <T as frame_system::Config>::RuntimeOrigin, cid: Cid, duration: BlockNumberFor<T>) -> DispatchResult;
+    fn add_file(data: Vec<u8>) -> Result<Cid, sp_runtime::offchain::http::Error>;
+    fn temporary_pin(cid: &Cid, duration: BlockNumberFor<T>) -> ExpirationBlockNumber;
 }
\ No newline at end of file
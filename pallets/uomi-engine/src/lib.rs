@@ -9,21 +9,36 @@ mod mock;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
-mod consts;
-mod types;
+pub mod consts;
+pub mod types;
 mod payloads;
 mod offchain;
 mod opoc;
 mod aimodelscalc;
+mod api;
+mod fees;
+mod requests;
+mod retention;
+mod executions;
+mod rewards;
+mod reputation;
+pub mod migrations;
+pub mod offences;
+pub mod transactions;
+pub mod callbacks;
 pub mod ipfs;
 pub mod crypto;
+#[cfg(feature = "std")]
+pub mod wasm;
+#[cfg(feature = "std")]
+pub mod wasm_cache;
 
 pub use pallet::*; // Re-export pallet items so that they can be accessed from the crate namespace.
 pub mod weights;
 pub use weights::*;
 
 use frame_support::pallet_prelude::DispatchClass;
-use codec::{Decode, Encode};
+use codec::{Codec, Decode, Encode};
 use frame_support::{
     BoundedVec,
     Blake2_128Concat,
@@ -32,18 +47,20 @@ use frame_support::{
     inherent::{InherentData, InherentIdentifier, IsFatalError, ProvideInherent},
     pallet_prelude::{
         DispatchError, DispatchResultWithPostInfo, Hooks, InvalidTransaction, IsType, 
-        MaxEncodedLen, Member, RuntimeDebug, StorageDoubleMap, StorageMap, 
-        TransactionPriority, TransactionSource, TransactionValidity, ValidTransaction, 
-        ValidateUnsigned, ValueQuery,
+        MaxEncodedLen, Member, OptionQuery, RuntimeDebug, StorageDoubleMap, StorageMap, 
+        TransactionPriority, TransactionSource, TransactionValidity, TransactionValidityError, 
+        ValidTransaction, ValidateUnsigned, ValueQuery,
     },
     parameter_types,
     storage::types::StorageValue,
-    traits::Randomness,
+    traits::{ConstU32, StorageVersion},
+    weights::Weight,
+    traits::{Currency, EnsureOrigin, Get, Imbalance, Randomness, ReservableCurrency, ValidatorSetWithIdentification},
 };
 use frame_system::{
     ensure_signed,
     ensure_none,
-    offchain::{AppCrypto, CreateSignedTransaction, Signer},
+    offchain::{AppCrypto, CreateSignedTransaction, SignedPayload, Signer},
     pallet_prelude::{BlockNumberFor, OriginFor},
 };
 use pallet_ipfs::{
@@ -52,20 +69,23 @@ use pallet_ipfs::{
     types::{Cid, ExpirationBlockNumber, UsableFromBlockNumber},
 };
 use pallet_session::{self as session};
-use sp_core::{H160, U256};
+use sp_core::{H160, H256, U256};
 use sp_runtime::{
-    traits::IdentifyAccount,
+    traits::{Convert, IdentifyAccount, Zero},
     DispatchResult,
+    Perbill,
 };
+use sp_staking::{offence::ReportOffence, SessionIndex};
 use sp_std::{
     collections::btree_map::BTreeMap,
     marker::PhantomData,
     vec,
     vec::Vec,
 };
-use types::{Address, AiModelKey, BlockNumber, Data, NftId, RequestId, Version};
+use types::{Address, AgentRequest, AgentResult, AgentTransaction, AiModel, AiModelKey, AiModelModality, BlockNumber, Data, EraIndex, ExecutionId, MigrationCursor, NftId, NodeOutput, NodeReputation, OffenceId, OpocOffenceKind, OpocOperations, OutputCid, PendingExecution, PendingOffence, RequestAssignment, RequestCallback, RequestCallbackResult, RequestFailureReason, RequestFee, RequestId, RequestInfo, RequestStatus, Version};
 
 use crate::ipfs::IpfsInterface;
+use crate::offences::{IdentificationTuple, OpocOffence};
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
 pub struct EmptyInherent; 
@@ -98,10 +118,18 @@ pub mod pallet {
     parameter_types! {
         pub const MaxDataSize: u32 = 1024 * 1024; // bytes
         pub const BlockTime: u64 = 3; // seconds
+        pub const MaxModelsPerNode: u32 = 64; // AI models advertised by a node
     }
+
+    // The in-code storage version, see migrations.rs for the migrations between versions.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(6);
+
+    // Balance type of the currency used to pay the requests fees.
+    pub type BalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
     
     // Pallet
     #[pallet::pallet]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(PhantomData<T>);
 
     // Config
@@ -113,6 +141,44 @@ pub mod pallet {
 		type Randomness: Randomness<Option<<Self as frame_system::Config>::Hash>, BlockNumberFor<Self>>;
         type IpfsPallet: ipfs::IpfsInterface<Self>;
         type InherentDataType: Default + Encode + Decode + Clone + Parameter + Member + MaxEncodedLen;
+        type Currency: ReservableCurrency<Self::AccountId>; // Currency used to reserve and pay the requests fees.
+        type AddressToAccountId: Convert<Address, Self::AccountId>; // Convert the address of a request to the account id paying its fee.
+        type GovernanceOrigin: EnsureOrigin<Self::RuntimeOrigin>; // Origin allowed to manage the engine parameters.
+        #[pallet::constant]
+        type RequestBaseFee: Get<BalanceOf<Self>>; // Fixed fee paid for every request.
+        #[pallet::constant]
+        type RequestFeePerByte: Get<BalanceOf<Self>>; // Fee paid for every byte of input_data.
+        #[pallet::constant]
+        type RequestFeePerValidator: Get<BalanceOf<Self>>; // Fee paid for every execution required by min_validators.
+        #[pallet::constant]
+        type RequestFeePerBlock: Get<BalanceOf<Self>>; // Fee paid for every execution for every block of min_blocks.
+        type ValidatorSet: ValidatorSetWithIdentification<Self::AccountId>; // Validator set used to identify the offenders.
+        type ReportOffence: ReportOffence<Self::AccountId, IdentificationTuple<Self>, OpocOffence<IdentificationTuple<Self>>>; // Reporter of the OPoC offences.
+        #[pallet::constant]
+        type InvalidOutputSlashFraction: Get<Perbill>; // Fraction of the stake slashed for an output that does not match the consensus.
+        #[pallet::constant]
+        type TimeoutSlashFraction: Get<Perbill>; // Fraction of the stake slashed for an assignment in timeout.
+        #[pallet::constant]
+        type OffenceReportDelay: Get<u32>; // Number of blocks the governance has to cancel an offence before it is reported.
+        #[pallet::constant]
+        type ReputationDecayWindow: Get<u32>; // Number of blocks after which the penalties of a validator are fully decayed.
+        #[pallet::constant]
+        type ModelActivationThreshold: Get<Perbill>; // Fraction of the stake of the active validators that must serve a model to activate it.
+        #[pallet::constant]
+        type OutputsPinDuration: Get<u32>; // Number of blocks the outputs offloaded to IPFS are pinned by the validators.
+        type AgentTransactionExecutor: transactions::AgentTransactionExecutor<Self>; // Executor of the transactions emitted by the agents.
+        type CallbackExecutor: callbacks::RequestCallbackExecutor<Self>; // Executor of the EVM callbacks of the completed requests.
+        #[pallet::constant]
+        type CallbackFeePerGas: Get<BalanceOf<Self>>; // Fee prepaid for every unit of gas of a callback.
+        #[pallet::constant]
+        type MaxCallbackGas: Get<u64>; // Maximum gas limit of a callback.
+        #[pallet::constant]
+        type RequestMaxLifetime: Get<u32>; // Number of blocks after which a request not completed is closed as failed by the OPoC.
+        #[pallet::constant]
+        type OutputsRetentionPeriod: Get<u32>; // Number of blocks the history of a closed request is kept before it is pruned, 0 to keep it forever.
+        #[pallet::constant]
+        type ArchiveOutputsHashes: Get<bool>; // True to keep the hash of the outputs pruned, so the contracts can still verify them.
+        type WeightInfo: WeightInfo; // Weights of the calls of the pallet, generated by the benchmarks.
 	}
 
     // Events
@@ -129,12 +195,9 @@ pub mod pallet {
             output_data: Data, // The output data of the request.
             total_executions: u32, // The total executions of the request.
             total_consensus: u32, // The total consensus of the request.
-        },
-        OpocBlacklistAdd {
-            account_id: T::AccountId, // The account ID of the validator.
-        },
-        OpocBlacklistRemove {
-            account_id: T::AccountId, // The account ID of the validator.
+            output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS.
+            output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent.
+            out_of_fuel: bool, // True if the agent exhausted the fuel of the request.
         },
         OpocAssignmentAdd {
             request_id: RequestId, // The request ID.
@@ -149,6 +212,14 @@ pub mod pallet {
             request_id: RequestId, // The request ID.
             account_id: T::AccountId, // The account ID of the validator.
             output_data: Data, // The output data of the request.
+            output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS.
+            output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent.
+            out_of_fuel: bool, // True if the agent exhausted the fuel of the request.
+        },
+        NodeOutputCommitted {
+            request_id: RequestId, // The request ID.
+            account_id: T::AccountId, // The account ID of the validator.
+            output_hash: H256, // The salted hash of the output.
         },
         NodeVersionReceived {
             account_id: T::AccountId, // The account ID of the validator.
@@ -160,6 +231,108 @@ pub mod pallet {
             inference_index: u32, // The inference index.
             inference_proof: Data, // The inference proof.
         },
+        RequestFeeReserved {
+            request_id: RequestId, // The request ID.
+            account_id: T::AccountId, // The account ID of the payer.
+            amount: BalanceOf<T>, // The amount held in escrow.
+        },
+        RequestFeePaid {
+            request_id: RequestId, // The request ID.
+            account_id: T::AccountId, // The account ID of the validator.
+            amount: BalanceOf<T>, // The amount paid to the validator.
+        },
+        RequestFeeRefunded {
+            request_id: RequestId, // The request ID.
+            account_id: T::AccountId, // The account ID of the payer.
+            amount: BalanceOf<T>, // The amount refunded to the payer.
+        },
+        ModelFeeSet {
+            ai_model_key: AiModelKey, // The AI model key.
+            fee: BalanceOf<T>, // The fee paid for every execution using the model.
+        },
+        EraRewardsCredited {
+            era: EraIndex, // The era of the rewards.
+            account_id: T::AccountId, // The account ID of the validator.
+            amount: BalanceOf<T>, // The amount credited to the validator.
+        },
+        RewardsClaimed {
+            account_id: T::AccountId, // The account ID of the validator.
+            amount: BalanceOf<T>, // The amount claimed by the validator.
+        },
+        RewardsInflationSet {
+            amount: BalanceOf<T>, // The amount distributed to the validators at the end of every era.
+        },
+        OffenceScheduled {
+            offence_id: OffenceId, // The offence ID.
+            account_id: T::AccountId, // The account ID of the validator.
+            kind: OpocOffenceKind, // The kind of the offence.
+        },
+        OffenceReported {
+            offence_id: OffenceId, // The offence ID.
+            account_id: T::AccountId, // The account ID of the validator.
+            kind: OpocOffenceKind, // The kind of the offence.
+        },
+        OffenceCancelled {
+            offence_id: OffenceId, // The offence ID.
+        },
+        ReputationPenalized {
+            account_id: T::AccountId, // The account ID of the validator.
+            kind: OpocOffenceKind, // The kind of the misbehaviour.
+            score: u32, // The reputation score of the validator after the penalty.
+        },
+        ReputationReset {
+            account_id: T::AccountId, // The account ID of the validator.
+        },
+        ModelRegistered {
+            ai_model_key: AiModelKey, // The AI model key.
+            backend_name: Data, // The name of the model on the AI service of the nodes.
+        },
+        ModelReplaced {
+            ai_model_key: AiModelKey, // The AI model key.
+            backend_name: Data, // The name of the new model on the AI service of the nodes.
+        },
+        ModelDeprecated {
+            ai_model_key: AiModelKey, // The AI model key.
+        },
+        ModelSwitchScheduled {
+            ai_model_key: AiModelKey, // The AI model key.
+            local_name: Data, // The local name of the model after the switch, empty if the model is removed.
+            previous_local_name: Data, // The local name of the model before the switch.
+            available_from_block_number: BlockNumber, // The block number from which the switch is active.
+        },
+        ModelActivated {
+            ai_model_key: AiModelKey, // The AI model key.
+            local_name: Data, // The local name of the model activated, empty if the model is removed.
+        },
+        NodeModelsReceived {
+            account_id: T::AccountId, // The account ID of the validator.
+            ai_model_keys: BoundedVec<AiModelKey, MaxModelsPerNode>, // The AI models served by the node.
+        },
+        AgentTransactionExecuted {
+            request_id: RequestId, // The request ID.
+            nft_id: NftId, // The NFT ID of the agent.
+            address: Address, // The address of the agent executing the transaction.
+            result: DispatchResult, // The result of the transaction.
+        },
+        RequestCallbackRegistered {
+            request_id: RequestId, // The request ID.
+            target: Address, // The contract called on completion.
+            gas_limit: u64, // The gas limit of the callback.
+            fee: BalanceOf<T>, // The amount reserved for the gas limit.
+        },
+        RequestCallbackExecuted {
+            request_id: RequestId, // The request ID.
+            target: Address, // The contract called on completion.
+            used_gas: u64, // The gas used by the callback.
+            result: DispatchResult, // The result of the callback.
+        },
+        RequestFailed {
+            request_id: RequestId, // The request ID.
+            reason: RequestFailureReason, // The reason why the request is closed without an output.
+        },
+        RequestCancelled {
+            request_id: RequestId, // The request ID.
+        },
     }
 
     // Errors
@@ -168,6 +341,32 @@ pub mod pallet {
         SomethingWentWrong,
         InvalidAddress,
         InvalidCid,
+        InsufficientBalance,
+        InvalidFeeParameters,
+        NoRewardsToClaim,
+        OffenceNotFound,
+        ReputationNotFound,
+        InvalidModelKey,
+        InvalidModelBackendName,
+        InvalidModelConsensus,
+        ModelAlreadyRegistered,
+        ModelNotFound,
+        ModelDowngrade,
+        InvalidOutputCid,
+        InvalidCallback,
+        InvalidOutOfFuelOutput,
+        RequestNotFound,
+        RequestNotCancellable,
+        NotRequestOwner,
+        NodeNotAssigned,
+        NodeOutputAlreadyCommitted,
+        NodeOutputNotCommitted,
+        NodeOutputRevealNotOpen,
+        ExecutionWeightExceeded,
+        RewardsBelowExistentialDeposit,
+        InvalidNodesModelsWitness,
+        MinBlocksAboveMaxLifetime,
+        NodeOutputNotMatchingCommitment,
     }
 
     // InherentDidUpdate storage is used to store the execution of the inherent function.
@@ -190,6 +389,69 @@ pub mod pallet {
 		ValueQuery
 	>;
 
+	// NodesOutputsCids storage is used to store the references of the outputs offloaded to IPFS by the validators.
+	// NOTE: The output_data stored on NodesOutputs is empty when the output is offloaded to IPFS.
+	#[pallet::storage]
+	pub type NodesOutputsCids<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RequestId, // request_id
+		Blake2_128Concat,
+		T::AccountId, // account_id
+		OutputCid, // output_cid
+		OptionQuery
+	>;
+
+	// NodesOutputsTransactions storage is used to store the transactions emitted by the agents on the executions of the validators.
+	#[pallet::storage]
+	pub type NodesOutputsTransactions<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RequestId, // request_id
+		Blake2_128Concat,
+		T::AccountId, // account_id
+		AgentTransaction, // output_transaction
+		OptionQuery
+	>;
+
+	// NodesOutputsModels storage is used to store the AI models called by the agents on the executions of the validators.
+	#[pallet::storage]
+	pub type NodesOutputsModels<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RequestId, // request_id
+		Blake2_128Concat,
+		T::AccountId, // account_id
+		BoundedVec<AiModelKey, MaxModelsPerNode>, // ai_models
+		OptionQuery
+	>;
+
+	// NodesOutputsOutOfFuel storage is used to store the validators whose execution of the agent exhausted the fuel of the request.
+	// NOTE: The output_data stored on NodesOutputs is empty when the execution is out of fuel.
+	#[pallet::storage]
+	pub type NodesOutputsOutOfFuel<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RequestId, // request_id
+		Blake2_128Concat,
+		T::AccountId, // account_id
+		bool, // out_of_fuel
+		ValueQuery
+	>;
+
+	// NodesOutputsCommitments storage is used to store the salted hashes of the outputs committed by the validators before they are revealed.
+	// NOTE: The outputs are revealed on NodesOutputs only when all the validators assigned to the request have committed or are in timeout.
+	#[pallet::storage]
+	pub type NodesOutputsCommitments<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		RequestId, // request_id
+		Blake2_128Concat,
+		T::AccountId, // account_id
+		H256, // output_hash
+		OptionQuery
+	>;
+
     // NodesWorks storage is used to store the number of works that have every validator
 	#[pallet::storage]
 	pub type NodesWorks<T: Config> = StorageDoubleMap<
@@ -202,24 +464,14 @@ pub mod pallet {
 		ValueQuery
 	>;
 
-    // NodesTimeouts storage is used to store the number of timeouts that have every validator
-	#[pallet::storage]
-	pub type NodesTimeouts<T: Config> = StorageMap<
-        _,
-        Blake2_128Concat,
-        T::AccountId, // account_id
-        u32, // number_of_timeouts
-        ValueQuery
-	>;
-
-    // NodesErrors storage is used to store the number of errors that have every validator
+    // NodesReputations storage is used to store the penalties of the validators, validators without penalties have no entry.
     #[pallet::storage]
-    pub type NodesErrors<T: Config> = StorageMap<
+    pub type NodesReputations<T: Config> = StorageMap<
         _,
         Blake2_128Concat,
         T::AccountId, // account_id
-        u32, // number_of_errors
-        ValueQuery
+        NodeReputation,
+        OptionQuery
     >;
 
     // NodesVersions storage is used to store the versions of the nodes.
@@ -253,15 +505,7 @@ pub mod pallet {
         _,
         Blake2_128Concat,
         RequestId, // request_id
-        (
-            BlockNumber, // block_number
-            NftId, // nft_id
-            U256, // nft_required_consensus
-            U256, // nft_execution_max_time
-            Cid, // nft_file_cid
-            Data, // input_data
-            Cid, // input_file_cidx
-        ),
+        AgentRequest, // request
         ValueQuery
 	>;
 
@@ -271,21 +515,42 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		RequestId, // request_id
-		(
-			Data, // output_data
-			u32, // total executions
-			u32, // total conseusus
-		),
+		AgentResult, // result
+		ValueQuery
+	>;
+
+	// ActiveValidators storage is used to store the snapshot of the active validators used by the OPoC, it is refreshed on every new session.
+	#[pallet::storage]
+	#[pallet::unbounded]
+	pub type ActiveValidators<T: Config> = StorageValue<
+		_,
+		Vec<T::AccountId>,
 		ValueQuery
 	>;
 
-	// OpocBlacklist storage is used to store the blacklist of validators
+	// ActiveValidatorsSessionIndex storage is used to store the session index of the ActiveValidators snapshot.
 	#[pallet::storage]
-	pub type OpocBlacklist<T: Config> = StorageMap<
+	pub type ActiveValidatorsSessionIndex<T: Config> = StorageValue<
+		_,
+		SessionIndex,
+		OptionQuery
+	>;
+
+	// ActiveValidatorsStakes storage is used to store the snapshot of the stakes of the ActiveValidators.
+	#[pallet::storage]
+	pub type ActiveValidatorsStakes<T: Config> = StorageMap<
 		_,
 		Blake2_128Concat,
 		T::AccountId, // account_id
-		bool, // is_blacklisted
+		u128, // stake
+		ValueQuery
+	>;
+
+	// OpocRandomSeed storage is used to store the random seed used by the OPoC to select the validators of the next block.
+	#[pallet::storage]
+	pub type OpocRandomSeed<T: Config> = StorageValue<
+		_,
+		Data,
 		ValueQuery
 	>;
 
@@ -315,9 +580,298 @@ pub mod pallet {
         ValueQuery
     >;
 
+    // ModelsRegistry storage is used to store the AI models registered by the governance.
+    #[pallet::storage]
+    pub type ModelsRegistry<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        AiModelKey, // ai_model_key
+        AiModel, // model
+        OptionQuery
+    >;
+
+    // NodesModels storage is used to store the AI models served by the nodes.
+    #[pallet::storage]
+    pub type NodesModels<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId, // account_id
+        BoundedVec<AiModelKey, MaxModelsPerNode>, // ai_model_keys
+        ValueQuery
+    >;
+
+    // ModelsPendingActivations storage is used to store the switches of the AI models scheduled and not activated yet.
+    #[pallet::storage]
+    pub type ModelsPendingActivations<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        AiModelKey, // ai_model_key
+        BlockNumber, // available_from_block_number
+        OptionQuery
+    >;
+
+    // ModelsActivatedNames storage is used to store the local names activated for every AI model, they can not be activated again.
+    #[pallet::storage]
+    pub type ModelsActivatedNames<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        AiModelKey, // ai_model_key
+        Blake2_128Concat,
+        [u8; 32], // local_name_hash
+        BlockNumber, // activated_at_block_number
+        OptionQuery
+    >;
+
+    // ModelsFees storage is used to store the fee paid for every execution of a request for each AI model.
+    #[pallet::storage]
+    pub type ModelsFees<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        AiModelKey,
+        BalanceOf<T>, // fee
+        ValueQuery
+    >;
+
+    // ModelsMaxFee storage is used to store the fee of the most expensive AI model, kept by set_model_fee so the fee of a request
+    // is calculated without reading the fees of every model.
+    #[pallet::storage]
+    pub type ModelsMaxFee<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
+
+    // RequestsFees storage is used to store the fees held in escrow for the requests received by the run_request function.
+    #[pallet::storage]
+    pub type RequestsFees<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        RequestFee<T::AccountId, BalanceOf<T>>,
+        OptionQuery
+    >;
+
+    // RequestsModelsFees storage is used to store the model fee held in escrow for every execution of a request.
+    // The model fee is held for the most expensive model and the difference with the fee of the models called is refunded on completion.
+    #[pallet::storage]
+    pub type RequestsModelsFees<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        BalanceOf<T>, // model_fee
+        OptionQuery
+    >;
+
+    // RequestsAddresses storage is used to store the address that sent the requests not closed yet, the only address allowed to cancel them.
+    // NOTE: The address that sent the request is the owner passed by the agent contract, not the contract that pays the fee.
+    #[pallet::storage]
+    pub type RequestsAddresses<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        Address, // address
+        OptionQuery
+    >;
+
+    // RequestsStatuses storage is used to store the status of the requests closed without an output (Failed or Cancelled).
+    // NOTE: The status of the other requests is derived from Inputs, OpocAssignment and Outputs, see requests_status.
+    #[pallet::storage]
+    pub type RequestsStatuses<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        RequestStatus, // status
+        OptionQuery
+    >;
+
+    // FinishedRequests storage is used to store the requests closed on every block, their history is pruned after the retention period.
+    #[pallet::storage]
+    pub type FinishedRequests<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        BlockNumber, // block_number
+        Blake2_128Concat,
+        RequestId, // request_id
+        (),
+        OptionQuery
+    >;
+
+    // RetentionCursor storage is used to store the block number of the next FinishedRequests to prune.
+    #[pallet::storage]
+    pub type RetentionCursor<T: Config> = StorageValue<
+        _,
+        BlockNumber,
+        ValueQuery
+    >;
+
+    // NodesWorksCursor storage is used to store the raw key of the last NodesWorks checked by the running sweep, None if no sweep is running.
+    #[pallet::storage]
+    pub type NodesWorksCursor<T: Config> = StorageValue<
+        _,
+        BoundedVec<u8, ConstU32<{ consts::MAX_STORAGE_KEY_SIZE }>>,
+        OptionQuery
+    >;
+
+    // MigrationsCursor storage is used to store the progress of the migration of the history run by on_idle, None if no migration is running.
+    #[pallet::storage]
+    pub type MigrationsCursor<T: Config> = StorageValue<
+        _,
+        MigrationCursor,
+        OptionQuery
+    >;
+
+    // OutputsHashes storage is used to store the hash of the outputs pruned after the retention period, see retention_output_hash.
+    #[pallet::storage]
+    pub type OutputsHashes<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        H256, // output hash
+        OptionQuery
+    >;
+
+    // RequestsCallbacks storage is used to store the callbacks of the requests not completed yet.
+    #[pallet::storage]
+    pub type RequestsCallbacks<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        RequestCallback<T::AccountId, BalanceOf<T>>,
+        OptionQuery
+    >;
+
+    // CallbacksResults storage is used to store the results of the callbacks executed on the completion of the requests.
+    #[pallet::storage]
+    pub type CallbacksResults<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        RequestId, // request_id
+        RequestCallbackResult,
+        OptionQuery
+    >;
+
+    // RewardsCurrentEra storage is used to store the era in which the validators are currently credited.
+    #[pallet::storage]
+    pub type RewardsCurrentEra<T: Config> = StorageValue<
+        _,
+        EraIndex,
+        ValueQuery
+    >;
+
+    // RewardsInflationPerEra storage is used to store the amount minted and distributed to the validators at the end of every era.
+    #[pallet::storage]
+    pub type RewardsInflationPerEra<T: Config> = StorageValue<
+        _,
+        BalanceOf<T>,
+        ValueQuery
+    >;
+
+    // ErasRewardPoints storage is used to store the points of every validator, a point is credited for every output that matched the consensus.
+    #[pallet::storage]
+    pub type ErasRewardPoints<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        EraIndex, // era
+        Blake2_128Concat,
+        T::AccountId, // account_id
+        u32, // points
+        ValueQuery
+    >;
+
+    // ErasTotalRewardPoints storage is used to store the sum of the points of all the validators in an era.
+    #[pallet::storage]
+    pub type ErasTotalRewardPoints<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        EraIndex, // era
+        u32, // total_points
+        ValueQuery
+    >;
+
+    // ClaimableRewards storage is used to store the rewards credited to the validators and not claimed yet.
+    #[pallet::storage]
+    pub type ClaimableRewards<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        T::AccountId, // account_id
+        BalanceOf<T>, // amount
+        ValueQuery
+    >;
+
+    // PendingOffences storage is used to store the offences detected by the OPoC and not reported yet.
+    #[pallet::storage]
+    pub type PendingOffences<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        OffenceId, // offence_id
+        PendingOffence<T::AccountId>,
+        OptionQuery
+    >;
+
+    // NextOffenceId storage is used to store the id of the next offence detected by the OPoC.
+    #[pallet::storage]
+    pub type NextOffenceId<T: Config> = StorageValue<
+        _,
+        OffenceId,
+        ValueQuery
+    >;
+
+    // NextOffenceToReport storage is used to store the id of the next offence to report to the offences pallet.
+    #[pallet::storage]
+    pub type NextOffenceToReport<T: Config> = StorageValue<
+        _,
+        OffenceId,
+        ValueQuery
+    >;
+
+    // PendingExecutions storage is used to store the executions of the completed requests waiting to be run by on_idle.
+    #[pallet::storage]
+    pub type PendingExecutions<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        ExecutionId, // execution_id
+        PendingExecution,
+        OptionQuery
+    >;
+
+    // NextExecutionId storage is used to store the id of the next execution scheduled by the inherent.
+    #[pallet::storage]
+    pub type NextExecutionId<T: Config> = StorageValue<
+        _,
+        ExecutionId,
+        ValueQuery
+    >;
+
+    // NextExecutionToRun storage is used to store the id of the next execution to run on on_idle.
+    #[pallet::storage]
+    pub type NextExecutionToRun<T: Config> = StorageValue<
+        _,
+        ExecutionId,
+        ValueQuery
+    >;
+
     // Hooks are used to execute code in response to certain events.
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        // The `on_initialize` function is executed at the beginning of each block, it is used to close the eras of the rewards and to report the offences.
+        // NOTE: The inherent is created after on_initialize but checked on the parent state, so on_initialize must not change the state read by
+        // opoc_run and aimodelscalc_run. The rewards and the offences are never read by them, the changes that they read are applied on on_finalize.
+        fn on_initialize(n: BlockNumberFor<T>) -> Weight {
+            let current_block_number: BlockNumber = n.into();
+            Self::rewards_run()
+                .saturating_add(Self::offences_run(current_block_number))
+                .saturating_add(Self::aimodelscalc_activate_weight(current_block_number + 1)) // AI models activated on on_finalize
+                .saturating_add(Self::opoc_snapshot_weight(n)) // OPoC snapshot taken on on_finalize
+        }
+
+        // The `on_idle` function is executed at the end of each block with the remaining weight, it is used to run the executions of the completed
+        // requests and to prune the history of the closed requests.
+        fn on_idle(n: BlockNumberFor<T>, remaining_weight: Weight) -> Weight {
+            let mut used_weight = Self::migrations_run(remaining_weight);
+            used_weight = used_weight.saturating_add(Self::executions_run(remaining_weight.saturating_sub(used_weight)));
+            // The retention is paused until the history of the closed requests is migrated, it is recorded on the block of the upgrade
+            if MigrationsCursor::<T>::exists() {
+                return used_weight.saturating_add(T::DbWeight::get().reads(1));
+            }
+            used_weight.saturating_add(Self::retention_run(n.into(), remaining_weight.saturating_sub(used_weight)))
+        }
+
         // The `offchain_worker` function is executed by the offchain worker in the runtime at the beginning of each block.
         #[cfg(feature = "std")]
         fn offchain_worker(_: BlockNumberFor<T>) {
@@ -347,10 +901,15 @@ pub mod pallet {
             });
         }
 
-		fn on_finalize(_n: BlockNumberFor<T>) {
+		fn on_finalize(n: BlockNumberFor<T>) {
             // Be sure that the InherentDidUpdate is set to true and reset it to false.
             // This is required to be sure that the inherent function is executed once in the block.
             assert!(InherentDidUpdate::<T>::take(), "UOMI-ENGINE: inherent must be updated once in the block");
+
+            // Activate the AI models of the next block and take the snapshot of the state used by the OPoC of the next block.
+            let current_block_number: BlockNumber = n.into();
+            Self::aimodelscalc_activate(current_block_number + 1);
+            Self::opoc_snapshot(current_block_number);
 		}
     }
 
@@ -371,44 +930,82 @@ pub mod pallet {
                         .propagate(true)
                         .build()
                 },
-                Call::store_nodes_outputs { .. } => {
+                Call::store_nodes_outputs { payload, signature } => {
                     // Existing validation for store_nodes_outputs
                     if source == TransactionSource::External && current_block_number < 510000.into() { // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
                         log::info!("UOMI-ENGINE: Rejecting store_nodes_outputs unsigned transaction from external origin");
                         return InvalidTransaction::BadSigner.into()
                     }
+                    let account_id = Self::validate_signed_payload(payload, signature)?;
+
+                    // The output not matching the commitment of the validator is rejected before it is stored on chain
+                    let node_output = NodeOutput {
+                        output_data: payload.output_data.clone(),
+                        output_cid: payload.output_cid.clone(),
+                        output_transaction: payload.output_transaction.clone(),
+                        out_of_fuel: payload.out_of_fuel,
+                        ai_models: payload.ai_models.clone(),
+                    };
+                    if !Self::opoc_output_matches_commitment(&payload.request_id, &account_id, &node_output, &payload.salt) {
+                        return InvalidTransaction::BadProof.into()
+                    }
 
+                    // Every validator stores a single output for every request
                     ValidTransaction::with_tag_prefix("UomiEnginePallet")
                         .priority(TransactionPriority::MAX)
-                        .and_provides(&call)
+                        .and_provides(("store_nodes_outputs", account_id, payload.request_id))
                         .longevity(64_u64)
                         .propagate(true)
                         .build()
                 },
-                Call::store_nodes_versions { .. } => {
+                Call::commit_nodes_outputs { payload, signature } => {
+                    let account_id = Self::validate_signed_payload(payload, signature)?;
+
+                    // Every validator stores a single commitment for every request
+                    ValidTransaction::with_tag_prefix("UomiEnginePallet")
+                        .priority(TransactionPriority::MAX)
+                        .and_provides(("commit_nodes_outputs", account_id, payload.request_id))
+                        .longevity(64_u64)
+                        .propagate(true)
+                        .build()
+                },
+                Call::store_nodes_versions { payload, signature } => {
                     // Existing validation for store_nodes_versions
                     if source == TransactionSource::External && current_block_number < 510000.into() { // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
                         log::info!("UOMI-ENGINE: Rejecting store_nodes_versions unsigned transaction from external origin");
                         return InvalidTransaction::BadSigner.into()
                     }
+                    let account_id = Self::validate_signed_payload(payload, signature)?;
     
                     ValidTransaction::with_tag_prefix("UomiEnginePallet")
                         .priority(TransactionPriority::MAX)
-                        .and_provides(&call)
+                        .and_provides(("store_nodes_versions", account_id, payload.version))
+                        .longevity(64_u64)
+                        .propagate(true)
+                        .build()
+                },
+                Call::store_nodes_models { payload, signature } => {
+                    let account_id = Self::validate_signed_payload(payload, signature)?;
+
+                    ValidTransaction::with_tag_prefix("UomiEnginePallet")
+                        .priority(TransactionPriority::MAX)
+                        .and_provides(("store_nodes_models", account_id, &payload.ai_model_keys))
                         .longevity(64_u64)
                         .propagate(true)
                         .build()
                 },
-                Call::store_nodes_opoc_l0_inferences { .. } => {
+                Call::store_nodes_opoc_l0_inferences { payload, signature } => {
                     // Existing validation for store_nodes_versions
                     if source == TransactionSource::External && current_block_number < 510000.into() { // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
                         log::info!("UOMI-ENGINE: Rejecting store_nodes_opoc_l0_inferences unsigned transaction from external origin");
                         return InvalidTransaction::BadSigner.into()
                     }
+                    let account_id = Self::validate_signed_payload(payload, signature)?;
 
+                    // Every validator stores a single inference for every request
                     ValidTransaction::with_tag_prefix("UomiEnginePallet")
                         .priority(TransactionPriority::MAX)
-                        .and_provides(&call)
+                        .and_provides(("store_nodes_opoc_l0_inferences", account_id, payload.request_id))
                         .longevity(64_u64)
                         .propagate(true)
                         .build()
@@ -425,17 +1022,10 @@ pub mod pallet {
     #[pallet::call]
     impl<T: Config> Pallet<T> {
 		#[pallet::call_index(0)]
-		#[pallet::weight((500_000, DispatchClass::Mandatory))]
+		#[pallet::weight((Pallet::<T>::opoc_operations_weight(opoc_operations, aimodelscalc_operations.len() as u32), DispatchClass::Mandatory))]
 		pub fn set_inherent_data(
 			origin: OriginFor<T>,
-			opoc_operations: (
-                BTreeMap<T::AccountId, bool>, 
-                BTreeMap<(RequestId, T::AccountId), BlockNumber>, 
-                BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>, 
-                BTreeMap<T::AccountId, u32>, 
-                BTreeMap<T::AccountId, u32>, 
-                BTreeMap<RequestId, (Data, u32, u32)>
-            ),
+			opoc_operations: OpocOperations<T::AccountId>,
             aimodelscalc_operations: BTreeMap<AiModelKey, (Data, Data, BlockNumber)>,
 		) -> DispatchResultWithPostInfo {
             log::info!("UOMI-ENGINE: Inherent data called");
@@ -451,7 +1041,7 @@ pub mod pallet {
 		}
         
         #[pallet::call_index(1)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::store_nodes_outputs(payload.output_data.len().saturating_add(payload.output_transaction.encoded_size()).saturating_add(payload.ai_models.encoded_size()) as u32))]
         pub fn store_nodes_outputs(
             origin: OriginFor<T>,
             payload: payloads::PayloadNodesOutputs<T::Public>,
@@ -460,7 +1050,7 @@ pub mod pallet {
             log::info!("UOMI-ENGINE: Storing nodes outputs");
             ensure_none(origin)?;
 
-            let payloads::PayloadNodesOutputs { request_id, output_data, public } = payload;
+            let payloads::PayloadNodesOutputs { request_id, output_data, output_cid, output_transaction, out_of_fuel, ai_models, salt, public } = payload;
             log::info!("UOMI-ENGINE: Storing output for request ID: {:?}", request_id);
 
             let public_account_id = public.into_account();
@@ -469,23 +1059,51 @@ pub mod pallet {
                 return Err("Only validators can call this function".into());
             }
 
-            let output_already_exists = |request_id: U256, account_id: &T::AccountId| -> bool {
-                NodesOutputs::<T>::contains_key(request_id, account_id)
-            };
-            if output_already_exists(request_id, &public_account_id) {
+            if NodesOutputs::<T>::contains_key(request_id, &public_account_id) {
                 return Err("Request ID already exists".into());
             }
 
+            // The output is the reveal of the commitment stored before, it is accepted only when all the validators assigned to the request
+            // have committed or are in timeout, so no validator can copy the output of another one
+            ensure!(NodesOutputsCommitments::<T>::contains_key(request_id, &public_account_id), Error::<T>::NodeOutputNotCommitted);
+            let current_block_number: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+            ensure!(Self::opoc_reveal_open(&request_id, &current_block_number), Error::<T>::NodeOutputRevealNotOpen);
+
+            // The output that does not match the commitment is never stored, the validator that does not reveal its output is in timeout
+            let node_output = NodeOutput { output_data: output_data.clone(), output_cid: output_cid.clone(), output_transaction: output_transaction.clone(), out_of_fuel, ai_models: ai_models.clone() };
+            ensure!(
+                Self::opoc_output_matches_commitment(&request_id, &public_account_id, &node_output, &salt),
+                Error::<T>::NodeOutputNotMatchingCommitment
+            );
+
+            // The output offloaded to IPFS is referenced only by its CID and hash
+            if let Some(ref output_cid) = output_cid {
+                ensure!(output_data.is_empty() && !output_cid.cid.is_empty(), Error::<T>::InvalidOutputCid);
+                NodesOutputsCids::<T>::insert(request_id, public_account_id.clone(), output_cid.clone());
+            }
+            if let Some(ref output_transaction) = output_transaction {
+                NodesOutputsTransactions::<T>::insert(request_id, public_account_id.clone(), output_transaction.clone());
+            }
+            // The execution out of fuel has no output, so it can not be mistaken for an agent setting an empty output
+            if out_of_fuel {
+                ensure!(output_data.is_empty() && output_cid.is_none() && output_transaction.is_none(), Error::<T>::InvalidOutOfFuelOutput);
+                NodesOutputsOutOfFuel::<T>::insert(request_id, public_account_id.clone(), true);
+            }
+            // The AI models called are not part of the consensus, they are used only to charge the model fee
+            if !ai_models.is_empty() {
+                NodesOutputsModels::<T>::insert(request_id, public_account_id.clone(), ai_models);
+            }
+
             log::info!("UOMI-ENGINE: Stored output for request ID: {:?}", request_id);
             NodesOutputs::<T>::insert(request_id, public_account_id.clone(), output_data.clone());
 
-            Self::deposit_event(Event::NodeOutputReceived { request_id, account_id: public_account_id, output_data });
+            Self::deposit_event(Event::NodeOutputReceived { request_id, account_id: public_account_id, output_data, output_cid, output_transaction, out_of_fuel });
 
             Ok(())
         }
         
         #[pallet::call_index(2)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::store_nodes_versions())]
         pub fn store_nodes_versions(
             origin: OriginFor<T>, 
             payload: payloads::PayloadNodesVersions<T::Public>,
@@ -517,7 +1135,7 @@ pub mod pallet {
         }
 
         #[pallet::call_index(3)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::temporary_cleanup_inputs())]
         pub fn temporary_cleanup_inputs(origin: OriginFor<T>) -> DispatchResult { // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
             log::info!("UOMI-ENGINE: Cleaning up inputs");
             let _ = ensure_signed(origin)?;
@@ -532,14 +1150,14 @@ pub mod pallet {
         }
 
         #[pallet::call_index(4)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::temporary_function())]
         pub fn temporary_function(origin: OriginFor<T>) -> DispatchResult { // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
             let _ = ensure_none(origin)?;
             Ok(())
         }
 
         #[pallet::call_index(5)]
-        #[pallet::weight(0)]
+        #[pallet::weight(T::WeightInfo::store_nodes_opoc_l0_inferences(payload.inference_proof.len() as u32))]
         pub fn store_nodes_opoc_l0_inferences(
             origin: OriginFor<T>, 
             payload: payloads::PayloadNodesOpocL0Inferences<T::Public>,
@@ -572,6 +1190,249 @@ pub mod pallet {
 
             Ok(())
         }
+
+        #[pallet::call_index(6)]
+        #[pallet::weight(T::WeightInfo::set_model_fee())]
+        pub fn set_model_fee(
+            origin: OriginFor<T>,
+            ai_model_key: AiModelKey,
+            fee: BalanceOf<T>
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            let previous_fee = ModelsFees::<T>::get(ai_model_key);
+            if fee.is_zero() {
+                ModelsFees::<T>::remove(ai_model_key);
+            } else {
+                ModelsFees::<T>::insert(ai_model_key, fee);
+            }
+
+            // The most expensive model is searched again only when its fee is decreased
+            // NOTE: The fees of the models are set only by the governance, so the models with a fee are few
+            let max_fee = ModelsMaxFee::<T>::get();
+            if fee >= max_fee {
+                ModelsMaxFee::<T>::put(fee);
+            } else if previous_fee == max_fee {
+                ModelsMaxFee::<T>::put(ModelsFees::<T>::iter_values().max().unwrap_or_else(Zero::zero));
+            }
+
+            Self::deposit_event(Event::ModelFeeSet { ai_model_key, fee });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(7)]
+        #[pallet::weight(T::WeightInfo::set_rewards_inflation())]
+        pub fn set_rewards_inflation(
+            origin: OriginFor<T>,
+            amount: BalanceOf<T>
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            RewardsInflationPerEra::<T>::put(amount);
+
+            Self::deposit_event(Event::RewardsInflationSet { amount });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(8)]
+        #[pallet::weight(T::WeightInfo::claim_rewards())]
+        pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
+            let account_id = ensure_signed(origin)?;
+
+            let amount = ClaimableRewards::<T>::take(&account_id);
+            ensure!(!amount.is_zero(), Error::<T>::NoRewardsToClaim);
+
+            // Rewards are minted on claim, they are the inflation share reserved to the validators of the engine
+            // NOTE: The deposit on an account without balance fails below the existential deposit, the claim is rejected
+            // so the rewards are kept claimable until they are enough to create the account
+            let imbalance = <T as Config>::Currency::deposit_creating(&account_id, amount);
+            ensure!(imbalance.peek() == amount, Error::<T>::RewardsBelowExistentialDeposit);
+
+            Self::deposit_event(Event::RewardsClaimed { account_id, amount });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(9)]
+        #[pallet::weight(T::WeightInfo::cancel_offence())]
+        pub fn cancel_offence(
+            origin: OriginFor<T>,
+            offence_id: OffenceId
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            ensure!(PendingOffences::<T>::contains_key(offence_id), Error::<T>::OffenceNotFound);
+            PendingOffences::<T>::remove(offence_id);
+
+            Self::deposit_event(Event::OffenceCancelled { offence_id });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(10)]
+        #[pallet::weight(T::WeightInfo::reset_reputation())]
+        pub fn reset_reputation(
+            origin: OriginFor<T>,
+            account_id: T::AccountId
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            ensure!(NodesReputations::<T>::contains_key(&account_id), Error::<T>::ReputationNotFound);
+            NodesReputations::<T>::remove(&account_id);
+
+            Self::deposit_event(Event::ReputationReset { account_id });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(11)]
+        #[pallet::weight(T::WeightInfo::register_model())]
+        pub fn register_model(
+            origin: OriginFor<T>,
+            ai_model_key: AiModelKey,
+            backend_name: Data,
+            modality: AiModelModality,
+            min_consensus: u32,
+            max_consensus: u32,
+            activation_block_number: BlockNumber
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            ensure!(!ModelsRegistry::<T>::contains_key(ai_model_key), Error::<T>::ModelAlreadyRegistered);
+            let model = AiModel { backend_name, modality, min_consensus, max_consensus, activation_block_number, deprecated: false };
+            Self::aimodelscalc_ensure_valid_model(&ai_model_key, &model)?;
+            ModelsRegistry::<T>::insert(ai_model_key, model.clone());
+
+            Self::deposit_event(Event::ModelRegistered { ai_model_key, backend_name: model.backend_name });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(12)]
+        #[pallet::weight(T::WeightInfo::deprecate_model())]
+        pub fn deprecate_model(
+            origin: OriginFor<T>,
+            ai_model_key: AiModelKey
+        ) -> DispatchResult {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            ModelsRegistry::<T>::try_mutate(ai_model_key, |model| -> DispatchResult {
+                let model = model.as_mut().ok_or(Error::<T>::ModelNotFound)?;
+                model.deprecated = true;
+                Ok(())
+            })?;
+
+            Self::deposit_event(Event::ModelDeprecated { ai_model_key });
+
+            Ok(())
+        }
+
+        // NOTE: The models advertised by every node are updated, so the governance has to provide the number of NodesModels as witness.
+        #[pallet::call_index(13)]
+        #[pallet::weight(T::WeightInfo::replace_model(*nodes_models_witness))]
+        pub fn replace_model(
+            origin: OriginFor<T>,
+            ai_model_key: AiModelKey,
+            backend_name: Data,
+            modality: AiModelModality,
+            min_consensus: u32,
+            max_consensus: u32,
+            activation_block_number: BlockNumber,
+            nodes_models_witness: u32
+        ) -> DispatchResultWithPostInfo {
+            T::GovernanceOrigin::ensure_origin(origin)?;
+
+            ensure!(ModelsRegistry::<T>::contains_key(ai_model_key), Error::<T>::ModelNotFound);
+            let model = AiModel { backend_name, modality, min_consensus, max_consensus, activation_block_number, deprecated: false };
+            Self::aimodelscalc_ensure_valid_model(&ai_model_key, &model)?;
+            ensure!(!Self::aimodelscalc_is_downgrade(&ai_model_key, &model.backend_name), Error::<T>::ModelDowngrade);
+            let nodes_models_count = NodesModels::<T>::iter_keys().take(nodes_models_witness as usize + 1).count() as u32;
+            ensure!(nodes_models_count <= nodes_models_witness, Error::<T>::InvalidNodesModelsWitness);
+            ModelsRegistry::<T>::insert(ai_model_key, model.clone());
+
+            // The nodes have to advertise again the model when they serve the new backend name
+            NodesModels::<T>::translate_values::<BoundedVec<AiModelKey, MaxModelsPerNode>, _>(|mut ai_model_keys| {
+                ai_model_keys.retain(|key| *key != ai_model_key);
+                Some(ai_model_keys)
+            });
+
+            Self::deposit_event(Event::ModelReplaced { ai_model_key, backend_name: model.backend_name });
+
+            Ok(Some(T::WeightInfo::replace_model(nodes_models_count)).into())
+        }
+
+        #[pallet::call_index(14)]
+        #[pallet::weight(T::WeightInfo::store_nodes_models(payload.ai_model_keys.len() as u32))]
+        pub fn store_nodes_models(
+            origin: OriginFor<T>,
+            payload: payloads::PayloadNodesModels<T::Public>,
+            _signature: T::Signature
+        ) -> DispatchResult {
+            log::info!("UOMI-ENGINE: Storing node models onchain");
+            ensure_none(origin)?;
+            let payloads::PayloadNodesModels { public, ai_model_keys } = payload;
+            let public_account_id = public.into_account();
+
+            if !Self::address_is_active_validator(&public_account_id) {
+                log::info!("UOMI-ENGINE: Only validators can call this function");
+                return Err("Only validators can call this function".into());
+            }
+
+            if NodesModels::<T>::get(&public_account_id) == ai_model_keys {
+                log::info!("UOMI-ENGINE: Models already stored");
+                return Err("Models already stored".into());
+            }
+
+            NodesModels::<T>::insert(&public_account_id, ai_model_keys.clone());
+
+            Self::deposit_event(Event::NodeModelsReceived { account_id: public_account_id, ai_model_keys });
+
+            Ok(())
+        }
+
+        #[pallet::call_index(15)]
+        #[pallet::weight(T::WeightInfo::cancel_request())]
+        pub fn cancel_request(
+            origin: OriginFor<T>,
+            request_id: RequestId
+        ) -> DispatchResult {
+            let account_id = ensure_signed(origin)?;
+
+            let address = RequestsAddresses::<T>::get(request_id).ok_or(Error::<T>::RequestNotFound)?;
+            ensure!(T::AddressToAccountId::convert(address) == account_id, Error::<T>::NotRequestOwner);
+
+            Self::requests_cancel(&request_id)
+        }
+
+        #[pallet::call_index(16)]
+        #[pallet::weight(T::WeightInfo::commit_nodes_outputs())]
+        pub fn commit_nodes_outputs(
+            origin: OriginFor<T>,
+            payload: payloads::PayloadNodesOutputsCommitments<T::Public>,
+            _signature: T::Signature
+        ) -> DispatchResult {
+            log::info!("UOMI-ENGINE: Storing nodes outputs commitments");
+            ensure_none(origin)?;
+
+            let payloads::PayloadNodesOutputsCommitments { request_id, output_hash, public } = payload;
+            let public_account_id = public.into_account();
+            log::info!("UOMI-ENGINE: Storing commitment for request ID: {:?}", request_id);
+
+            if !Self::address_is_active_validator(&public_account_id) {
+                return Err("Only validators can call this function".into());
+            }
+
+            ensure!(OpocAssignment::<T>::contains_key(request_id, &public_account_id), Error::<T>::NodeNotAssigned);
+            ensure!(!NodesOutputsCommitments::<T>::contains_key(request_id, &public_account_id), Error::<T>::NodeOutputAlreadyCommitted);
+
+            NodesOutputsCommitments::<T>::insert(request_id, public_account_id.clone(), output_hash);
+
+            Self::deposit_event(Event::NodeOutputCommitted { request_id, account_id: public_account_id, output_hash });
+
+            Ok(())
+        }
     }
 
     // Inherent functions are used to execute code at the beginning of each block.
@@ -622,66 +1483,73 @@ pub mod pallet {
 
             match call {
                 Call::set_inherent_data { opoc_operations, aimodelscalc_operations } => {
-                    // let expected_opoc_operations = match Self::opoc_run(expected_block_number) {
-                    //     Ok(opoc_operations) => {
-                    //         opoc_operations
-                    //     },
-                    //     Err(error) => {
-                    //         log::info!("UOMI-ENGINE: Failed to run OPoC on check_inherent. error: {:?}", error);
-                    //         return Err(InherentError::InvalidInherentValue);
-                    //     },
-                    // };
-                    // let (opoc_blacklist_operations, opoc_assignment_operations, nodes_works_operations, nodes_timeouts_operations, outputs_operations, nodes_errors_operations) = opoc_operations;
-                    // let (expected_opoc_blacklist_operations, expected_opoc_assignment_operations, expected_nodes_works_operations, expected_nodes_timeouts_operations, expected_outputs_operations, expected_nodes_errors_operations) = expected_opoc_operations;
+                    let expected_opoc_operations = match Self::opoc_run(expected_block_number) {
+                        Ok(opoc_operations) => {
+                            opoc_operations
+                        },
+                        Err(error) => {
+                            log::info!("UOMI-ENGINE: Failed to run OPoC on check_inherent. error: {:?}", error);
+                            return Err(InherentError::InvalidInherentValue);
+                        },
+                    };
+                    let OpocOperations {
+                        opoc_penalties_operations,
+                        opoc_assignment_operations,
+                        nodes_works_operations,
+                        outputs_operations,
+                        failures_operations,
+                    } = opoc_operations;
+                    let OpocOperations {
+                        opoc_penalties_operations: expected_opoc_penalties_operations,
+                        opoc_assignment_operations: expected_opoc_assignment_operations,
+                        nodes_works_operations: expected_nodes_works_operations,
+                        outputs_operations: expected_outputs_operations,
+                        failures_operations: expected_failures_operations,
+                    } = expected_opoc_operations;
                     
-                    // if opoc_blacklist_operations != &expected_opoc_blacklist_operations {
-                    //     log::info!("failed check opoc_blacklist_operations: {:?}", opoc_blacklist_operations);
-                    //     log::info!("expected_opoc_blacklist_operations: {:?}", expected_opoc_blacklist_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-                    // if opoc_assignment_operations != &expected_opoc_assignment_operations {
-                    //     log::info!("failed check opoc_assignment_operations: {:?}", opoc_assignment_operations);
-                    //     log::info!("expected_opoc_assignment_operations: {:?}", expected_opoc_assignment_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-                    // if nodes_works_operations != &expected_nodes_works_operations {
-                    //     log::info!("failed check nodes_works_operations: {:?}", nodes_works_operations);
-                    //     log::info!("expected_nodes_works_operations: {:?}", expected_nodes_works_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-                    // if nodes_timeouts_operations != &expected_nodes_timeouts_operations {
-                    //     log::info!("failed check nodes_timeouts_operations: {:?}", nodes_timeouts_operations);
-                    //     log::info!("expected_nodes_timeouts_operations: {:?}", expected_nodes_timeouts_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-                    // if outputs_operations != &expected_outputs_operations {
-                    //     log::info!("failed check outputs_operations: {:?}", outputs_operations);
-                    //     log::info!("expected_outputs_operations: {:?}", expected_outputs_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-                    // if nodes_errors_operations != &expected_nodes_errors_operations {
-                    //     log::info!("failed check nodes_errors_operations: {:?}", nodes_errors_operations);
-                    //     log::info!("expected_nodes_errors_operations: {:?}", expected_nodes_errors_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
-
-                    // let expected_aimodelscalc_operations = match Self::aimodelscalc_run(expected_block_number) {
-                    //     Ok(operations) => {
-                    //         operations
-                    //     },
-                    //     Err(error) => {
-                    //         log::info!("Failed to run AI models calc on check_inherent. error: {:?}", error);
-                    //         return Err(InherentError::InvalidInherentValue);
-                    //     },
-                    // };
+                    if opoc_penalties_operations != &expected_opoc_penalties_operations {
+                        log::info!("failed check opoc_penalties_operations: {:?}", opoc_penalties_operations);
+                        log::info!("expected_opoc_penalties_operations: {:?}", expected_opoc_penalties_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
+                    if opoc_assignment_operations != &expected_opoc_assignment_operations {
+                        log::info!("failed check opoc_assignment_operations: {:?}", opoc_assignment_operations);
+                        log::info!("expected_opoc_assignment_operations: {:?}", expected_opoc_assignment_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
+                    if nodes_works_operations != &expected_nodes_works_operations {
+                        log::info!("failed check nodes_works_operations: {:?}", nodes_works_operations);
+                        log::info!("expected_nodes_works_operations: {:?}", expected_nodes_works_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
+                    if outputs_operations != &expected_outputs_operations {
+                        log::info!("failed check outputs_operations: {:?}", outputs_operations);
+                        log::info!("expected_outputs_operations: {:?}", expected_outputs_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
+                    if failures_operations != &expected_failures_operations {
+                        log::info!("failed check failures_operations: {:?}", failures_operations);
+                        log::info!("expected_failures_operations: {:?}", expected_failures_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
+
+                    let expected_aimodelscalc_operations = match Self::aimodelscalc_run(expected_block_number) {
+                        Ok(operations) => {
+                            operations
+                        },
+                        Err(error) => {
+                            log::info!("Failed to run AI models calc on check_inherent. error: {:?}", error);
+                            return Err(InherentError::InvalidInherentValue);
+                        },
+                    };
                     
-                    // if expected_aimodelscalc_operations != *aimodelscalc_operations {
-                    //     log::info!("failed check aimodelscalc_operations: {:?}", aimodelscalc_operations);
-                    //     log::info!("expected_aimodelscalc_operations: {:?}", expected_aimodelscalc_operations);
-                    //     return Err(InherentError::InvalidInherentValue);
-                    // }
+                    if expected_aimodelscalc_operations != *aimodelscalc_operations {
+                        log::info!("failed check aimodelscalc_operations: {:?}", aimodelscalc_operations);
+                        log::info!("expected_aimodelscalc_operations: {:?}", expected_aimodelscalc_operations);
+                        return Err(InherentError::InvalidInherentValue);
+                    }
 
-                    // log::info!("UOMI-ENGINE: Checking inherent OK");
+                    log::info!("UOMI-ENGINE: Checking inherent OK");
  
                     Ok(())
                 }
@@ -709,147 +1577,213 @@ impl<T: Config> Pallet<T> {
     //////////////////////////////////////////////////////////////////////////////////
 
     // This function is used by the runtime to run a request on the UOMI Network.
+    // The address pays the fee of the request (the agent contract), the sender is the owner of the request, the only address allowed to cancel it.
     pub fn run_request(
         request_id: U256,
         address: H160,
+        sender: H160,
         nft_id: U256,
         input_data: Vec<u8>,
         input_file_cid: Vec<u8>,
         min_validators: U256,
         min_blocks: U256,
     ) -> DispatchResult {
-        // Be sure request_id is > 0
-        ensure!(request_id > U256::zero(), "Request ID must be greater than 0.");
-        // Be sure address is not zero
-        ensure!(address != H160::zero(), "Address must not be zero.");
-        // Be sure nft_id is > 0
-        ensure!(nft_id > U256::zero(), "NFT ID must be greater than 0.");
-        // Be sure request_id is not already in the Inputs storage
-        ensure!(!Inputs::<T>::contains_key(request_id), "Request ID already exists.");
-
-        // Get the current block number in U256 format
-        let block_number: U256 = frame_system::Pallet::<T>::block_number().into();
-
-        // Get input_data and input_file_cid in BoundedVec<u8, MaxDataSize> format
-        let input_data: BoundedVec<u8, MaxDataSize> = input_data
-            .clone()
-            .try_into()
-            .map_err(|_| "Input data too large.")?;
-        let input_file_cid: Cid = input_file_cid
-            .clone()
-            .try_into()
-            .map_err(|_| Error::<T>::InvalidCid)?;
-
-        // Be sure to pin the input_file_cid if it is not empty
-        if !input_file_cid.is_empty() {
-            let account_id = Self::h160_to_account_id(address)?;
-            let origin = frame_system::RawOrigin::Signed(account_id).into();
-            T::IpfsPallet::pin_file(origin, input_file_cid.clone(), MinExpireDuration::get().into())?;
-        }
-
-        // Get the nft_file_cid from the nft_id
-        let nft_file_cid;
-        if cfg!(test) { // For testing purposes, we set the nft_file_cid to default
-            nft_file_cid = Cid::default();
-        } else {
-            nft_file_cid = match T::IpfsPallet::get_agent_cid(nft_id) {
-                Ok(cid) => cid,
-                Err(error) => {
-                    log::error!("UOMI-ENGINE: Failed to get agent from NFT ID on run_request. error: {:?}", error);
-                    return Err("Failed to get agent from NFT ID.".into());
-                },
-            };
+        // The request is accepted atomically, so a failure after the pin of the input file or the reserve of the fee does not keep any change
+        frame_support::storage::with_storage_layer(|| -> DispatchResult {
+            // Be sure request_id is > 0
+            ensure!(request_id > U256::zero(), "Request ID must be greater than 0.");
+            // Be sure address is not zero
+            ensure!(address != H160::zero(), "Address must not be zero.");
+            // Be sure sender is not zero
+            ensure!(sender != H160::zero(), "Sender must not be zero.");
+            // Be sure nft_id is > 0
+            ensure!(nft_id > U256::zero(), "NFT ID must be greater than 0.");
+            // Be sure request_id is not already in the Inputs storage
+            ensure!(!Inputs::<T>::contains_key(request_id), "Request ID already exists.");
+            // Be sure the request can be executed before the OPoC closes it as expired
+            ensure!(min_blocks <= U256::from(T::RequestMaxLifetime::get()), Error::<T>::MinBlocksAboveMaxLifetime);
+
+            // Get the current block number in U256 format
+            let block_number: U256 = frame_system::Pallet::<T>::block_number().into();
+
+            // Get input_data and input_file_cid in BoundedVec<u8, MaxDataSize> format
+            let input_data: BoundedVec<u8, MaxDataSize> = input_data
+                .clone()
+                .try_into()
+                .map_err(|_| "Input data too large.")?;
+            let input_file_cid: Cid = input_file_cid
+                .clone()
+                .try_into()
+                .map_err(|_| Error::<T>::InvalidCid)?;
+
+            // Be sure to pin the input_file_cid if it is not empty
+            if !input_file_cid.is_empty() {
+                let account_id = Self::h160_to_account_id(address)?;
+                let origin = frame_system::RawOrigin::Signed(account_id).into();
+                T::IpfsPallet::pin_file(origin, input_file_cid.clone(), MinExpireDuration::get().into())?;
+            }
 
-            // Be sure the nft_file_cid is valid and pinned by nodes
-            let (nft_file_cid_expiration_block_number, nft_file_cid_usable_from_block_number) =
-                match T::IpfsPallet::get_cid_status(&nft_file_cid) {
-                    Ok((expiration_block_number, usable_from_block_number)) =>
-                        (expiration_block_number, usable_from_block_number),
+            // Get the nft_file_cid from the nft_id
+            let nft_file_cid;
+            if cfg!(test) { // For testing purposes, we set the nft_file_cid to default
+                nft_file_cid = Cid::default();
+            } else {
+                nft_file_cid = match T::IpfsPallet::get_agent_cid(nft_id) {
+                    Ok(cid) => cid,
                     Err(error) => {
-                        log::error!(
-                            "UOMI-ENGINE: Failed to get status of nft file cid {:?}. error: {:?}",
-                            nft_file_cid,
-                            error
-                        );
-                        return Err("Failed to get status of nft file cid.".into());
-                    }
+                        log::error!("UOMI-ENGINE: Failed to get agent from NFT ID on run_request. error: {:?}", error);
+                        return Err("Failed to get agent from NFT ID.".into());
+                    },
                 };
+
+                // Be sure the nft_file_cid is valid and pinned by nodes
+                let (nft_file_cid_expiration_block_number, nft_file_cid_usable_from_block_number) =
+                    match T::IpfsPallet::get_cid_status(&nft_file_cid) {
+                        Ok((expiration_block_number, usable_from_block_number)) =>
+                            (expiration_block_number, usable_from_block_number),
+                        Err(error) => {
+                            log::error!(
+                                "UOMI-ENGINE: Failed to get status of nft file cid {:?}. error: {:?}",
+                                nft_file_cid,
+                                error
+                            );
+                            return Err("Failed to get status of nft file cid.".into());
+                        }
+                    };
             
-            let ipfs_min_expire_duration = U256::from(MinExpireDuration::get());
-            let current_block = frame_system::Pallet::<T>::block_number().into();
-            if
-                nft_file_cid_expiration_block_number != ExpirationBlockNumber::zero() &&
-                block_number + ipfs_min_expire_duration > nft_file_cid_expiration_block_number
-            {
-                log::info!(
-                    "UOMI-ENGINE: NFT file cid {:?} expired before the minimum expiration duration",
-                    nft_file_cid
-                );
-                return Err("NFT file cid expired before the minimum expiration duration.".into());
-            }
-            if nft_file_cid_usable_from_block_number == UsableFromBlockNumber::zero() {
-                log::info!("UOMI-ENGINE: NFT file cid {:?} not usable yet", nft_file_cid);
-                return Err("NFT file cid not usable yet.".into());
-            }
-            if nft_file_cid_usable_from_block_number > current_block {
-                log::info!("UOMI-ENGINE: NFT file cid {:?} not usable yet", nft_file_cid);
-                return Err("NFT file cid not usable yet.".into());
+                let ipfs_min_expire_duration = U256::from(MinExpireDuration::get());
+                let current_block = frame_system::Pallet::<T>::block_number().into();
+                if
+                    nft_file_cid_expiration_block_number != ExpirationBlockNumber::zero() &&
+                    block_number + ipfs_min_expire_duration > nft_file_cid_expiration_block_number
+                {
+                    log::info!(
+                        "UOMI-ENGINE: NFT file cid {:?} expired before the minimum expiration duration",
+                        nft_file_cid
+                    );
+                    return Err("NFT file cid expired before the minimum expiration duration.".into());
+                }
+                if nft_file_cid_usable_from_block_number == UsableFromBlockNumber::zero() {
+                    log::info!("UOMI-ENGINE: NFT file cid {:?} not usable yet", nft_file_cid);
+                    return Err("NFT file cid not usable yet.".into());
+                }
+                if nft_file_cid_usable_from_block_number > current_block {
+                    log::info!("UOMI-ENGINE: NFT file cid {:?} not usable yet", nft_file_cid);
+                    return Err("NFT file cid not usable yet.".into());
+                }
             }
-        }
        
-        // Get the minimum number of validators required for the request to be considered valid
-        let nft_required_consensus = min_validators;
-        let nft_execution_max_time = min_blocks;
-
-        // Store the inputs in the Inputs storage
-        Inputs::<T>::insert(request_id, (block_number, nft_id, nft_required_consensus, nft_execution_max_time, nft_file_cid, input_data, input_file_cid));
-
-        // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
-        if request_id <= U256::from(47) && nft_required_consensus <= U256::from(1) {
-            log::info!("UOMI-ENGINE: Managed old unsecured mode");
-            let mut opoc_blacklist_operations = BTreeMap::<T::AccountId, bool>::new();
-            let mut opoc_assignment_operations = BTreeMap::<(U256, T::AccountId), U256>::new();
-            let mut nodes_works_operations = BTreeMap::<T::AccountId, BTreeMap<U256, bool>>::new();
-            let current_block = frame_system::Pallet::<T>::block_number().into();
-            match Self::opoc_assignment_finney_v1(
-                &mut opoc_blacklist_operations,
-                &mut opoc_assignment_operations,
-                &mut nodes_works_operations,
-                &request_id,
-                &current_block,
-                1,
-                vec![],
-                true
-            ) {
-                Ok(_) => {
-                    log::info!("UOMI-ENGINE: Request assigned to a random validator for OPoC level 0 on run_request");
-                    Self::opoc_store_operations((
-                        opoc_blacklist_operations,
-                        opoc_assignment_operations,
-                        nodes_works_operations,
-                        BTreeMap::<T::AccountId, u32>::new(),
-                        BTreeMap::<T::AccountId, u32>::new(),
-                        BTreeMap::<RequestId, (Data, u32, u32)>::new()
-                    ))?;
-                },
-                Err(error) => {
-                    log::error!("UOMI-ENGINE: Failed to assign request to a random validator for OPoC level 0 on run_request. error: {:?}", error);
-                    // NOTE: If assigned is not valid, is not a problem, the request should be assigned by the opoc execution
-                },
-            };
-        }
+            // Reserve the fee of the request from the payer, it will be held in escrow until the request is completed
+            // NOTE: The payer is the address of the agent contract, pre-funded by the senders of its requests, not the sender
+            let payer = T::AddressToAccountId::convert(address);
+            Self::fees_reserve(&request_id, &payer, &min_validators, &min_blocks, input_data.len())?;
+
+            // Get the minimum number of validators required for the request to be considered valid
+            let nft_required_consensus = min_validators;
+            let nft_execution_max_time = min_blocks;
+
+            // Store the inputs in the Inputs storage
+            Inputs::<T>::insert(request_id, AgentRequest {
+                block_number,
+                nft_id,
+                nft_required_consensus,
+                nft_execution_max_time,
+                nft_file_cid,
+                input_data,
+                input_file_cid,
+            });
+            RequestsAddresses::<T>::insert(request_id, sender);
+
+            // NOTE: This code is used to maintain the retro-compatibility with old blocks on finney network
+            if request_id <= U256::from(47) && nft_required_consensus <= U256::from(1) {
+                log::info!("UOMI-ENGINE: Managed old unsecured mode");
+                let mut opoc_assignment_operations = BTreeMap::<(U256, T::AccountId), U256>::new();
+                let mut nodes_works_operations = BTreeMap::<T::AccountId, BTreeMap<U256, bool>>::new();
+                let current_block = frame_system::Pallet::<T>::block_number().into();
+                match Self::opoc_assignment_finney_v1(
+                    &mut opoc_assignment_operations,
+                    &mut nodes_works_operations,
+                    &request_id,
+                    &current_block,
+                    1,
+                    vec![],
+                    true
+                ) {
+                    Ok(_) => {
+                        log::info!("UOMI-ENGINE: Request assigned to a random validator for OPoC level 0 on run_request");
+                        Self::opoc_store_operations(OpocOperations {
+                            opoc_assignment_operations,
+                            nodes_works_operations,
+                            ..Default::default()
+                        })?;
+                    },
+                    Err(error) => {
+                        log::error!("UOMI-ENGINE: Failed to assign request to a random validator for OPoC level 0 on run_request. error: {:?}", error);
+                        // NOTE: If assigned is not valid, is not a problem, the request should be assigned by the opoc execution
+                    },
+                };
+            }
+
+            // Emit the RequestAccepted event
+            Self::deposit_event(Event::RequestAccepted { request_id, address, nft_id });
+
+            log::info!("UOMI-ENGINE: Accepted request with ID on run_request: {:?}", request_id);
+            Ok(())
+        })
+    }
 
-        // Emit the RequestAccepted event
-        Self::deposit_event(Event::RequestAccepted { request_id, address, nft_id });
+    // This function is used by the runtime to run a request with an EVM callback executed on its completion.
+    // NOTE: The request is not accepted if the gas of the callback can not be prepaid.
+    pub fn run_request_with_callback(
+        request_id: U256,
+        address: H160,
+        sender: H160,
+        nft_id: U256,
+        input_data: Vec<u8>,
+        input_file_cid: Vec<u8>,
+        min_validators: U256,
+        min_blocks: U256,
+        callback_target: H160,
+        callback_selector: [u8; 4],
+        callback_gas_limit: u64,
+    ) -> DispatchResult {
+        frame_support::storage::with_storage_layer(|| {
+            Self::run_request(request_id, address, sender, nft_id, input_data, input_file_cid, min_validators, min_blocks)?;
 
-        log::info!("UOMI-ENGINE: Accepted request with ID on run_request: {:?}", request_id);
-        Ok(())
+            let payer = T::AddressToAccountId::convert(address);
+            Self::callbacks_register(&request_id, &payer, callback_target, callback_selector, callback_gas_limit)
+        })
+    }
+
+    // This function is used by the runtime to cancel a request not assigned yet, on behalf of the address that sent it (the owner of the request).
+    pub fn cancel_request_from_address(request_id: U256, address: H160) -> DispatchResult {
+        let request_address = RequestsAddresses::<T>::get(request_id).ok_or(Error::<T>::RequestNotFound)?;
+        ensure!(request_address == address, Error::<T>::NotRequestOwner);
+
+        Self::requests_cancel(&request_id)
     }
 
     // OTHER FUNCTIONS
     //////////////////////////////////////////////////////////////////////////////////
     
-    // This function is used to check if an address is a validator.
+    // This function verifies the signature of a payload submitted by an offchain worker and returns the account of the active validator that signed it.
+    // NOTE: It is called by validate_unsigned, so the payloads not signed by an active validator never enter the transaction pool
+    pub fn validate_signed_payload<P: SignedPayload<T>>(payload: &P, signature: &T::Signature) -> Result<T::AccountId, TransactionValidityError> {
+        if !SignedPayload::<T>::verify::<T::UomiAuthorityId>(payload, signature.clone()) {
+            log::info!("UOMI-ENGINE: Rejecting unsigned transaction with an invalid payload signature");
+            return Err(InvalidTransaction::BadProof.into());
+        }
+
+        let account_id = payload.public().into_account();
+        if !Self::address_is_active_validator(&account_id) {
+            log::info!("UOMI-ENGINE: Rejecting unsigned transaction from a not active validator");
+            return Err(InvalidTransaction::BadSigner.into());
+        }
+
+        Ok(account_id)
+    }
+
+    // This function is used to check if an address is a validator of the current session.
     pub fn address_is_active_validator(account_id: &T::AccountId) -> bool {
         // TODO: For tests we return validators only from pallet_staking::Validators::<T>.
         // In the future we should fix tests to return validators from session::Validators::<T>.
@@ -863,14 +1797,19 @@ impl<T: Config> Pallet<T> {
         active_validators.contains(&validator_id)
     }
 
-    // TODO: It should be better to load directly validators from session::Validators::<T> but we need to find a way to convert them to T::AccountId
+    // This function returns the snapshot of the active validators taken on the last on_finalize.
+    // NOTE: The snapshot is used to have the same validators on create_inherent and on check_inherent.
     pub fn get_active_validators() -> Vec<T::AccountId> {
-        // TODO: For tests we return validators only from pallet_staking::Validators::<T>.
-        // In the future we should fix tests to return validators from session::Validators::<T>.
-        if cfg!(test) {
-            return pallet_staking::Validators::<T>::iter().map(|(account_id, _)| account_id).collect();
-        }
+        ActiveValidators::<T>::get()
+    }
+
+    // This function is used to get the stake of an active validator, the stake of the ActiveValidators snapshot is used.
+    pub fn get_active_validator_stake(account_id: &T::AccountId) -> u128 {
+        ActiveValidatorsStakes::<T>::get(account_id)
+    }
 
+    // TODO: It should be better to load directly validators from session::Validators::<T> but we need to find a way to convert them to T::AccountId
+    pub fn get_session_active_validators() -> Vec<T::AccountId> {
         let validators: Vec<T::AccountId> = pallet_staking::Validators::<T>::iter().map(|(account_id, _)| account_id)
             .into_iter()
             .filter(|account_id| Self::address_is_active_validator(account_id))
@@ -892,3 +1831,14 @@ impl<T: Config> Pallet<T> {
             .map_err(|_| DispatchError::Other("Failed to decode account"))
     }
 }
+
+sp_api::decl_runtime_apis! {
+    pub trait UomiEngineApi<AccountId> where AccountId: Codec {
+        fn get_request(request_id: RequestId) -> Option<RequestInfo<AccountId>>;
+        fn pending_requests() -> Vec<RequestId>;
+        fn request_assignments(request_id: RequestId) -> Vec<RequestAssignment<AccountId>>;
+        fn agent_file_cid(nft_id: NftId) -> Option<Cid>;
+        fn ai_model_local_name(ai_model_key: AiModelKey) -> Option<Data>;
+        fn opoc_l0_inferences(request_id: RequestId) -> Vec<(u32, Data)>;
+    }
+}
@@ -0,0 +1,338 @@
+use frame_support::{
+    pallet_prelude::Weight,
+    storage::unhashed,
+    traits::{Get, GetStorageVersion, OnRuntimeUpgrade, StorageVersion},
+    BoundedVec,
+};
+use codec::{Decode, Encode, MaxEncodedLen};
+use sp_std::marker::PhantomData;
+
+use crate::{types::AgentResult, Config, FinishedRequests, MigrationsCursor, Outputs, Pallet, RequestsStatuses};
+#[cfg(feature = "try-runtime")]
+use frame_support::ensure;
+#[cfg(feature = "try-runtime")]
+use sp_runtime::TryRuntimeError;
+#[cfg(feature = "try-runtime")]
+use sp_std::vec::Vec;
+
+// The storage layout of the pallet is migrated from any previous version to the version 6 by a single migration:
+// - The values bounded by the requests in progress and by the AI models are migrated on the runtime upgrade.
+// - The history of the closed requests (Outputs and RequestsStatuses), that grows with the life of the chain, is migrated
+//   by on_idle in batches within the remaining weight of the block, see migrations_run.
+// The migration is gated by the on-chain StorageVersion of the pallet, so it can be left in the runtime Migrations until the next release.
+pub mod v6 {
+    use super::*;
+    use pallet_ipfs::types::Cid;
+    use sp_core::U256;
+
+    use codec::DecodeAll;
+
+    use crate::{
+        types::{AgentRequest, AgentTransaction, AiModel, AiModelModality, BlockNumber, Data, MigrationCursor, NftId, OutputCid},
+        AIModels, Inputs, ModelsRegistry, RetentionCursor,
+    };
+
+    // The value stored in Inputs before the version 1.
+    type OldAgentRequest = (BlockNumber, NftId, U256, U256, Cid, Data, Cid);
+
+    // AgentResultV1 is the value stored in Outputs before the version 3, the tuple stored before the version 1 has the same encoding.
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+    pub struct AgentResultV1 {
+        pub output_data: Data,
+        pub total_executions: u32,
+        pub total_consensus: u32,
+    }
+
+    // AgentResultV3 is the value stored in Outputs from the version 3 to the version 4, it adds the outputs offloaded to IPFS.
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+    pub struct AgentResultV3 {
+        pub output_data: Data,
+        pub total_executions: u32,
+        pub total_consensus: u32,
+        pub output_cid: Option<OutputCid>,
+        pub output_cid_expiration_block_number: BlockNumber,
+    }
+
+    // AgentResultV4 is the value stored in Outputs from the version 4 to the version 5, it adds the transaction emitted by the agent.
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+    pub struct AgentResultV4 {
+        pub output_data: Data,
+        pub total_executions: u32,
+        pub total_consensus: u32,
+        pub output_cid: Option<OutputCid>,
+        pub output_cid_expiration_block_number: BlockNumber,
+        pub output_transaction: Option<AgentTransaction>,
+    }
+
+    // MigrateToV6 migrates the storage of the pallet from any previous version to the version 6:
+    // - version 1: the anonymous tuples stored in Inputs are translated to AgentRequest,
+    // - version 2: the AI models hard-coded on the nodes are registered on the models registry,
+    // - versions 3, 4 and 5: the results stored in Outputs are translated to AgentResult by on_idle,
+    // - version 6: the closed requests are recorded for the retention by on_idle, as closed on the block of the upgrade,
+    //   and the snapshot of the active validators and of the random seed read by the OPoC is taken.
+    pub struct MigrateToV6<T>(PhantomData<T>);
+
+    impl<T: Config> OnRuntimeUpgrade for MigrateToV6<T> {
+        fn on_runtime_upgrade() -> Weight {
+            let on_chain_version = Pallet::<T>::on_chain_storage_version();
+            if on_chain_version >= 6 {
+                log::info!("UOMI-ENGINE: MigrateToV6 skipped, on-chain storage version is {:?}", on_chain_version);
+                return T::DbWeight::get().reads(1);
+            }
+
+            let mut weight = T::DbWeight::get().reads_writes(1, 3);
+            if on_chain_version < 1 {
+                weight = weight.saturating_add(translate_inputs::<T>());
+            }
+            if on_chain_version < 2 {
+                weight = weight.saturating_add(register_ai_models::<T>());
+            }
+
+            // The history of the closed requests is migrated by on_idle, the retention starts from the block of the upgrade
+            let current_block_number: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+            MigrationsCursor::<T>::put(MigrationCursor {
+                block_number: current_block_number,
+                outputs_completed: false,
+                last_raw_key: None,
+            });
+            RetentionCursor::<T>::put(current_block_number);
+
+            // The snapshot read by the OPoC is taken on on_finalize, it is seeded for the inherent of the block of the upgrade
+            weight = weight.saturating_add(Pallet::<T>::opoc_snapshot_weight(frame_system::Pallet::<T>::block_number()));
+            Pallet::<T>::opoc_snapshot(current_block_number);
+
+            StorageVersion::new(6).put::<Pallet<T>>();
+            log::info!("UOMI-ENGINE: MigrateToV6 migrated the storage from the version {:?}, the history is migrated by on_idle", on_chain_version);
+
+            weight
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<Vec<u8>, TryRuntimeError> {
+            let inputs_count = Inputs::<T>::iter_keys().count() as u64;
+
+            Ok(inputs_count.encode())
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade(state: Vec<u8>) -> Result<(), TryRuntimeError> {
+            let inputs_count = u64::decode(&mut &state[..])
+                .map_err(|_| "UOMI-ENGINE: Failed to decode the pre_upgrade state")?;
+
+            ensure!(Pallet::<T>::on_chain_storage_version() == 6, "UOMI-ENGINE: Storage version not updated to 6");
+            // NOTE: iter_values skips the values that can not be decoded, so the counts match only if every value has been translated
+            ensure!(Inputs::<T>::iter_values().count() as u64 == inputs_count, "UOMI-ENGINE: Inputs not translated");
+            ensure!(MigrationsCursor::<T>::exists(), "UOMI-ENGINE: Migration of the history not scheduled");
+            ensure!(crate::ActiveValidatorsSessionIndex::<T>::exists(), "UOMI-ENGINE: Snapshot of the active validators not taken");
+
+            Ok(())
+        }
+    }
+
+    // This function is used to translate the anonymous tuples stored in Inputs before the version 1 to AgentRequest.
+    fn translate_inputs<T: Config>() -> Weight {
+        let mut translated = 0u64;
+        let mut proof_size = 0u64;
+        Inputs::<T>::translate::<OldAgentRequest, _>(|_request_id, (
+            block_number,
+            nft_id,
+            nft_required_consensus,
+            nft_execution_max_time,
+            nft_file_cid,
+            input_data,
+            input_file_cid,
+        )| {
+            let request = AgentRequest {
+                block_number,
+                nft_id,
+                nft_required_consensus,
+                nft_execution_max_time,
+                nft_file_cid,
+                input_data,
+                input_file_cid,
+            };
+            translated += 1;
+            proof_size = proof_size.saturating_add(request.encoded_size() as u64);
+            Some(request)
+        });
+        log::info!("UOMI-ENGINE: MigrateToV6 translated {:?} inputs", translated);
+
+        T::DbWeight::get().reads_writes(translated + 1, translated)
+            .saturating_add(Weight::from_parts(0, proof_size))
+    }
+
+    // This function is used to register on the models registry the AI models hard-coded on the nodes before the version 2.
+    // NOTE: The models with key >= 100 were the image generation models, they could be called only with consensus 1.
+    fn register_ai_models<T: Config>() -> Weight {
+        let mut read = 0u64;
+        let mut registered = 0u64;
+        let mut proof_size = 0u64;
+        for (ai_model_key, (local_name, _previous_local_name, _available_from_block_number)) in AIModels::<T>::iter() {
+            read += 1;
+            proof_size = proof_size.saturating_add(local_name.encoded_size() as u64);
+            if local_name.is_empty() || ModelsRegistry::<T>::contains_key(ai_model_key) {
+                continue;
+            }
+
+            let is_image = ai_model_key >= U256::from(100);
+            ModelsRegistry::<T>::insert(ai_model_key, AiModel {
+                backend_name: local_name,
+                modality: if is_image { AiModelModality::Image } else { AiModelModality::Text },
+                min_consensus: 1,
+                max_consensus: if is_image { 1 } else { u32::MAX },
+                activation_block_number: U256::zero(),
+                deprecated: false,
+            });
+            registered += 1;
+        }
+        log::info!("UOMI-ENGINE: MigrateToV6 registered {:?} models", registered);
+
+        T::DbWeight::get().reads_writes(read * 2 + 1, registered)
+            .saturating_add(Weight::from_parts(0, proof_size.saturating_mul(2)))
+    }
+
+    // This function is used to translate a value stored in Outputs with the layout of a previous version to AgentResult.
+    // The layouts are tried from the most recent and every value must be decoded entirely, a value with a layout extends the
+    // value with the previous layout, so a value can not be decoded with two layouts.
+    // It returns None if the value has already the layout of AgentResult and an error if the value has an unknown layout.
+    pub(crate) fn translate_output(value: &[u8]) -> Result<Option<AgentResult>, codec::Error> {
+        if AgentResult::decode_all(&mut &value[..]).is_ok() {
+            return Ok(None);
+        }
+        if let Ok(AgentResultV4 {
+            output_data,
+            total_executions,
+            total_consensus,
+            output_cid,
+            output_cid_expiration_block_number,
+            output_transaction,
+        }) = AgentResultV4::decode_all(&mut &value[..]) {
+            return Ok(Some(AgentResult {
+                output_data,
+                total_executions,
+                total_consensus,
+                output_cid,
+                output_cid_expiration_block_number,
+                output_transaction,
+                out_of_fuel: false,
+            }));
+        }
+        if let Ok(AgentResultV3 {
+            output_data,
+            total_executions,
+            total_consensus,
+            output_cid,
+            output_cid_expiration_block_number,
+        }) = AgentResultV3::decode_all(&mut &value[..]) {
+            return Ok(Some(AgentResult {
+                output_data,
+                total_executions,
+                total_consensus,
+                output_cid,
+                output_cid_expiration_block_number,
+                ..Default::default()
+            }));
+        }
+        let AgentResultV1 { output_data, total_executions, total_consensus } = AgentResultV1::decode_all(&mut &value[..])?;
+
+        Ok(Some(AgentResult { output_data, total_executions, total_consensus, ..Default::default() }))
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    // Migrations entry point, called by on_idle to migrate the history of the closed requests after the runtime upgrade.
+    // The values stored in Outputs are translated to AgentResult and recorded as closed on the block of the upgrade, then the
+    // requests in RequestsStatuses are recorded too. The migration continues from MigrationsCursor on the next blocks until it is completed.
+    // NOTE: The requests closed during the migration are recorded on the block of the upgrade too, so their history is pruned
+    //       up to the duration of the migration before the end of the retention period.
+    pub fn migrations_run(remaining_weight: Weight) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let mut used_weight = db_weight.reads_writes(1, 1); // The read and the write of the cursor
+        if used_weight.any_gt(remaining_weight) {
+            return Weight::zero();
+        }
+        let mut cursor = match MigrationsCursor::<T>::get() {
+            Some(cursor) => cursor,
+            None => return db_weight.reads(1),
+        };
+
+        // The read and the translation of an output, bounded by the largest output, and the record of the request
+        let output_weight = db_weight.reads_writes(1, 2).saturating_add(Weight::from_parts(0, AgentResult::max_encoded_len() as u64));
+        let status_weight = db_weight.reads_writes(1, 1); // The record of a request closed without an output
+        let mut migrated = 0u32;
+        let mut completed = false;
+
+        if !cursor.outputs_completed {
+            let mut request_ids = match cursor.last_raw_key.take() {
+                Some(last_raw_key) => Outputs::<T>::iter_keys_from(last_raw_key.into_inner()),
+                None => Outputs::<T>::iter_keys(),
+            };
+            loop {
+                if used_weight.saturating_add(output_weight).any_gt(remaining_weight) {
+                    break;
+                }
+                match request_ids.next() {
+                    Some(request_id) => {
+                        let key = Outputs::<T>::hashed_key_for(request_id);
+                        let value = unhashed::get_raw(&key).unwrap_or_default();
+                        match v6::translate_output(&value) {
+                            Ok(Some(result)) => unhashed::put(&key, &result),
+                            Ok(None) => (),
+                            Err(_) => log::error!("UOMI-ENGINE: Output of request {:?} can not be migrated, unknown layout", request_id),
+                        }
+                        FinishedRequests::<T>::insert(cursor.block_number, request_id, ());
+                        used_weight = used_weight
+                            .saturating_add(db_weight.reads_writes(1, 2))
+                            .saturating_add(Weight::from_parts(0, (key.len() + value.len()) as u64));
+                        migrated += 1;
+                    },
+                    None => {
+                        cursor.outputs_completed = true;
+                        break;
+                    },
+                }
+            }
+            if !cursor.outputs_completed {
+                cursor.last_raw_key = BoundedVec::try_from(request_ids.last_raw_key().to_vec()).ok();
+            }
+        }
+
+        if cursor.outputs_completed {
+            let mut request_ids = match cursor.last_raw_key.take() {
+                Some(last_raw_key) => RequestsStatuses::<T>::iter_keys_from(last_raw_key.into_inner()),
+                None => RequestsStatuses::<T>::iter_keys(),
+            };
+            loop {
+                if used_weight.saturating_add(status_weight).any_gt(remaining_weight) {
+                    break;
+                }
+                match request_ids.next() {
+                    Some(request_id) => {
+                        FinishedRequests::<T>::insert(cursor.block_number, request_id, ());
+                        used_weight = used_weight.saturating_add(status_weight);
+                        migrated += 1;
+                    },
+                    None => {
+                        completed = true;
+                        break;
+                    },
+                }
+            }
+            if !completed {
+                cursor.last_raw_key = BoundedVec::try_from(request_ids.last_raw_key().to_vec()).ok();
+            }
+        }
+
+        if completed {
+            MigrationsCursor::<T>::kill();
+            log::info!("UOMI-ENGINE: Migration of the history completed");
+        } else {
+            MigrationsCursor::<T>::put(cursor);
+        }
+        if migrated > 0 {
+            log::info!("UOMI-ENGINE: Migrated the history of {:?} requests", migrated);
+        }
+
+        used_weight
+    }
+}
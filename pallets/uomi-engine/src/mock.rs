@@ -1,3 +1,4 @@
+use codec::Decode;
 use core::marker::PhantomData;
 use frame_election_provider_support::{
     self,
@@ -9,7 +10,7 @@ use frame_support::{
     derive_impl,
     inherent::{InherentData, InherentIdentifier, ProvideInherent},
     parameter_types,
-    traits::{ConstU16, ConstU32, ConstU64, EstimateNextSessionRotation},
+    traits::{ConstU16, ConstU32, ConstU64, EstimateNextSessionRotation, ValidatorSet, ValidatorSetWithIdentification},
     weights::Weight,
 };
 use frame_system::offchain::{CreateSignedTransaction, SendTransactionTypes, SigningTypes};
@@ -21,14 +22,13 @@ use pallet_session::{SessionHandler, ShouldEndSession};
 use pallet_staking::TestBenchmarkingConfig;
 use sp_core::{
     sr25519::{Public, Signature},
-    ConstU128,
     Get,
     H256,
     U256,
 };
 use sp_runtime::{
     curve::PiecewiseLinear,
-    traits::{BlakeTwo256, IdentityLookup},
+    traits::{BlakeTwo256, Convert, ConvertInto, Dispatchable, IdentityLookup},
     testing::UintAuthorityId,
     BuildStorage,
     DispatchError,
@@ -37,7 +37,11 @@ use sp_runtime::{
     Permill,
     RuntimeAppPublic,
 };
-use sp_staking::currency_to_vote::SaturatingCurrencyToVote;
+use sp_staking::{
+    currency_to_vote::SaturatingCurrencyToVote,
+    offence::{OffenceError, ReportOffence},
+    SessionIndex,
+};
 use sp_std::collections::btree_map::BTreeMap;
 
 // Local imports
@@ -45,7 +49,10 @@ use crate::{
     Call,
     DispatchResult,
     InherentError,
-    types::{AiModelKey, BlockNumber, Data, RequestId},
+    offences::{IdentificationTuple, OpocOffence},
+    callbacks::RequestCallbackExecutor,
+    transactions::AgentTransactionExecutor,
+    types::{Address, AgentTransaction, AiModelKey, BlockNumber, Data, OpocOperations},
 };
 use crate as pallet_uomi_engine;
 use pallet_uomi_engine::Call as UomiCall;
@@ -96,6 +103,15 @@ impl pallet_uomi_engine::ipfs::IpfsInterface<Test> for IpfsWrapper {
     ) -> DispatchResult {
         pallet_ipfs::Pallet::<Test>::pin_file(origin, cid, duration)
     }
+
+    // In tests, the file is not added to IPFS and the CID is the hash of the data
+    fn add_file(data: Vec<u8>) -> Result<Cid, sp_runtime::offchain::http::Error> {
+        Ok(Cid::truncate_from(sp_io::hashing::blake2_256(&data).to_vec()))
+    }
+
+    fn temporary_pin(cid: &Cid, duration: u64) -> ExpirationBlockNumber {
+        pallet_ipfs::Pallet::<Test>::temporary_pin(cid, duration)
+    }
 }
 
 impl pallet_babe::Config for Test {
@@ -134,22 +150,8 @@ impl onchain::Config for OnChainSeqPhragmen {
 }
 
 impl MockInherentDataProvider {
-    fn opoc_run() -> Result<(
-        BTreeMap<AccountId, bool>,
-        BTreeMap<(RequestId, AccountId), BlockNumber>,
-        BTreeMap<AccountId, BTreeMap<RequestId, bool>>,
-        BTreeMap<AccountId, u32>,
-        BTreeMap<AccountId, u32>,
-        BTreeMap<RequestId, (Data, u32, u32)>
-    ), DispatchError> {
-        Ok((
-            BTreeMap::new(),
-            BTreeMap::new(),
-            BTreeMap::new(),
-            BTreeMap::new(),
-            BTreeMap::new(),
-            BTreeMap::new(),
-        ))
+    fn opoc_run() -> Result<OpocOperations<AccountId>, DispatchError> {
+        Ok(OpocOperations::default())
     }
 
     pub fn aimodelscalc_run() ->  BTreeMap<AiModelKey, (Data, Data, BlockNumber)> {
@@ -296,7 +298,7 @@ impl frame_system::Config for Test {
    type BaseCallFilter = frame_support::traits::Everything;
    type BlockWeights = ();
    type BlockLength = ();
-   type DbWeight = ();
+   type DbWeight = frame_support::weights::constants::RocksDbWeight;
    type RuntimeOrigin = RuntimeOrigin;
    type RuntimeCall = RuntimeCall;
    type Nonce = u64;
@@ -366,6 +368,128 @@ impl pallet_ipfs::Config for Test {
     type Currency = pallet_balances::Pallet<Test>;
     type BlockNumber = u64;
     type TemporaryPinningCost = IpfsTemporaryPinningCost;
+    type WeightInfo = ();
+}
+
+parameter_types! {
+    pub static RequestBaseFee: Balance = 0;
+    pub static RequestFeePerByte: Balance = 0;
+    pub static RequestFeePerValidator: Balance = 0;
+    pub static RequestFeePerBlock: Balance = 0;
+}
+
+pub struct TestAddressToAccountId;
+
+impl Convert<Address, AccountId> for TestAddressToAccountId {
+    fn convert(address: Address) -> AccountId {
+        let mut data = [0u8; 32];
+        data[0..20].copy_from_slice(&address.as_bytes());
+        AccountId::from_raw(data)
+    }
+}
+
+parameter_types! {
+    pub const InvalidOutputSlashFraction: Perbill = Perbill::from_percent(10);
+    pub const TimeoutSlashFraction: Perbill = Perbill::from_percent(1);
+    pub const OffenceReportDelay: u32 = 10;
+    pub static ReportedOffences: Vec<OpocOffence<IdentificationTuple<Test>>> = vec![];
+}
+
+parameter_types! {
+    pub const ReputationDecayWindow: u32 = 100;
+    pub const ModelActivationThreshold: Perbill = Perbill::from_percent(66);
+    pub const OutputsPinDuration: u32 = 28800;
+    pub const CallbackFeePerGas: u128 = 1;
+    pub const MaxCallbackGas: u64 = 1_000_000;
+    pub const RequestMaxLifetime: u32 = 1000;
+    pub const OutputsRetentionPeriod: u32 = 100;
+    pub const ArchiveOutputsHashes: bool = true;
+    pub static ExecutedCallbacks: Vec<(Address, Vec<u8>, u64)> = vec![];
+    pub static AgentCallMaxWeight: Weight = Weight::from_parts(100_000_000, 0);
+    pub static AgentCallUsedWeight: Weight = Weight::from_parts(10_000_000, 0);
+    pub static ExistentialDeposit: Balance = 1;
+}
+
+// The test executor dispatches the substrate calls of the agents, the EVM is not available on the tests.
+pub struct TestAgentTransactionExecutor;
+
+impl AgentTransactionExecutor<Test> for TestAgentTransactionExecutor {
+    fn max_weight(transaction: &AgentTransaction) -> Weight {
+        match transaction {
+            AgentTransaction::Evm { gas_limit, .. } => Weight::from_parts(*gas_limit, 0),
+            AgentTransaction::Substrate { .. } => AgentCallMaxWeight::get(),
+        }
+    }
+
+    fn execute(address: Address, transaction: &AgentTransaction) -> (Weight, DispatchResult) {
+        match transaction {
+            AgentTransaction::Evm { .. } => (Weight::zero(), Err(DispatchError::Other("EVM not available"))),
+            AgentTransaction::Substrate { call } => {
+                let call = match RuntimeCall::decode(&mut &call[..]) {
+                    Ok(call) => call,
+                    Err(_) => return (Weight::zero(), Err(DispatchError::Other("Invalid call"))),
+                };
+                let account_id = TestAddressToAccountId::convert(address);
+                match call.dispatch(RuntimeOrigin::signed(account_id)) {
+                    Ok(_) => (AgentCallUsedWeight::get(), Ok(())),
+                    Err(error) => (AgentCallUsedWeight::get(), Err(error.error)),
+                }
+            },
+        }
+    }
+}
+
+// The test executor records the callbacks, the callbacks to the address 0xFF..FF revert using all the gas.
+pub struct TestCallbackExecutor;
+
+impl RequestCallbackExecutor<Test> for TestCallbackExecutor {
+    fn gas_to_weight(gas: u64) -> Weight {
+        Weight::from_parts(gas, 0)
+    }
+
+    fn execute(target: Address, input: Vec<u8>, gas_limit: u64) -> (u64, DispatchResult) {
+        ExecutedCallbacks::mutate(|callbacks| callbacks.push((target, input, gas_limit)));
+        if target == Address::repeat_byte(0xFF) {
+            return (gas_limit, Err(DispatchError::Other("Callback reverted")));
+        }
+        (21_000, Ok(()))
+    }
+}
+
+pub struct TestValidatorSet;
+
+impl ValidatorSet<AccountId> for TestValidatorSet {
+    type ValidatorId = AccountId;
+    type ValidatorIdOf = ConvertInto;
+
+    fn session_index() -> SessionIndex {
+        pallet_session::Pallet::<Test>::current_index()
+    }
+
+    fn validators() -> Vec<AccountId> {
+        pallet_staking::Validators::<Test>::iter_keys().collect()
+    }
+}
+
+impl ValidatorSetWithIdentification<AccountId> for TestValidatorSet {
+    type Identification = AccountId;
+    type IdentificationOf = ConvertInto;
+}
+
+pub struct TestReportOffence;
+
+impl ReportOffence<AccountId, IdentificationTuple<Test>, OpocOffence<IdentificationTuple<Test>>> for TestReportOffence {
+    fn report_offence(
+        _reporters: Vec<AccountId>,
+        offence: OpocOffence<IdentificationTuple<Test>>
+    ) -> Result<(), OffenceError> {
+        ReportedOffences::mutate(|offences| offences.push(offence));
+        Ok(())
+    }
+
+    fn is_known_offence(_offenders: &[IdentificationTuple<Test>], _time_slot: &u64) -> bool {
+        false
+    }
 }
 
 impl pallet_uomi_engine::Config for Test {
@@ -375,6 +499,29 @@ impl pallet_uomi_engine::Config for Test {
     type Randomness = pallet_babe::ParentBlockRandomness<Test>;
     type IpfsPallet = IpfsWrapper;
     type InherentDataType = ();
+    type Currency = Balances;
+    type AddressToAccountId = TestAddressToAccountId;
+    type GovernanceOrigin = frame_system::EnsureRoot<AccountId>;
+    type RequestBaseFee = RequestBaseFee;
+    type RequestFeePerByte = RequestFeePerByte;
+    type RequestFeePerValidator = RequestFeePerValidator;
+    type RequestFeePerBlock = RequestFeePerBlock;
+    type ValidatorSet = TestValidatorSet;
+    type ReportOffence = TestReportOffence;
+    type InvalidOutputSlashFraction = InvalidOutputSlashFraction;
+    type TimeoutSlashFraction = TimeoutSlashFraction;
+    type OffenceReportDelay = OffenceReportDelay;
+    type ReputationDecayWindow = ReputationDecayWindow;
+    type ModelActivationThreshold = ModelActivationThreshold;
+    type OutputsPinDuration = OutputsPinDuration;
+    type AgentTransactionExecutor = TestAgentTransactionExecutor;
+    type CallbackExecutor = TestCallbackExecutor;
+    type CallbackFeePerGas = CallbackFeePerGas;
+    type MaxCallbackGas = MaxCallbackGas;
+    type RequestMaxLifetime = RequestMaxLifetime;
+    type OutputsRetentionPeriod = OutputsRetentionPeriod;
+    type ArchiveOutputsHashes = ArchiveOutputsHashes;
+    type WeightInfo = ();
 }
 
 impl pallet_timestamp::Config for Test {
@@ -391,7 +538,7 @@ impl pallet_balances::Config for Test {
    type Balance = Balance;
    type RuntimeEvent = RuntimeEvent;
    type DustRemoval = ();
-   type ExistentialDeposit = ConstU128<1>;
+   type ExistentialDeposit = ExistentialDeposit;
    type AccountStore = System;
    type WeightInfo = ();
    type FreezeIdentifier = ();
@@ -431,4 +578,14 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     .unwrap();
 
     t.into()
-}
\ No newline at end of file
+}
+
+// This function is used to take the snapshot of the active validators read by the OPoC, as the runtime does on on_finalize.
+// NOTE: The snapshot is taken only on a new session, so the session of the snapshot is cleared to take it after a change of the validators.
+pub fn snapshot_active_validators() {
+    use frame_support::traits::Hooks;
+
+    crate::ActiveValidatorsSessionIndex::<Test>::kill();
+    crate::InherentDidUpdate::<Test>::put(true);
+    TestingPallet::on_finalize(System::block_number());
+}
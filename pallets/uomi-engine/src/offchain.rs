@@ -2,24 +2,55 @@ use frame_support::pallet_prelude::{DispatchError, DispatchResult};
 use frame_system::offchain::{SendUnsignedTransaction, Signer};
 use pallet_ipfs::types::{Cid, ExpirationBlockNumber, UsableFromBlockNumber};
 use pallet_ipfs::MinExpireDuration;
-use sp_core::U256;
+use sp_core::{H256, U256};
+use sp_io::hashing::blake2_256;
+use frame_support::{traits::Get, BoundedVec};
+use sp_runtime::offchain::{http, storage::{MutateStorageError, StorageRetrievalError, StorageValueRef}};
 use sp_std::{
+    collections::btree_map::BTreeMap,
+    marker::PhantomData,
     vec,
     vec::Vec,
 };
 use scale_info::prelude::string::String;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicU32, Ordering};
 
-static mut SEMAPHORE: AtomicBool = AtomicBool::new(false);
+// This is the number of executions of agents running on the node, shared by the offchain workers of all the blocks.
+static ACTIVE_WORKERS: AtomicU32 = AtomicU32::new(0);
+
+// OffchainWorkerSlot is a slot of the worker pool of the node, released when it is dropped.
+struct OffchainWorkerSlot;
+
+impl OffchainWorkerSlot {
+    // This function is used to acquire a slot, it returns None if max_workers executions are already running.
+    fn acquire(max_workers: u32) -> Option<Self> {
+        ACTIVE_WORKERS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| (active < max_workers).then(|| active + 1))
+            .ok()
+            .map(|_| Self)
+    }
+}
+
+impl Drop for OffchainWorkerSlot {
+    fn drop(&mut self) {
+        ACTIVE_WORKERS.fetch_sub(1, Ordering::AcqRel);
+    }
+}
 
 use crate::{
-    consts::{MAX_INPUTS_MANAGED_PER_BLOCK, PALLET_VERSION, TEMP_BLOCK_FOR_NEW_OPOC},
+    consts::{
+        AI_SERVICE_CONFIG_STORAGE_KEY, DEFAULT_AI_SERVICE_URL, DEFAULT_MAX_WORKERS, MAX_INPUTS_MANAGED_PER_BLOCK, MAX_WORKERS, MAX_WORKERS_STORAGE_KEY,
+        PALLET_VERSION, PENDING_REVEALS_STORAGE_KEY, STARTED_REQUESTS_STORAGE_KEY, TEMP_BLOCK_FOR_NEW_OPOC,
+    },
     ipfs::IpfsInterface,
-    payloads::{PayloadNodesOutputs, PayloadNodesVersions, PayloadNodesOpocL0Inferences},
-    types::{BlockNumber, Data, NftId, RequestId, Version, AiModelKey},
-    {BlockTime, Call, Config, Inputs, NodesOutputs, NodesVersions, OpocAssignment, Pallet, AIModels, NodesOpocL0Inferences},
+    payloads::{PayloadNodesOutputs, PayloadNodesOutputsCommitments, PayloadNodesVersions, PayloadNodesModels, PayloadNodesOpocL0Inferences},
+    types::{AgentRequest, AiServiceConfig, AiServiceEndpoint, BlockNumber, Data, ExecutionLogs, NftId, NodeOutput, OutputCid, RequestId, Version, AiModelKey, WasmOutput},
+    {BlockTime, Call, Config, Inputs, MaxDataSize, NodesOutputs, NodesOutputsCommitments, NodesVersions, NodesModels, OpocAssignment, Pallet, AIModels, ModelsRegistry, MaxModelsPerNode, NodesOpocL0Inferences},
 };
 
+#[cfg(feature = "std")]
+use crate::{types::ExecutionBudget, wasm::{wasm_interrupted, wasm_run, AgentHost}};
+
 #[derive(miniserde::Serialize, miniserde::Deserialize)]
 struct CallAiRequestWithProof {
     model: String,
@@ -45,9 +76,15 @@ struct CallAiResponseCleaned {
     response: String,
 }
 
+#[derive(miniserde::Serialize, miniserde::Deserialize)]
+struct AiServiceStatus {
+    models: Vec<String>, // The names of the models loaded by the AI service.
+}
+
 impl<T: Config> Pallet<T> {
-    pub fn semaphore_status() -> bool {
-        unsafe { SEMAPHORE.load(Ordering::Relaxed) }
+    // This function is used to get the number of executions of agents running on the node.
+    pub fn offchain_active_workers() -> u32 {
+        ACTIVE_WORKERS.load(Ordering::Acquire)
     }
 
     // Offchain worker entry point
@@ -69,6 +106,25 @@ impl<T: Config> Pallet<T> {
             });
         }
 
+        // Store the AI models served by the node on the chain
+        match Self::offchain_get_served_models() {
+            Ok(ai_model_keys) => {
+                if ai_model_keys != NodesModels::<T>::get(&account_id) { // we don't need to store the models if they are already stored correctly
+                    Self::offchain_store_models(&ai_model_keys).unwrap_or_else(|e| {
+                        log::error!("UOMI-ENGINE: Error storing updated node models: {:?}", e);
+                    });
+                }
+            },
+            Err(e) => {
+                log::error!("UOMI-ENGINE: Error getting the models served by the node: {:?}", e);
+            },
+        }
+
+        // Reveal the outputs committed by the node when all the validators of the requests have committed
+        Self::offchain_reveal_outputs(&account_id).unwrap_or_else(|e| {
+            log::error!("UOMI-ENGINE: Error revealing outputs: {:?}", e);
+        });
+
         // Run agents
         Self::offchain_run_agents(&account_id).unwrap_or_else(|e| {
             log::error!("UOMI-ENGINE: Error running agents: {:?}", e);
@@ -81,32 +137,30 @@ impl<T: Config> Pallet<T> {
     fn offchain_run_agents(account_id: &T::AccountId) -> DispatchResult {
         log::info!("UOMI-ENGINE: Running agents");
 
-        // Check semaphore to be sure to do nothing if another agent is running
-        // SAFETY: This is safe because offchain workers run in their own thread
-        let semaphore = unsafe { &SEMAPHORE };
-        if semaphore.compare_exchange(
-            false,  // expected value
-            true,   // new value
-            Ordering::Acquire,
-            Ordering::Relaxed
-        ).is_err() {
-            // Another worker is already running
-            log::info!("UOMI-ENGINE: Another worker is already running");
+        // Acquire a slot of the worker pool to be sure to do nothing if all the workers of the node are running an agent
+        // NOTE: The offchain workers of consecutive blocks run in parallel, every one of them runs at most one request while it holds its slot.
+        let Some(_slot) = OffchainWorkerSlot::acquire(Self::offchain_get_max_workers()) else {
+            log::info!("UOMI-ENGINE: All the workers are already running");
             return Ok(());
-        }
+        };
 
-        // Find the request with less expiration block number to execute
-        let (request_id, expiration_block_number) = Self::offchain_find_request_with_min_expiration_block_number(&account_id);
-        if request_id == RequestId::default() {
+        // Claim the request with less expiration block number not started yet by the node
+        let Some((request_id, expiration_block_number)) = Self::offchain_claim_request(&account_id) else {
             log::info!("UOMI-ENGINE: No requests to run found");
-            // Unlock the semaphore
-            semaphore.store(false, Ordering::Release);
             return Ok(());
-        }
+        };
         log::info!("UOMI-ENGINE: Request with request id: {:?} - Expiration block number: {:?}", request_id, expiration_block_number);
 
         // Load request data from Inputs storage
-        let (block_number, nft_id, nft_required_consensus, nft_execution_max_time, nft_file_cid, input_data, input_file_cid) = Inputs::<T>::get(&request_id);
+        let AgentRequest {
+            block_number,
+            nft_id,
+            nft_required_consensus,
+            nft_execution_max_time,
+            nft_file_cid,
+            input_data,
+            input_file_cid,
+        } = Inputs::<T>::get(&request_id);
         log::info!("UOMI-ENGINE: Request data loaded with block number: {:?}, nft_id: {:?} and nft_execution_max_time: {:?}", block_number, nft_id, nft_execution_max_time);
 
         // Detect the level of opoc the execution should have
@@ -118,42 +172,96 @@ impl<T: Config> Pallet<T> {
             Err(error) => {
                 log::error!("UOMI-ENGINE: Error loading the wasm from the NFT ID: {:?}", error);
                 // In case of error loading the wasm, complete the request with an empty output
-                Self::offchain_store_output_data(&request_id, &Data::default()).unwrap_or_else(|e| {
+                Self::offchain_store_output_data(&request_id, &NodeOutput::default()).unwrap_or_else(|e| {
                     log::error!("UOMI-ENGINE: Error storing output data: {:?}", e);
                 });
-                // Unlock the semaphore
-                semaphore.store(false, Ordering::Release);
                 return Ok(());
             },
         };
         log::info!("UOMI-ENGINE: Wasm loaded with length: {:?}", wasm.len());
 
         // Run the wasm and store the output data
-        match Self::offchain_run_wasm(wasm, input_data, input_file_cid, block_number, expiration_block_number, nft_required_consensus, nft_execution_max_time, opoc_level, request_id) {
-            Ok(output_data) => {
-                log::info!("UOMI-ENGINE: Request {:?} executed successfully with output data length: {:?}", request_id, output_data.len());
-                // Store the output data
-                Self::offchain_store_output_data(&request_id, &output_data).unwrap_or_else(|e| {
+        let (result, logs) = Self::offchain_run_wasm_with_logs(wasm, Some(&nft_file_cid), input_data, input_file_cid, block_number, expiration_block_number, nft_required_consensus, nft_execution_max_time, opoc_level, request_id);
+        // Store the logs of the execution, also when the execution fails, so they can be inspected over RPC
+        Self::offchain_store_execution_logs(&request_id, &logs);
+        match result {
+            Ok(WasmOutput { output, output_transaction, out_of_fuel, ai_models, .. }) => {
+                log::info!("UOMI-ENGINE: Request {:?} executed successfully with output data length: {:?} - Out of fuel: {:?}", request_id, output.len(), out_of_fuel);
+                // Store the output data, or its reference on IPFS if it is too large to be stored on chain, with the transaction emitted by the agent
+                // The AI models called by the agent are stored with the output, the request is charged only for their fees
+                let ai_models = BoundedVec::truncate_from(ai_models);
+                let node_output = NodeOutput { output_transaction, out_of_fuel, ai_models, ..Self::offchain_node_output(output) };
+                Self::offchain_store_output_data(&request_id, &node_output).unwrap_or_else(|e| {
                     log::error!("UOMI-ENGINE: Error storing output data: {:?}", e);
                 });
             },
+            Err(error) if wasm_interrupted(&error) => {
+                // The execution interrupted by the timeout of the node has no output comparable with the ones of the other validators,
+                // so nothing is committed and the validator is in timeout for the request
+                log::warn!("UOMI-ENGINE: Request {:?} interrupted by the timeout of the node before the end of its fuel, the output is not committed", request_id);
+            },
             Err(error) => {
                 log::error!("UOMI-ENGINE: Error running request {:?}: {:?}", request_id, error);
                 // In case of error running the wasm, complete the request with an empty output
-                Self::offchain_store_output_data(&request_id, &Data::default()).unwrap_or_else(|e| {
+                Self::offchain_store_output_data(&request_id, &NodeOutput::default()).unwrap_or_else(|e| {
                     log::error!("UOMI-ENGINE: Error storing output data: {:?}", e);
                 });
             },
         }
 
-        // Unlock the semaphore
-        semaphore.store(false, Ordering::Release);
-
         log::info!("UOMI-ENGINE: Request {:?} completed", request_id);
         Ok(())
     }
 
-    fn offchain_find_request_with_min_expiration_block_number(account_id: &T::AccountId) -> (RequestId, BlockNumber) {
+    // This function is used to get the maximum number of agents executed in parallel by the node, stored by the node on the offchain local storage.
+    pub fn offchain_get_max_workers() -> u32 {
+        StorageValueRef::persistent(MAX_WORKERS_STORAGE_KEY)
+            .get::<u32>()
+            .ok()
+            .flatten()
+            .unwrap_or(DEFAULT_MAX_WORKERS)
+            .clamp(1, MAX_WORKERS)
+    }
+
+    // This function is used to claim the request with less expiration block number assigned to the validator and not started yet by the node.
+    // The started requests are stored on the offchain local storage, so every request runs at most once on the node, also across the offchain
+    // workers of different blocks running in parallel and across restarts of the node.
+    // NOTE: The requests are stored with the expiration block number of their assignment, so a request assigned again to the validator runs again.
+    pub fn offchain_claim_request(account_id: &T::AccountId) -> Option<(RequestId, BlockNumber)> {
+        let requests = Self::offchain_find_requests_by_expiration_block_number(account_id);
+        if requests.is_empty() {
+            return None;
+        }
+
+        let current_block_number = U256::from(0) + <frame_system::Pallet<T>>::block_number();
+        let storage = StorageValueRef::persistent(STARTED_REQUESTS_STORAGE_KEY);
+        // NOTE: The claim is retried if an offchain worker of another block claims a request at the same time.
+        for _ in 0..3 {
+            let mut claimed = None;
+            let result = storage.mutate(|started: Result<Option<BTreeMap<(RequestId, BlockNumber), BlockNumber>>, StorageRetrievalError>| {
+                let mut started = started.ok().flatten().unwrap_or_default();
+                // Forget the requests whose assignment is removed, they are completed or assigned to other validators
+                started.retain(|(request_id, expiration_block_number), _| {
+                    OpocAssignment::<T>::contains_key(request_id, account_id) && OpocAssignment::<T>::get(request_id, account_id) == *expiration_block_number
+                });
+                let request = requests.iter().find(|request| !started.contains_key(request)).cloned().ok_or(())?;
+                started.insert(request, current_block_number);
+                claimed = Some(request);
+                Ok::<_, ()>(started)
+            });
+            match result {
+                Ok(_) => return claimed,
+                Err(MutateStorageError::ValueFunctionFailed(())) => return None,
+                Err(MutateStorageError::ConcurrentModification(_)) => continue,
+            }
+        }
+
+        log::error!("UOMI-ENGINE: Error claiming a request, the started requests are modified concurrently");
+        None
+    }
+
+    // This function is used to get the requests assigned to the validator without an output, sorted by expiration block number.
+    fn offchain_find_requests_by_expiration_block_number(account_id: &T::AccountId) -> Vec<(RequestId, BlockNumber)> {
         let mut opoc_assignments = Vec::<(RequestId, BlockNumber)>::new();
         let inputs = Inputs::<T>::iter().collect::<Vec<_>>();
 
@@ -167,8 +275,8 @@ impl<T: Config> Pallet<T> {
                 continue;
             }
 
-            // Be sure request is not already managed by checking if the request_id is in the NodesOutputs storage
-            let has_node_output = NodesOutputs::<T>::contains_key(*request_id, &account_id);
+            // Be sure request is not already managed by checking if the request_id is in the NodesOutputsCommitments or NodesOutputs storage
+            let has_node_output = NodesOutputsCommitments::<T>::contains_key(*request_id, &account_id) || NodesOutputs::<T>::contains_key(*request_id, &account_id);
             if has_node_output {
                 log::info!("Request {:?} is already managed", request_id);
                 continue;
@@ -183,16 +291,10 @@ impl<T: Config> Pallet<T> {
             log::info!("Request {:?} is assigned to the validator and not already managed", request_id);
         }
 
-        // Return zero if no opoc_assignments has been found
-        if opoc_assignments.is_empty() {
-            return (Default::default(), Default::default());
-        }
-
         // Sort opoc_assignments by expiration block number
         opoc_assignments.sort_by(|a, b| a.1.cmp(&b.1));
 
-        // Return first request
-        opoc_assignments[0]
+        opoc_assignments
     }
 
     fn offchain_load_wasm_from_nft_id(nft_id: &NftId, nft_file_cid: &Cid) -> Result<Vec<u8>, DispatchError> {
@@ -233,186 +335,43 @@ impl<T: Config> Pallet<T> {
     }
 
     #[cfg(feature = "std")]
-    pub fn offchain_run_wasm(wasm: Vec<u8>, input_data: Data, input_file_cid: Cid, block_number: BlockNumber, expiration_block_number: BlockNumber, nft_required_consensus: U256, nft_execution_max_time: U256, opoc_level: u8, request_id: RequestId) -> Result<Data, wasmtime::Error> {
+    pub fn offchain_run_wasm(wasm: Vec<u8>, input_data: Data, input_file_cid: Cid, block_number: BlockNumber, expiration_block_number: BlockNumber, nft_required_consensus: U256, nft_execution_max_time: U256, opoc_level: u8, request_id: RequestId) -> Result<WasmOutput, wasmtime::Error> {
+        let (result, _logs) = Self::offchain_run_wasm_with_logs(wasm, None, input_data, input_file_cid, block_number, expiration_block_number, nft_required_consensus, nft_execution_max_time, opoc_level, request_id);
+        result
+    }
+
+    // This function is used to run the wasm of an agent returning, together with the result, the logs collected during the execution.
+    // NOTE: The logs are returned also when the execution fails, they are empty only if the execution has not been started.
+    // NOTE: The wasm is compiled once for the nft_file_cid when the node uses a cache of the compiled modules.
+    #[cfg(feature = "std")]
+    pub fn offchain_run_wasm_with_logs(wasm: Vec<u8>, nft_file_cid: Option<&Cid>, input_data: Data, input_file_cid: Cid, block_number: BlockNumber, expiration_block_number: BlockNumber, nft_required_consensus: U256, nft_execution_max_time: U256, opoc_level: u8, request_id: RequestId) -> (Result<WasmOutput, wasmtime::Error>, ExecutionLogs) {
         // Convert input_data to a Vec<u8>
         let input_data_as_vec = input_data.to_vec();
 
-        // Calculate the timeout for the execution of the request
-        // The timeout should be calculated as expiration_block_number - start_block
-        // If timeout is > nft_execution_max_time - 3, timeout should be nft_execution_max_time - 3
+        // Calculate the timeout for the execution of the request as the time until the expiration of the assignment
+        // NOTE: The timeout is only a backstop of the node, the output of an execution running after the expiration can not be committed anyway.
+        // The fuel of the budget is the only limit of the execution compared by the OPoC, so it is the same on every validator.
         // NOTE: We calculate time after the wasm loading to avoid the wasm loading time to be counted in the timeout.
         let start_block = U256::from(0) + <frame_system::Pallet<T>>::block_number();
-        let timeout_blocks_max = nft_execution_max_time - U256::from(3);
         if expiration_block_number < start_block { // NOTE: This case should never happen, but check to avoid runtime error
             log::error!("UOMI-ENGINE: Expiration block number is before the start block number");
-            return Err(wasmtime::Error::new(std::io::Error::new(std::io::ErrorKind::Other, "Expiration block number is before the start block number")));
-        }
-        let mut timeout_blocks = expiration_block_number - start_block;
-        if timeout_blocks > timeout_blocks_max {
-            timeout_blocks = nft_execution_max_time - U256::from(3);
+            return (Err(wasmtime::Error::new(std::io::Error::new(std::io::ErrorKind::Other, "Expiration block number is before the start block number"))), ExecutionLogs::default());
         }
-        let timeout_time = timeout_blocks * U256::from(BlockTime::get());
-        let timeout_time_cs = timeout_time.low_u64() as u64 * 10;
-        let timeout_time_ms = timeout_time.low_u64() as u64 * 1000;
-
-        type HostState = Vec<u8>;
-        let mut config = wasmtime::Config::new();
-        config.epoch_interruption(true);
-        let engine = match wasmtime::Engine::new(&config) {
-            Ok(engine) => engine,
-            Err(error) => {
-                log::error!("UOMI-ENGINE: Error creating the wasm engine: {:?}", error);
-                return Err(error);
-            },
-        };
-        let mut store = wasmtime::Store::new(&engine, HostState::new());
-        store.set_epoch_deadline(timeout_time_cs);
-        store.epoch_deadline_trap();
-        let module = match wasmtime::Module::new(&engine, &wasm) {
-            Ok(module) => module,
-            Err(error) => {
-                log::error!("UOMI-ENGINE: Error loading the wasm module: {:?}", error);
-                return Err(error);
-            },
-        };
-
-        let get_input_data = move |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, _len: i32| {
-            let data_to_write = Self::offchain_worker_generate_data_for_wasm(input_data_as_vec.clone());
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            memory.write(caller, ptr as usize, &data_to_write).expect("Failed to write memory");
-        };
-
-        let get_input_file = move |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, _len: i32| {
-            let file = match T::IpfsPallet::get_file(&input_file_cid) {
-                Ok(file) => file,
-                Err(error) => {
-                    log::error!("Error getting the file from the IPFS pallet: {:?}", error);
-                    Vec::new()
-                }
-            };
-            let data_to_write = Self::offchain_worker_generate_data_for_wasm(file);
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            memory.write(caller, ptr as usize, &data_to_write).expect("Failed to write memory");
-        };
-
-        let set_output = move |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32| {
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            let mut buffer = vec![0u8; len as usize];
-            memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
-            *caller.data_mut() = buffer;
-        };
-
-        let set_output_transaction = move |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32| {
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            let mut buffer = vec![0u8; len as usize];
-            memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
-            *caller.data_mut() = buffer;
-        };
-
-        let get_cid_file = move |mut caller: wasmtime::Caller<'_, HostState>, ptr: i32, len: i32, output_ptr: i32, _: i32| {
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            let mut buffer = vec![0u8; len as usize];
-            memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
-            let cid = match Cid::try_from(buffer) {
-                Ok(cid) => cid,
-                Err(error) => {
-                    log::error!("Error converting buffer to CID: {:?}", error);
-                    Cid::default()
-                }
-            };
-            let file = match Self::offcahin_worker_get_cid_file(cid, block_number) {
-                Ok(file) => file,
-                Err(error) => {
-                    log::error!("Error getting the file from the IPFS pallet: {:?}", error);
-                    Vec::new()
-                }
-            };
-            let data_to_write = Self::offchain_worker_generate_data_for_wasm(file);
-            memory.write(caller, output_ptr as usize, &data_to_write).expect("Failed to write memory");
-        };
-
-        let console_log = move |caller: wasmtime::Caller<'_, HostState>, _ptr: i32, _len: i32| {
-            // Do nothing, function exposed to help wasm debugging
-        };
-
-        // NOTE: The call_ai function is "special". It needs to track the number of calls and count them by incrementing a counter.
-        // This is required to permit us to log the executions and store them on OpocL0Inferences (on Opoc level 0) or read them from OpocL0Inferences (on Opoc level 1/2).
-        let call_ai_counter = std::sync::RwLock::new(0u32);
-        let call_ai = move |mut caller: wasmtime::Caller<'_, HostState>, model: i32, ptr: i32, len: i32, output_ptr: i32, _: i32| {
-            *call_ai_counter.write().unwrap() += 1;
-
-            let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
-            let mut buffer = vec![0u8; len as usize];
-            memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
-            let model = AiModelKey::from(model as u32);
-            let output = match Self::offchain_worker_call_ai(model, block_number, buffer, nft_required_consensus, opoc_level, *call_ai_counter.read().unwrap(), request_id) {
-                Ok(output) => output,
-                Err(error) => {
-                    log::error!("Error calling the AI: {:?}", error);
-                    Vec::new()
-                }
-            };
-            let data_to_write = Self::offchain_worker_generate_data_for_wasm(output);
-            memory.write(caller, output_ptr as usize, &data_to_write).expect("Failed to write memory");
-        };
-
-        let mut linker = wasmtime::Linker::new(&engine);
-        linker.func_wrap("env", "get_input_file", get_input_file).unwrap();
-        linker.func_wrap("env", "get_input_data", get_input_data).unwrap();
-        linker.func_wrap("env", "set_output", set_output).unwrap();
-        linker.func_wrap("env", "set_output_transaction", set_output_transaction).unwrap();
-        linker.func_wrap("env", "get_cid_file", get_cid_file).unwrap();
-        linker.func_wrap("env", "console_log", console_log).unwrap();
-        linker.func_wrap("env", "call_ai", call_ai).unwrap();
-
-        let instance = match linker.instantiate(&mut store, &module) {
-            Ok(instance) => instance,
-            Err(error) => {
-                log::error!("Error instantiating the wasm module: {:?}", error);
-                return Err(error);
-            }
-        };
-        let run = match instance.get_typed_func::<(), ()>(&mut store, "run") {
-            Ok(run) => run,
-            Err(error) => {
-                log::error!("Error getting the run function: {:?}", error);
-                return Err(error);
-            }
+        let timeout_blocks = expiration_block_number - start_block;
+        let timeout_time = timeout_blocks.saturating_mul(U256::from(BlockTime::get()));
+        let timeout_time_ms = timeout_time.low_u64().saturating_mul(1000);
+
+        let host = OffchainAgentHost::<T> {
+            input_file_cid,
+            block_number,
+            nft_required_consensus,
+            opoc_level,
+            request_id,
+            _marker: PhantomData,
         };
-
-        // Start a thread to increment the epoch counter
-        let mut time_passed_ms = 0;
-        let engine_clone = engine.clone();
-        std::thread::spawn(move || {
-            while time_passed_ms < timeout_time_ms {
-                std::thread::sleep(std::time::Duration::from_millis(100));
-                engine_clone.increment_epoch();
-                time_passed_ms += 100;
-            }
-        });
-
-        match run.call(&mut store, ()) {
-            Ok(_) => {
-                let stored_data = store.data().clone();
-                let data: Data = stored_data.try_into().unwrap_or_else(|_| Data::default());
-                Ok(data)
-            }
-            Err(err) => {
-                log::error!("UOMI-ENGINE: WASM execution error: {:?}", err);
-                Err(wasmtime::Error::new(std::io::Error::new(std::io::ErrorKind::Other, "WASM execution error")))
-            }
-        }
-    }
-
-    fn offchain_worker_generate_data_for_wasm(data: Vec<u8>) -> Vec<u8> {
-        let data_len = data.len();
-        let mut wasm_data = Vec::new();
-
-        // write data_len on first 4 bytes of wasm_data, then write data
-        wasm_data.extend(&(data_len as u32).to_le_bytes());
-        wasm_data.extend(data);
-
-        wasm_data
+        // NOTE: The fuel and the memory of the execution derive from the request, so they are the same on every validator.
+        let budget = ExecutionBudget::from_execution_max_time(nft_execution_max_time);
+        wasm_run(&wasm, nft_file_cid.filter(|cid| !cid.is_empty()), input_data_as_vec, timeout_time_ms, budget, host)
     }
 
     #[cfg(feature = "std")]
@@ -422,8 +381,9 @@ impl<T: Config> Pallet<T> {
             return Ok(output);
         }
 
-        if model >= U256::from(100) && required_consensus > U256::from(1) { // Models with id > 100 (example image generation) can not be called with security (consensus > 1)
-            return Err(DispatchError::Other("Model can not be called by agents with required consensus > 1"));
+        let required_consensus = u32::try_from(required_consensus).unwrap_or(u32::MAX);
+        if !Self::aimodelscalc_is_allowed_consensus(&model, required_consensus) { // Models like image generation can not be called with security (consensus > 1)
+            return Err(DispatchError::Other("Model not registered or not allowed for the required consensus of the agent"));
         }
 
         let (local_name, previous_local_name, available_from_block_number) = AIModels::<T>::get(&model);
@@ -437,6 +397,8 @@ impl<T: Config> Pallet<T> {
             return Err(DispatchError::Other("Error getting the model name from the AiModels storage - final_local_name is empty"));
         }
 
+        let endpoint = Self::offchain_get_ai_service_config().endpoint(&model).clone();
+
         let input_data = String::from_utf8(input).map_err(|_| {
             log::error!("UOMI-ENGINE: Invalid UTF-8 in input data");
             DispatchError::Other("Invalid UTF-8 in input data")
@@ -455,7 +417,7 @@ impl<T: Config> Pallet<T> {
             };
             let body = miniserde::json::to_string(&body_data);
 
-            let output = Self::offchain_worker_call_ai_send_request(body)?;
+            let output = Self::offchain_worker_call_ai_send_request(&endpoint, body)?;
             let output_string = String::from_utf8(output.to_vec()).map_err(|_| {
                 log::error!("UOMI-ENGINE: Invalid UTF-8 in output data");
                 DispatchError::Other("Invalid UTF-8 in output data")
@@ -468,11 +430,8 @@ impl<T: Config> Pallet<T> {
             let output_json_cleaned = CallAiResponseCleaned {
                 response: output_json.response,
             };
-            let output_string_cleaned = miniserde::json::to_string(&output_json_cleaned);
-            let output: Data = output_string_cleaned.as_bytes().to_vec().try_into().map_err(|_| {
-                log::error!("UOMI-ENGINE: Failed to convert output to Data type");
-                DispatchError::Other("Failed to convert output")
-            })?;
+            // NOTE: The output is not bounded to MaxDataSize, the large outputs of the agent are offloaded to IPFS
+            let output = miniserde::json::to_string(&output_json_cleaned).into_bytes();
 
             if !output_json.proof.is_empty() && current_block_number >= TEMP_BLOCK_FOR_NEW_OPOC.into() { // For finney update. remove on turing {
                 // Store the inference on OpocL0Inferences
@@ -502,7 +461,7 @@ impl<T: Config> Pallet<T> {
                 );
             }
 
-            Ok(output.to_vec())
+            Ok(output)
         } else {
             let mut proof: Data = Data::default();
             for (_account_id, inference_data) in NodesOpocL0Inferences::<T>::iter_prefix(request_id) { // TODO: On turing, we need to be sure the account_id is the same of the node used on opoc level 0
@@ -528,7 +487,7 @@ impl<T: Config> Pallet<T> {
                 miniserde::json::to_string(&body_data)
             };
 
-            let output = Self::offchain_worker_call_ai_send_request(body)?;
+            let output = Self::offchain_worker_call_ai_send_request(&endpoint, body)?;
             let output_string = String::from_utf8(output.to_vec()).map_err(|_| {
                 log::error!("UOMI-ENGINE: Invalid UTF-8 in output data");
                 DispatchError::Other("Invalid UTF-8 in output data")
@@ -542,39 +501,15 @@ impl<T: Config> Pallet<T> {
             let output_json_cleaned = CallAiResponseCleaned {
                 response: output_json.response,
             };
-            let output_string_cleaned = miniserde::json::to_string(&output_json_cleaned);
-            let output: Data = output_string_cleaned.as_bytes().to_vec().try_into().map_err(|_| {
-                log::error!("UOMI-ENGINE: Failed to convert output to Data type");
-                DispatchError::Other("Failed to convert output")
-            })?;
+            // NOTE: The output is not bounded to MaxDataSize, the large outputs of the agent are offloaded to IPFS
+            let output = miniserde::json::to_string(&output_json_cleaned).into_bytes();
 
-            Ok(output.to_vec())
+            Ok(output)
         }
     }
     
-    fn offchain_worker_call_ai_send_request(body: String) -> Result<Data, DispatchError> {
-        let url = "http://127.0.0.1:8888/run";
-
-        let mut request = sp_runtime::offchain::http::Request::post(url, vec![body.as_bytes()]);
-        request = request
-            .add_header("Content-Type", "application/json")
-            .add_header("Accept", "application/json");
-
-        let pending = match request.send() {
-            Ok(pending_request) => pending_request,
-            Err(e) => {
-                log::error!("UOMI-ENGINE: Failed to send HTTP request: {:?}", e);
-                return Err(DispatchError::Other("Failed to send HTTP request"));
-            }
-        };
-    
-        let response = match pending.wait() {
-            Ok(response) => response,
-            Err(e) => {
-                log::error!("UOMI-ENGINE: HTTP request failed after sending: {:?}", e);
-                return Err(DispatchError::Other("HTTP request failed after sending"));
-            }
-        };
+    fn offchain_worker_call_ai_send_request(endpoint: &AiServiceEndpoint, body: String) -> Result<Vec<u8>, DispatchError> {
+        let response = Self::offchain_worker_send_ai_service_request(endpoint, "/run", Some(body))?;
     
         if response.code != 200 {
             let body = response.body().collect::<Vec<u8>>();
@@ -586,13 +521,7 @@ impl<T: Config> Pallet<T> {
             return Err(DispatchError::Other("Error response from AI service"));
         }
     
-        let response_body = response.body().collect::<Vec<u8>>();
-        let output: Data = response_body.try_into().map_err(|_| {
-            log::error!("UOMI-ENGINE: Failed to convert response body to Data type");
-            DispatchError::Other("Failed to convert response body")
-        })?;
-
-        Ok(output)
+        Ok(response.body().collect::<Vec<u8>>())
     }
 
     fn offcahin_worker_get_cid_file(cid: Cid, block_number: BlockNumber) -> Result<Vec<u8>, DispatchError> {
@@ -624,7 +553,261 @@ impl<T: Config> Pallet<T> {
         Ok(file)
     }
 
-    fn offchain_store_output_data(request_id: &RequestId, output_data: &Data) -> DispatchResult {
+    // This function is used to build the output stored by the node from the output of the wasm.
+    // The outputs larger than MaxDataSize are added to the local IPFS node and only their CID and hash are stored on chain,
+    // the other validators of the OPoC add the same output to IPFS, so they get the same CID and hash.
+    #[cfg(feature = "std")]
+    pub fn offchain_node_output(output: Vec<u8>) -> NodeOutput {
+        if output.len() <= MaxDataSize::get() as usize {
+            return NodeOutput { output_data: Data::truncate_from(output), ..Default::default() };
+        }
+
+        let hash = H256::from(blake2_256(&output));
+        match T::IpfsPallet::add_file(output) {
+            Ok(cid) => {
+                log::info!("UOMI-ENGINE: Output added to IPFS with CID: {:?}", cid);
+                NodeOutput { output_cid: Some(OutputCid { cid, hash }), ..Default::default() }
+            },
+            Err(error) => {
+                log::error!("UOMI-ENGINE: Error adding the output to IPFS: {:?}", error);
+                NodeOutput::default()
+            },
+        }
+    }
+
+    // This function is used to commit the salted hash of the output of the node, the output and the salt are kept on the offchain local storage
+    // until the output is revealed by offchain_reveal_outputs.
+    fn offchain_store_output_data(request_id: &RequestId, node_output: &NodeOutput) -> DispatchResult {
+        let signer = Signer::<T, T::UomiAuthorityId>::all_accounts();
+        if !signer.can_sign() {
+            log::error!("No accounts available to sign the transaction");
+            return Err(DispatchError::Other("No accounts available to sign"));
+        }
+
+        let salt = H256::from(sp_io::offchain::random_seed());
+        Self::offchain_mutate_pending_reveals(|pending_reveals| {
+            pending_reveals.insert(*request_id, (node_output.clone(), salt));
+        })?;
+
+        let _ = signer.send_unsigned_transaction(
+            |acct| PayloadNodesOutputsCommitments {
+                request_id: request_id.clone(),
+                output_hash: Self::opoc_output_hash(node_output, &salt, &acct.id),
+                public: acct.public.clone(),
+            },
+            |payload, signature| Call::commit_nodes_outputs {
+                payload,
+                signature
+            },
+        );
+
+        Ok(())
+    }
+
+    // This function is used to reveal the outputs committed by the node when the reveal of their requests is open.
+    // The outputs of the requests closed or already revealed are removed from the offchain local storage.
+    fn offchain_reveal_outputs(account_id: &T::AccountId) -> DispatchResult {
+        let pending_reveals = StorageValueRef::persistent(PENDING_REVEALS_STORAGE_KEY)
+            .get::<BTreeMap<RequestId, (NodeOutput, H256)>>()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        if pending_reveals.is_empty() {
+            return Ok(());
+        }
+
+        let signer = Signer::<T, T::UomiAuthorityId>::all_accounts();
+        if !signer.can_sign() {
+            log::error!("No accounts available to sign the transaction");
+            return Err(DispatchError::Other("No accounts available to sign"));
+        }
+
+        let current_block_number = U256::from(0) + <frame_system::Pallet<T>>::block_number();
+        let mut closed_requests = Vec::<RequestId>::new();
+        for (request_id, (node_output, salt)) in pending_reveals.iter() {
+            // The output is no more needed when the request is closed or when it is revealed
+            if !Inputs::<T>::contains_key(request_id) || NodesOutputs::<T>::contains_key(request_id, account_id) {
+                closed_requests.push(*request_id);
+                continue;
+            }
+
+            // Wait the commitment to be stored and the other validators to commit
+            if !NodesOutputsCommitments::<T>::contains_key(request_id, account_id) || !Self::opoc_reveal_open(request_id, &current_block_number) {
+                continue;
+            }
+
+            log::info!("UOMI-ENGINE: Revealing output for request {:?}", request_id);
+            let _ = signer.send_unsigned_transaction(
+                |acct| PayloadNodesOutputs {
+                    request_id: request_id.clone(),
+                    output_data: node_output.output_data.clone(),
+                    output_cid: node_output.output_cid.clone(),
+                    output_transaction: node_output.output_transaction.clone(),
+                    out_of_fuel: node_output.out_of_fuel,
+                    ai_models: node_output.ai_models.clone(),
+                    salt: *salt,
+                    public: acct.public.clone(),
+                },
+                |payload, signature| Call::store_nodes_outputs {
+                    payload,
+                    signature
+                },
+            );
+        }
+
+        if !closed_requests.is_empty() {
+            Self::offchain_mutate_pending_reveals(|pending_reveals| {
+                pending_reveals.retain(|request_id, _| !closed_requests.contains(request_id));
+            })?;
+        }
+
+        Ok(())
+    }
+
+    // This function is used to update the outputs committed and not revealed yet stored on the offchain local storage.
+    // NOTE: The update is retried if an offchain worker of another block updates the outputs at the same time.
+    fn offchain_mutate_pending_reveals(f: impl Fn(&mut BTreeMap<RequestId, (NodeOutput, H256)>)) -> DispatchResult {
+        let storage = StorageValueRef::persistent(PENDING_REVEALS_STORAGE_KEY);
+        for _ in 0..3 {
+            let result = storage.mutate(|pending_reveals: Result<Option<BTreeMap<RequestId, (NodeOutput, H256)>>, StorageRetrievalError>| {
+                let mut pending_reveals = pending_reveals.ok().flatten().unwrap_or_default();
+                f(&mut pending_reveals);
+                Ok::<_, ()>(pending_reveals)
+            });
+            match result {
+                Ok(_) => return Ok(()),
+                Err(MutateStorageError::ValueFunctionFailed(())) => break,
+                Err(MutateStorageError::ConcurrentModification(_)) => continue,
+            }
+        }
+
+        log::error!("UOMI-ENGINE: Error storing the outputs to reveal, the outputs are modified concurrently");
+        Err(DispatchError::Other("Outputs to reveal modified concurrently"))
+    }
+
+    // This function is used to store the logs of an execution on the offchain local storage, keyed by the request id.
+    // NOTE: The logs are local to the node and they are never part of the consensus, a new execution of the same request overwrites them.
+    #[cfg(feature = "std")]
+    fn offchain_store_execution_logs(request_id: &RequestId, logs: &ExecutionLogs) {
+        StorageValueRef::persistent(&ExecutionLogs::storage_key(request_id)).set(logs);
+        log::info!("UOMI-ENGINE: Stored {:?} log lines and {:?} host calls for request {:?}", logs.lines.len(), logs.host_calls.len(), request_id);
+    }
+
+    // This function is used to get the AI service configuration stored by the node on the offchain local storage.
+    // NOTE: The configuration is local to the node and it is not part of the consensus, the default endpoint is used
+    // when the node does not store a configuration.
+    pub fn offchain_get_ai_service_config() -> AiServiceConfig {
+        let mut config = match StorageValueRef::persistent(AI_SERVICE_CONFIG_STORAGE_KEY).get::<AiServiceConfig>() {
+            Ok(Some(config)) => config,
+            Ok(None) => AiServiceConfig::default(),
+            Err(e) => {
+                log::error!("UOMI-ENGINE: Failed to decode the AI service config from the offchain local storage: {:?}", e);
+                AiServiceConfig::default()
+            }
+        };
+
+        if config.default_endpoint.url.is_empty() {
+            config.default_endpoint.url = DEFAULT_AI_SERVICE_URL.to_vec();
+        }
+
+        config
+    }
+
+    // This function is used to send a request to a path of an AI service endpoint, the request is a POST if it has a body.
+    fn offchain_worker_send_ai_service_request(endpoint: &AiServiceEndpoint, path: &str, body: Option<String>) -> Result<http::Response, DispatchError> {
+        let mut url = String::from_utf8(endpoint.url.clone()).map_err(|_| {
+            log::error!("UOMI-ENGINE: Invalid UTF-8 in AI service URL");
+            DispatchError::Other("Invalid UTF-8 in AI service URL")
+        })?;
+        while url.ends_with('/') {
+            url.pop();
+        }
+        url.push_str(path);
+
+        let headers = endpoint.headers.iter()
+            .map(|(name, value)| (String::from_utf8_lossy(name).into_owned(), String::from_utf8_lossy(value).into_owned()))
+            .collect::<Vec<(String, String)>>();
+
+        let (method, chunks) = match body {
+            Some(ref body) => (http::Method::Post, vec![body.as_bytes()]),
+            None => (http::Method::Get, Vec::new()),
+        };
+        let mut request = http::Request::new(&url)
+            .method(method)
+            .body(chunks)
+            .add_header("Accept", "application/json");
+        if body.is_some() {
+            request = request.add_header("Content-Type", "application/json");
+        }
+        for (name, value) in headers.iter() {
+            request = request.add_header(name, value);
+        }
+
+        let pending = request.send().map_err(|e| {
+            log::error!("UOMI-ENGINE: Failed to send HTTP request: {:?}", e);
+            DispatchError::Other("Failed to send HTTP request")
+        })?;
+        let response = pending.wait().map_err(|e| {
+            log::error!("UOMI-ENGINE: HTTP request failed after sending: {:?}", e);
+            DispatchError::Other("HTTP request failed after sending")
+        })?;
+
+        Ok(response)
+    }
+
+    // This function is used to get the registered models served by the AI service of the node.
+    // Every model is checked on the endpoint configured for it, so a node can serve its models from different hosts.
+    fn offchain_get_served_models() -> Result<BoundedVec<AiModelKey, MaxModelsPerNode>, DispatchError> {
+        let config = Self::offchain_get_ai_service_config();
+        let mut endpoints_backend_names = BTreeMap::<Vec<u8>, Vec<Vec<u8>>>::new(); // the models loaded by every endpoint, by url
+
+        let mut ai_model_keys = Vec::<AiModelKey>::new();
+        for (ai_model_key, model) in ModelsRegistry::<T>::iter() {
+            if model.deprecated {
+                continue;
+            }
+
+            let endpoint = config.endpoint(&ai_model_key);
+            if !endpoints_backend_names.contains_key(&endpoint.url) {
+                let backend_names = Self::offchain_worker_get_ai_service_models(endpoint)?;
+                endpoints_backend_names.insert(endpoint.url.clone(), backend_names);
+            }
+
+            if endpoints_backend_names.get(&endpoint.url).map_or(false, |backend_names| backend_names.contains(&model.backend_name.to_vec())) {
+                ai_model_keys.push(ai_model_key);
+            }
+        }
+        ai_model_keys.sort();
+        ai_model_keys.truncate(MaxModelsPerNode::get() as usize);
+
+        BoundedVec::try_from(ai_model_keys).map_err(|_| DispatchError::Other("Too many models served by the node"))
+    }
+
+    fn offchain_worker_get_ai_service_models(endpoint: &AiServiceEndpoint) -> Result<Vec<Vec<u8>>, DispatchError> {
+        // In case of tests, the AI service serves all the registered models
+        if cfg!(test) {
+            return Ok(ModelsRegistry::<T>::iter_values().map(|model| model.backend_name.to_vec()).collect());
+        }
+
+        let response = Self::offchain_worker_send_ai_service_request(endpoint, "/status", None)?;
+        if response.code != 200 {
+            log::error!("UOMI-ENGINE: Error response from AI service status. Status: {}", response.code);
+            return Err(DispatchError::Other("Error response from AI service status"));
+        }
+
+        let response_body = String::from_utf8(response.body().collect::<Vec<u8>>()).map_err(|_| {
+            log::error!("UOMI-ENGINE: Invalid UTF-8 in AI service status");
+            DispatchError::Other("Invalid UTF-8 in AI service status")
+        })?;
+        let status: AiServiceStatus = miniserde::json::from_str(&response_body).map_err(|_| {
+            log::error!("UOMI-ENGINE: Error parsing AI service status to JSON");
+            DispatchError::Other("Error parsing AI service status to JSON")
+        })?;
+
+        Ok(status.models.into_iter().map(|model| model.into_bytes()).collect())
+    }
+
+    fn offchain_store_models(ai_model_keys: &BoundedVec<AiModelKey, MaxModelsPerNode>) -> DispatchResult {
         let signer = Signer::<T, T::UomiAuthorityId>::all_accounts();
         if !signer.can_sign() {
             log::error!("No accounts available to sign the transaction");
@@ -632,12 +815,11 @@ impl<T: Config> Pallet<T> {
         }
 
         let _ = signer.send_unsigned_transaction(
-            |acct| PayloadNodesOutputs { 
-                request_id: request_id.clone(), 
-                output_data: output_data.clone(),
+            |acct| PayloadNodesModels { 
                 public: acct.public.clone(),
+                ai_model_keys: ai_model_keys.clone(),
             },
-            |payload, signature| Call::store_nodes_outputs { 
+            |payload, signature| Call::store_nodes_models { 
                 payload, 
                 signature 
             },
@@ -667,7 +849,7 @@ impl<T: Config> Pallet<T> {
         Ok(())
     }
 
-    fn offchain_detect_opoc_level(request_id: &RequestId, nft_required_consensus: &U256) -> u8 {
+    pub fn offchain_detect_opoc_level(request_id: &RequestId, nft_required_consensus: &U256) -> u8 {
         let opoc_assignments_of_level_0 = 1 as usize;
         let opoc_assignments_of_level_1 = nft_required_consensus.as_u32() as usize;
 
@@ -690,4 +872,36 @@ impl<T: Config> Pallet<T> {
 
         opoc_level
     }
-}
\ No newline at end of file
+}
+
+// OffchainAgentHost provides to the agents executed by the offchain worker the files on IPFS and the AI models served by the node.
+#[cfg(feature = "std")]
+struct OffchainAgentHost<T: Config> {
+    input_file_cid: Cid, // The CID of the input file of the request.
+    block_number: BlockNumber, // The block number where the request has been accepted.
+    nft_required_consensus: U256, // The minimum number of validators required by the agent.
+    opoc_level: u8, // The level of OPoC of the execution.
+    request_id: RequestId, // The id of the request.
+    _marker: PhantomData<T>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Config> AgentHost for OffchainAgentHost<T> {
+    fn get_input_file(&mut self) -> Vec<u8> {
+        match T::IpfsPallet::get_file(&self.input_file_cid) {
+            Ok(file) => file,
+            Err(error) => {
+                log::error!("Error getting the file from the IPFS pallet: {:?}", error);
+                Vec::new()
+            }
+        }
+    }
+
+    fn get_cid_file(&mut self, cid: Cid) -> Result<Vec<u8>, DispatchError> {
+        Pallet::<T>::offcahin_worker_get_cid_file(cid, self.block_number)
+    }
+
+    fn call_ai(&mut self, model: AiModelKey, input: Vec<u8>, counter: u32) -> Result<Vec<u8>, DispatchError> {
+        Pallet::<T>::offchain_worker_call_ai(model, self.block_number, input, self.nft_required_consensus, self.opoc_level, counter, self.request_id)
+    }
+}
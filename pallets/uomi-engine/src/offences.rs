@@ -0,0 +1,147 @@
+use frame_support::{
+    pallet_prelude::Weight,
+    traits::{Get, ValidatorSet, ValidatorSetWithIdentification},
+};
+use sp_runtime::{
+    traits::Convert,
+    Perbill,
+};
+use sp_staking::{
+    offence::{Kind, Offence, ReportOffence},
+    SessionIndex,
+};
+use sp_std::{vec, vec::Vec};
+
+use crate::{
+    consts::MAX_OFFENCES_REPORTED_PER_BLOCK,
+    types::{BlockNumber, OffenceId, OpocOffenceKind, PendingOffence},
+    Config, Event, NextOffenceId, NextOffenceToReport, Pallet, PendingOffences,
+};
+
+// A tuple of (ValidatorId, Identification) where Identification is the full identification of ValidatorId.
+pub type IdentificationTuple<T> = (
+    <<T as Config>::ValidatorSet as ValidatorSet<<T as frame_system::Config>::AccountId>>::ValidatorId,
+    <<T as Config>::ValidatorSet as ValidatorSetWithIdentification<<T as frame_system::Config>::AccountId>>::Identification,
+);
+
+// OpocOffence is the offence reported for a validator that misbehaved during the execution of a request.
+#[derive(PartialEq, Eq, Clone, sp_runtime::RuntimeDebug)]
+pub struct OpocOffence<Offender> {
+    pub kind: OpocOffenceKind, // The kind of the offence.
+    pub session_index: SessionIndex, // The session index in which the offence has been reported.
+    pub validator_set_count: u32, // The size of the validator set in the session.
+    pub offender: Offender, // The validator that misbehaved.
+    pub time_slot: OffenceId, // The id of the offence, used to not merge different misbehaviours of the same validator.
+    pub slash_fraction: Perbill, // The fraction of the stake to slash.
+}
+
+impl<Offender: Clone> Offence<Offender> for OpocOffence<Offender> {
+    const ID: Kind = *b"uomi-engine:opoc";
+    type TimeSlot = OffenceId;
+
+    fn offenders(&self) -> Vec<Offender> {
+        vec![self.offender.clone()]
+    }
+
+    fn session_index(&self) -> SessionIndex {
+        self.session_index
+    }
+
+    fn validator_set_count(&self) -> u32 {
+        self.validator_set_count
+    }
+
+    fn time_slot(&self) -> Self::TimeSlot {
+        self.time_slot
+    }
+
+    fn slash_fraction(&self, _offenders_count: u32) -> Perbill {
+        self.slash_fraction
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    // Offences entry point, it reports to the offences pallet the pending offences whose delay is expired.
+    // The offences are reported in the same order they are scheduled, so the cursor never skips an offence not reported yet.
+    pub fn offences_run(current_block: BlockNumber) -> Weight {
+        let next_offence_id = NextOffenceId::<T>::get();
+        let mut offence_id = NextOffenceToReport::<T>::get();
+        let mut reported = 0;
+
+        while offence_id < next_offence_id && reported < MAX_OFFENCES_REPORTED_PER_BLOCK {
+            match PendingOffences::<T>::get(offence_id) {
+                Some(pending_offence) => {
+                    if pending_offence.report_block_number > current_block {
+                        break;
+                    }
+                    PendingOffences::<T>::remove(offence_id);
+                    Self::offences_report(offence_id, pending_offence);
+                    reported += 1;
+                }
+                None => {} // The offence has been cancelled by the governance
+            }
+            offence_id += 1;
+        }
+
+        NextOffenceToReport::<T>::put(offence_id);
+
+        T::DbWeight::get().reads_writes(3 + (reported as u64) * 4, 1 + (reported as u64) * 3)
+    }
+
+    // This function is used to schedule an offence of a validator, the offence is reported after the OffenceReportDelay.
+    pub fn offences_schedule(account_id: &T::AccountId, kind: OpocOffenceKind) {
+        let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+        let offence_id = NextOffenceId::<T>::get();
+
+        PendingOffences::<T>::insert(offence_id, PendingOffence {
+            account_id: account_id.clone(),
+            kind,
+            report_block_number: current_block + BlockNumber::from(T::OffenceReportDelay::get()),
+        });
+        NextOffenceId::<T>::put(offence_id + 1);
+
+        Self::deposit_event(Event::OffenceScheduled {
+            offence_id,
+            account_id: account_id.clone(),
+            kind,
+        });
+    }
+
+    fn offences_report(offence_id: OffenceId, pending_offence: PendingOffence<T::AccountId>) {
+        let PendingOffence { account_id, kind, .. } = pending_offence;
+
+        let offender = <T::ValidatorSet as ValidatorSet<T::AccountId>>::ValidatorIdOf::convert(account_id.clone())
+            .and_then(|validator_id| {
+                <T::ValidatorSet as ValidatorSetWithIdentification<T::AccountId>>::IdentificationOf::convert(validator_id.clone())
+                    .map(|full_identification| (validator_id, full_identification))
+            });
+        let offender = match offender {
+            Some(offender) => offender,
+            None => {
+                log::info!("UOMI-ENGINE: Offence {:?} not reported, validator {:?} can not be identified", offence_id, account_id);
+                return;
+            }
+        };
+
+        let slash_fraction = match kind {
+            OpocOffenceKind::InvalidOutput => T::InvalidOutputSlashFraction::get(),
+            OpocOffenceKind::Timeout => T::TimeoutSlashFraction::get(),
+        };
+
+        let offence = OpocOffence {
+            kind,
+            session_index: T::ValidatorSet::session_index(),
+            validator_set_count: T::ValidatorSet::validators().len() as u32,
+            offender,
+            time_slot: offence_id,
+            slash_fraction,
+        };
+
+        if let Err(error) = T::ReportOffence::report_offence(vec![], offence) {
+            log::error!("UOMI-ENGINE: Failed to report offence {:?}. error: {:?}", offence_id, error);
+            return;
+        }
+
+        Self::deposit_event(Event::OffenceReported { offence_id, account_id, kind });
+    }
+}
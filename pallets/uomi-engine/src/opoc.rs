@@ -1,70 +1,96 @@
 use codec::Encode;
-use frame_support::{ pallet_prelude::{ DispatchError, DispatchResult }, traits::Randomness };
+use frame_support::{ pallet_prelude::{ DispatchError, DispatchResult }, traits::{ EstimateNextSessionRotation, Get, Randomness }, weights::Weight };
 use pallet_ipfs::types::{ UsableFromBlockNumber, ExpirationBlockNumber };
 use pallet_ipfs::MinExpireDuration;
-use sp_core::U256;
+use sp_core::{ H256, U256 };
+use sp_io::hashing::blake2_256;
+use sp_runtime::traits::SaturatedConversion;
 use sp_std::{ collections::btree_map::BTreeMap, vec, vec::Vec };
+use frame_system::pallet_prelude::BlockNumberFor;
 
 use crate::{
     consts::MAX_INPUTS_MANAGED_PER_BLOCK,
+    consts::MAX_OPOC_BLOCK_WEIGHT,
+    consts::OPOC_REVEAL_DURATION,
+    consts::REPUTATION_EXCLUSION_THRESHOLD,
     consts::TEMP_BLOCK_FOR_NEW_OPOC,
     ipfs::IpfsInterface,
-    types::{ BlockNumber, Data, RequestId },
+    types::{ AgentRequest, AgentResult, BlockNumber, Data, NodeOutput, OpocOffenceKind, OpocOperations, PendingExecution, RequestFailureReason, RequestId },
+    ActiveValidators,
+    ActiveValidatorsSessionIndex,
+    ActiveValidatorsStakes,
     Config,
     Inputs,
-    NodesErrors,
     NodesOutputs,
-    NodesTimeouts,
+    NodesOutputsCids,
+    NodesOutputsTransactions,
+    NodesOutputsOutOfFuel,
+    NodesOutputsModels,
+    NodesOutputsCommitments,
     NodesWorks,
     OpocAssignment,
-    OpocBlacklist,
+    OpocRandomSeed,
     Outputs,
     Pallet,
     Event,
     NodesOpocL0Inferences,
+    RequestsAddresses,
+    RequestsCallbacks,
+    WeightInfo,
 };
 
 impl<T: Config> Pallet<T> {
     // OPoC entry point
-    pub fn opoc_run(current_block: BlockNumber) -> Result<
-        (
-            BTreeMap<T::AccountId, bool>, // opoc_blacklist_operations
-            BTreeMap<(RequestId, T::AccountId), BlockNumber>, // opoc_assignment_operations
-            BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>, // nodes_works_operations
-            BTreeMap<T::AccountId, u32>, // nodes_timeouts_operations
-            BTreeMap<T::AccountId, u32>, // nodes_errors_operations
-            BTreeMap<RequestId, (Data, u32, u32)>, // outputs_operations
-        ),
-        DispatchError
-    > {
-        let mut opoc_blacklist_operations = BTreeMap::<T::AccountId, bool>::new();
+    pub fn opoc_run(current_block: BlockNumber) -> Result<OpocOperations<T::AccountId>, DispatchError> {
+        let mut opoc_penalties_operations = BTreeMap::<T::AccountId, Vec<OpocOffenceKind>>::new();
         let mut opoc_assignment_operations = BTreeMap::<
             (RequestId, T::AccountId),
             BlockNumber
         >::new();
         let mut nodes_works_operations = BTreeMap::<T::AccountId, BTreeMap<RequestId, bool>>::new();
-        let mut nodes_timeouts_operations = BTreeMap::<T::AccountId, u32>::new();
-        let mut nodes_errors_operations = BTreeMap::<T::AccountId, u32>::new();
-        let mut outputs_operations = BTreeMap::<RequestId, (Data, u32, u32)>::new();
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        let mut failures_operations = BTreeMap::<RequestId, RequestFailureReason>::new();
 
         let ipfs_min_expire_duration = U256::from(MinExpireDuration::get());
+        let max_weight = MAX_OPOC_BLOCK_WEIGHT * <T as frame_system::Config>::BlockWeights::get().max_block;
 
         let inputs = Inputs::<T>::iter().collect::<Vec<_>>();
         for (
             request_id,
-            (
+            AgentRequest {
                 block_number,
-                _nft_id,
                 nft_required_consensus,
-                _nft_execution_max_time,
                 nft_file_cid,
-                _input_data,
                 input_file_cid,
-            ),
+                ..
+            },
         ) in inputs.iter().take(MAX_INPUTS_MANAGED_PER_BLOCK) {
+            // Stop managing the inputs when the operations computed so far fill the share of the block weight of the OPoC
+            // NOTE: The other inputs are managed on the next blocks, the operations of a single input are bounded by its nft_required_consensus
+            let weight = Self::opoc_weight(
+                &opoc_penalties_operations,
+                &opoc_assignment_operations,
+                &nodes_works_operations,
+                &outputs_operations,
+                &failures_operations,
+                0
+            );
+            if weight.any_gt(max_weight) {
+                log::info!("OPoC weight limit reached, the remaining inputs are managed on the next blocks");
+                break;
+            }
+
             let opoc_assignments_of_level_0 = 1 as usize;
             let opoc_assignments_of_level_1 = nft_required_consensus.as_u32() as usize;
 
+            // If the request is not completed before its maximum lifetime, we close it as failed
+            // NOTE: This case happens when there are no validators to assign the request or the validators go repeatedly in timeout
+            if current_block > block_number.saturating_add(U256::from(T::RequestMaxLifetime::get())) {
+                log::info!("Request {:?} expired before the completion", request_id);
+                failures_operations.insert(request_id.clone(), RequestFailureReason::Expired);
+                continue;
+            }
+
             // If the nft_file_cid is not usable, we skip the request to wait it to be usable
             // NOTE: This case should never happen because the check of the nft_file_cid is done on run_request before accepting the request
             if !nft_file_cid.is_empty() {
@@ -151,7 +177,7 @@ impl<T: Config> Pallet<T> {
                     // No assignments for input, so we need to assign it to a validator for opoc level 0
                     match
                         Self::opoc_assignment(
-                            &mut opoc_blacklist_operations,
+                            &opoc_penalties_operations,
                             &mut opoc_assignment_operations,
                             &mut nodes_works_operations,
                             &request_id,
@@ -174,8 +200,10 @@ impl<T: Config> Pallet<T> {
                 }
                 x if x == opoc_assignments_of_level_0 => {
                     // One assignment for input, so we need to check the output of the first validator and assign the input to validators for opoc level 1
-                    let (output, validators_not_completed, validators_in_timeout) =
-                        Self::opoc_get_outputs(&request_id, &current_block)?;
+                    // NOTE: When a consensus is required the output of the first validator is revealed only after the commitments of the validators of opoc level 1,
+                    // so its commitment is enough to assign the input to them
+                    let (output, validators_committed, validators_not_completed, validators_in_fault) =
+                        Self::opoc_get_outputs(&request_id, &current_block, opoc_assignments_of_level_1 <= 1)?;
 
                     // Continue if validators_not_completed is not empty (wait next block to check again)
                     if validators_not_completed.len() > 0 {
@@ -186,30 +214,31 @@ impl<T: Config> Pallet<T> {
                         continue;
                     }
 
-                    // Manage timeout if validators_in_timeout is not empty (remove assignment from validator, register timeout, re-assign to another validator)
-                    if validators_in_timeout.len() > 0 {
-                        let validator = validators_in_timeout[0].clone();
+                    // Manage fault if validators_in_fault is not empty (remove assignment from validator, register the offence, re-assign to another validator)
+                    if validators_in_fault.len() > 0 {
+                        let (validator, kind) = &validators_in_fault[0];
 
                         // Deassign the request from the validator
                         match
-                            Self::opoc_deassignment_per_timeout(
-                                &mut opoc_blacklist_operations,
+                            Self::opoc_deassignment_per_fault(
+                                &mut opoc_penalties_operations,
                                 &mut opoc_assignment_operations,
                                 &mut nodes_works_operations,
-                                &mut nodes_timeouts_operations,
                                 &request_id,
-                                &validator
+                                &validator,
+                                *kind
                             )
                         {
                             Ok(_) => {
                                 log::info!(
-                                    "Request deassigned from validator {:?} of OPoC level 0 for timeout",
-                                    validator
+                                    "Request deassigned from validator {:?} of OPoC level 0 for {:?}",
+                                    validator,
+                                    kind
                                 );
                             }
                             Err(error) => {
                                 log::error!(
-                                    "Failed to deassign request from validator of OPoC level 0 for timeout. error: {:?}",
+                                    "Failed to deassign request from validator of OPoC level 0 for fault. error: {:?}",
                                     error
                                 );
                                 // NOTE: This case should not happen, but if it does, we need to handle it is some way...
@@ -219,7 +248,7 @@ impl<T: Config> Pallet<T> {
                         // Reassign the request to another validator
                         match
                             Self::opoc_assignment(
-                                &mut opoc_blacklist_operations,
+                                &opoc_penalties_operations,
                                 &mut opoc_assignment_operations,
                                 &mut nodes_works_operations,
                                 &request_id,
@@ -245,9 +274,8 @@ impl<T: Config> Pallet<T> {
                         continue;
                     }
 
-                    // Load validator and output of the validator
-                    let validator = output.keys().next().unwrap().clone();
-                    let final_output = output.get(&validator).unwrap();
+                    // Load validator of the output or of the commitment
+                    let validator = output.keys().chain(validators_committed.iter()).next().unwrap().clone();
 
                     // Manage completed request from validator
                     match
@@ -277,7 +305,7 @@ impl<T: Config> Pallet<T> {
                         // Assign the request to validators for opoc level 1
                         match
                             Self::opoc_assignment(
-                                &mut opoc_blacklist_operations,
+                                &opoc_penalties_operations,
                                 &mut opoc_assignment_operations,
                                 &mut nodes_works_operations,
                                 &request_id,
@@ -301,6 +329,7 @@ impl<T: Config> Pallet<T> {
                         }
                     } else {
                         // When we do not require consensus, we can close the request with only one execution
+                        let final_output = output.get(&validator).unwrap();
                         let executions = 1 as u32;
                         match
                             Self::opoc_complete(
@@ -325,32 +354,37 @@ impl<T: Config> Pallet<T> {
                 }
                 x if x == opoc_assignments_of_level_1 => {
                     // Opoc level 1 + 1 assignments for input, so we need to check the output of the validators of opoc level 1 and choose if needs opoc level 2
-                    let (output, validators_not_completed, validators_in_timeout) =
-                        Self::opoc_get_outputs(&request_id, &current_block)?;
-
-                    // Manage timouts if validators_in_timeout is not empty (remove assignment from validators, register timeout, re-assign to other validators)
-                    if validators_in_timeout.len() > 0 {
-                        for validator in validators_in_timeout.iter() {
+                    // NOTE: The outputs are revealed when every validator of opoc level 1 has committed or is in timeout, only the salted hashes
+                    // bound to the validators are published before, so the outputs are compared after the reveal
+                    let reveal = Self::opoc_reveal_open(&request_id, &current_block);
+                    let (output, validators_committed, validators_not_completed, validators_in_fault) =
+                        Self::opoc_get_outputs(&request_id, &current_block, reveal)?;
+
+                    // Manage faults if validators_in_fault is not empty (remove assignment from validators, register the offences, re-assign to other validators)
+                    // NOTE: A validator in fault is replaced only while no output is revealed, otherwise the request goes to opoc level 2
+                    if validators_in_fault.len() > 0 {
+                        for (validator, kind) in validators_in_fault.iter() {
                             // Deassign the request from the validator
                             match
-                                Self::opoc_deassignment_per_timeout(
-                                    &mut opoc_blacklist_operations,
+                                Self::opoc_deassignment_per_fault(
+                                    &mut opoc_penalties_operations,
                                     &mut opoc_assignment_operations,
                                     &mut nodes_works_operations,
-                                    &mut nodes_timeouts_operations,
                                     &request_id,
-                                    &validator
+                                    &validator,
+                                    *kind
                                 )
                             {
                                 Ok(_) => {
                                     log::info!(
-                                        "Request deassigned from validator {:?} of OPoC level 1 for timeout",
-                                        validator
+                                        "Request deassigned from validator {:?} of OPoC level 1 for {:?}",
+                                        validator,
+                                        kind
                                     );
                                 }
                                 Err(error) => {
                                     log::error!(
-                                        "Failed to deassign request from validator of OPoC level 1 for timeout. error: {:?}",
+                                        "Failed to deassign request from validator of OPoC level 1 for fault. error: {:?}",
                                         error
                                     );
                                     // NOTE: This case should not happen, but if it does, we need to handle it is some way...
@@ -360,22 +394,67 @@ impl<T: Config> Pallet<T> {
 
                         //create a vec with the validators to exclude from output keys and validators_not_completed to avoid reassigning the request to the same validators
                         let mut validators_to_exclude = Vec::<T::AccountId>::new();
-                        for validator in validators_not_completed.iter() {
+                        for validator in validators_not_completed.iter().chain(validators_committed.iter()) {
                             validators_to_exclude.push(validator.clone());
                         }
                         output.keys().for_each(|validator| {
                             validators_to_exclude.push(validator.clone());
                         });
 
+                        // When some outputs are already revealed a replacement could copy them, so the request is assigned to the validators
+                        // of opoc level 2 instead, or it is closed as failed when they are not enough to go over opoc level 1
+                        if !output.is_empty() {
+                            validators_in_fault.iter().for_each(|(validator, _kind)| {
+                                validators_to_exclude.push(validator.clone());
+                            });
+                            let number_of_validators = Self::get_active_validators()
+                                .into_iter()
+                                .filter(|account_id| !validators_to_exclude.contains(account_id))
+                                .count() as u32;
+
+                            if number_of_validators <= validators_in_fault.len() as u32 {
+                                log::info!("Request {:?} closed without validators for OPoC level 2 after a fault at OPoC level 1", request_id);
+                                failures_operations.insert(request_id.clone(), RequestFailureReason::NoConsensus);
+                                continue;
+                            }
+
+                            match
+                                Self::opoc_assignment(
+                                    &opoc_penalties_operations,
+                                    &mut opoc_assignment_operations,
+                                    &mut nodes_works_operations,
+                                    &request_id,
+                                    &current_block,
+                                    number_of_validators,
+                                    validators_to_exclude,
+                                    false
+                                )
+                            {
+                                Ok(_) => {
+                                    log::info!(
+                                        "Request assigned to all validators for OPoC level 2 after a fault at OPoC level 1"
+                                    );
+                                }
+                                Err(error) => {
+                                    log::error!(
+                                        "Failed to assign request to all validators for OPoC level 2 after a fault at OPoC level 1. error: {:?}",
+                                        error
+                                    );
+                                }
+                            }
+
+                            continue;
+                        }
+
                         // Reassign the request to other validators
                         match
                             Self::opoc_assignment(
-                                &mut opoc_blacklist_operations,
+                                &opoc_penalties_operations,
                                 &mut opoc_assignment_operations,
                                 &mut nodes_works_operations,
                                 &request_id,
                                 &current_block,
-                                validators_in_timeout.len() as u32,
+                                validators_in_fault.len() as u32,
                                 validators_to_exclude,
                                 false
                             )
@@ -405,8 +484,8 @@ impl<T: Config> Pallet<T> {
                         continue;
                     }
 
-                    //for every key in output do Self::opoc_deassignment_per_completed
-                    for validator in output.keys() {
+                    //for every key in output and every validator committed do Self::opoc_deassignment_per_completed
+                    for validator in output.keys().chain(validators_committed.iter()) {
                         match
                             Self::opoc_deassignment_per_completed(
                                 &mut nodes_works_operations,
@@ -430,11 +509,12 @@ impl<T: Config> Pallet<T> {
                         }
                     }
 
-                    // check if every outputs are the same
+                    // check if every outputs are revealed and are the same
                     let mut output_values = output.values();
-                    let first_output = output_values.next().unwrap();
+                    let first_output = output_values.next();
+                    let outputs_match = validators_committed.is_empty() && first_output.map_or(false, |first_output| output_values.all(|output| output == first_output));
 
-                    if output_values.all(|output| output == first_output) {
+                    if let (true, Some(first_output)) = (outputs_match, first_output) {
                         let output_values_len = output.len() as u32;
 
                         match
@@ -458,7 +538,7 @@ impl<T: Config> Pallet<T> {
                         }
                     } else {
                         let mut validators_to_exclude = Vec::<T::AccountId>::new();
-                        output.keys().for_each(|validator| {
+                        output.keys().chain(validators_committed.iter()).for_each(|validator| {
                             validators_to_exclude.push(validator.clone());
                         });
 
@@ -477,7 +557,7 @@ impl<T: Config> Pallet<T> {
                         // Assign the request to validators for opoc level 2 to all validators
                         match
                             Self::opoc_assignment(
-                                &mut opoc_blacklist_operations,
+                                &opoc_penalties_operations,
                                 &mut opoc_assignment_operations,
                                 &mut nodes_works_operations,
                                 &request_id,
@@ -500,8 +580,8 @@ impl<T: Config> Pallet<T> {
                     }
                 }
                 x if x > opoc_assignments_of_level_1 => {
-                    let (output, validators_not_completed, validators_in_timeout) =
-                        Self::opoc_get_outputs(&request_id, &current_block)?;
+                    let (output, _validators_committed, validators_not_completed, validators_in_fault) =
+                        Self::opoc_get_outputs(&request_id, &current_block, true)?;
 
                     //if validators_not_completed is not empty, wait next block to check again
                     if validators_not_completed.len() > 0 {
@@ -512,30 +592,31 @@ impl<T: Config> Pallet<T> {
                         continue;
                     }
 
-                    //if some validators are in timeout, remove the assignment from them, register the timeout
+                    //if some validators are in fault, remove the assignment from them, register the offence
 
-                    if validators_in_timeout.len() > 0 {
-                        for validator in validators_in_timeout.iter() {
+                    if validators_in_fault.len() > 0 {
+                        for (validator, kind) in validators_in_fault.iter() {
                             // Deassign the request from the validator
                             match
-                                Self::opoc_deassignment_per_timeout(
-                                    &mut opoc_blacklist_operations,
+                                Self::opoc_deassignment_per_fault(
+                                    &mut opoc_penalties_operations,
                                     &mut opoc_assignment_operations,
                                     &mut nodes_works_operations,
-                                    &mut nodes_timeouts_operations,
                                     &request_id,
-                                    &validator
+                                    &validator,
+                                    *kind
                                 )
                             {
                                 Ok(_) => {
                                     log::info!(
-                                        "Request deassigned from validator {:?} of OPoC level 2 for timeout",
-                                        validator
+                                        "Request deassigned from validator {:?} of OPoC level 2 for {:?}",
+                                        validator,
+                                        kind
                                     );
                                 }
                                 Err(error) => {
                                     log::error!(
-                                        "Failed to deassign request from validator of OPoC level 2 for timeout. error: {:?}",
+                                        "Failed to deassign request from validator of OPoC level 2 for fault. error: {:?}",
                                         error
                                     );
                                 }
@@ -543,7 +624,7 @@ impl<T: Config> Pallet<T> {
                         }
                     }
 
-                    let mut value_counts: BTreeMap<&Data, usize> = BTreeMap::new();
+                    let mut value_counts: BTreeMap<&NodeOutput, usize> = BTreeMap::new();
 
                     // Count occurrences of each value
                     for value in output.values() {
@@ -553,10 +634,15 @@ impl<T: Config> Pallet<T> {
                     log::info!("Value counts: {:?}", value_counts);
 
                     // Store the max value before converting to Option
-                    let (max_value, _) = value_counts
-                        .iter()
-                        .max_by_key(|&(_, count)| count)
-                        .expect("Should have at least one value");
+                    // NOTE: When every validator of opoc level 2 is in fault there is no output, so the request is closed as failed and the fees are refunded
+                    let max_value = match value_counts.iter().max_by_key(|&(_, count)| count) {
+                        Some((max_value, _)) => max_value,
+                        None => {
+                            log::info!("Request {:?} closed without outputs at OPoC level 2", request_id);
+                            failures_operations.insert(request_id.clone(), RequestFailureReason::NoConsensus);
+                            continue;
+                        }
+                    };
 
                     let output_completed = Some((*max_value).clone());
 
@@ -565,10 +651,9 @@ impl<T: Config> Pallet<T> {
                         if Some(output) != output_completed.as_ref() {
                             match
                                 Self::opoc_deassignment_per_invalid_output(
-                                    &mut opoc_blacklist_operations,
+                                    &mut opoc_penalties_operations,
                                     &mut opoc_assignment_operations,
                                     &mut nodes_works_operations,
-                                    &mut nodes_errors_operations,
                                     &request_id,
                                     &validator
                                 )
@@ -650,18 +735,17 @@ impl<T: Config> Pallet<T> {
             }
         }
 
-        Ok((
-            opoc_blacklist_operations,
+        Ok(OpocOperations {
+            opoc_penalties_operations,
             opoc_assignment_operations,
             nodes_works_operations,
-            nodes_timeouts_operations,
-            nodes_errors_operations,
             outputs_operations,
-        ))
+            failures_operations,
+        })
     }
 
     pub fn opoc_assignment(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
+        opoc_penalties_operations: &BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
         opoc_assignment_operations: &mut BTreeMap<(RequestId, T::AccountId), BlockNumber>,
         nodes_works_operations: &mut BTreeMap<T::AccountId, BTreeMap<U256, bool>>,
         request_id: &RequestId,
@@ -672,7 +756,9 @@ impl<T: Config> Pallet<T> {
     ) -> Result<(), DispatchError> {
         let random_validators = match
             Self::opoc_assignment_get_random_validators(
+                opoc_penalties_operations,
                 nodes_works_operations,
+                current_block,
                 U256::from(validators_amount),
                 first_free,
                 validators_to_exclude
@@ -685,16 +771,6 @@ impl<T: Config> Pallet<T> {
         };
 
         for validator in random_validators {
-            let is_blacklisted = Self::opoc_blacklist_operations_check(
-                opoc_blacklist_operations,
-                &validator
-            );
-
-            // if black listed, remove the validator from the list of validators
-            if is_blacklisted {
-                Self::opoc_blacklist_operations_remove(opoc_blacklist_operations, &validator);
-            }
-
             // increment the number of works of the validator
             Self::opoc_nodes_works_operations_add(nodes_works_operations, &validator, &request_id);
 
@@ -718,34 +794,22 @@ impl<T: Config> Pallet<T> {
         Ok(())
     }
 
-    pub fn opoc_store_operations(
-        operations: (
-            BTreeMap<T::AccountId, bool>, // opoc_blacklist_operations
-            BTreeMap<(RequestId, T::AccountId), BlockNumber>, // opoc_assignment_operations
-            BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>, // nodes_works_operations
-            BTreeMap<T::AccountId, u32>, // nodes_timeouts_operations
-            BTreeMap<T::AccountId, u32>, // nodes_errors_operations
-            BTreeMap<U256, (Data, u32, u32)>, // outputs_operations
-        )
-    ) -> Result<(), DispatchError> {
+    pub fn opoc_store_operations(operations: OpocOperations<T::AccountId>) -> Result<(), DispatchError> {
         // get operations to do
-        let (
-            opoc_blacklist_operations,
+        let OpocOperations {
+            opoc_penalties_operations,
             opoc_assignment_operations,
             nodes_works_operations,
-            nodes_timeouts_operations,
-            nodes_errors_operations,
             outputs_operations,
-        ) = operations;
-
-        // set opoc_blacklist_operations
-        for (account_id, is_blacklisted) in opoc_blacklist_operations.iter() {
-            if *is_blacklisted {
-                OpocBlacklist::<T>::insert(account_id, is_blacklisted);
-                Self::deposit_event(Event::OpocBlacklistAdd { account_id: account_id.clone() });
-            } else {
-                OpocBlacklist::<T>::remove(account_id);
-                Self::deposit_event(Event::OpocBlacklistRemove { account_id: account_id.clone() });
+            failures_operations,
+        } = operations;
+
+        // set opoc_penalties_operations
+        // NOTE: Every misbehaviour of a validator decreases its reputation and is scheduled as an offence
+        for (account_id, kinds) in opoc_penalties_operations.iter() {
+            for kind in kinds.iter() {
+                Self::reputation_penalize(account_id, *kind);
+                Self::offences_schedule(account_id, *kind);
             }
         }
 
@@ -781,83 +845,219 @@ impl<T: Config> Pallet<T> {
             }
         }
 
-        // set nodes_timeouts_operations
-        for (account_id, timeouts_number) in nodes_timeouts_operations.iter() {
-            if timeouts_number == &0 {
-                NodesTimeouts::<T>::remove(account_id);
-            } else {
-                NodesTimeouts::<T>::insert(account_id, timeouts_number);
-            }
-        }
-
         // set outputs_operations
         // NOTE: For every output, we need to clear other storages from data associated with the request_id
-        for (
-            request_id,
-            (output_data, total_executions, total_consensus),
-        ) in outputs_operations.iter() {
-            // insert in Outputs
-            Outputs::<T>::insert(request_id, (
-                output_data.clone(),
-                total_executions.clone(),
-                total_consensus.clone(),
-            ));
+        for (request_id, result) in outputs_operations.iter() {
+            let mut result = result.clone();
+            // pin the output offloaded to IPFS on all the validators until the expiration of the pin
+            if let Some(output_cid) = &result.output_cid {
+                result.output_cid_expiration_block_number = T::IpfsPallet::temporary_pin(&output_cid.cid, T::OutputsPinDuration::get().into());
+            }
+            // insert in Outputs and record the completion for the retention
+            Outputs::<T>::insert(request_id, result.clone());
+            Self::retention_record(request_id);
+            let AgentResult { output_data, total_executions, total_consensus, output_cid, output_transaction, out_of_fuel, .. } = result.clone();
+            // pay the fee held in escrow and credit the rewards points to the validators that produced the consensus output
+            let consensus_validators = NodesOutputs::<T>::iter_prefix(request_id)
+                .filter(|(account_id, node_output_data)| {
+                    *node_output_data == output_data &&
+                        NodesOutputsCids::<T>::get(request_id, account_id) == output_cid &&
+                        NodesOutputsTransactions::<T>::get(request_id, account_id) == output_transaction &&
+                        NodesOutputsOutOfFuel::<T>::get(request_id, account_id) == out_of_fuel
+                })
+                .map(|(account_id, _)| account_id)
+                .collect::<Vec<T::AccountId>>();
             Self::deposit_event(Event::RequestCompleted {
                 request_id: request_id.clone(),
-                output_data: output_data.clone(),
-                total_executions: total_executions.clone(),
-                total_consensus: total_consensus.clone(),
+                output_data,
+                total_executions,
+                total_consensus,
+                output_cid,
+                output_transaction: output_transaction.clone(),
+                out_of_fuel,
             });
-            // remove from Inputs
-            Inputs::<T>::remove(request_id);
-            // remove all assignments from OpocAssignment
-            for (account_id, _) in OpocAssignment::<T>::iter_prefix(request_id) {
-                OpocAssignment::<T>::remove(request_id, account_id);
+            Self::fees_pay(request_id, &consensus_validators)?;
+            Self::rewards_credit(&consensus_validators);
+            // queue the transaction emitted by the agent, executed from the agent address by on_idle out of the inherent
+            if let Some(transaction) = output_transaction {
+                let nft_id = Inputs::<T>::get(request_id).nft_id;
+                Self::executions_schedule(PendingExecution::AgentTransaction { request_id: *request_id, nft_id, transaction });
             }
-            // remove all outputs from NodesOutputs
-            for (account_id, _) in NodesOutputs::<T>::iter_prefix(request_id) {
-                NodesOutputs::<T>::remove(request_id, account_id);
+            // queue the callback of the request, executed by on_idle out of the inherent
+            if let Some(callback) = RequestsCallbacks::<T>::get(request_id) {
+                Self::executions_schedule(PendingExecution::Callback { request_id: *request_id, gas_limit: callback.gas_limit });
             }
-            // remove all inferences from NodesOpocL0Inferences
-            let current_block_number = frame_system::Pallet::<T>::block_number().into(); // For finney update. remove on turing
-            if current_block_number >= TEMP_BLOCK_FOR_NEW_OPOC.into() { // For finney update. remove on turing
-                for (account_id, _) in NodesOpocL0Inferences::<T>::iter_prefix(request_id) {
-                    NodesOpocL0Inferences::<T>::remove(request_id, account_id);
-                }
+            // remove the request from the other storages
+            Self::opoc_clear_request(request_id);
+        }
+
+        // set failures_operations
+        // NOTE: The failed requests are closed without an output and their fees are refunded
+        for (request_id, reason) in failures_operations.iter() {
+            Self::requests_fail(request_id, *reason);
+        }
+
+        Ok(())
+    }
+
+    // This function returns the weight of the inherent applying the operations computed by the OPoC and by the AI models calculation.
+    pub fn opoc_operations_weight(operations: &OpocOperations<T::AccountId>, aimodelscalc_operations_count: u32) -> Weight {
+        Self::opoc_weight(
+            &operations.opoc_penalties_operations,
+            &operations.opoc_assignment_operations,
+            &operations.nodes_works_operations,
+            &operations.outputs_operations,
+            &operations.failures_operations,
+            aimodelscalc_operations_count
+        )
+    }
+
+    fn opoc_weight(
+        opoc_penalties_operations: &BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
+        opoc_assignment_operations: &BTreeMap<(RequestId, T::AccountId), BlockNumber>,
+        nodes_works_operations: &BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>,
+        outputs_operations: &BTreeMap<RequestId, AgentResult>,
+        failures_operations: &BTreeMap<RequestId, RequestFailureReason>,
+        aimodelscalc_operations_count: u32
+    ) -> Weight {
+        T::WeightInfo::set_inherent_data(
+            opoc_penalties_operations.values().map(|kinds| kinds.len() as u32).sum(),
+            opoc_assignment_operations.len() as u32,
+            nodes_works_operations.values().map(|requests| requests.len() as u32).sum(),
+            outputs_operations.len() as u32,
+            failures_operations.len() as u32,
+            aimodelscalc_operations_count
+        )
+    }
+
+    // This function is used to clear the storages from the data associated with a request closed by completion, failure or cancellation.
+    pub fn opoc_clear_request(request_id: &RequestId) {
+        // remove from Inputs and RequestsAddresses
+        Inputs::<T>::remove(request_id);
+        RequestsAddresses::<T>::remove(request_id);
+        // remove all assignments from OpocAssignment
+        for (account_id, _) in OpocAssignment::<T>::iter_prefix(request_id) {
+            OpocAssignment::<T>::remove(request_id, account_id);
+        }
+        // remove all outputs from NodesOutputs, NodesOutputsCids, NodesOutputsTransactions, NodesOutputsOutOfFuel and NodesOutputsModels
+        for (account_id, _) in NodesOutputs::<T>::iter_prefix(request_id) {
+            NodesOutputs::<T>::remove(request_id, account_id);
+        }
+        for (account_id, _) in NodesOutputsCids::<T>::iter_prefix(request_id) {
+            NodesOutputsCids::<T>::remove(request_id, account_id);
+        }
+        for (account_id, _) in NodesOutputsTransactions::<T>::iter_prefix(request_id) {
+            NodesOutputsTransactions::<T>::remove(request_id, account_id);
+        }
+        for (account_id, _) in NodesOutputsOutOfFuel::<T>::iter_prefix(request_id) {
+            NodesOutputsOutOfFuel::<T>::remove(request_id, account_id);
+        }
+        for (account_id, _) in NodesOutputsModels::<T>::iter_prefix(request_id) {
+            NodesOutputsModels::<T>::remove(request_id, account_id);
+        }
+        // remove all commitments from NodesOutputsCommitments
+        for (account_id, _) in NodesOutputsCommitments::<T>::iter_prefix(request_id) {
+            NodesOutputsCommitments::<T>::remove(request_id, account_id);
+        }
+        // remove all inferences from NodesOpocL0Inferences
+        let current_block_number = frame_system::Pallet::<T>::block_number().into(); // For finney update. remove on turing
+        if current_block_number >= TEMP_BLOCK_FOR_NEW_OPOC.into() { // For finney update. remove on turing
+            for (account_id, _) in NodesOpocL0Inferences::<T>::iter_prefix(request_id) {
+                NodesOpocL0Inferences::<T>::remove(request_id, account_id);
             }
         }
+    }
 
-        for (account_id, errors_number) in nodes_errors_operations.iter() {
-            if errors_number == &0 {
-                NodesErrors::<T>::remove(account_id);
-            } else {
-                NodesErrors::<T>::insert(account_id, errors_number);
+    // This function is used on on_finalize to take a snapshot of the state read by opoc_run that can change on the initialization of the next block.
+    // With the snapshot the result of opoc_run depends only on the parent state, so the importing nodes can verify the inherent on check_inherent.
+    pub fn opoc_snapshot(current_block: BlockNumber) {
+        // The active validators change only with a new session
+        let session_index = pallet_session::Pallet::<T>::current_index();
+        if ActiveValidatorsSessionIndex::<T>::get() != Some(session_index) {
+            let active_validators = Self::get_session_active_validators();
+            let _ = ActiveValidatorsStakes::<T>::clear(u32::MAX, None);
+            for account_id in active_validators.iter() {
+                let stake = pallet_staking::Pallet::<T>::slashable_balance_of(account_id).saturated_into::<u128>();
+                ActiveValidatorsStakes::<T>::insert(account_id, stake);
             }
+            ActiveValidators::<T>::put(active_validators);
+            ActiveValidatorsSessionIndex::<T>::put(session_index);
         }
 
-        Ok(())
+        // The random seed is taken after the finalization of the randomness of the current block
+        let random_bytes: Vec<u8>;
+        if current_block < U256::from(720000) {
+            let random_seed = T::RandomnessOld::random(&b"validator_selection"[..]);
+            random_bytes = random_seed.0.encode();
+        } else {
+            let random_seed = T::Randomness::random(&b"validator_selection"[..]);
+            random_bytes = random_seed.0.encode();
+        }
+        OpocRandomSeed::<T>::put(Data::truncate_from(random_bytes));
+    }
+
+    // This function returns the weight of opoc_snapshot, the snapshot of the validators and of their stakes is taken only on a new session.
+    // NOTE: The session can rotate on the initialization of the block after this pallet, so the rotation estimated on the block is charged too.
+    pub fn opoc_snapshot_weight(n: BlockNumberFor<T>) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let weight = db_weight.reads_writes(3, 1); // The session indexes and the random seed
+        let (next_session_rotation, estimate_weight) = <T as pallet_staking::Config>::NextSessionRotation::estimate_next_session_rotation(n);
+        let weight = weight.saturating_add(estimate_weight);
+        let session_rotated = ActiveValidatorsSessionIndex::<T>::get() != Some(pallet_session::Pallet::<T>::current_index());
+        if !session_rotated && next_session_rotation.map_or(true, |rotation_block_number| rotation_block_number > n) {
+            return weight;
+        }
+
+        // Every validator is checked on the session and its stake is read from the staking ledger, the old stakes are removed
+        let validators_count = pallet_staking::Validators::<T>::count() as u64;
+        weight.saturating_add(db_weight.reads_writes(validators_count * 4 + 2, validators_count * 2 + 2))
     }
 
+    // This function is used to select random validators for an assignment.
+    // Validators with a reputation score under REPUTATION_EXCLUSION_THRESHOLD are excluded if there are enough other validators,
+    // the probability of a validator to be selected is proportional to its reputation score.
     pub fn opoc_assignment_get_random_validators(
+        opoc_penalties_operations: &BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
         nodes_works_operations: &BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>,
+        current_block: &BlockNumber,
         number: U256,
         first_free: bool,
         validators_to_exclude: Vec<T::AccountId>
     ) -> Result<Vec<T::AccountId>, DispatchError> {
         let number_usize = number.low_u64() as usize;
         
-        // Get active validators excluding specified ones
-        let validators: Vec<T::AccountId> = Self::get_active_validators()
+        // Get active validators excluding specified ones, with their reputation score
+        let validators: Vec<(T::AccountId, u32)> = Self::get_active_validators()
             .into_iter()
             .filter(|account_id| !validators_to_exclude.contains(account_id))
+            .map(|account_id| {
+                let score = Self::opoc_penalties_operations_score(
+                    opoc_penalties_operations,
+                    &account_id,
+                    current_block
+                );
+                (account_id, score)
+            })
+            .collect();
+
+        // Exclude validators with a low reputation if there are enough validators with a good reputation
+        let reputable_validators: Vec<(T::AccountId, u32)> = validators
+            .iter()
+            .filter(|(_account_id, score)| *score >= REPUTATION_EXCLUSION_THRESHOLD)
+            .cloned()
             .collect();
+        let validators = if reputable_validators.len() >= number_usize {
+            reputable_validators
+        } else {
+            validators
+        };
     
         // Get potential validators based on first_free flag
-        let potential_validators: Vec<T::AccountId> = if first_free {
-            let free_validators: Vec<T::AccountId> = validators
+        let mut potential_validators: Vec<(T::AccountId, u32)> = if first_free {
+            let free_validators: Vec<(T::AccountId, u32)> = validators
                 .iter()
                 .filter(
-                    |account_id| Self::opoc_nodes_works_operations_count(
+                    |(account_id, _score)| Self::opoc_nodes_works_operations_count(
                         nodes_works_operations, 
                         account_id
                     ) == 0
@@ -875,103 +1075,76 @@ impl<T: Config> Pallet<T> {
         };
     
         // Check if we have enough validators
-        let validator_count = potential_validators.len();
-        if validator_count < number_usize {
+        if potential_validators.len() < number_usize {
             return Err(DispatchError::Other("Not enough validators"));
         }
     
-        // Get random seed
-        let current_block = U256::zero() + frame_system::Pallet::<T>::block_number();
-        let random_bytes: Vec<u8>;
-        if current_block < U256::from(720000) {
-            let random_seed = T::RandomnessOld::random(&b"validator_selection"[..]);
-            random_bytes = random_seed.0.encode();
-        } else {
-            let random_seed = T::Randomness::random(&b"validator_selection"[..]);
-            random_bytes = random_seed.0.encode();
-        }
+        // Get random seed taken on the finalization of the previous block
+        let random_bytes = OpocRandomSeed::<T>::get().into_inner();
 
+        // Weighted random selection without replacement, the weight of every validator is its reputation score (at least 1)
         let mut selected_validators = Vec::with_capacity(number_usize);
-        let mut used_indices = Vec::new();
-        
-        // Modified random selection logic
-        let mut current_byte_index = 0;
-        let bytes_per_selection = 4.min(random_bytes.len() / number_usize);
-    
-        for _ in 0..number_usize {
-            if current_byte_index + bytes_per_selection > random_bytes.len() {
-                // If we run out of random bytes, create a new selection using existing bytes
-                current_byte_index = 0;
-            }
-    
-            // Create random value from available bytes
-            let random_slice = &random_bytes[current_byte_index..current_byte_index + bytes_per_selection];
-            let random_value = U256::from_little_endian(random_slice);
-            
-            let mut index = (random_value % U256::from(validator_count)).low_u64() as usize;
-            
-            // Find next unused index
-            let mut attempts = 0;
-            while used_indices.contains(&index) {
-                index = (index + 1) % validator_count;
-                attempts += 1;
-                if attempts >= validator_count {
-                    return Err(DispatchError::Other("Failed to find unique validator index"));
+        for selection_index in 0..number_usize {
+            let total_weight: u64 = potential_validators
+                .iter()
+                .map(|(_account_id, score)| (*score).max(1) as u64)
+                .sum();
+
+            // Every selection uses a different random value derived from the random seed
+            let random_value = U256::from_little_endian(
+                &blake2_256(&(&random_bytes, selection_index as u32).encode())
+            );
+            let mut target = (random_value % U256::from(total_weight)).low_u64();
+
+            let mut index = 0;
+            for (position, (_account_id, score)) in potential_validators.iter().enumerate() {
+                let weight = (*score).max(1) as u64;
+                if target < weight {
+                    index = position;
+                    break;
                 }
+                target -= weight;
             }
-            
-            used_indices.push(index);
-            if let Some(validator) = potential_validators.get(index) {
-                selected_validators.push(validator.clone());
-            }
-            
-            current_byte_index += bytes_per_selection;
-        }
-    
-        // Verify we selected enough validators
-        if selected_validators.len() < number_usize {
-            return Err(DispatchError::Other("Failed to select enough unique validators"));
+
+            let (validator, _score) = potential_validators.remove(index);
+            selected_validators.push(validator);
         }
     
         Ok(selected_validators)
     }
 
     fn opoc_deassignment_per_invalid_output(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
+        opoc_penalties_operations: &mut BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
         opoc_assignment_operations: &mut BTreeMap<(RequestId, T::AccountId), BlockNumber>,
         nodes_works_operations: &mut BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>,
-        nodes_errors_operations: &mut BTreeMap<T::AccountId, u32>,
         request_id: &RequestId,
         validator: &T::AccountId
     ) -> Result<(), DispatchError> {
-        // Decrease the number of works of the validator
-        Self::opoc_nodes_works_operations_remove(nodes_works_operations, validator, request_id);
-        // Remove the request from the OpocAssignment storage
-        Self::opoc_assignment_operations_remove(opoc_assignment_operations, request_id, validator);
-        // Increment the number of errors of the validator
-        Self::opoc_nodes_errors_operations_incr(nodes_errors_operations, validator);
-        // Set the validator as blacklisted
-        Self::opoc_blacklist_operations_add(opoc_blacklist_operations, validator);
-
-        Ok(())
+        Self::opoc_deassignment_per_fault(
+            opoc_penalties_operations,
+            opoc_assignment_operations,
+            nodes_works_operations,
+            request_id,
+            validator,
+            OpocOffenceKind::InvalidOutput
+        )
     }
 
-    fn opoc_deassignment_per_timeout(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
+    // This function is used to deassign a validator in timeout or with a revealed output not matching its commitment.
+    fn opoc_deassignment_per_fault(
+        opoc_penalties_operations: &mut BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
         opoc_assignment_operations: &mut BTreeMap<(RequestId, T::AccountId), BlockNumber>,
         nodes_works_operations: &mut BTreeMap<T::AccountId, BTreeMap<RequestId, bool>>,
-        nodes_timeouts_operations: &mut BTreeMap<T::AccountId, u32>,
         request_id: &RequestId,
-        validator: &T::AccountId
+        validator: &T::AccountId,
+        kind: OpocOffenceKind
     ) -> Result<(), DispatchError> {
         // Decrease the number of works of the validator
         Self::opoc_nodes_works_operations_remove(nodes_works_operations, validator, request_id);
         // Remove the request from the OpocAssignment storage
         Self::opoc_assignment_operations_remove(opoc_assignment_operations, request_id, validator);
-        // Increment the number of timeouts of the validator
-        Self::opoc_nodes_timeouts_operations_incr(nodes_timeouts_operations, validator);
-        // Set the validator as blacklisted
-        Self::opoc_blacklist_operations_add(opoc_blacklist_operations, validator);
+        // Penalize the reputation of the validator
+        Self::opoc_penalties_operations_add(opoc_penalties_operations, validator, kind);
 
         Ok(())
     }
@@ -988,22 +1161,34 @@ impl<T: Config> Pallet<T> {
     }
 
     // This function is used to get informations about the execution of a request by the validators that has an assignment.
-    // It take the request_id and the current_block as input and return:
-    // - A BTreeMap with the validator as key and the output as value
-    // - A vector with the validators that have not responded to the request and are not in timeout
-    // - A vector with the validators that are in timeout
+    // It take the request_id, the current_block and if the outputs must be revealed as input and return:
+    // - A BTreeMap with the validator as key and the revealed output as value
+    // - A vector with the validators that have committed the output and are not required to reveal it yet
+    // - A vector with the validators that have not responded to the request and are not in fault
+    // - A vector with the validators that are in timeout, with the offence
     fn opoc_get_outputs(
         request_id: &RequestId,
-        current_block: &BlockNumber
+        current_block: &BlockNumber,
+        reveal: bool
     ) -> Result<
-        (BTreeMap<T::AccountId, Data>, Vec<T::AccountId>, Vec<T::AccountId>),
+        (BTreeMap<T::AccountId, NodeOutput>, Vec<T::AccountId>, Vec<T::AccountId>, Vec<(T::AccountId, OpocOffenceKind)>),
         DispatchError
     > {
-        let mut outputs = BTreeMap::<T::AccountId, Data>::new();
+        let mut outputs = BTreeMap::<T::AccountId, NodeOutput>::new();
+        let mut validators_committed = Vec::<T::AccountId>::new();
         let mut validators_not_completed = Vec::<T::AccountId>::new();
-        let mut validators_in_timeout = Vec::<T::AccountId>::new();
+        let mut validators_in_fault = Vec::<(T::AccountId, OpocOffenceKind)>::new();
+
+        let opoc_assignments = OpocAssignment::<T>::iter_prefix(*request_id).collect::<Vec<_>>();
+        // The validators have OPOC_REVEAL_DURATION blocks to reveal the outputs after the expiration of the last assignment,
+        // at that block the reveal is open because every validator has committed or is in timeout
+        let reveal_expiration_block_number = opoc_assignments
+            .iter()
+            .map(|(_validator, expiration_block_number)| *expiration_block_number)
+            .max()
+            .unwrap_or_default()
+            .saturating_add(U256::from(OPOC_REVEAL_DURATION));
 
-        let opoc_assignments = OpocAssignment::<T>::iter_prefix(*request_id);
         for (validator, expiration_block_number) in opoc_assignments {
             // Check if the validator has responded to the request
             // IMPORTANT: The check is done by iterating over the outputs of the request_id and checking if the validator is in the outputs BTreeMap
@@ -1015,49 +1200,101 @@ impl<T: Config> Pallet<T> {
 
             // If the validator has responded, add the output to the outputs BTreeMap and continue
             if is_validator_output {
-                let node_output = NodesOutputs::<T>::get(*request_id, validator.clone());
+                let node_output = NodeOutput {
+                    output_data: NodesOutputs::<T>::get(*request_id, validator.clone()),
+                    output_cid: NodesOutputsCids::<T>::get(*request_id, validator.clone()),
+                    output_transaction: NodesOutputsTransactions::<T>::get(*request_id, validator.clone()),
+                    out_of_fuel: NodesOutputsOutOfFuel::<T>::get(*request_id, validator.clone()),
+                    ai_models: Default::default(), // The AI models called are not part of the consensus, they are used only to charge the model fee
+                };
                 outputs.insert(validator.clone(), node_output);
                 continue;
             }
 
+            // If the validator has committed the output, wait the reveal until its expiration
+            if NodesOutputsCommitments::<T>::contains_key(*request_id, &validator) {
+                if !reveal {
+                    validators_committed.push(validator.clone());
+                } else if current_block.clone() > reveal_expiration_block_number {
+                    validators_in_fault.push((validator.clone(), OpocOffenceKind::Timeout));
+                } else {
+                    validators_not_completed.push(validator.clone());
+                }
+                continue;
+            }
+
             // Check if the validator is in timeout
             let timeout = current_block.clone() > expiration_block_number;
             if timeout {
-                validators_in_timeout.push(validator.clone());
+                validators_in_fault.push((validator.clone(), OpocOffenceKind::Timeout));
             } else {
                 validators_not_completed.push(validator.clone());
             }
         }
 
-        Ok((outputs, validators_not_completed, validators_in_timeout))
+        Ok((outputs, validators_committed, validators_not_completed, validators_in_fault))
     }
 
-    fn opoc_blacklist_operations_check(
-        opoc_blacklist_operations: &BTreeMap<T::AccountId, bool>,
-        validator: &T::AccountId
-    ) -> bool {
-        match opoc_blacklist_operations.get(&validator) {
-            Some(&blacklisted) => blacklisted,
-            None => {
-                let blacklisted = OpocBlacklist::<T>::get(&validator);
-                blacklisted
-            }
+    // This function is used to check if the validators assigned to a request can reveal their outputs.
+    // The reveal is open when the request is assigned to all the validators required by its consensus and every one of them
+    // has committed the output or is in timeout, so the outputs revealed can not be copied by a validator still executing the request.
+    // NOTE: Nothing comparable with the outputs is published before the reveal, the commitments are salted hashes bound to the validators.
+    pub fn opoc_reveal_open(request_id: &RequestId, current_block: &BlockNumber) -> bool {
+        let nft_required_consensus = Inputs::<T>::get(request_id).nft_required_consensus.low_u32().max(1) as usize;
+        let opoc_assignments = OpocAssignment::<T>::iter_prefix(*request_id).collect::<Vec<_>>();
+
+        // The validator of opoc level 0 waits the commitments of the validators of opoc level 1
+        if opoc_assignments.len() < nft_required_consensus {
+            return false;
         }
+
+        opoc_assignments.iter().all(|(validator, expiration_block_number)| {
+            NodesOutputsCommitments::<T>::contains_key(*request_id, validator) || current_block > expiration_block_number
+        })
     }
 
-    fn opoc_blacklist_operations_add(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
-        validator: &T::AccountId
+    // This function returns the salted hash committed by a validator before revealing its output.
+    // The hash is bound to the validator, so a commitment copied from another validator can not be revealed.
+    pub fn opoc_output_hash(node_output: &NodeOutput, salt: &H256, account_id: &T::AccountId) -> H256 {
+        H256::from(blake2_256(&(node_output, salt, account_id).encode()))
+    }
+
+    // This function is used to check if the output revealed by a validator matches the salted hash of its commitment.
+    pub fn opoc_output_matches_commitment(
+        request_id: &RequestId,
+        account_id: &T::AccountId,
+        node_output: &NodeOutput,
+        salt: &H256
     ) -> bool {
-        opoc_blacklist_operations.insert(validator.clone(), true);
-        true
+        match NodesOutputsCommitments::<T>::get(*request_id, account_id) {
+            Some(output_hash) => Self::opoc_output_hash(node_output, salt, account_id) == output_hash,
+            None => false,
+        }
     }
 
-    fn opoc_blacklist_operations_remove(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
-        validator: &T::AccountId
+    // This function is used to get the reputation score of a validator considering the penalties not stored yet.
+    fn opoc_penalties_operations_score(
+        opoc_penalties_operations: &BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
+        validator: &T::AccountId,
+        current_block: &BlockNumber
+    ) -> u32 {
+        let score = Self::reputation_score(validator, current_block);
+        match opoc_penalties_operations.get(&validator) {
+            Some(kinds) => {
+                kinds
+                    .iter()
+                    .fold(score, |score, kind| score.saturating_sub(Self::reputation_kind_penalty(*kind)))
+            }
+            None => score,
+        }
+    }
+
+    fn opoc_penalties_operations_add(
+        opoc_penalties_operations: &mut BTreeMap<T::AccountId, Vec<OpocOffenceKind>>,
+        validator: &T::AccountId,
+        kind: OpocOffenceKind
     ) -> bool {
-        opoc_blacklist_operations.insert(validator.clone(), false);
+        opoc_penalties_operations.entry(validator.clone()).or_insert_with(Vec::new).push(kind);
         true
     }
 
@@ -1134,16 +1371,7 @@ impl<T: Config> Pallet<T> {
         // Calculate the sum of the execution_max_time of the request_ids by reading the Inputs storage
         let mut sum = U256::from(0);
         for request_id in request_ids.iter() {
-            let (
-                _block_number,
-                _nft_id,
-                _nft_required_consensus,
-                nft_execution_max_time,
-                _nft_file_cid,
-                _input_data,
-                _input_file_cid,
-            ) = Inputs::<T>::get(request_id);
-            sum += nft_execution_max_time;
+            sum += Inputs::<T>::get(request_id).nft_execution_max_time;
         }
 
         sum
@@ -1193,82 +1421,28 @@ impl<T: Config> Pallet<T> {
         }
     }
 
-    fn opoc_nodes_errors_operations_incr(
-        nodes_errors_operations: &mut BTreeMap<T::AccountId, u32>,
-        validator: &T::AccountId
-    ) -> u32 {
-        match nodes_errors_operations.get(&validator) {
-            Some(&failures) => {
-                nodes_errors_operations.insert(validator.clone(), failures + 1);
-                failures + 1
-            }
-            None => {
-                let failures = NodesErrors::<T>::get(&validator);
-                nodes_errors_operations.insert(validator.clone(), failures + 1);
-                failures + 1
-            }
-        }
-    }
-
-    fn opoc_nodes_timeouts_operations_incr(
-        nodes_timeouts_operations: &mut BTreeMap<T::AccountId, u32>,
-        validator: &T::AccountId
-    ) -> u32 {
-        match nodes_timeouts_operations.get(&validator) {
-            Some(&failures) => {
-                nodes_timeouts_operations.insert(validator.clone(), failures + 1);
-                failures + 1
-            }
-            None => {
-                let failures = NodesTimeouts::<T>::get(&validator);
-                nodes_timeouts_operations.insert(validator.clone(), failures + 1);
-                failures + 1
-            }
-        }
-    }
-
-    // NOTE: Commented because it's not used for now
-    // fn opoc_nodes_timeouts_operations_decr(
-    // 	nodes_timeouts_operations: &mut BTreeMap<T::AccountId, u32>,
-    // 	validator: &T::AccountId,
-    // ) -> u32 {
-    // 	match nodes_timeouts_operations.get(&validator) {
-    // 		Some(&failures) => {
-    // 			if failures > 0 {
-    // 				nodes_timeouts_operations.insert(validator.clone(), failures - 1);
-    // 				return failures - 1;
-    // 			}
-    // 			failures
-    // 		},
-    // 		None => {
-    // 			let failures = NodesTimeouts::<T>::get(&validator);
-    // 			if failures > 0 {
-    // 				nodes_timeouts_operations.insert(validator.clone(), failures - 1);
-    // 				return failures - 1;
-    // 			}
-    // 			failures
-    // 		},
-    // 	}
-    // }
-
     fn opoc_complete(
-        outputs_operations: &mut BTreeMap<RequestId, (Data, u32, u32)>,
+        outputs_operations: &mut BTreeMap<RequestId, AgentResult>,
         request_id: &RequestId,
-        output: &Data,
+        output: &NodeOutput,
         total_executions: &u32,
         total_consensus: &u32
     ) -> DispatchResult {
-        outputs_operations.insert(request_id.clone(), (
-            output.clone(),
-            *total_executions,
-            *total_consensus,
-        ));
+        // NOTE: The expiration of the pin of an output offloaded to IPFS is set when the result is stored.
+        outputs_operations.insert(request_id.clone(), AgentResult {
+            output_data: output.output_data.clone(),
+            total_executions: *total_executions,
+            total_consensus: *total_consensus,
+            output_cid: output.output_cid.clone(),
+            output_cid_expiration_block_number: U256::zero(),
+            output_transaction: output.output_transaction.clone(),
+            out_of_fuel: output.out_of_fuel,
+        });
         Ok(())
     }
 
     // NOTE: The following functions are used to maintain retro-compatibility with the finney chain.
     pub fn opoc_assignment_finney_v1(
-        opoc_blacklist_operations: &mut BTreeMap<T::AccountId, bool>,
         opoc_assignment_operations: &mut BTreeMap<(RequestId, T::AccountId), BlockNumber>,
         nodes_works_operations: &mut BTreeMap<T::AccountId, BTreeMap<U256, bool>>,
         request_id: &RequestId,
@@ -1292,16 +1466,6 @@ impl<T: Config> Pallet<T> {
         };
 
         for validator in random_validators {
-            let is_blacklisted = Self::opoc_blacklist_operations_check(
-                opoc_blacklist_operations,
-                &validator
-            );
-
-            // if black listed, remove the validator from the list of validators
-            if is_blacklisted {
-                Self::opoc_blacklist_operations_remove(opoc_blacklist_operations, &validator);
-            }
-
             // increment the number of works of the validator
             Self::opoc_nodes_works_operations_add(nodes_works_operations, &validator, &request_id);
 
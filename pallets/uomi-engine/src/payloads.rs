@@ -4,11 +4,12 @@ use frame_support::{
    pallet_prelude::RuntimeDebug,
 };
 use frame_system::offchain::{SignedPayload, SigningTypes};
-use sp_core::U256;
+use sp_core::{H256, U256};
 
 use crate::{
    MaxDataSize,
-   types::Version,
+   MaxModelsPerNode,
+   types::{AgentTransaction, AiModelKey, OutputCid, Version},
 };
 
 // PayloadNodesOutputs
@@ -17,6 +18,11 @@ use crate::{
 pub struct PayloadNodesOutputs<Public> {
     pub request_id: U256,
     pub output_data: BoundedVec<u8, MaxDataSize>,
+    pub output_cid: Option<OutputCid>,
+    pub output_transaction: Option<AgentTransaction>,
+    pub out_of_fuel: bool,
+    pub ai_models: BoundedVec<AiModelKey, MaxModelsPerNode>,
+    pub salt: H256,
     pub public: Public,
 }
 
@@ -26,6 +32,21 @@ impl <T: SigningTypes> SignedPayload<T> for PayloadNodesOutputs<T::Public> {
     }
 }
 
+// PayloadNodesOutputsCommitments
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct PayloadNodesOutputsCommitments<Public> {
+    pub request_id: U256,
+    pub output_hash: H256,
+    pub public: Public,
+}
+
+impl <T: SigningTypes> SignedPayload<T> for PayloadNodesOutputsCommitments<T::Public> {
+    fn public(&self) -> T::Public {
+        self.public.clone()
+    }
+}
+
 // PayloadNodesVersions
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
@@ -40,6 +61,20 @@ impl <T: SigningTypes> SignedPayload<T> for PayloadNodesVersions<T::Public> {
     }
 }
 
+// PayloadNodesModels
+
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct PayloadNodesModels<Public> {
+    pub ai_model_keys: BoundedVec<AiModelKey, MaxModelsPerNode>,
+    pub public: Public,
+}
+
+impl <T: SigningTypes> SignedPayload<T> for PayloadNodesModels<T::Public> {
+    fn public(&self) -> T::Public {
+        self.public.clone()
+    }
+}
+
 // PayloadNodesOpocL0Inferences
 
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
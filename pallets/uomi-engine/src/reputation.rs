@@ -0,0 +1,65 @@
+use frame_support::traits::Get;
+use sp_core::U256;
+
+use crate::{
+    consts::{MAX_REPUTATION, REPUTATION_PENALTY_INVALID_OUTPUT, REPUTATION_PENALTY_TIMEOUT},
+    types::{BlockNumber, NodeReputation, OpocOffenceKind},
+    Config, Event, NodesReputations, Pallet,
+};
+
+// The reputation of a validator is a score between 0 and MAX_REPUTATION.
+// Every misbehaviour detected by the OPoC adds a penalty to the validator, the penalty decays linearly to zero
+// in ReputationDecayWindow blocks from its last update, so a validator with a low score is excluded from the
+// assignments only for a limited period of time.
+impl<T: Config> Pallet<T> {
+    // This function is used to get the reputation score of a validator at the given block number.
+    pub fn reputation_score(account_id: &T::AccountId, current_block: &BlockNumber) -> u32 {
+        MAX_REPUTATION.saturating_sub(Self::reputation_penalty(account_id, current_block))
+    }
+
+    // This function is used to get the penalty of a validator at the given block number, after the decay.
+    pub fn reputation_penalty(account_id: &T::AccountId, current_block: &BlockNumber) -> u32 {
+        match NodesReputations::<T>::get(account_id) {
+            Some(reputation) => Self::reputation_decay(&reputation, current_block),
+            None => 0,
+        }
+    }
+
+    // This function is used to add the penalty of a misbehaviour to the reputation of a validator.
+    pub fn reputation_penalize(account_id: &T::AccountId, kind: OpocOffenceKind) {
+        let current_block: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+
+        let penalty = Self::reputation_penalty(account_id, &current_block)
+            .saturating_add(Self::reputation_kind_penalty(kind))
+            .min(MAX_REPUTATION);
+        NodesReputations::<T>::insert(account_id, NodeReputation {
+            penalty,
+            updated_at_block_number: current_block,
+        });
+
+        Self::deposit_event(Event::ReputationPenalized {
+            account_id: account_id.clone(),
+            kind,
+            score: MAX_REPUTATION - penalty,
+        });
+    }
+
+    // This function is used to get the penalty added to the reputation of a validator for a kind of misbehaviour.
+    pub fn reputation_kind_penalty(kind: OpocOffenceKind) -> u32 {
+        match kind {
+            OpocOffenceKind::InvalidOutput => REPUTATION_PENALTY_INVALID_OUTPUT,
+            OpocOffenceKind::Timeout => REPUTATION_PENALTY_TIMEOUT,
+        }
+    }
+
+    fn reputation_decay(reputation: &NodeReputation, current_block: &BlockNumber) -> u32 {
+        let window = U256::from(T::ReputationDecayWindow::get());
+        let elapsed = current_block.saturating_sub(reputation.updated_at_block_number);
+        if elapsed >= window {
+            return 0;
+        }
+
+        let penalty = U256::from(reputation.penalty) * (window - elapsed) / window;
+        penalty.low_u32()
+    }
+}
@@ -0,0 +1,60 @@
+use frame_support::{ensure, pallet_prelude::DispatchResult};
+
+use crate::{
+    types::{RequestFailureReason, RequestId, RequestStatus},
+    Config, Error, Event, Inputs, NodesWorks, OpocAssignment, Outputs, Pallet, RequestsStatuses,
+};
+
+impl<T: Config> Pallet<T> {
+    // This function is used to get the status of a request, it returns None if the request is not found.
+    pub fn requests_status(request_id: &RequestId) -> Option<RequestStatus> {
+        if Inputs::<T>::contains_key(request_id) {
+            if OpocAssignment::<T>::iter_prefix(request_id).next().is_some() {
+                return Some(RequestStatus::Running);
+            }
+            return Some(RequestStatus::Pending);
+        }
+
+        if Outputs::<T>::contains_key(request_id) {
+            return Some(RequestStatus::Completed);
+        }
+
+        RequestsStatuses::<T>::get(request_id)
+    }
+
+    // This function is used to cancel a request not assigned yet to the validators.
+    // NOTE: The caller must check the origin is allowed to cancel the request.
+    pub fn requests_cancel(request_id: &RequestId) -> DispatchResult {
+        ensure!(Inputs::<T>::contains_key(request_id), Error::<T>::RequestNotFound);
+        ensure!(OpocAssignment::<T>::iter_prefix(request_id).next().is_none(), Error::<T>::RequestNotCancellable);
+
+        Self::requests_close(request_id, RequestStatus::Cancelled);
+
+        Self::deposit_event(Event::RequestCancelled { request_id: *request_id });
+
+        Ok(())
+    }
+
+    // This function is used to close a request failed by the OPoC without an output.
+    pub fn requests_fail(request_id: &RequestId, reason: RequestFailureReason) {
+        Self::requests_close(request_id, RequestStatus::Failed(reason));
+
+        Self::deposit_event(Event::RequestFailed { request_id: *request_id, reason });
+    }
+
+    // This function is used to close a request without an output.
+    // The fees held in escrow and the fee of the gas of the callback are refunded to the payer, the callback is not executed.
+    fn requests_close(request_id: &RequestId, status: RequestStatus) {
+        Self::fees_refund(request_id);
+        Self::callbacks_refund(request_id);
+
+        // The validators still assigned to the request are free to receive other works
+        for (account_id, _) in OpocAssignment::<T>::iter_prefix(request_id) {
+            NodesWorks::<T>::remove(account_id, request_id);
+        }
+        Self::opoc_clear_request(request_id);
+
+        RequestsStatuses::<T>::insert(request_id, status);
+        Self::retention_record(request_id);
+    }
+}
@@ -0,0 +1,152 @@
+use frame_support::{
+    pallet_prelude::Weight,
+    traits::Get,
+    BoundedVec,
+};
+use sp_core::{H256, U256};
+use sp_io::hashing::keccak_256;
+
+use crate::{
+    consts::NODES_WORKS_SWEEP_INTERVAL,
+    types::{AgentResult, BlockNumber, RequestId},
+    CallbacksResults, Config, FinishedRequests, Inputs, NodesWorks, NodesWorksCursor, Outputs, OutputsHashes, Pallet,
+    RequestsStatuses, RetentionCursor,
+};
+
+impl<T: Config> Pallet<T> {
+    // This function is used to record a request closed on the current block, its history is pruned after the retention period.
+    pub fn retention_record(request_id: &RequestId) {
+        let current_block_number: BlockNumber = frame_system::Pallet::<T>::block_number().into();
+        FinishedRequests::<T>::insert(current_block_number, request_id, ());
+    }
+
+    // This function is used by on_idle to prune the history of the requests closed more than OutputsRetentionPeriod blocks ago.
+    // The FinishedRequests are pruned block by block from RetentionCursor, stopping when the remaining weight is not enough.
+    pub fn retention_run(current_block: BlockNumber, remaining_weight: Weight) -> Weight {
+        let retention_period = T::OutputsRetentionPeriod::get();
+        if retention_period == 0 { // Nothing to prune if the history is kept forever
+            return Weight::zero();
+        }
+
+        let db_weight = T::DbWeight::get();
+        let step_weight = db_weight.reads(1); // The read of the requests closed on a block
+        let request_weight = db_weight.reads_writes(2, 5); // The pruning of a single request
+        let mut used_weight = db_weight.reads_writes(1, 1); // The read and the write of the cursor
+        if used_weight.any_gt(remaining_weight) {
+            return Weight::zero();
+        }
+
+        let mut cursor = RetentionCursor::<T>::get();
+        let mut pruned = 0u32;
+        while cursor.saturating_add(U256::from(retention_period)) < current_block {
+            if used_weight.saturating_add(step_weight).any_gt(remaining_weight) {
+                break;
+            }
+            used_weight = used_weight.saturating_add(step_weight);
+
+            // NOTE: drain_prefix removes only the requests returned by next, so the block is completed by the next on_idle if the weight ends
+            let mut requests = FinishedRequests::<T>::drain_prefix(cursor);
+            let mut block_completed = false;
+            loop {
+                if used_weight.saturating_add(request_weight).any_gt(remaining_weight) {
+                    break;
+                }
+                match requests.next() {
+                    Some((request_id, _)) => {
+                        Self::retention_prune_request(&request_id);
+                        used_weight = used_weight.saturating_add(request_weight);
+                        pruned += 1;
+                    },
+                    None => {
+                        block_completed = true;
+                        break;
+                    },
+                }
+            }
+            if !block_completed {
+                break;
+            }
+            cursor = cursor.saturating_add(U256::one());
+        }
+        RetentionCursor::<T>::put(cursor);
+
+        if pruned > 0 {
+            log::info!("UOMI-ENGINE: Pruned the history of {:?} requests, retention cursor at block {:?}", pruned, cursor);
+        }
+
+        used_weight.saturating_add(Self::retention_sweep_nodes_works(current_block, remaining_weight.saturating_sub(used_weight)))
+    }
+
+    // This function is used to remove the history of a request, archiving the hash of its output if ArchiveOutputsHashes is enabled.
+    fn retention_prune_request(request_id: &RequestId) {
+        if let Ok(result) = Outputs::<T>::try_get(request_id) {
+            if T::ArchiveOutputsHashes::get() {
+                OutputsHashes::<T>::insert(request_id, Self::retention_output_hash(&result));
+            }
+            Outputs::<T>::remove(request_id);
+        }
+        RequestsStatuses::<T>::remove(request_id);
+        CallbacksResults::<T>::remove(request_id);
+    }
+
+    // This function is used to get the hash of the output of a request, archived when the output is pruned.
+    // It is the keccak256 of the output data, so it can be verified by the contracts, or the hash of the output offloaded to IPFS.
+    pub fn retention_output_hash(result: &AgentResult) -> H256 {
+        match &result.output_cid {
+            Some(output_cid) => output_cid.hash,
+            None => H256::from(keccak_256(&result.output_data)),
+        }
+    }
+
+    // This function is used to get the hash of the output of a completed request, also after the output is pruned if its hash is archived.
+    pub fn retention_get_output_hash(request_id: &RequestId) -> Option<H256> {
+        match Outputs::<T>::try_get(request_id) {
+            Ok(result) => Some(Self::retention_output_hash(&result)),
+            Err(_) => OutputsHashes::<T>::get(request_id),
+        }
+    }
+
+    // This function is used by on_idle to remove the NodesWorks of the requests no more in Inputs, left by the OPoC.
+    // A sweep starts every NODES_WORKS_SWEEP_INTERVAL blocks and continues from NodesWorksCursor on the next blocks until it is completed.
+    fn retention_sweep_nodes_works(current_block: BlockNumber, remaining_weight: Weight) -> Weight {
+        let db_weight = T::DbWeight::get();
+        let work_weight = db_weight.reads_writes(2, 1); // The check of a single work
+        let mut used_weight = db_weight.reads_writes(1, 1); // The read and the write of the cursor
+        if used_weight.any_gt(remaining_weight) {
+            return Weight::zero();
+        }
+
+        let mut works = match NodesWorksCursor::<T>::get() {
+            Some(cursor) => NodesWorks::<T>::iter_from(cursor.into_inner()),
+            None if current_block % U256::from(NODES_WORKS_SWEEP_INTERVAL) == U256::zero() => NodesWorks::<T>::iter(),
+            None => return db_weight.reads(1),
+        };
+
+        let mut sweep_completed = false;
+        loop {
+            if used_weight.saturating_add(work_weight).any_gt(remaining_weight) {
+                break;
+            }
+            match works.next() {
+                Some((account_id, request_id, _)) => {
+                    if !Inputs::<T>::contains_key(request_id) {
+                        NodesWorks::<T>::remove(account_id, request_id);
+                    }
+                    used_weight = used_weight.saturating_add(work_weight);
+                },
+                None => {
+                    sweep_completed = true;
+                    break;
+                },
+            }
+        }
+
+        let cursor = BoundedVec::try_from(works.last_raw_key().to_vec()).ok();
+        match cursor {
+            Some(cursor) if !sweep_completed => NodesWorksCursor::<T>::put(cursor),
+            _ => NodesWorksCursor::<T>::kill(),
+        }
+
+        used_weight
+    }
+}
@@ -0,0 +1,88 @@
+use frame_support::{
+    pallet_prelude::Weight,
+    traits::Get,
+};
+use sp_runtime::{
+    traits::{Saturating, Zero},
+    Perbill,
+};
+use sp_std::vec::Vec;
+
+use crate::{
+    types::EraIndex,
+    ClaimableRewards, Config, ErasRewardPoints, ErasTotalRewardPoints, Event, Pallet,
+    RewardsCurrentEra, RewardsInflationPerEra,
+};
+
+// Validators are rewarded in two ways:
+// - The fee of every request is paid to the validators of the consensus output on completion (see fees.rs).
+// - At the end of every era, the inflation share configured by the governance is distributed to the validators
+//   proportionally to the points they collected producing consensus outputs during the era.
+impl<T: Config> Pallet<T> {
+    // Rewards entry point, it closes the current era of the rewards when the active era of the staking changes.
+    pub fn rewards_run() -> Weight {
+        let active_era = Self::rewards_active_era();
+        let current_era = RewardsCurrentEra::<T>::get();
+        if active_era <= current_era {
+            return T::DbWeight::get().reads(2);
+        }
+
+        let credited_validators = Self::rewards_close_era(current_era);
+        RewardsCurrentEra::<T>::put(active_era);
+
+        T::DbWeight::get().reads_writes(
+            4 + (credited_validators as u64),
+            3 + (credited_validators as u64) * 2
+        )
+    }
+
+    // This function is used to credit a point to every validator that produced the consensus output of a request.
+    pub fn rewards_credit(validators: &Vec<T::AccountId>) {
+        if validators.is_empty() {
+            return;
+        }
+
+        let era = RewardsCurrentEra::<T>::get();
+        for validator in validators.iter() {
+            ErasRewardPoints::<T>::mutate(era, validator, |points| *points = points.saturating_add(1));
+        }
+        ErasTotalRewardPoints::<T>::mutate(era, |total_points| {
+            *total_points = total_points.saturating_add(validators.len() as u32)
+        });
+    }
+
+    // This function is used to distribute the inflation share of an era to the validators proportionally to their points.
+    // It returns the number of credited validators.
+    fn rewards_close_era(era: EraIndex) -> u32 {
+        let total_points = ErasTotalRewardPoints::<T>::take(era);
+        let points = ErasRewardPoints::<T>::drain_prefix(era).collect::<Vec<_>>();
+        let pool = RewardsInflationPerEra::<T>::get();
+
+        if total_points == 0 || pool.is_zero() {
+            return 0;
+        }
+
+        let mut credited_validators = 0;
+        for (validator, validator_points) in points.iter() {
+            let amount = Perbill::from_rational(*validator_points, total_points).mul_floor(pool);
+            if amount.is_zero() {
+                continue;
+            }
+
+            ClaimableRewards::<T>::mutate(validator, |claimable| *claimable = claimable.saturating_add(amount));
+            credited_validators += 1;
+
+            Self::deposit_event(Event::EraRewardsCredited {
+                era,
+                account_id: validator.clone(),
+                amount,
+            });
+        }
+
+        credited_validators
+    }
+
+    fn rewards_active_era() -> EraIndex {
+        pallet_staking::ActiveEra::<T>::get().map(|active_era| active_era.index).unwrap_or(0)
+    }
+}
@@ -1,32 +1,46 @@
 use pallet_ipfs::types::Cid;
 use pallet_ipfs::CidsStatus;
 use crate::{
-    mock::*, Event, Inputs, NodesErrors,
-    NodesOutputs, NodesTimeouts, NodesWorks, OpocAssignment, OpocBlacklist,
-    Outputs, MaxDataSize, InherentDidUpdate
+    mock::*, Error, Event, Inputs, NodesReputations,
+    NodesOutputs, NodesWorks, OpocAssignment,
+    Outputs, MaxDataSize, InherentDidUpdate, RequestsFees, ClaimableRewards,
+    ErasRewardPoints, ErasTotalRewardPoints, RewardsCurrentEra, PendingOffences,
+    NextOffenceToReport, ActiveValidators, ActiveValidatorsSessionIndex, AIModels, OpocRandomSeed,
+    ModelsRegistry, NodesModels, MaxModelsPerNode, ModelsActivatedNames, ModelsPendingActivations,
+    NodesOutputsCids, NodesOutputsTransactions, RequestsCallbacks, CallbacksResults, RequestsAddresses,
+    FinishedRequests, MigrationsCursor, RetentionCursor, NodesWorksCursor, OutputsHashes, RequestsStatuses, WeightInfo,
+    NodesOutputsCommitments, PendingExecutions, NextExecutionToRun,
+    NodesOutputsModels, RequestsModelsFees, ModelsMaxFee,
 };
-use crate::types::{Address, NftId, RequestId};
+use crate::wasm::{wasm_interrupted, wasm_run, wasm_run_with_epoch_duration, wasm_validate, AgentHost, WASM_ABI_V1, WASM_ABI_V2};
+use crate::wasm_cache::WasmModuleCache;
+use crate::migrations::v6::{AgentResultV1, AgentResultV3, AgentResultV4, MigrateToV6};
+use crate::consts::{AI_SERVICE_CONFIG_STORAGE_KEY, MAX_INPUTS_MANAGED_PER_BLOCK, MAX_OPOC_BLOCK_WEIGHT, DEFAULT_AI_SERVICE_URL, DEFAULT_MAX_WORKERS, FUEL_PER_EXECUTION_BLOCK, MAX_AGENT_MEMORY_SIZE, MAX_BLOCKS_TO_WAIT_NODE_UPDATE, MAX_EXECUTION_LOGS_HOST_CALLS, MAX_EXECUTION_LOGS_SIZE, MAX_EXECUTION_LOG_LINE_SIZE, MAX_EXECUTION_FUEL, MAX_REPUTATION, MAX_WORKERS, MAX_WORKERS_STORAGE_KEY, NODES_WORKS_SWEEP_INTERVAL, OPOC_REVEAL_DURATION, REPUTATION_PENALTY_INVALID_OUTPUT, REPUTATION_PENALTY_TIMEOUT, STARTED_REQUESTS_STORAGE_KEY};
+use crate::types::{Address, AgentRequest, AgentResult, AgentTransaction, AiModel, AiModelKey, AiModelModality, AiServiceConfig, AiServiceEndpoint, BlockNumber, Data, ExecutionBudget, ExecutionLogs, HostCallTiming, MigrationCursor, NftId, NodeOutput, OpocOffenceKind, OpocOperations, PendingExecution, RequestCallbackResult, RequestFailureReason, RequestId, RequestStatus};
 use sp_std::vec;
 use env_logger::Builder;
 use frame_support::{
-    assert_ok,
+    assert_noop, assert_ok,
+    dispatch::{DispatchClass, GetDispatchInfo},
     inherent::ProvideInherent,
-    pallet_prelude::InherentData,
-    traits::{Currency, OffchainWorker},
+    pallet_prelude::{InherentData, InvalidTransaction, TransactionSource, ValidateUnsigned},
+    storage::unhashed,
+    weights::Weight,
+    traits::{Currency, Get, GetStorageVersion, Hooks, OffchainWorker, OnRuntimeUpgrade, StorageVersion},
     BoundedVec,
 };
 use log::LevelFilter;
 use sp_core::{
     sr25519::Public,
-    H160, U256,
+    H160, H256, U256,
 };
 use sp_keystore::{
     testing::MemoryKeystore,
     Keystore, KeystoreExt,
 };
 use sp_runtime::{
-    offchain::{testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt},
-    traits::Dispatchable,
+    offchain::{storage::StorageValueRef, testing::TestOffchainExt, OffchainDbExt, OffchainWorkerExt},
+    traits::{Convert, Dispatchable},
     Perbill,
 };
 use std::{
@@ -34,7 +48,8 @@ use std::{
     sync::{Arc, Mutex},
 };
 use sp_core::offchain::testing::TestTransactionPoolExt;
-use sp_core::offchain::TransactionPoolExt;
+use sp_core::offchain::{StorageKind, TransactionPoolExt};
+use codec::{Decode, Encode};
 use sp_std::collections::btree_map::BTreeMap;
 use serial_test::serial;
 
@@ -67,19 +82,18 @@ fn test_run_request_success() {
         let input_data = vec![1, 2, 3];
         let input_file_cid = vec![1, 2, 3];
 
-        let result = TestingPallet::run_request(request_id, address, nft_id.clone(), input_data.clone(), input_file_cid.clone(), U256::from(5), U256::from(25)).unwrap();
+        let result = TestingPallet::run_request(request_id, address, address, nft_id.clone(), input_data.clone(), input_file_cid.clone(), U256::from(5), U256::from(25)).unwrap();
         assert_eq!(result, ());
 
         // Be sure request is stored on the Inputs storage
         let storage_input = Inputs::<Test>::get(request_id);
-        let (si_block_number, si_nft_id, si_nft_required_consensus, si_nft_execution_max_time, si_nft_file_cid, si_input_data, si_input_file_cid) = storage_input;
-        assert_eq!(si_block_number, 1.into());
-        assert_eq!(si_nft_id, nft_id);
-        assert_eq!(si_nft_required_consensus, U256::from(5));
-        assert_eq!(si_nft_execution_max_time, U256::from(25));
-        assert_eq!(si_nft_file_cid, Cid::new());
-        assert_eq!(si_input_data, input_data);
-        assert_eq!(si_input_file_cid, input_file_cid);
+        assert_eq!(storage_input.block_number, 1.into());
+        assert_eq!(storage_input.nft_id, nft_id);
+        assert_eq!(storage_input.nft_required_consensus, U256::from(5));
+        assert_eq!(storage_input.nft_execution_max_time, U256::from(25));
+        assert_eq!(storage_input.nft_file_cid, Cid::new());
+        assert_eq!(storage_input.input_data, input_data);
+        assert_eq!(storage_input.input_file_cid, input_file_cid);
 
         // Be sure the RequestAccepted event is emitted
         let events = System::events();
@@ -111,7 +125,7 @@ fn test_run_request_failure_with_zero_address() {
     let input_data = vec![1, 2, 3];
     let input_file_cid = vec![1, 2, 3];
 
-    let result = TestingPallet::run_request(request_id, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
+    let result = TestingPallet::run_request(request_id, address, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
     assert!(result.is_err());
 
     let error = result.err().unwrap();
@@ -131,7 +145,7 @@ fn test_run_request_failure_with_zero_request_id() {
     let input_data = vec![1, 2, 3];
     let input_file_cid = vec![1, 2, 3];
 
-    let result = TestingPallet::run_request(request_id, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
+    let result = TestingPallet::run_request(request_id, address, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
     assert!(result.is_err());
 
     let error = result.err().unwrap();
@@ -151,7 +165,7 @@ fn test_run_request_failure_with_zero_nft_id() {
     let input_data = vec![1, 2, 3];
     let input_file_cid = vec![1, 2, 3];
 
-    let result = TestingPallet::run_request(request_id, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
+    let result = TestingPallet::run_request(request_id, address, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
     assert!(result.is_err());
 
     let error = result.err().unwrap();
@@ -183,6 +197,7 @@ fn test_run_request_failure_with_existing_request_id() {
         assert_ok!(TestingPallet::run_request(
             request_id, 
             address, 
+            address, 
             nft_id, 
             input_data.clone(), 
             input_file_cid.clone(),
@@ -192,6 +207,7 @@ fn test_run_request_failure_with_existing_request_id() {
         let result = TestingPallet::run_request(
             request_id, 
             address, 
+            address, 
             nft_id, 
             input_data, 
             input_file_cid,
@@ -218,7 +234,7 @@ fn test_run_request_failure_with_large_input_data() {
         let input_data = vec![0; (1024 * 1024) + 1];
         let input_file_cid = vec![1, 2, 3];
 
-        let result = TestingPallet::run_request(request_id, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
+        let result = TestingPallet::run_request(request_id, address, address, nft_id, input_data, input_file_cid, U256::from(5), U256::from(25));
         assert!(result.is_err());
 
         let error = result.err().unwrap();
@@ -245,7 +261,7 @@ fn test_run_request_failure_with_large_input_data() {
 //         let input_data = vec![1, 2, 3];
 //         let input_file_cid = vec![1, 2, 3];
 
-//         let result = TestingPallet::run_request(request_id, address, nft_id.clone(), input_data.clone(), input_file_cid.clone(), U256::from(1), U256::from(25)).unwrap();
+//         let result = TestingPallet::run_request(request_id, address, address, nft_id.clone(), input_data.clone(), input_file_cid.clone(), U256::from(1), U256::from(25)).unwrap();
 //         assert_eq!(result, ());
 
 //         // Be sure request is stored on the Inputs storage
@@ -269,11 +285,405 @@ fn test_run_request_failure_with_large_input_data() {
 //     });
 // }
 
+// FEES
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should force the execution of the run_request function with fees enabled and check the fee is held in escrow.
+#[test]
+fn test_run_request_reserves_fee() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+
+        // Set a fee for an AI model, the most expensive model is used to calculate the execution fee
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(1), 5));
+        assert!(TestingPallet::set_model_fee(RuntimeOrigin::signed(payer.clone()), U256::from(1), 5).is_err());
+
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+
+        // execution_fee = 10 (validator) + 5 (model) + 5 * 1 (blocks) = 20
+        // amount = 100 (base) + 3 * 1 (bytes) + 3 * 20 (executions) = 163
+        let fee = RequestsFees::<Test>::get(request_id).unwrap();
+        assert_eq!(fee.payer, payer);
+        assert_eq!(fee.amount, 163);
+        assert_eq!(fee.execution_fee, 20);
+        assert_eq!(fee.min_validators, 3);
+        assert_eq!(Balances::reserved_balance(&payer), 163);
+        assert_eq!(Balances::free_balance(&payer), 1_000 - 163);
+    });
+}
+
+// This test should keep the fee of the most expensive model when the fees of the models are set, increased, decreased and removed.
+#[test]
+fn test_set_model_fee_keeps_max_fee() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        assert_eq!(ModelsMaxFee::<Test>::get(), 0);
+
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(1), 5));
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(2), 2));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 5);
+
+        // The increase of a model over the most expensive one makes it the most expensive
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(2), 7));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 7);
+
+        // The decrease or the removal of the most expensive model makes the next one the most expensive
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(2), 3));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 5);
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(1), 0));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 3);
+
+        // The decrease of a model that is not the most expensive keeps the fee
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(3), 2));
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(3), 1));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 3);
+
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(2), 0));
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(3), 0));
+        assert_eq!(ModelsMaxFee::<Test>::get(), 0);
+    });
+}
+
+// This test should force the execution of the run_request function with a failure result because the payer cannot pay the fee.
+#[test]
+fn test_run_request_failure_with_insufficient_balance() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        set_fees(100, 1, 10, 1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+
+        let result = TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5));
+        assert_eq!(result, Err(Error::<Test>::InsufficientBalance.into()));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert!(RequestsFees::<Test>::get(request_id).is_none());
+    });
+}
+
+// This test should reserve the fee from the agent contract pre-funded by the sender and not keep any change when the contract can not pay it.
+// It should check the pin of the input file is reverted together with the request.
+#[test]
+fn test_run_request_fee_paid_by_agent_contract() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let agent_contract: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(agent_contract);
+        let sender: Address = H160::repeat_byte(0xDD);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&TestAddressToAccountId::convert(sender), 1_000);
+
+        let mut cid_data = vec![18, 32];
+        cid_data.extend_from_slice(&[1; 45]);
+        let input_file_cid: Cid = cid_data.clone().try_into().unwrap();
+
+        // The sender has the balance to pay the fee, but the fee is reserved from the agent contract that has not been funded
+        let request_id: RequestId = 1.into();
+        let result = TestingPallet::run_request(request_id, agent_contract, sender, 1.into(), vec![1, 2, 3], cid_data.clone(), U256::from(3), U256::from(5));
+        assert_eq!(result, Err(Error::<Test>::InsufficientBalance.into()));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert!(RequestsAddresses::<Test>::get(request_id).is_none());
+        assert!(!CidsStatus::<Test>::contains_key(&input_file_cid));
+        assert_eq!(Balances::reserved_balance(&TestAddressToAccountId::convert(sender)), 0);
+
+        // The fee is reserved from the agent contract once it is funded
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+        assert_ok!(TestingPallet::run_request(request_id, agent_contract, sender, 1.into(), vec![1, 2, 3], cid_data, U256::from(3), U256::from(5)));
+        assert!(CidsStatus::<Test>::contains_key(&input_file_cid));
+        assert_eq!(Balances::reserved_balance(&payer), 148);
+        assert_eq!(RequestsFees::<Test>::get(request_id).unwrap().payer, payer);
+    });
+}
+
+// This test should complete a request and check the fee is paid to the validators of the consensus output and the remainder is refunded.
+#[test]
+fn test_fees_paid_to_consensus_validators_on_completion() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+
+        // amount = 100 (base) + 3 (bytes) + 3 * (10 + 5) (executions) = 148
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+        assert_eq!(Balances::reserved_balance(&payer), 148);
+
+        // Two validators agree on the output, the third one not
+        let consensus_output: Data = BoundedVec::try_from(vec![1, 2, 3]).unwrap();
+        let invalid_output: Data = BoundedVec::try_from(vec![3, 2, 1]).unwrap();
+        NodesOutputs::<Test>::insert(request_id, validators[0].clone(), consensus_output.clone());
+        NodesOutputs::<Test>::insert(request_id, validators[1].clone(), consensus_output.clone());
+        NodesOutputs::<Test>::insert(request_id, validators[2].clone(), invalid_output);
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult { output_data: consensus_output, total_executions: 3, total_consensus: 2, ..Default::default() });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+
+        // total paid = 103 (base + bytes) + 2 * 15 (executions) = 133, 66 for every validator, 16 refunded
+        assert_eq!(Balances::free_balance(&validators[0]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[1]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[2]), stake);
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000 - 132);
+        assert!(RequestsFees::<Test>::get(request_id).is_none());
+    });
+}
+
+// This test should complete a request where the agent called a cheaper model and check the unused model fee is refunded to the payer.
+#[test]
+fn test_fees_refund_unused_model_fee_on_completion() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(2, stake);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+
+        // The model fee held in escrow is the fee of the most expensive model
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(1), 5));
+        assert_ok!(TestingPallet::set_model_fee(RuntimeOrigin::root(), U256::from(2), 2));
+
+        // execution_fee = 10 (validator) + 5 (model) + 5 * 1 (blocks) = 20
+        // amount = 100 (base) + 3 * 1 (bytes) + 3 * 20 (executions) = 163
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+        assert_eq!(Balances::reserved_balance(&payer), 163);
+        assert_eq!(RequestsModelsFees::<Test>::get(request_id), Some(5));
+
+        // Both validators agree on the output, the first one reports also the expensive model but the lowest fee reported is used
+        let consensus_output: Data = BoundedVec::try_from(vec![1, 2, 3]).unwrap();
+        NodesOutputs::<Test>::insert(request_id, validators[0].clone(), consensus_output.clone());
+        NodesOutputs::<Test>::insert(request_id, validators[1].clone(), consensus_output.clone());
+        NodesOutputsModels::<Test>::insert(request_id, validators[0].clone(), BoundedVec::try_from(vec![U256::from(1), U256::from(2)]).unwrap());
+        NodesOutputsModels::<Test>::insert(request_id, validators[1].clone(), BoundedVec::try_from(vec![U256::from(2)]).unwrap());
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult { output_data: consensus_output, total_executions: 2, total_consensus: 2, ..Default::default() });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+
+        // execution fee used = 10 (validator) + 2 (model) + 5 (blocks) = 17
+        // total paid = 103 (base + bytes) + 2 * 17 (executions) = 137, 68 for every validator, 27 refunded
+        assert_eq!(Balances::free_balance(&validators[0]), stake + 68);
+        assert_eq!(Balances::free_balance(&validators[1]), stake + 68);
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000 - 136);
+        assert!(RequestsModelsFees::<Test>::get(request_id).is_none());
+        assert_eq!(NodesOutputsModels::<Test>::iter_prefix(request_id).count(), 0);
+    });
+}
+
+// REWARDS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should credit points to the validators during an era and distribute the inflation share when the era changes.
+#[test]
+fn test_rewards_distributed_per_era_and_claimed() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(2, stake);
+
+        assert_ok!(TestingPallet::set_rewards_inflation(RuntimeOrigin::root(), 1_000));
+
+        // The first validator produced two consensus outputs, the second one only one
+        TestingPallet::rewards_credit(&vec![validators[0].clone(), validators[1].clone()]);
+        TestingPallet::rewards_credit(&vec![validators[0].clone()]);
+        assert_eq!(ErasRewardPoints::<Test>::get(0, &validators[0]), 2);
+        assert_eq!(ErasRewardPoints::<Test>::get(0, &validators[1]), 1);
+        assert_eq!(ErasTotalRewardPoints::<Test>::get(0), 3);
+
+        // Nothing is distributed while the era is not changed
+        TestingPallet::on_initialize(2);
+        assert_eq!(ClaimableRewards::<Test>::get(&validators[0]), 0);
+
+        // Change the era and be sure the rewards are credited proportionally to the points
+        pallet_staking::ActiveEra::<Test>::put(pallet_staking::ActiveEraInfo { index: 1, start: None });
+        TestingPallet::on_initialize(3);
+        assert_eq!(RewardsCurrentEra::<Test>::get(), 1);
+        assert_eq!(ClaimableRewards::<Test>::get(&validators[0]), 666);
+        assert_eq!(ClaimableRewards::<Test>::get(&validators[1]), 333);
+        assert_eq!(ErasTotalRewardPoints::<Test>::get(0), 0);
+        assert_eq!(ErasRewardPoints::<Test>::iter_prefix(0).count(), 0);
+
+        // Claim the rewards
+        assert_ok!(TestingPallet::claim_rewards(RuntimeOrigin::signed(validators[0].clone())));
+        assert_eq!(Balances::free_balance(&validators[0]), stake + 666);
+        assert_eq!(ClaimableRewards::<Test>::get(&validators[0]), 0);
+        assert_eq!(
+            TestingPallet::claim_rewards(RuntimeOrigin::signed(validators[0].clone())),
+            Err(Error::<Test>::NoRewardsToClaim.into())
+        );
+    });
+}
+
+// This test should reject the claim of rewards below the existential deposit of an account without balance and keep them claimable.
+#[test]
+fn test_claim_rewards_below_existential_deposit_kept_claimable() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        ExistentialDeposit::set(100);
+
+        let account_id = Public::from_raw([7u8; 32]);
+        ClaimableRewards::<Test>::insert(&account_id, 50);
+
+        // The deposit would not create the account, so the rewards are not burned
+        assert_noop!(
+            TestingPallet::claim_rewards(RuntimeOrigin::signed(account_id.clone())),
+            Error::<Test>::RewardsBelowExistentialDeposit
+        );
+        assert_eq!(ClaimableRewards::<Test>::get(&account_id), 50);
+        assert_eq!(Balances::free_balance(&account_id), 0);
+
+        // The rewards are claimed when they reach the existential deposit
+        ClaimableRewards::<Test>::insert(&account_id, 150);
+        assert_ok!(TestingPallet::claim_rewards(RuntimeOrigin::signed(account_id.clone())));
+        assert_eq!(ClaimableRewards::<Test>::get(&account_id), 0);
+        assert_eq!(Balances::free_balance(&account_id), 150);
+    });
+}
+
+// OFFENCES
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should schedule an offence for every timeout and invalid output and report them after the delay if not cancelled.
+#[test]
+fn test_offences_scheduled_reported_and_cancelled() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(2, stake);
+
+        // The first validator is in timeout, the second one stored an invalid output
+        let mut opoc_penalties_operations = BTreeMap::<AccountId, Vec<OpocOffenceKind>>::new();
+        opoc_penalties_operations.insert(validators[0].clone(), vec![OpocOffenceKind::Timeout]);
+        opoc_penalties_operations.insert(validators[1].clone(), vec![OpocOffenceKind::InvalidOutput]);
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            opoc_penalties_operations,
+            ..Default::default()
+        }));
+
+        let timeout_offence = PendingOffences::<Test>::get(0).unwrap();
+        assert_eq!(timeout_offence.account_id, validators[0]);
+        assert_eq!(timeout_offence.kind, OpocOffenceKind::Timeout);
+        assert_eq!(timeout_offence.report_block_number, U256::from(11));
+        let invalid_output_offence = PendingOffences::<Test>::get(1).unwrap();
+        assert_eq!(invalid_output_offence.account_id, validators[1]);
+        assert_eq!(invalid_output_offence.kind, OpocOffenceKind::InvalidOutput);
+
+        // Only the governance can cancel an offence
+        assert!(TestingPallet::cancel_offence(RuntimeOrigin::signed(validators[0].clone()), 0).is_err());
+        assert_ok!(TestingPallet::cancel_offence(RuntimeOrigin::root(), 0));
+        assert_eq!(TestingPallet::cancel_offence(RuntimeOrigin::root(), 0), Err(Error::<Test>::OffenceNotFound.into()));
+
+        // Nothing is reported before the delay
+        TestingPallet::on_initialize(10);
+        assert_eq!(ReportedOffences::get().len(), 0);
+        assert_eq!(NextOffenceToReport::<Test>::get(), 0);
+
+        // The invalid output is reported after the delay, the cancelled timeout is skipped
+        TestingPallet::on_initialize(11);
+        let reported_offences = ReportedOffences::get();
+        assert_eq!(reported_offences.len(), 1);
+        assert_eq!(reported_offences[0].offender, (validators[1].clone(), validators[1].clone()));
+        assert_eq!(reported_offences[0].kind, OpocOffenceKind::InvalidOutput);
+        assert_eq!(reported_offences[0].slash_fraction, Perbill::from_percent(10));
+        assert_eq!(NextOffenceToReport::<Test>::get(), 2);
+        assert_eq!(PendingOffences::<Test>::iter().count(), 0);
+    });
+}
+
+// REPUTATION
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should decay the penalties of a validator over the decay window and let the governance reset its reputation.
+#[test]
+fn test_reputation_decays_and_resets() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(1, stake);
+        let validator = validators[0].clone();
+
+        // A validator without penalties has the max reputation
+        assert_eq!(TestingPallet::reputation_score(&validator, &U256::from(1)), MAX_REPUTATION);
+
+        // An invalid output takes the validator under the exclusion threshold
+        TestingPallet::reputation_penalize(&validator, OpocOffenceKind::InvalidOutput);
+        assert_eq!(TestingPallet::reputation_score(&validator, &U256::from(1)), MAX_REPUTATION - REPUTATION_PENALTY_INVALID_OUTPUT);
+
+        // The penalty decays linearly over the decay window
+        assert_eq!(TestingPallet::reputation_score(&validator, &U256::from(51)), MAX_REPUTATION - REPUTATION_PENALTY_INVALID_OUTPUT / 2);
+
+        // A new penalty is added to the decayed one and restarts the decay
+        System::set_block_number(51);
+        TestingPallet::reputation_penalize(&validator, OpocOffenceKind::Timeout);
+        let nodes_reputation = NodesReputations::<Test>::get(&validator).unwrap();
+        assert_eq!(nodes_reputation.penalty, REPUTATION_PENALTY_INVALID_OUTPUT / 2 + REPUTATION_PENALTY_TIMEOUT);
+        assert_eq!(nodes_reputation.updated_at_block_number, U256::from(51));
+
+        // The penalties expire after the decay window
+        assert_eq!(TestingPallet::reputation_score(&validator, &U256::from(151)), MAX_REPUTATION);
+
+        // Only the governance can reset the reputation of a validator
+        assert!(TestingPallet::reset_reputation(RuntimeOrigin::signed(validator.clone()), validator.clone()).is_err());
+        assert_ok!(TestingPallet::reset_reputation(RuntimeOrigin::root(), validator.clone()));
+        assert_eq!(NodesReputations::<Test>::get(&validator), None);
+        assert_eq!(
+            TestingPallet::reset_reputation(RuntimeOrigin::root(), validator.clone()),
+            Err(Error::<Test>::ReputationNotFound.into())
+        );
+    });
+}
+
 // OFFCHAIN WORKER
 //////////////////////////////////////////////////////////////////////////////////
 
 // This test should force the execution of the offchain_worker function for the uomi_whitepaper_chat_agent.wasm with an invalid input.
-// It should check the semaphore is released correctly.
+// It should check the slot of the worker pool is released correctly.
 #[test]
 #[serial]
 fn test_offchain_worker_uomi_whitepaper_chat_agent_fail() {
@@ -304,30 +714,28 @@ fn test_offchain_worker_uomi_whitepaper_chat_agent_fail() {
 
         // Insert an input on the Inputs storage
         let request_id: RequestId = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(), // block_number
-            U256::from(1312), // nft_id
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(), // nft_file_cid
-            not_empty_bounded_vec.clone(), // input_data
-            empty_cid.clone(), // input_file_cid
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::from(1312),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: not_empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(current_block_number + 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
         NodesWorks::<Test>::insert(validator.clone(), request_id, true);
 
-        // Read semaphore status and be sure is false
-        let semaphore = TestingPallet::semaphore_status();
-        assert_eq!(semaphore, false);
+        // Be sure no worker is running
+        assert_eq!(TestingPallet::offchain_active_workers(), 0);
 
         // Run the offchain worker
         TestingPallet::offchain_worker(current_block_number);
 
-        // Read semaphore status and be sure is false
-        let semaphore = TestingPallet::semaphore_status();
-        assert_eq!(semaphore, false);
+        // Be sure no worker is running
+        assert_eq!(TestingPallet::offchain_active_workers(), 0);
 
         // Verify transactions in the pool
         let state_read = state.read();
@@ -366,15 +774,15 @@ fn test_offchain_worker_ok() {
 
         // Insert an input on the Inputs storage
         let request_id: RequestId = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(), // block_number
-            U256::zero(), // nft_id
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(), // nft_file_cid
-            not_empty_bounded_vec.clone(), // input_data
-            empty_cid.clone(), // input_file_cid
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: not_empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(current_block_number + 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
@@ -386,6 +794,10 @@ fn test_offchain_worker_ok() {
         // Verify transactions in the pool
         let state_read = state.read();
         assert_eq!(state_read.transactions.len(), 2); // 1 to store the execution and 1 to store the node version
+
+        // Verify the logs of the execution are stored on the offchain local storage
+        let logs = sp_io::offchain::local_storage_get(StorageKind::PERSISTENT, &ExecutionLogs::storage_key(&request_id)).expect("Execution logs not stored");
+        assert!(ExecutionLogs::decode(&mut &logs[..]).is_ok());
     });
 }
 
@@ -419,15 +831,15 @@ fn test_offchain_worker_infinite() {
 
         // Insert an input on the Inputs storage
         let request_id = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(), // block_number
-            U256::from(1), // nft_id
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(), // nft_file_cid
-            not_empty_bounded_vec.clone(), // input_data
-            empty_cid.clone(), // input_file_cid
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::from(1),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: not_empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(current_block_number + 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
@@ -442,6 +854,98 @@ fn test_offchain_worker_infinite() {
     });
 }
 
+// This test should claim the requests assigned to the validator by expiration block number.
+// It should check every assignment is claimed at most once, also by the offchain workers of the next blocks.
+#[test]
+#[serial]
+fn test_offchain_claim_request() {
+    make_logger();
+
+    let mut ext = new_test_ext();
+
+    // Set up the offchain worker test environment
+    let (offchain, _state) = TestOffchainExt::new();
+    ext.register_extension(OffchainDbExt::new(offchain.clone()));
+    ext.register_extension(OffchainWorkerExt::new(offchain));
+
+    let validator = Public::from_raw([1; 32]);
+    let empty_cid = Cid::default();
+    let not_empty_bounded_vec = BoundedVec::try_from(vec![1, 2, 3]).expect("Vector exceeds the bound");
+
+    ext.execute_with(|| {
+        System::set_block_number(12);
+
+        // Insert two requests assigned to the validator, the second one with an earlier expiration block number
+        for (request_id, expiration_block_number) in [(U256::from(1), U256::from(30)), (U256::from(2), U256::from(20))] {
+            Inputs::<Test>::insert(request_id, AgentRequest {
+                block_number: U256::zero(),
+                nft_id: U256::from(1312),
+                nft_required_consensus: U256::from(5),
+                nft_execution_max_time: U256::from(25),
+                nft_file_cid: empty_cid.clone(),
+                input_data: not_empty_bounded_vec.clone(),
+                input_file_cid: empty_cid.clone(),
+            });
+            OpocAssignment::<Test>::insert(request_id, validator.clone(), expiration_block_number);
+            NodesWorks::<Test>::insert(validator.clone(), request_id, true);
+        }
+
+        // The requests are claimed by expiration block number
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), Some((U256::from(2), U256::from(20))));
+
+        // The offchain worker of the next block claims the other request
+        System::set_block_number(13);
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), Some((U256::from(1), U256::from(30))));
+
+        // No request is claimed twice
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), None);
+
+        // A request whose assignment is removed is forgotten
+        Inputs::<Test>::remove(U256::from(2));
+        OpocAssignment::<Test>::remove(U256::from(2), validator.clone());
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), None);
+        let started = StorageValueRef::persistent(STARTED_REQUESTS_STORAGE_KEY).get::<BTreeMap<(RequestId, BlockNumber), BlockNumber>>().unwrap().unwrap();
+        assert_eq!(started.keys().cloned().collect::<Vec<_>>(), vec![(U256::from(1), U256::from(30))]);
+
+        // A request assigned again to the validator after a timeout is claimed again, also when it is still in Inputs
+        OpocAssignment::<Test>::remove(U256::from(1), validator.clone());
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), None);
+        OpocAssignment::<Test>::insert(U256::from(1), validator.clone(), U256::from(40));
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), Some((U256::from(1), U256::from(40))));
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), None);
+
+        // The new assignment is claimed again also when it is assigned again without being seen removed by the node
+        OpocAssignment::<Test>::insert(U256::from(1), validator.clone(), U256::from(50));
+        assert_eq!(TestingPallet::offchain_claim_request(&validator), Some((U256::from(1), U256::from(50))));
+    });
+}
+
+// This test should read the maximum number of workers stored by the node on the offchain local storage.
+// It should check the default value and the bounds.
+#[test]
+#[serial]
+fn test_offchain_get_max_workers() {
+    let mut ext = new_test_ext();
+
+    // Set up the offchain worker test environment
+    let (offchain, _state) = TestOffchainExt::new();
+    ext.register_extension(OffchainDbExt::new(offchain.clone()));
+    ext.register_extension(OffchainWorkerExt::new(offchain));
+
+    ext.execute_with(|| {
+        assert_eq!(TestingPallet::offchain_get_max_workers(), DEFAULT_MAX_WORKERS);
+
+        StorageValueRef::persistent(MAX_WORKERS_STORAGE_KEY).set(&4u32);
+        assert_eq!(TestingPallet::offchain_get_max_workers(), 4);
+
+        StorageValueRef::persistent(MAX_WORKERS_STORAGE_KEY).set(&0u32);
+        assert_eq!(TestingPallet::offchain_get_max_workers(), 1);
+
+        StorageValueRef::persistent(MAX_WORKERS_STORAGE_KEY).set(&(MAX_WORKERS + 1));
+        assert_eq!(TestingPallet::offchain_get_max_workers(), MAX_WORKERS);
+    });
+}
+
 #[test]
 #[serial]
 fn test_offchain_worker_not_existing() {
@@ -472,15 +976,15 @@ fn test_offchain_worker_not_existing() {
 
         // Insert an input on the Inputs storage
         let request_id = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(), // block_number
-            U256::from(999), // nft_id
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(), // nft_file_cid
-            not_empty_bounded_vec.clone(), // input_data
-            empty_cid.clone(), // input_file_cid
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::from(999),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: not_empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(current_block_number + 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
@@ -521,7 +1025,7 @@ fn test_offchain_run_wasm_function_with_valid_wasm() {
 
         // Be sure result is input_data reversed
         let input_data_reversed = input_data.iter().rev().cloned().collect::<Vec<u8>>();
-        assert_eq!(result.unwrap(), input_data_reversed);
+        assert_eq!(result.unwrap().output, input_data_reversed);
     });
 }
 
@@ -581,7 +1085,7 @@ fn test_offchain_run_wasm_function_with_call_ai() {
 
         // Be sure result is input_data reversed
         let input_data_reversed = input_data.iter().rev().cloned().collect::<Vec<u8>>();
-        assert_eq!(result.unwrap(), input_data_reversed);
+        assert_eq!(result.unwrap().output, input_data_reversed);
     });
 }
 
@@ -617,92 +1121,420 @@ fn test_offchain_run_wasm_function_with_get_file_cid() {
     });
 }
 
-// OPOC
-//////////////////////////////////////////////////////////////////////////////////
+// EchoHost is an agent host returning the CID as file and the input inverted as inference.
+struct EchoHost;
+
+impl AgentHost for EchoHost {
+    fn get_input_file(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn get_cid_file(&mut self, cid: Cid) -> Result<Vec<u8>, sp_runtime::DispatchError> {
+        Ok(cid.to_vec())
+    }
+
+    fn call_ai(&mut self, _model: AiModelKey, input: Vec<u8>, _counter: u32) -> Result<Vec<u8>, sp_runtime::DispatchError> {
+        Ok(input.into_iter().rev().collect())
+    }
+}
 
+// This test should run an agent with the shared wasm runner and a custom host, as the tools of the node do.
 #[test]
-fn test_inherent_opoc_no_assignment() {
+fn test_wasm_run_with_custom_host() {
     make_logger();
-    
-    new_test_ext().execute_with(|| {
-        let empty_cid = Cid::default();
-        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
 
-        let stake = 10_000_000_000_000_000_000;
-        let num_validators = 1;
-        let validators = create_validators(num_validators, stake);
-        let validator = validators[0].clone();
+    let wasm = include_bytes!("./test_agents/agent2.wasm").to_vec();
+    let (result, logs) = wasm_run(&wasm, None, vec![1, 2, 3], 10_000, ExecutionBudget::from_execution_max_time(U256::from(10)), EchoHost);
+    let output = result.expect("Agent execution failed");
 
-        // Set current block
-        System::set_block_number(1);
-        let current_block_number = System::block_number();
+    // Be sure result is input_data reversed and the AI calls are counted and timed
+    assert_eq!(output.output, vec![3, 2, 1]);
+    assert!(output.ai_calls > 0);
+    assert_eq!(logs.host_calls.len(), output.ai_calls as usize);
+    assert!(logs.host_calls.iter().all(|call| call.name == b"call_ai".to_vec() && call.success));
+}
 
-        // Insert an input on the Inputs storage
-        let request_id: U256 = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+// This test should accept the test agents and reject a wasm that can not be executed by the engine.
+#[test]
+fn test_wasm_validate() {
+    make_logger();
 
-        let inherent_data = InherentData::new();
-        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
-        System::set_block_number(0);
-        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
-        assert!(TestingPallet::is_inherent(&inherent_call));
-        let runtime_call: RuntimeCall = inherent_call.into();
-        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
-        
-        // After the execution of the inherent, should exist 1 assignment for the request_id 1 and the validator
-        let opoc_assignment = OpocAssignment::<Test>::get(request_id, validator.clone());
-        assert_ne!(opoc_assignment, U256::zero());
-        // After the execution of the inherent, the opoc assignment should have the expiration block number set to the current block number + the input lifetime
-        assert_eq!(opoc_assignment, U256::from(current_block_number + 25));
-        // After the execution of the inherent, the NodesWorks storage should contain a record assigned to the validator with the value 1
-        let nodes_works_number = NodesWorks::<Test>::get(validator.clone(), request_id);
-        assert_eq!(nodes_works_number, true);
-    });
+    assert!(wasm_validate(include_bytes!("./test_agents/agent0.wasm")).is_ok());
+    assert!(wasm_validate(include_bytes!("./test_agents/agent2.wasm")).is_ok());
+    assert!(wasm_validate(&[0, 1, 2, 3]).is_err());
 }
 
+// This test should negotiate the ABI v1 with the agents that do not export their ABI version.
 #[test]
-fn test_inherent_opoc_no_assignment_without_validators_available() {
+fn test_wasm_abi_v1_by_default() {
     make_logger();
-    
-    new_test_ext().execute_with(|| {
-        let empty_cid = Cid::default();
-        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
 
-        // Set current block
-        System::set_block_number(1);
+    let wasm = include_bytes!("./test_agents/agent2.wasm").to_vec();
+    let (result, _) = wasm_run(&wasm, None, vec![1, 2, 3], 10_000, ExecutionBudget::from_execution_max_time(U256::from(10)), EchoHost);
+    assert_eq!(result.expect("Agent execution failed").abi_version, WASM_ABI_V1);
+}
 
-        // Insert an input on the Inputs storage
-        let request_id: U256 = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+// This test should run an agent of the ABI v2, with bounded buffers and error codes instead of writes outside of the buffers.
+#[test]
+fn test_wasm_abi_v2() {
+    make_logger();
 
-        let inherent_data = InherentData::new();
-        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
-        System::set_block_number(0);
-        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
-        assert!(TestingPallet::is_inherent(&inherent_call));
-        let runtime_call: RuntimeCall = inherent_call.into();
-        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
-        
-        // After the execution of the inherent, the OpocAssignment storage should contain 0 assignment for the request_id 1
-        let opoc_assignment = OpocAssignment::<Test>::iter_prefix_values(request_id).collect::<Vec<_>>();
-        assert_eq!(opoc_assignment.len(), 0);
-        // After the execution of the inherent, the NodesWorks storage should contain 0 assignment
+    let wasm = r#"
+        (module
+            (import "uomi_v2" "get_input_data" (func $get_input_data (result i32)))
+            (import "uomi_v2" "read_result" (func $read_result (param i32 i32) (result i32)))
+            (import "uomi_v2" "call_ai" (func $call_ai (param i32 i32 i32) (result i32)))
+            (import "uomi_v2" "set_output" (func $set_output (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "uomi_abi_version") (result i32) (i32.const 2))
+            (func (export "run") (local $len i32)
+                ;; the input data does not fit in a buffer of 1 byte, it is kept and read again
+                (if (i32.ne (call $get_input_data) (i32.const 3)) (then unreachable))
+                (if (i32.ne (call $read_result (i32.const 0) (i32.const 1)) (i32.const -2)) (then unreachable))
+                (local.set $len (call $read_result (i32.const 0) (i32.const 1024)))
+                (if (i32.ne (call $read_result (i32.const 0) (i32.const 1024)) (i32.const -5)) (then unreachable))
+                ;; a buffer outside of the memory is rejected
+                (if (i32.ne (call $set_output (i32.const 65535) (i32.const 2)) (i32.const -1)) (then unreachable))
+                (if (i32.ne (call $call_ai (i32.const 0) (i32.const 0) (local.get $len)) (local.get $len)) (then unreachable))
+                (local.set $len (call $read_result (i32.const 1024) (i32.const 1024)))
+                (drop (call $set_output (i32.const 1024) (local.get $len)))
+            )
+        )
+    "#;
+    let (result, logs) = wasm_run(wasm.as_bytes(), None, vec![1, 2, 3], 10_000, ExecutionBudget::from_execution_max_time(U256::from(10)), EchoHost);
+    let output = result.expect("Agent execution failed");
+
+    // Be sure result is input_data reversed and the AI calls are counted as on the ABI v1
+    assert_eq!(output.abi_version, WASM_ABI_V2);
+    assert_eq!(output.output, vec![3, 2, 1]);
+    assert_eq!(output.ai_calls, 1);
+    assert_eq!(output.ai_models, vec![AiModelKey::from(0)]);
+    assert_eq!(logs.host_calls.len(), 1);
+}
+
+// This test should reject the agents declaring an unsupported ABI version or importing the host functions of another version.
+#[test]
+fn test_wasm_abi_version_negotiation() {
+    make_logger();
+
+    let unsupported = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "uomi_abi_version") (result i32) (i32.const 99))
+            (func (export "run"))
+        )
+    "#;
+    let (result, _) = wasm_run(unsupported.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(10)), EchoHost);
+    assert!(result.is_err());
+    assert!(wasm_validate(unsupported.as_bytes()).is_err());
+
+    let mixed = r#"
+        (module
+            (import "env" "set_output" (func $set_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "uomi_abi_version") (result i32) (i32.const 2))
+            (func (export "run") (call $set_output (i32.const 0) (i32.const 0)))
+        )
+    "#;
+    let (result, _) = wasm_run(mixed.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(10)), EchoHost);
+    assert!(result.is_err());
+    assert!(wasm_validate(mixed.as_bytes()).is_err());
+}
+
+// This test should complete with out_of_fuel and an empty output an agent exhausting the fuel of the request.
+#[test]
+fn test_wasm_out_of_fuel() {
+    make_logger();
+
+    let wasm = r#"
+        (module
+            (import "env" "set_output" (func $set_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "partial")
+            (func (export "run")
+                (call $set_output (i32.const 0) (i32.const 7))
+                (loop $forever (br $forever))
+            )
+        )
+    "#;
+    let budget = ExecutionBudget { fuel: 1_000_000, ..ExecutionBudget::from_execution_max_time(U256::from(1)) };
+    let (result, _) = wasm_run(wasm.as_bytes(), None, Vec::new(), 10_000, budget, EchoHost);
+    let output = result.expect("Out of fuel should be a completed execution");
+
+    assert!(output.out_of_fuel);
+    assert!(output.output.is_empty());
+    assert_eq!(output.fuel_consumed, 1_000_000);
+
+    // The same agent with a budget large enough completes, consuming the same fuel on every execution
+    let terminating = r#"
+        (module
+            (import "env" "set_output" (func $set_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "run") (call $set_output (i32.const 0) (i32.const 1)))
+        )
+    "#;
+    let (first, _) = wasm_run(terminating.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(1)), EchoHost);
+    let (second, _) = wasm_run(terminating.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(1)), EchoHost);
+    let (first, second) = (first.expect("Agent execution failed"), second.expect("Agent execution failed"));
+    assert!(!first.out_of_fuel);
+    assert!(first.fuel_consumed > 0);
+    assert_eq!(first.fuel_consumed, second.fuel_consumed);
+}
+
+// This test should give the same result to an agent at different epoch durations, the fuel is the only limit of the execution.
+// It should check the agent interrupted by the timeout of the node is an error recognized as interrupted, so its output is not committed.
+#[test]
+fn test_wasm_result_independent_of_epoch_duration() {
+    make_logger();
+
+    // The agent counts to 100_000 before setting its output
+    let counting = r#"
+        (module
+            (import "env" "set_output" (func $set_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "run") (local $i i32)
+                (loop $count
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br_if $count (i32.lt_u (local.get $i) (i32.const 100000)))
+                )
+                (i32.store (i32.const 0) (local.get $i))
+                (call $set_output (i32.const 0) (i32.const 4))
+            )
+        )
+    "#;
+    let forever = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "run") (loop $forever (br $forever)))
+        )
+    "#;
+
+    let results = [1, 10, 100].map(|epoch_duration_ms| {
+        let budget = ExecutionBudget::from_execution_max_time(U256::from(1));
+        let (counted, _) = wasm_run_with_epoch_duration(counting.as_bytes(), None, Vec::new(), 10_000, epoch_duration_ms, budget, EchoHost);
+        let budget = ExecutionBudget { fuel: 1_000_000, ..ExecutionBudget::from_execution_max_time(U256::from(1)) };
+        let (looped, _) = wasm_run_with_epoch_duration(forever.as_bytes(), None, Vec::new(), 10_000, epoch_duration_ms, budget, EchoHost);
+        let (counted, looped) = (counted.expect("Agent execution failed"), looped.expect("Out of fuel should be a completed execution"));
+        (counted.output, counted.out_of_fuel, counted.fuel_consumed, looped.output, looped.out_of_fuel, looped.fuel_consumed)
+    });
+    assert_eq!(results[0].0, 100_000i32.to_le_bytes().to_vec());
+    assert!(results[0].4);
+    assert!(results.iter().all(|result| result == &results[0]));
+
+    // The agent with more fuel than the time given by the node is interrupted before the end of its fuel
+    let budget = ExecutionBudget::from_execution_max_time(U256::from(100));
+    let (result, _) = wasm_run_with_epoch_duration(forever.as_bytes(), None, Vec::new(), 50, 1, budget, EchoHost);
+    let error = result.expect_err("The execution should be interrupted");
+    assert!(wasm_interrupted(&error));
+
+    // The agent failing on its own is not interrupted, so the node commits its empty output as the other validators
+    let trapping = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "run") unreachable)
+        )
+    "#;
+    let (result, _) = wasm_run(trapping.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(1)), EchoHost);
+    assert!(!wasm_interrupted(&result.expect_err("The execution should fail")));
+}
+
+// This test should refuse to grow the memory of an agent over the memory limit of the budget.
+#[test]
+fn test_wasm_memory_limit() {
+    make_logger();
+
+    // The agent sets as output the result of memory.grow, -1 if the memory can not be grown
+    let wasm = r#"
+        (module
+            (import "env" "set_output" (func $set_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "run")
+                (i32.store (i32.const 0) (memory.grow (i32.const 2)))
+                (call $set_output (i32.const 0) (i32.const 4))
+            )
+        )
+    "#;
+    let budget = ExecutionBudget { memory_size: 2 * 65536, ..ExecutionBudget::from_execution_max_time(U256::from(1)) };
+    let (result, _) = wasm_run(wasm.as_bytes(), None, Vec::new(), 10_000, budget, EchoHost);
+    assert_eq!(result.expect("Agent execution failed").output, (-1i32).to_le_bytes().to_vec());
+
+    let (result, _) = wasm_run(wasm.as_bytes(), None, Vec::new(), 10_000, ExecutionBudget::from_execution_max_time(U256::from(1)), EchoHost);
+    assert_eq!(result.expect("Agent execution failed").output, 1i32.to_le_bytes().to_vec());
+}
+
+// This test should derive the budget of a request from its maximum execution time, bounded by the maximum fuel.
+#[test]
+fn test_execution_budget_from_execution_max_time() {
+    assert_eq!(ExecutionBudget::from_execution_max_time(U256::from(2)).fuel, 2 * FUEL_PER_EXECUTION_BLOCK);
+    assert_eq!(ExecutionBudget::from_execution_max_time(U256::max_value()).fuel, MAX_EXECUTION_FUEL);
+    assert_eq!(ExecutionBudget::from_execution_max_time(U256::from(2)).memory_size, MAX_AGENT_MEMORY_SIZE as u64);
+}
+
+// MODULE CACHE
+//////////////////////////////////////////////////////////////////////////////////
+
+// This function creates an empty directory for a cache of the compiled modules used by a test.
+fn module_cache_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("uomi-engine-module-cache-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+// This test should compile an agent once, load it from the cache after and compile it again if the cached file is corrupted.
+#[test]
+fn test_wasm_cache_load_or_compile() {
+    make_logger();
+
+    let dir = module_cache_dir("load");
+    let cache = WasmModuleCache::new(dir.clone(), 64 * 1024 * 1024).expect("Cache directory not created");
+    let engine = wasmtime::Engine::default();
+    let cid = Cid::try_from(b"agent-cid".to_vec()).unwrap();
+    let wasm = include_bytes!("./test_agents/agent0.wasm").to_vec();
+
+    // The first load compiles and stores the module
+    assert!(cache.load_or_compile(&engine, &cid, &wasm).is_ok());
+    let path = cache.entry_path(&engine, &wasm);
+    let stored = std::fs::read(&path).expect("Compiled module not stored");
+
+    // The second load reads the same file
+    assert!(cache.load_or_compile(&engine, &cid, &wasm).is_ok());
+    assert_eq!(std::fs::read(&path).unwrap(), stored);
+
+    // A corrupted file is replaced by a new compilation
+    let mut corrupted = stored.clone();
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xff;
+    std::fs::write(&path, &corrupted).unwrap();
+    assert!(cache.load_or_compile(&engine, &cid, &wasm).is_ok());
+    let recompiled = std::fs::read(&path).unwrap();
+    assert_ne!(recompiled, corrupted);
+    assert_eq!(recompiled.len(), stored.len());
+
+    // Another wasm with the same CID is stored on another file, keyed by the hash of the wasm
+    let other_wasm = include_bytes!("./test_agents/agent2.wasm").to_vec();
+    assert!(cache.load_or_compile(&engine, &cid, &other_wasm).is_ok());
+    let other_path = cache.entry_path(&engine, &other_wasm);
+    assert_ne!(other_path, path);
+    assert_ne!(std::fs::read(&other_path).unwrap()[..72], recompiled[..72]);
+    assert_eq!(std::fs::read(&path).unwrap(), recompiled);
+
+    // The name of the file is the same for another cache of the same engine configuration
+    let other_cache = WasmModuleCache::new(dir.clone(), 64 * 1024 * 1024).expect("Cache directory not created");
+    assert_eq!(other_cache.entry_path(&wasmtime::Engine::default(), &wasm), path);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// This test should evict the least recently used modules when the cache exceeds its maximum size.
+#[test]
+fn test_wasm_cache_eviction() {
+    make_logger();
+
+    let dir = module_cache_dir("eviction");
+    let cache = WasmModuleCache::new(dir.clone(), 1).expect("Cache directory not created");
+    let engine = wasmtime::Engine::default();
+    let first_cid = Cid::try_from(b"first-agent-cid".to_vec()).unwrap();
+    let second_cid = Cid::try_from(b"second-agent-cid".to_vec()).unwrap();
+    let first_wasm = include_bytes!("./test_agents/agent0.wasm").to_vec();
+    let second_wasm = include_bytes!("./test_agents/agent2.wasm").to_vec();
+
+    assert!(cache.load_or_compile(&engine, &first_cid, &first_wasm).is_ok());
+    assert!(cache.entry_path(&engine, &first_wasm).exists());
+
+    // The module just stored is kept also if it exceeds the maximum size, the others are evicted
+    assert!(cache.load_or_compile(&engine, &second_cid, &second_wasm).is_ok());
+    assert!(!cache.entry_path(&engine, &first_wasm).exists());
+    assert!(cache.entry_path(&engine, &second_wasm).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+// OPOC
+//////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_inherent_opoc_no_assignment() {
+    make_logger();
+    
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
+
+        let stake = 10_000_000_000_000_000_000;
+        let num_validators = 1;
+        let validators = create_validators(num_validators, stake);
+        let validator = validators[0].clone();
+
+        // Set current block
+        System::set_block_number(1);
+        let current_block_number = System::block_number();
+
+        // Insert an input on the Inputs storage
+        let request_id: U256 = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
+
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        System::set_block_number(0);
+        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
+        assert!(TestingPallet::is_inherent(&inherent_call));
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+        
+        // After the execution of the inherent, should exist 1 assignment for the request_id 1 and the validator
+        let opoc_assignment = OpocAssignment::<Test>::get(request_id, validator.clone());
+        assert_ne!(opoc_assignment, U256::zero());
+        // After the execution of the inherent, the opoc assignment should have the expiration block number set to the current block number + the input lifetime
+        assert_eq!(opoc_assignment, U256::from(current_block_number + 25));
+        // After the execution of the inherent, the NodesWorks storage should contain a record assigned to the validator with the value 1
+        let nodes_works_number = NodesWorks::<Test>::get(validator.clone(), request_id);
+        assert_eq!(nodes_works_number, true);
+    });
+}
+
+#[test]
+fn test_inherent_opoc_no_assignment_without_validators_available() {
+    make_logger();
+    
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
+
+        // Set current block
+        System::set_block_number(1);
+
+        // Insert an input on the Inputs storage
+        let request_id: U256 = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
+
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        System::set_block_number(0);
+        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
+        assert!(TestingPallet::is_inherent(&inherent_call));
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+        
+        // After the execution of the inherent, the OpocAssignment storage should contain 0 assignment for the request_id 1
+        let opoc_assignment = OpocAssignment::<Test>::iter_prefix_values(request_id).collect::<Vec<_>>();
+        assert_eq!(opoc_assignment.len(), 0);
+        // After the execution of the inherent, the NodesWorks storage should contain 0 assignment
         let nodes_works_number = NodesWorks::<Test>::iter().collect::<Vec<_>>();
         assert_eq!(nodes_works_number.len(), 0);
     });
@@ -725,15 +1557,15 @@ fn test_inherent_opoc_level_0_no_timeout() {
 
         // Insert an input on the Inputs storage
         let request_id: U256 = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(current_block_number + 1));
@@ -753,12 +1585,9 @@ fn test_inherent_opoc_level_0_no_timeout() {
         // After the execution of the inherent, the NodesWorks storage should contain 1 assignment for the first validator
         let nodes_works_number = NodesWorks::<Test>::get(validators[0].clone(), request_id);
         assert_eq!(nodes_works_number, true);
-        // The validator should not be blacklisted
-        let opoc_blacklist = OpocBlacklist::<Test>::get(validators[0].clone());
-        assert_eq!(opoc_blacklist, false);
-        // The validator should not have timeouts
-        let nodes_timeouts = NodesTimeouts::<Test>::get(validators[0].clone());
-        assert_eq!(nodes_timeouts, 0 as u32);
+        // The validator should not be penalized
+        let nodes_reputation = NodesReputations::<Test>::get(validators[0].clone());
+        assert_eq!(nodes_reputation, None);
     });
 }
 
@@ -779,15 +1608,15 @@ fn test_inherent_opoc_level_0_timeout() {
 
         // Insert an input on the Inputs storage
         let request_id: U256 = U256::from(1);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            U256::from(5), // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(5),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(U256::from(1), validators[0].clone(), U256::from(current_block_number - 1));
@@ -807,12 +1636,9 @@ fn test_inherent_opoc_level_0_timeout() {
         // After the execution of the inherent, the NodesWorks storage should contain 1 assignment
         let nodes_works_number = NodesWorks::<Test>::iter().collect::<Vec<_>>();
         assert_eq!(nodes_works_number.len(), 1);
-        // The validator should be blacklisted
-        let opoc_blacklist = OpocBlacklist::<Test>::get(validators[0].clone());
-        assert_eq!(opoc_blacklist, true);
-        // The validator should have timeouts
-        let nodes_timeouts = NodesTimeouts::<Test>::get(validators[0].clone());
-        assert_eq!(nodes_timeouts, 1 as u32);
+        // The validator should be penalized for the timeout
+        let nodes_reputation = NodesReputations::<Test>::get(validators[0].clone()).unwrap();
+        assert_eq!(nodes_reputation.penalty, REPUTATION_PENALTY_TIMEOUT);
     });
 }
 
@@ -834,15 +1660,15 @@ fn test_inherent_opoc_level_0_completed() {
         // Insert an input on the Inputs storage
         let request_id: U256 = U256::from(1);
         let nft_required_consensus = U256::from(5);
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            nft_required_consensus, // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: nft_required_consensus,
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(U256::from(1), validators[0].clone(), U256::from(current_block_number - 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
@@ -894,15 +1720,15 @@ fn test_inherent_opoc_level_0_completed_unsecure() {
         // Insert an input on the Inputs storage
         let request_id: U256 = U256::from(1);
         let nft_required_consensus = U256::from(1); // unsecure means that nft_required_consensus is 1
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            nft_required_consensus, // nft_required_consensus
-            U256::from(25), // nft_execution_max_time
-            empty_cid.clone(),
-            empty_bounded_vec.clone(),
-            empty_cid.clone(),
-        ));
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: nft_required_consensus,
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
 
         // Insert an assignment for the first validator
         OpocAssignment::<Test>::insert(U256::from(1), validators[0].clone(), U256::from(current_block_number - 1)); // NOTE: We set the expiration block number to the previous block so we simulate that the assignment is expired but the output is available
@@ -921,7 +1747,7 @@ fn test_inherent_opoc_level_0_completed_unsecure() {
 
         // storage Outputs should contain the output of the request
         let outputs = Outputs::<Test>::get(request_id);
-        assert_eq!(outputs, (empty_bounded_vec.clone(), 1, 1));
+        assert_eq!(outputs, AgentResult { output_data: empty_bounded_vec.clone(), total_executions: 1, total_consensus: 1, ..Default::default() });
 
         //check that storage_opoc_assignment is empty
         let opoc_assignments = OpocAssignment::<Test>::iter_prefix_values(request_id).collect::<Vec<_>>();
@@ -932,12 +1758,9 @@ fn test_inherent_opoc_level_0_completed_unsecure() {
         //check that storage_nodes_works is empty
         let nodes_works_number = NodesWorks::<Test>::iter().collect::<Vec<_>>();
         assert_eq!(nodes_works_number.len() as u32, 0);
-        //check that storage_opoc_blacklist is empty
-        let opoc_blacklist = OpocBlacklist::<Test>::iter().collect::<Vec<_>>();
-        assert_eq!(opoc_blacklist.len() as u32, 0);
-        //check that storage_nodes_timeouts is empty
-        let nodes_timeouts = NodesTimeouts::<Test>::iter().collect::<Vec<_>>();
-        assert_eq!(nodes_timeouts.len() as u32, 0); 
+        //check that storage_nodes_reputations is empty
+        let nodes_reputations = NodesReputations::<Test>::iter().collect::<Vec<_>>();
+        assert_eq!(nodes_reputations.len() as u32, 0);
     });
 }
 
@@ -959,15 +1782,15 @@ fn test_inherent_opoc_level_1_no_timeouts() {
       // Insert an input on the Inputs storage
       let request_id: U256 = U256::from(1);
       let nft_required_consensus = U256::from(5);
-      Inputs::<Test>::insert(request_id, (
-          U256::zero(),
-          U256::zero(),
-          nft_required_consensus,
-          U256::from(25), // nft_execution_max_time
-          empty_cid.clone(),
-          empty_bounded_vec.clone(),
-          empty_cid.clone(),
-      ));
+      Inputs::<Test>::insert(request_id, AgentRequest {
+          block_number: U256::zero(),
+          nft_id: U256::zero(),
+          nft_required_consensus: nft_required_consensus,
+          nft_execution_max_time: U256::from(25),
+          nft_file_cid: empty_cid.clone(),
+          input_data: empty_bounded_vec.clone(),
+          input_file_cid: empty_cid.clone(),
+      });
 
       // Insert an assignment for the first validator
       OpocAssignment::<Test>::insert(U256::from(1), validators[4].clone(), U256::from(current_block_number - 1)); 
@@ -1023,15 +1846,15 @@ fn test_inherent_opoc_level_1_some_timeouts() { // Case where during the executi
       // Insert an input on the Inputs storage
       let request_id: U256 = U256::from(1);
       let nft_required_consensus = U256::from(5);
-      Inputs::<Test>::insert(request_id, (
-          U256::zero(),
-          U256::zero(),
-          nft_required_consensus,
-          U256::from(25), // nft_execution_max_time
-          empty_cid.clone(),
-          empty_bounded_vec.clone(),
-          empty_cid.clone(),
-      ));
+      Inputs::<Test>::insert(request_id, AgentRequest {
+          block_number: U256::zero(),
+          nft_id: U256::zero(),
+          nft_required_consensus: nft_required_consensus,
+          nft_execution_max_time: U256::from(25),
+          nft_file_cid: empty_cid.clone(),
+          input_data: empty_bounded_vec.clone(),
+          input_file_cid: empty_cid.clone(),
+      });
 
       // Insert an assignment for the first validator
       OpocAssignment::<Test>::insert(U256::from(1), validators[4].clone(), U256::from(current_block_number - 1)); 
@@ -1081,11 +1904,9 @@ fn test_inherent_opoc_level_1_some_timeouts() { // Case where during the executi
       // this means the the validator with the updated timeout is a new validator chosen to retry the execution that was in timeout
       assert_eq!(opoc_assignment_with_new_expiration, 1);
 
-      let opoc_blacklist = OpocBlacklist::<Test>::get(validators[3].clone());
-      assert_eq!(opoc_blacklist, true);
-      // The validator should have timeouts
-      let nodes_timeouts = NodesTimeouts::<Test>::get(validators[3].clone());
-      assert_eq!(nodes_timeouts, 1 as u32);
+      // The validator should be penalized for the timeout
+      let nodes_reputation = NodesReputations::<Test>::get(validators[3].clone()).unwrap();
+      assert_eq!(nodes_reputation.penalty, REPUTATION_PENALTY_TIMEOUT);
   });
     
 }
@@ -1109,15 +1930,15 @@ fn test_inherent_opoc_level_1_completed_valid() {
 
       // Insert an input on the Inputs storage
       let request_id: U256 = U256::from(1);
-      Inputs::<Test>::insert(request_id, (
-          U256::zero(),
-          U256::zero(),
-          U256::from(5), // nft_required_consensus
-          U256::from(25), // nft_execution_max_time
-          empty_cid.clone(),
-          bounded_vec.clone(),
-          empty_cid.clone(),
-      ));
+      Inputs::<Test>::insert(request_id, AgentRequest {
+          block_number: U256::zero(),
+          nft_id: U256::zero(),
+          nft_required_consensus: U256::from(5),
+          nft_execution_max_time: U256::from(25),
+          nft_file_cid: empty_cid.clone(),
+          input_data: bounded_vec.clone(),
+          input_file_cid: empty_cid.clone(),
+      });
 
       // Insert an assignment for the first validator
       OpocAssignment::<Test>::insert(U256::from(1), validators[4].clone(), U256::from(current_block_number - 1)); 
@@ -1141,7 +1962,7 @@ fn test_inherent_opoc_level_1_completed_valid() {
       
       // storage Outputs should contain the output of the request
       let outputs = Outputs::<Test>::get(request_id);
-      assert_eq!(outputs, (bounded_vec.clone(), 5, 5));
+      assert_eq!(outputs, AgentResult { output_data: bounded_vec.clone(), total_executions: 5, total_consensus: 5, ..Default::default() });
 
       //check that storage_opoc_assignment is empty
       let opoc_assignments = OpocAssignment::<Test>::iter_prefix_values(request_id).collect::<Vec<_>>();
@@ -1152,12 +1973,9 @@ fn test_inherent_opoc_level_1_completed_valid() {
       //check that storage_nodes_works is empty
       let nodes_works_number = NodesWorks::<Test>::iter().collect::<Vec<_>>();
       assert_eq!(nodes_works_number.len() as u32, 0);
-      //check that storage_opoc_blacklist is empty
-      let opoc_blacklist = OpocBlacklist::<Test>::iter().collect::<Vec<_>>();
-      assert_eq!(opoc_blacklist.len() as u32, 0);
-      //check that storage_nodes_timeouts is empty
-      let nodes_timeouts = NodesTimeouts::<Test>::iter().collect::<Vec<_>>();
-      assert_eq!(nodes_timeouts.len() as u32, 0); 
+      //check that storage_nodes_reputations is empty
+      let nodes_reputations = NodesReputations::<Test>::iter().collect::<Vec<_>>();
+      assert_eq!(nodes_reputations.len() as u32, 0);
   });
 }
 
@@ -1181,15 +1999,15 @@ fn test_inherent_opoc_level_1_completed_invalid() {
 
     // Insert an input on the Inputs storage
     let request_id: U256 = U256::from(1);
-    Inputs::<Test>::insert(request_id, (
-        U256::zero(),
-        U256::zero(),
-        U256::from(5), // nft_required_consensus
-        U256::from(25), // nft_execution_max_time
-        empty_cid.clone(),
-        bounded_vec.clone(),
-        empty_cid.clone(),
-    ));
+    Inputs::<Test>::insert(request_id, AgentRequest {
+        block_number: U256::zero(),
+        nft_id: U256::zero(),
+        nft_required_consensus: U256::from(5),
+        nft_execution_max_time: U256::from(25),
+        nft_file_cid: empty_cid.clone(),
+        input_data: bounded_vec.clone(),
+        input_file_cid: empty_cid.clone(),
+    });
 
     // Insert an assignment for the first validator
     OpocAssignment::<Test>::insert(U256::from(1), validators[4].clone(), U256::from(current_block_number - 1)); 
@@ -1242,15 +2060,15 @@ fn test_inherent_opoc_level_2_completed() {
 
     // Insert an input on the Inputs storage
     let request_id: U256 = U256::from(1);
-    Inputs::<Test>::insert(request_id, (
-        U256::zero(),
-        U256::zero(),
-        U256::from(5), // nft_required_consensus
-        U256::from(25), // nft_execution_max_time
-        empty_cid.clone(),
-        bounded_vec.clone(),
-        empty_cid.clone(),
-    ));
+    Inputs::<Test>::insert(request_id, AgentRequest {
+        block_number: U256::zero(),
+        nft_id: U256::zero(),
+        nft_required_consensus: U256::from(5),
+        nft_execution_max_time: U256::from(25),
+        nft_file_cid: empty_cid.clone(),
+        input_data: bounded_vec.clone(),
+        input_file_cid: empty_cid.clone(),
+    });
 
     // Insert an assignment and an output for the first 7 validators
     for i in 0..7 {
@@ -1278,7 +2096,7 @@ fn test_inherent_opoc_level_2_completed() {
 
     // storage Outputs should contain the output of the request generated from the majority of validators
     let outputs = Outputs::<Test>::get(request_id);
-    assert_eq!(outputs, (bounded_vec.clone(), 10, 7));
+    assert_eq!(outputs, AgentResult { output_data: bounded_vec.clone(), total_executions: 10, total_consensus: 7, ..Default::default() });
 
     //check that storage_opoc_assignment is empty
     let opoc_assignments = OpocAssignment::<Test>::iter_prefix_values(request_id).collect::<Vec<_>>();
@@ -1289,15 +2107,13 @@ fn test_inherent_opoc_level_2_completed() {
     //check that storage_nodes_works is empty
     let nodes_works_number = NodesWorks::<Test>::iter().collect::<Vec<_>>();
     assert_eq!(nodes_works_number.len() as u32, 0);
-    //check that storage_opoc_blacklist has 3 elements
-    let opoc_blacklist = OpocBlacklist::<Test>::iter().collect::<Vec<_>>();
-    assert_eq!(opoc_blacklist.len() as u32, 3);
-    // check that storage_nodes_errors has 3 elements
-    let nodes_error = NodesErrors::<Test>::iter().collect::<Vec<_>>();
-    assert_eq!(nodes_error.len() as u32, 3);
-    //check that storage_nodes_timeouts is empty
-    let nodes_timeouts = NodesTimeouts::<Test>::iter().collect::<Vec<_>>();
-    assert_eq!(nodes_timeouts.len() as u32, 0);   
+    //check that storage_nodes_reputations has 3 elements, all penalized for the invalid output
+    let nodes_reputations = NodesReputations::<Test>::iter().collect::<Vec<_>>();
+    assert_eq!(nodes_reputations.len() as u32, 3);
+    for (account_id, nodes_reputation) in nodes_reputations {
+        assert!(validators[7..10].contains(&account_id));
+        assert_eq!(nodes_reputation.penalty, REPUTATION_PENALTY_INVALID_OUTPUT);
+    }
   });
 }
 
@@ -1323,11 +2139,182 @@ fn test_inherent_in_block() {
   });
 }
 
+// This test should reject on check_inherent the inherents forged by a malicious block author.
+#[test]
+fn test_inherent_check_rejects_forged_inherents() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
+        let forged_bounded_vec: Data = BoundedVec::try_from(vec![6, 6, 6]).expect("Vector exceeds the bound");
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let num_validators = 5;
+        let validators = create_validators(num_validators, stake);
+
+        // Set current block
+        System::set_block_number(3);
+        let current_block_number = System::block_number();
+
+        // Insert an input completed by the first validator, it will be completed at OPoC level 0
+        let request_id: U256 = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(current_block_number + 1));
+        NodesWorks::<Test>::insert(validators[0].clone(), request_id, true);
+        NodesOutputs::<Test>::insert(request_id, validators[0].clone(), empty_bounded_vec.clone());
+
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        let (opoc_operations, aimodelscalc_operations) = match inherent_call.clone() {
+            crate::Call::set_inherent_data { opoc_operations, aimodelscalc_operations } => (opoc_operations, aimodelscalc_operations),
+            _ => panic!("Unexpected inherent call"),
+        };
+        let forged_call = |opoc_operations, aimodelscalc_operations| {
+            crate::Call::<Test>::set_inherent_data { opoc_operations, aimodelscalc_operations }
+        };
+
+        // The importing nodes check the inherent on the parent state
+        System::set_block_number(2);
+        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
+
+        // The author can not write an arbitrary output
+        let mut forged_opoc_operations = opoc_operations.clone();
+        forged_opoc_operations.outputs_operations.insert(request_id, AgentResult { output_data: forged_bounded_vec.clone(), total_executions: 1, total_consensus: 1, ..Default::default() });
+        let call = forged_call(forged_opoc_operations, aimodelscalc_operations.clone());
+        assert!(TestingPallet::check_inherent(&call, &inherent_data).is_err());
+
+        // The author can not penalize an honest validator
+        let mut forged_opoc_operations = opoc_operations.clone();
+        forged_opoc_operations.opoc_penalties_operations.insert(validators[1].clone(), vec![OpocOffenceKind::InvalidOutput]);
+        let call = forged_call(forged_opoc_operations, aimodelscalc_operations.clone());
+        assert!(TestingPallet::check_inherent(&call, &inherent_data).is_err());
+
+        // The author can not choose the validators of an assignment
+        let mut forged_opoc_operations = opoc_operations.clone();
+        forged_opoc_operations.opoc_assignment_operations.insert((request_id, validators[2].clone()), U256::from(current_block_number + 25));
+        let call = forged_call(forged_opoc_operations, aimodelscalc_operations.clone());
+        assert!(TestingPallet::check_inherent(&call, &inherent_data).is_err());
+
+        // The author can not skip the operations computed by the OPoC
+        let call = forged_call(OpocOperations::default(), aimodelscalc_operations.clone());
+        assert!(TestingPallet::check_inherent(&call, &inherent_data).is_err());
+
+        // The author can not change the AI models
+        let mut forged_aimodelscalc_operations = aimodelscalc_operations.clone();
+        forged_aimodelscalc_operations.insert(AiModelKey::from(1), (forged_bounded_vec.clone(), forged_bounded_vec.clone(), U256::from(current_block_number)));
+        let call = forged_call(opoc_operations.clone(), forged_aimodelscalc_operations);
+        assert!(TestingPallet::check_inherent(&call, &inherent_data).is_err());
+
+        // The genuine inherent is executed and the snapshot for the next block is taken on finalization
+        System::set_block_number(3);
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+        assert_eq!(Outputs::<Test>::get(request_id), AgentResult { output_data: empty_bounded_vec.clone(), total_executions: 1, total_consensus: 1, ..Default::default() });
+        TestingPallet::on_finalize(3);
+        assert_eq!(ActiveValidatorsSessionIndex::<Test>::get(), Some(0));
+        assert_eq!(ActiveValidators::<Test>::get().len() as u32, num_validators);
+    });
+}
+
+// This test should accept on check_inherent, on the parent state, the inherent created after an on_initialize that changes the state.
+#[test]
+fn test_inherent_check_deterministic_with_on_initialize_changes() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_bounded_vec = BoundedVec::<u8, MaxDataSize>::default();
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+        let ai_model_key = AiModelKey::from(1);
+        let local_name: Data = BoundedVec::try_from(b"model-a".to_vec()).unwrap();
+
+        // The parent block leaves an era to close, an offence to report and an AI model to activate on the next block
+        System::set_block_number(2);
+        assert_ok!(TestingPallet::set_rewards_inflation(RuntimeOrigin::root(), 1_000));
+        TestingPallet::rewards_credit(&vec![validators[0].clone()]);
+        pallet_staking::ActiveEra::<Test>::put(pallet_staking::ActiveEraInfo { index: 1, start: None });
+        TestingPallet::offences_schedule(&validators[1], OpocOffenceKind::Timeout);
+        PendingOffences::<Test>::mutate(0, |pending_offence| pending_offence.as_mut().unwrap().report_block_number = U256::from(3));
+        AIModels::<Test>::insert(ai_model_key, (local_name.clone(), Data::default(), U256::from(3)));
+        ModelsPendingActivations::<Test>::insert(ai_model_key, U256::from(3));
+
+        // A request waiting to be assigned by the OPoC
+        let request_id: U256 = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(2),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: empty_bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
+
+        // The AI model of the next block is activated on the finalization of the parent block
+        InherentDidUpdate::<Test>::put(true);
+        TestingPallet::on_finalize(2);
+        assert_eq!(ModelsPendingActivations::<Test>::get(ai_model_key), None);
+        assert!(ModelsActivatedNames::<Test>::contains_key(ai_model_key, sp_io::hashing::blake2_256(&local_name)));
+
+        // The author creates the inherent after on_initialize, the state of the block is rolled back to the parent state
+        let inherent_data = InherentData::new();
+        let (inherent_call, era_closed, offence_reported) = frame_support::storage::with_transaction(|| {
+            System::set_block_number(3);
+            TestingPallet::on_initialize(3);
+            let result = (
+                TestingPallet::create_inherent(&inherent_data),
+                RewardsCurrentEra::<Test>::get() == 1,
+                !PendingOffences::<Test>::contains_key(0),
+            );
+            sp_runtime::TransactionOutcome::Rollback(Ok::<_, sp_runtime::DispatchError>(result))
+        }).unwrap();
+        let inherent_call = inherent_call.expect("Should create inherent");
+        assert!(era_closed);
+        assert!(offence_reported);
+
+        // The importing nodes check the inherent on the parent state
+        assert_eq!(System::block_number(), 2);
+        assert!(TestingPallet::check_inherent(&inherent_call, &inherent_data).is_ok());
+    });
+}
+
+// This test should charge on on_initialize the snapshot of the stake of every validator only when the session changes.
+#[test]
+fn test_on_initialize_charges_opoc_snapshot_on_new_session() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let num_validators = 3u64;
+        let _validators = create_validators(num_validators as u32, 10_000_000_000_000_000_000);
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+
+        // The snapshot of the validators is not taken again in the same session
+        System::set_block_number(2);
+        let same_session_weight = TestingPallet::opoc_snapshot_weight(2);
+        assert_eq!(same_session_weight, db_weight.reads_writes(3, 1));
+
+        // The snapshot of the validators is taken again on a new session, its weight grows with the validators
+        ActiveValidatorsSessionIndex::<Test>::kill();
+        let new_session_weight = TestingPallet::opoc_snapshot_weight(2);
+        assert_eq!(new_session_weight, db_weight.reads_writes(3, 1).saturating_add(db_weight.reads_writes(num_validators * 4 + 2, num_validators * 2 + 2)));
+        assert!(TestingPallet::on_initialize(2).all_gte(new_session_weight));
+    });
+}
+
 // OPOC ASSIGNMENT FUNCTIONS
 //////////////////////////////////////////////////////////////////////////////////
 
 #[test]
-fn test_opoc_assignment_for_one_validator_free_in_blacklist() {
+fn test_opoc_assignment_skips_free_validator_with_low_reputation() {
     make_logger();
 
     new_test_ext().execute_with(|| {
@@ -1338,23 +2325,23 @@ fn test_opoc_assignment_for_one_validator_free_in_blacklist() {
         let request_id: U256 = U256::from(1);
         let current_block: U256 = U256::from(1);
 
-        let mut opoc_blacklist_operations = BTreeMap::<AccountId, bool>::new();
+        let mut opoc_penalties_operations = BTreeMap::<AccountId, Vec<OpocOffenceKind>>::new();
         let mut opoc_assignment_operations = BTreeMap::<(U256, AccountId), U256>::new();
         let mut nodes_works_operations = BTreeMap::<AccountId, BTreeMap<U256, bool>>::new();
 
         // Add request_id to the Inputs storage to permit the calculation of the expiration block number works correctly
-        Inputs::<Test>::insert(request_id, (
-            U256::zero(),
-            U256::zero(),
-            U256::from(1), // nft_required_consensus
-            U256::from(45), // nft_execution_max_time
-            Cid::default(),
-            BoundedVec::<u8, MaxDataSize>::default(),
-            Cid::default(),
-        ));
-
-        // Put main_validator in blacklist
-        opoc_blacklist_operations.insert(main_validator.clone(), true);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::zero(),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(45),
+            nft_file_cid: Cid::default(),
+            input_data: BoundedVec::<u8, MaxDataSize>::default(),
+            input_file_cid: Cid::default(),
+        });
+
+        // Penalize main_validator for an invalid output, its reputation goes under the exclusion threshold
+        opoc_penalties_operations.insert(main_validator.clone(), vec![OpocOffenceKind::InvalidOutput]);
 
         // Put all validators except main_validator in nodes_works
         let mut request_id_true = BTreeMap::<U256, bool>::new();
@@ -1367,8 +2354,8 @@ fn test_opoc_assignment_for_one_validator_free_in_blacklist() {
         }
 
         // Run the opoc_assignment function
-        let assigned_completed = match TestingPallet::opoc_assignment(
-            &mut opoc_blacklist_operations,
+        assert_ok!(TestingPallet::opoc_assignment(
+            &opoc_penalties_operations,
             &mut opoc_assignment_operations,
             &mut nodes_works_operations,
             &request_id,
@@ -1376,18 +2363,27 @@ fn test_opoc_assignment_for_one_validator_free_in_blacklist() {
             1,
             vec![],
             true
-        ) {
-            Ok(_) => true,
-            Err(_) => false
-        };
-        assert_eq!(assigned_completed, true);
+        ));
+
+        // Be sure that the main_validator is not assigned even if it is the only free validator
+        assert_eq!(opoc_assignment_operations.len(), 1);
+        assert_eq!(opoc_assignment_operations.contains_key(&(request_id, main_validator.clone())), false);
 
-        // Be sure that the main_validator is not more on the blacklist
-        let opoc_blacklist = opoc_blacklist_operations.get(&main_validator).unwrap();
-        assert_eq!(*opoc_blacklist, false);
+        // Run the opoc_assignment function excluding all the other validators
+        let mut opoc_assignment_operations = BTreeMap::<(U256, AccountId), U256>::new();
+        assert_ok!(TestingPallet::opoc_assignment(
+            &opoc_penalties_operations,
+            &mut opoc_assignment_operations,
+            &mut nodes_works_operations,
+            &request_id,
+            &current_block,
+            1,
+            validators[1..].to_vec(),
+            true
+        ));
 
-        // Be sure that the main_validator is on the opoc_assignment_operations with the expiration block number set to the current block number + nft_execution_max_time
-        let opoc_assignment = opoc_assignment_operations.get(&(request_id, main_validator)).unwrap();
+        // Be sure that the main_validator is assigned because there are not enough validators with a good reputation
+        let opoc_assignment = opoc_assignment_operations.get(&(request_id, main_validator.clone())).unwrap();
         assert_eq!(*opoc_assignment, U256::from(1 + 45));
 
         // Be sure that the main_validator is on the nodes_works_operations with the request_id set to true
@@ -1409,11 +2405,15 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
         let num_validators = 100;
         let _validators = create_validators(num_validators, stake);
 
+        let opoc_penalties_operations = BTreeMap::<AccountId, Vec<OpocOffenceKind>>::new();
         let nodes_works_operations = BTreeMap::<AccountId, BTreeMap<U256, bool>>::new();
+        let current_block: U256 = U256::from(1);
 
         // Request 100 validators, all available
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(100),
             false,
             vec![]
@@ -1422,7 +2422,9 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
 
         // Request 50 validators, all available
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(50),
             false,
             vec![]
@@ -1431,7 +2433,9 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
 
         // Request 1 validator, all available
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(1),
             false,
             vec![]
@@ -1440,7 +2444,9 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
 
         // Request 101 validators, not enough available
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(101),
             false,
             vec![]
@@ -1450,7 +2456,9 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
         // Request 50 validators, exclude 50 validators
         let excluded_validators: Vec<AccountId> = (0..50).map(|i| AccountId::from_raw([i as u8; 32])).collect();
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(50),
             false,
             excluded_validators.clone()
@@ -1464,7 +2472,9 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
         // Request 50 validators, exclude 51 validators
         let excluded_validators: Vec<AccountId> = (0..51).map(|i| AccountId::from_raw([i as u8; 32])).collect();
         let validators = TestingPallet::opoc_assignment_get_random_validators(
+            &opoc_penalties_operations,
             &nodes_works_operations,
+            &current_block,
             U256::from(50),
             false,
             excluded_validators
@@ -1473,6 +2483,96 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
     });
 }
 
+// RUNTIME API FUNCTIONS
+//////////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn test_api_get_request_lifecycle() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let bounded_vec: BoundedVec<u8, MaxDataSize> = BoundedVec::try_from(vec![1, 2, 3]).expect("Vector exceeds the bound");
+
+        let stake = 10_000_000_000_000_000_000;
+        let num_validators = 2;
+        let validators = create_validators(num_validators, stake);
+
+        System::set_block_number(3);
+        let request_id: U256 = U256::from(1);
+
+        // Unknown requests are not returned
+        assert!(TestingPallet::api_get_request(request_id).is_none());
+        assert_eq!(TestingPallet::api_pending_requests().len(), 0);
+
+        // Insert an input on the Inputs storage with an assignment to the first validator that has already stored its output
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(1),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: bounded_vec.clone(),
+            input_file_cid: empty_cid.clone(),
+        });
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(26));
+        NodesOutputs::<Test>::insert(request_id, validators[0].clone(), bounded_vec.clone());
+
+        assert_eq!(TestingPallet::api_pending_requests(), vec![request_id]);
+
+        let request = TestingPallet::api_get_request(request_id).unwrap();
+        assert_eq!(request.status, RequestStatus::Running);
+        assert_eq!(request.block_number, U256::from(1));
+        assert_eq!(request.input_data, bounded_vec);
+        assert_eq!(request.opoc_level, Some(0));
+        assert_eq!(request.assignments.len(), 1);
+        assert_eq!(request.assignments[0].account_id, validators[0]);
+        assert_eq!(request.assignments[0].expiration_block_number, U256::from(26));
+        assert_eq!(request.assignments[0].output_committed, false);
+        assert_eq!(request.assignments[0].output_received, true);
+        assert_eq!(request.outputs.len(), 1);
+        assert_eq!(request.outputs[0].output_data, bounded_vec);
+        assert!(request.result.is_none());
+
+        // Complete the request with the inherent and be sure the result is returned
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+
+        assert_eq!(TestingPallet::api_pending_requests().len(), 0);
+        let request = TestingPallet::api_get_request(request_id).unwrap();
+        assert_eq!(request.status, RequestStatus::Completed);
+        assert_eq!(request.opoc_level, None);
+        assert_eq!(request.assignments.len(), 0);
+        let result = request.result.unwrap();
+        assert_eq!(result.output_data, bounded_vec);
+        assert_eq!(result.total_executions, 1);
+        assert_eq!(result.total_consensus, 1);
+    });
+}
+
+// This test should return the name of the active AI models, used by the node to dry run the agents.
+#[test]
+fn test_api_ai_model_local_name() {
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        let model = AiModelKey::from(1);
+
+        // Unknown models have no name
+        assert_eq!(TestingPallet::api_ai_model_local_name(model), None);
+
+        // The previous name is returned until the new name is available
+        let local_name: Data = BoundedVec::try_from(b"model-v2".to_vec()).unwrap();
+        let previous_local_name: Data = BoundedVec::try_from(b"model-v1".to_vec()).unwrap();
+        AIModels::<Test>::insert(model, (local_name.clone(), previous_local_name.clone(), U256::from(20)));
+        assert_eq!(TestingPallet::api_ai_model_local_name(model), Some(previous_local_name));
+
+        System::set_block_number(20);
+        assert_eq!(TestingPallet::api_ai_model_local_name(model), Some(local_name));
+    });
+}
+
 // OFFCHAIN WORKER CALL AI FUNCTIONS
 //////////////////////////////////////////////////////////////////////////////////
 
@@ -1515,16 +2615,1654 @@ fn test_opoc_assignment_get_random_validators_on_multiple_cases() {
 //     });
 // }
 
-// HELPERS
+// This test should read the AI service endpoints stored by the node on the offchain local storage.
+#[test]
+fn test_offchain_ai_service_config_from_local_storage() {
+    let mut ext = new_test_ext();
+    let (offchain, _state) = TestOffchainExt::new();
+    ext.register_extension(OffchainDbExt::new(offchain.clone()));
+    ext.register_extension(OffchainWorkerExt::new(offchain));
+
+    ext.execute_with(|| {
+        // The default endpoint is used when the node does not store a configuration
+        let config = TestingPallet::offchain_get_ai_service_config();
+        assert_eq!(config.endpoint(&AiModelKey::from(1)).url, DEFAULT_AI_SERVICE_URL.to_vec());
+        assert!(config.endpoint(&AiModelKey::from(1)).headers.is_empty());
+
+        // The models without a specific endpoint use the default endpoint stored by the node
+        let default_endpoint = AiServiceEndpoint {
+            url: b"https://gpu-1.example.com".to_vec(),
+            headers: vec![(b"Authorization".to_vec(), b"Bearer token".to_vec())],
+        };
+        let model_endpoint = AiServiceEndpoint {
+            url: b"http://10.0.0.2:8888".to_vec(),
+            headers: Vec::new(),
+        };
+        sp_io::offchain::local_storage_set(StorageKind::PERSISTENT, AI_SERVICE_CONFIG_STORAGE_KEY, &AiServiceConfig {
+            default_endpoint: default_endpoint.clone(),
+            models_endpoints: vec![(AiModelKey::from(2), model_endpoint.clone())],
+        }.encode());
+
+        let config = TestingPallet::offchain_get_ai_service_config();
+        assert_eq!(config.endpoint(&AiModelKey::from(1)), &default_endpoint);
+        assert_eq!(config.endpoint(&AiModelKey::from(2)), &model_endpoint);
+    });
+}
+
+// EXECUTION LOGS
 //////////////////////////////////////////////////////////////////////////////////
 
-// This function initializes the logger for the tests.
-// It makes possible to see the logs from the tested pallet directly in the console.
-// USAGE: Run tests with `cargo test -- --show-output`
-// This function also returns a counter that can be used to check how many logs were generated.
-// Example: `let counter = make_logger();`
-//          `assert_eq!(*counter.lock().unwrap(), 1);`
-fn make_logger() -> Arc<Mutex<u16>> {
+// This test should bound the lines logged by an agent and the host call timings collected during an execution.
+#[test]
+fn test_execution_logs_bounds() {
+    let mut logs = ExecutionLogs::default();
+
+    // The lines longer than the maximum line size are truncated
+    logs.push_line(vec![1u8; MAX_EXECUTION_LOG_LINE_SIZE + 1]);
+    assert_eq!(logs.lines[0].len(), MAX_EXECUTION_LOG_LINE_SIZE);
+    assert!(logs.truncated);
+
+    // The lines are dropped when the logs reach the maximum size
+    let mut logs = ExecutionLogs::default();
+    for _ in 0..(MAX_EXECUTION_LOGS_SIZE / MAX_EXECUTION_LOG_LINE_SIZE) {
+        logs.push_line(vec![1u8; MAX_EXECUTION_LOG_LINE_SIZE]);
+    }
+    assert!(!logs.truncated);
+    logs.push_line(vec![1u8]);
+    assert_eq!(logs.lines.len(), MAX_EXECUTION_LOGS_SIZE / MAX_EXECUTION_LOG_LINE_SIZE);
+    assert_eq!(logs.size as usize, MAX_EXECUTION_LOGS_SIZE);
+    assert!(logs.truncated);
+
+    // The host calls are dropped when the logs reach the maximum number of host calls
+    let mut logs = ExecutionLogs::default();
+    for _ in 0..(MAX_EXECUTION_LOGS_HOST_CALLS + 1) {
+        logs.push_host_call(HostCallTiming { name: b"call_ai".to_vec(), started_at_ms: 0, duration_ms: 1, success: true });
+    }
+    assert_eq!(logs.host_calls.len(), MAX_EXECUTION_LOGS_HOST_CALLS);
+    assert!(logs.truncated);
+
+    // The logs of different requests are stored on different keys
+    assert_ne!(ExecutionLogs::storage_key(&U256::from(1)), ExecutionLogs::storage_key(&U256::from(2)));
+}
+
+// IPFS OUTPUTS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should offload to IPFS the outputs larger than MaxDataSize and complete the request with the CID of the consensus output.
+#[test]
+fn test_outputs_larger_than_max_data_size_offloaded_to_ipfs() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(10);
+        set_fees(100, 1, 10, 1);
+
+        // The outputs not larger than MaxDataSize are stored on chain
+        let node_output = TestingPallet::offchain_node_output(vec![1, 2, 3]);
+        assert_eq!(node_output, NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() });
+
+        // The larger outputs are referenced by their CID and hash
+        let large_output = vec![7u8; MaxDataSize::get() as usize + 1];
+        let node_output = TestingPallet::offchain_node_output(large_output.clone());
+        assert!(node_output.output_data.is_empty());
+        let output_cid = node_output.output_cid.unwrap();
+        assert_eq!(output_cid.hash, H256::from(sp_io::hashing::blake2_256(&large_output)));
+        let invalid_output_cid = TestingPallet::offchain_node_output(vec![8u8; MaxDataSize::get() as usize + 1]).output_cid.unwrap();
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+
+        // Two validators agree on the output offloaded to IPFS, the third one not
+        for validator in validators.iter() {
+            NodesOutputs::<Test>::insert(request_id, validator.clone(), Data::default());
+        }
+        NodesOutputsCids::<Test>::insert(request_id, validators[0].clone(), output_cid.clone());
+        NodesOutputsCids::<Test>::insert(request_id, validators[1].clone(), output_cid.clone());
+        NodesOutputsCids::<Test>::insert(request_id, validators[2].clone(), invalid_output_cid);
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult {
+            output_data: Data::default(),
+            total_executions: 3,
+            total_consensus: 2,
+            output_cid: Some(output_cid.clone()),
+            output_cid_expiration_block_number: U256::zero(),
+            ..Default::default()
+        });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+
+        // The output is pinned by the validators and the expiration of the pin is recorded on Outputs
+        let expiration_block_number = U256::from(10 + OutputsPinDuration::get());
+        let result = Outputs::<Test>::get(request_id);
+        assert_eq!(result.output_cid, Some(output_cid.clone()));
+        assert_eq!(result.output_cid_expiration_block_number, expiration_block_number);
+        assert_eq!(CidsStatus::<Test>::get(&output_cid.cid).0, expiration_block_number);
+
+        // Only the validators of the consensus CID are paid
+        assert_eq!(Balances::free_balance(&validators[0]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[1]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[2]), stake);
+        assert_eq!(NodesOutputsCids::<Test>::iter_prefix(request_id).count(), 0);
+    });
+}
+
+// AGENT TRANSACTIONS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should execute the transaction emitted by the agent from the agent address when the OPoC reaches the consensus on it.
+#[test]
+fn test_agent_transaction_executed_from_agent_address_on_consensus() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+
+        let request_id: RequestId = 1.into();
+        let nft_id: NftId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+        assert_ok!(TestingPallet::run_request(request_id, address, address, nft_id, vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+
+        // The agent is funded on the account of its address
+        let agent_address = TestingPallet::transactions_agent_address(&nft_id);
+        assert_ne!(agent_address, TestingPallet::transactions_agent_address(&NftId::from(2)));
+        let agent = TestAddressToAccountId::convert(agent_address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&agent, 1_000);
+
+        // Two validators agree on the transaction emitted by the agent, the third one not
+        let recipient = AccountId::from_raw([9u8; 32]);
+        let transfer = |value: u128| AgentTransaction::Substrate {
+            call: Data::truncate_from(RuntimeCall::Balances(pallet_balances::Call::transfer_allow_death { dest: recipient, value }).encode()),
+        };
+        let output_data: Data = BoundedVec::try_from(vec![1, 2, 3]).unwrap();
+        for validator in validators.iter() {
+            NodesOutputs::<Test>::insert(request_id, validator.clone(), output_data.clone());
+        }
+        NodesOutputsTransactions::<Test>::insert(request_id, validators[0].clone(), transfer(100));
+        NodesOutputsTransactions::<Test>::insert(request_id, validators[1].clone(), transfer(100));
+        NodesOutputsTransactions::<Test>::insert(request_id, validators[2].clone(), transfer(200));
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult {
+            output_data,
+            total_executions: 3,
+            total_consensus: 2,
+            output_transaction: Some(transfer(100)),
+            ..Default::default()
+        });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+
+        // The transaction is queued by the inherent and executed by on_idle
+        assert_eq!(Balances::free_balance(&recipient), 0);
+        assert_eq!(PendingExecutions::<Test>::count(), 1);
+        TestingPallet::on_idle(1, Weight::MAX);
+        assert_eq!(PendingExecutions::<Test>::count(), 0);
+
+        // The transaction of the consensus is executed from the agent account
+        assert_eq!(Balances::free_balance(&recipient), 100);
+        assert_eq!(Balances::free_balance(&agent), 900);
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::AgentTransactionExecuted {
+            request_id,
+            nft_id,
+            address: agent_address,
+            result: Ok(()),
+        }));
+        assert_eq!(Outputs::<Test>::get(request_id).output_transaction, Some(transfer(100)));
+
+        // Only the validators of the consensus transaction are paid
+        assert_eq!(Balances::free_balance(&validators[0]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[1]), stake + 66);
+        assert_eq!(Balances::free_balance(&validators[2]), stake);
+        assert_eq!(NodesOutputsTransactions::<Test>::iter_prefix(request_id).count(), 0);
+    });
+}
+
+// This test should complete the request also when the transaction emitted by the agent fails.
+#[test]
+fn test_agent_transaction_failure_recorded_on_completion() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let request_id: RequestId = 1.into();
+        let nft_id: NftId = 1.into();
+        Inputs::<Test>::insert(request_id, AgentRequest { nft_id, ..Default::default() });
+
+        // The EVM is not available on the tests
+        let transaction = AgentTransaction::Evm { target: H160::repeat_byte(0xBB), value: U256::zero(), input: Data::default(), gas_limit: 21_000 };
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult {
+            total_executions: 1,
+            total_consensus: 1,
+            output_transaction: Some(transaction),
+            ..Default::default()
+        });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+        TestingPallet::on_idle(1, Weight::MAX);
+
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::AgentTransactionExecuted {
+            request_id,
+            nft_id,
+            address: TestingPallet::transactions_agent_address(&nft_id),
+            result: Err(sp_runtime::DispatchError::Other("EVM not available")),
+        }));
+        assert!(Outputs::<Test>::contains_key(request_id));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+    });
+}
+
+// This test should execute the queued transactions only while the remaining weight covers their maximum weight and report the weight used.
+#[test]
+fn test_agent_transactions_executed_within_remaining_weight() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let nft_id: NftId = 1.into();
+        let agent = TestAddressToAccountId::convert(TestingPallet::transactions_agent_address(&nft_id));
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&agent, 1_000);
+        let recipient = AccountId::from_raw([9u8; 32]);
+        let transaction = AgentTransaction::Substrate {
+            call: Data::truncate_from(RuntimeCall::Balances(pallet_balances::Call::transfer_allow_death { dest: recipient, value: 10 }).encode()),
+        };
+        for request_id in 1..=2 {
+            TestingPallet::executions_schedule(PendingExecution::AgentTransaction { request_id: request_id.into(), nft_id, transaction: transaction.clone() });
+        }
+
+        // The remaining weight covers the maximum weight of a single transaction
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let overhead = db_weight.reads_writes(2, 1);
+        let step = db_weight.reads_writes(1, 1);
+        let used_weight = TestingPallet::executions_run(overhead + step + AgentCallMaxWeight::get());
+        assert_eq!(used_weight, overhead + step + AgentCallUsedWeight::get());
+        assert_eq!(Balances::free_balance(&recipient), 10);
+        assert_eq!(NextExecutionToRun::<Test>::get(), 1);
+        assert_eq!(PendingExecutions::<Test>::count(), 1);
+
+        // Nothing is executed without enough weight
+        assert_eq!(TestingPallet::executions_run(overhead + step), overhead + step);
+        assert_eq!(NextExecutionToRun::<Test>::get(), 1);
+
+        TestingPallet::on_idle(2, Weight::MAX);
+        assert_eq!(Balances::free_balance(&recipient), 20);
+        assert_eq!(NextExecutionToRun::<Test>::get(), 2);
+        assert_eq!(PendingExecutions::<Test>::count(), 0);
+    });
+}
+
+// This test should drop a queued transaction heavier than the maximum weight of an execution without stopping the queue.
+#[test]
+fn test_agent_transaction_heavier_than_execution_weight_dropped() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let nft_id: NftId = 1.into();
+        let transaction = AgentTransaction::Evm { target: H160::repeat_byte(0xBB), value: U256::zero(), input: Data::default(), gas_limit: u64::MAX };
+        TestingPallet::executions_schedule(PendingExecution::AgentTransaction { request_id: 1.into(), nft_id, transaction: transaction.clone() });
+        TestingPallet::executions_schedule(PendingExecution::AgentTransaction { request_id: 2.into(), nft_id, transaction: AgentTransaction::Evm { gas_limit: 21_000, ..transaction } });
+
+        TestingPallet::on_idle(1, Weight::MAX);
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::AgentTransactionExecuted {
+            request_id: 1.into(),
+            nft_id,
+            address: TestingPallet::transactions_agent_address(&nft_id),
+            result: Err(Error::<Test>::ExecutionWeightExceeded.into()),
+        }));
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::AgentTransactionExecuted {
+            request_id: 2.into(),
+            nft_id,
+            address: TestingPallet::transactions_agent_address(&nft_id),
+            result: Err(sp_runtime::DispatchError::Other("EVM not available")),
+        }));
+        assert_eq!(NextExecutionToRun::<Test>::get(), 2);
+    });
+}
+
+// CALLBACKS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should call the callback of a request on completion and refund the fee of the gas not used.
+#[test]
+fn test_callback_executed_on_completion_and_unused_gas_refunded() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let target: Address = H160::repeat_byte(0xBB);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+
+        // The gas limit of the callback is prepaid with the fee of the request
+        assert_ok!(TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5), target, [1, 2, 3, 4], 100_000));
+        assert_eq!(Balances::reserved_balance(&payer), 148 + 100_000);
+        assert_eq!(RequestsCallbacks::<Test>::get(request_id).unwrap().fee, 100_000);
+
+        let output_data: Data = BoundedVec::try_from(vec![3, 2, 1]).unwrap();
+        for validator in validators.iter() {
+            NodesOutputs::<Test>::insert(request_id, validator.clone(), output_data.clone());
+        }
+        let result = AgentResult { output_data, total_executions: 3, total_consensus: 3, ..Default::default() };
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, result.clone());
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+
+        // The callback is queued by the inherent and executed by on_idle, the weight of the gas used is reported
+        assert!(ExecutedCallbacks::get().is_empty());
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let used_weight = TestingPallet::executions_run(Weight::MAX);
+        assert_eq!(used_weight, db_weight.reads_writes(2, 1) + db_weight.reads_writes(1, 1) + db_weight.reads_writes(4, 4) + Weight::from_parts(21_000, 0));
+
+        // The callback is called with the ABI encoding of (requestId, output, totalExecutions, totalConsensus)
+        let input = TestingPallet::callbacks_encode_input(&[1, 2, 3, 4], &request_id, &result);
+        assert_eq!(input.len(), 4 + 6 * 32);
+        assert_eq!(&input[0..4], &[1, 2, 3, 4]);
+        assert_eq!(U256::from_big_endian(&input[4..36]), request_id);
+        assert_eq!(U256::from_big_endian(&input[36..68]), U256::from(128));
+        assert_eq!(U256::from_big_endian(&input[68..100]), U256::from(3));
+        assert_eq!(U256::from_big_endian(&input[132..164]), U256::from(3));
+        assert_eq!(&input[164..167], &[3, 2, 1]);
+        assert_eq!(ExecutedCallbacks::get(), vec![(target, input, 100_000)]);
+
+        // The fee of the 21_000 gas used is burned, the remainder is refunded
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000_000 - 147 - 21_000);
+        assert_eq!(CallbacksResults::<Test>::get(request_id), Some(RequestCallbackResult { success: true, used_gas: 21_000 }));
+        assert!(RequestsCallbacks::<Test>::get(request_id).is_none());
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::RequestCallbackExecuted {
+            request_id,
+            target,
+            used_gas: 21_000,
+            result: Ok(()),
+        }));
+    });
+}
+
+// This test should record the failure of a callback without failing the completion of the request.
+#[test]
+fn test_callback_failure_recorded_on_completion() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+        assert_ok!(TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(1), U256::from(5), H160::repeat_byte(0xFF), [1, 2, 3, 4], 50_000));
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult { total_executions: 1, total_consensus: 1, ..Default::default() });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+        TestingPallet::on_idle(1, Weight::MAX);
+
+        // The reverted callback uses all its gas
+        assert!(Outputs::<Test>::contains_key(request_id));
+        assert_eq!(CallbacksResults::<Test>::get(request_id), Some(RequestCallbackResult { success: false, used_gas: 50_000 }));
+        assert_eq!(Balances::free_balance(&payer), 1_000_000 - 50_000);
+    });
+}
+
+// This test should refund the fee of a queued callback when the output of the request is pruned before its execution.
+#[test]
+fn test_callback_refunded_when_output_pruned_before_execution() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+        assert_ok!(TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(1), U256::from(5), H160::repeat_byte(0xBB), [1, 2, 3, 4], 50_000));
+
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        outputs_operations.insert(request_id, AgentResult { total_executions: 1, total_consensus: 1, ..Default::default() });
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+        Outputs::<Test>::remove(request_id);
+
+        TestingPallet::on_idle(1, Weight::MAX);
+        assert!(ExecutedCallbacks::get().is_empty());
+        assert_eq!(CallbacksResults::<Test>::get(request_id), Some(RequestCallbackResult { success: false, used_gas: 0 }));
+        assert_eq!(Balances::free_balance(&payer), 1_000_000);
+    });
+}
+
+// This test should reject a request with an invalid callback without keeping any change.
+#[test]
+fn test_run_request_with_invalid_callback_rejected() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+
+        // The gas limit must be between 1 and MaxCallbackGas
+        let result = TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5), H160::repeat_byte(0xBB), [1, 2, 3, 4], MaxCallbackGas::get() + 1);
+        assert_eq!(result, Err(Error::<Test>::InvalidCallback.into()));
+
+        // The callback gas can not be prepaid
+        let result = TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5), H160::repeat_byte(0xBB), [1, 2, 3, 4], 1_000_000);
+        assert_eq!(result, Err(Error::<Test>::InsufficientBalance.into()));
+
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert!(RequestsFees::<Test>::get(request_id).is_none());
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+    });
+}
+
+// REQUESTS LIFECYCLE
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should cancel a request not assigned yet and check the fees of the request and of the callback are refunded.
+#[test]
+fn test_cancel_request_refunds_fees() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let other = TestAddressToAccountId::convert(H160::repeat_byte(0xCC));
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+
+        assert_ok!(TestingPallet::run_request_with_callback(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5), H160::repeat_byte(0xBB), [1, 2, 3, 4], 100_000));
+        assert_eq!(Balances::reserved_balance(&payer), 148 + 100_000);
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Pending));
+
+        // Only the address that sent the request can cancel it
+        assert_noop!(TestingPallet::cancel_request(RuntimeOrigin::signed(other), request_id), Error::<Test>::NotRequestOwner);
+        assert_noop!(TestingPallet::cancel_request_from_address(request_id, H160::repeat_byte(0xCC)), Error::<Test>::NotRequestOwner);
+
+        assert_ok!(TestingPallet::cancel_request(RuntimeOrigin::signed(payer.clone()), request_id));
+
+        // The request is closed and every fee is refunded
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Cancelled));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert!(RequestsAddresses::<Test>::get(request_id).is_none());
+        assert!(RequestsFees::<Test>::get(request_id).is_none());
+        assert!(RequestsCallbacks::<Test>::get(request_id).is_none());
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000_000);
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::RequestCancelled { request_id }));
+
+        // The request can not be cancelled twice
+        assert_noop!(TestingPallet::cancel_request(RuntimeOrigin::signed(payer), request_id), Error::<Test>::RequestNotFound);
+        assert_eq!(TestingPallet::api_get_request(request_id).unwrap().status, RequestStatus::Cancelled);
+    });
+}
+
+// This test should let the address that sent a request through the agent contract cancel it, the contract that pays the fee can not.
+#[test]
+fn test_cancel_request_by_sender_of_agent_contract() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let agent_contract: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(agent_contract);
+        let sender: Address = H160::repeat_byte(0xDD);
+        let owner = TestAddressToAccountId::convert(sender);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+
+        // The agent contract pays the fee of the requests sent by the sender
+        let request_id: RequestId = 1.into();
+        assert_ok!(TestingPallet::run_request(request_id, agent_contract, sender, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+        assert_eq!(RequestsAddresses::<Test>::get(request_id), Some(sender));
+        assert_eq!(Balances::reserved_balance(&payer), 148);
+
+        // The agent contract is not the owner of the request
+        assert_noop!(TestingPallet::cancel_request(RuntimeOrigin::signed(payer.clone()), request_id), Error::<Test>::NotRequestOwner);
+        assert_noop!(TestingPallet::cancel_request_from_address(request_id, agent_contract), Error::<Test>::NotRequestOwner);
+
+        // The sender cancels the request with the extrinsic and the fee is refunded to the agent contract
+        assert_ok!(TestingPallet::cancel_request(RuntimeOrigin::signed(owner), request_id));
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Cancelled));
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000_000);
+
+        // The sender cancels the request with the precompile
+        let request_id: RequestId = 2.into();
+        assert_ok!(TestingPallet::run_request_with_callback(request_id, agent_contract, sender, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5), H160::repeat_byte(0xBB), [1, 2, 3, 4], 100_000));
+        assert_ok!(TestingPallet::cancel_request_from_address(request_id, sender));
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Cancelled));
+        assert_eq!(Balances::free_balance(&payer), 1_000_000);
+    });
+}
+
+// This test should force the cancellation of a request already assigned to a validator with a failure result.
+#[test]
+fn test_cancel_request_failure_when_assigned() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let validators = create_validators(1, 10_000_000_000_000_000_000);
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(1), U256::from(5)));
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(6));
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Running));
+
+        assert_noop!(TestingPallet::cancel_request_from_address(request_id, address), Error::<Test>::RequestNotCancellable);
+        assert!(Inputs::<Test>::contains_key(request_id));
+    });
+}
+
+// This test should close as failed a request not completed before its maximum lifetime.
+// It should check the fees are refunded and the validators still assigned are freed.
+#[test]
+fn test_opoc_run_fails_expired_request() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let validators = create_validators(1, 10_000_000_000_000_000_000);
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(3), U256::from(5)));
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(6));
+        NodesWorks::<Test>::insert(validators[0].clone(), request_id, true);
+
+        // The request is not expired until its maximum lifetime
+        let max_lifetime = U256::from(RequestMaxLifetime::get());
+        let operations = TestingPallet::opoc_run(U256::from(1) + max_lifetime).unwrap();
+        assert!(operations.failures_operations.is_empty());
+
+        let operations = TestingPallet::opoc_run(U256::from(2) + max_lifetime).unwrap();
+        assert_eq!(operations.failures_operations.get(&request_id), Some(&RequestFailureReason::Expired));
+        assert_ok!(TestingPallet::opoc_store_operations(operations));
+
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Failed(RequestFailureReason::Expired)));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert!(!OpocAssignment::<Test>::contains_key(request_id, validators[0].clone()));
+        assert!(!NodesWorks::<Test>::contains_key(validators[0].clone(), request_id));
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000);
+        System::assert_has_event(RuntimeEvent::TestingPallet(Event::RequestFailed { request_id, reason: RequestFailureReason::Expired }));
+    });
+}
+
+// This test should close as failed a request at opoc level 2 when every validator is in timeout without revealing its output.
+// It should check the fees are refunded instead of stopping the OPoC without a consensus output.
+#[test]
+fn test_opoc_run_fails_request_without_outputs_at_level_2() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let validators = create_validators(3, 10_000_000_000_000_000_000);
+        let request_id: RequestId = 1.into();
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000);
+
+        // The request is at opoc level 2 and every validator has committed an output without revealing it
+        assert_ok!(TestingPallet::run_request(request_id, address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(2), U256::from(5)));
+        for validator in validators.iter() {
+            OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(6));
+            NodesWorks::<Test>::insert(validator.clone(), request_id, true);
+            NodesOutputsCommitments::<Test>::insert(request_id, validator.clone(), (H256::repeat_byte(1), H256::repeat_byte(2)));
+        }
+
+        let operations = TestingPallet::opoc_run(U256::from(6 + OPOC_REVEAL_DURATION + 1)).unwrap();
+        for validator in validators.iter() {
+            assert_eq!(operations.opoc_penalties_operations.get(validator), Some(&vec![OpocOffenceKind::Timeout]));
+        }
+        assert!(operations.outputs_operations.is_empty());
+        assert_eq!(operations.failures_operations.get(&request_id), Some(&RequestFailureReason::NoConsensus));
+        assert_ok!(TestingPallet::opoc_store_operations(operations));
+
+        assert_eq!(TestingPallet::requests_status(&request_id), Some(RequestStatus::Failed(RequestFailureReason::NoConsensus)));
+        assert!(!Inputs::<Test>::contains_key(request_id));
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+        assert_eq!(Balances::free_balance(&payer), 1_000);
+    });
+}
+
+// This test should reject a request that requires more blocks than its maximum lifetime, it would be closed as expired.
+#[test]
+fn test_run_request_failure_when_min_blocks_above_max_lifetime() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+        set_fees(100, 1, 10, 1);
+
+        let address: Address = H160::repeat_byte(0xAA);
+        let payer = TestAddressToAccountId::convert(address);
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&payer, 1_000_000);
+        let max_lifetime = U256::from(RequestMaxLifetime::get());
+
+        assert_noop!(
+            TestingPallet::run_request(1.into(), address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(1), max_lifetime + 1),
+            Error::<Test>::MinBlocksAboveMaxLifetime
+        );
+        assert_eq!(Balances::reserved_balance(&payer), 0);
+
+        // A request can require all the blocks of its maximum lifetime
+        assert_ok!(TestingPallet::run_request(1.into(), address, address, 1.into(), vec![1, 2, 3], vec![], U256::from(1), max_lifetime));
+        assert_eq!(TestingPallet::requests_status(&1.into()), Some(RequestStatus::Pending));
+    });
+}
+
+// RETENTION
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should prune the history of the requests closed more than OutputsRetentionPeriod blocks ago.
+// It should check the pruning stops when the remaining weight is not enough and continues on the next blocks.
+#[test]
+fn test_retention_prunes_closed_requests() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        // Complete two requests on the block 1
+        let output_data: Data = BoundedVec::try_from(vec![1, 2, 3]).unwrap();
+        let mut outputs_operations = BTreeMap::<RequestId, AgentResult>::new();
+        for request_id in [U256::from(1), U256::from(2)] {
+            outputs_operations.insert(request_id, AgentResult { output_data: output_data.clone(), total_executions: 1, total_consensus: 1, ..Default::default() });
+        }
+        assert_ok!(TestingPallet::opoc_store_operations(OpocOperations {
+            outputs_operations,
+            ..Default::default()
+        }));
+        CallbacksResults::<Test>::insert(U256::from(1), RequestCallbackResult { success: true, used_gas: 21_000 });
+        assert!(FinishedRequests::<Test>::contains_key(U256::from(1), U256::from(1)));
+        assert!(FinishedRequests::<Test>::contains_key(U256::from(1), U256::from(2)));
+
+        // The history is kept for the retention period
+        let retention_period = OutputsRetentionPeriod::get() as u64;
+        TestingPallet::on_idle(1 + retention_period, Weight::MAX);
+        assert_eq!(Outputs::<Test>::iter_keys().count(), 2);
+
+        // A block with the weight to prune a single request prunes only one of them
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let weight = db_weight.reads_writes(1, 1)
+            .saturating_add(db_weight.reads(1))
+            .saturating_add(db_weight.reads_writes(2, 5));
+        TestingPallet::on_idle(2 + retention_period, weight);
+        assert_eq!(Outputs::<Test>::iter_keys().count(), 1);
+        assert_eq!(RetentionCursor::<Test>::get(), U256::from(1));
+
+        // The next block prunes the other one
+        TestingPallet::on_idle(3 + retention_period, Weight::MAX);
+        assert_eq!(Outputs::<Test>::iter_keys().count(), 0);
+        assert_eq!(FinishedRequests::<Test>::iter_keys().count(), 0);
+        assert!(CallbacksResults::<Test>::get(U256::from(1)).is_none());
+        assert_eq!(RetentionCursor::<Test>::get(), U256::from(3));
+
+        // The hash of the outputs is archived so the contracts can still verify them
+        let output_hash = H256::from(sp_io::hashing::keccak_256(&[1, 2, 3]));
+        assert_eq!(OutputsHashes::<Test>::get(U256::from(1)), Some(output_hash));
+        assert_eq!(TestingPallet::retention_get_output_hash(&U256::from(2)), Some(output_hash));
+        assert_eq!(TestingPallet::retention_get_output_hash(&U256::from(3)), None);
+    });
+}
+
+// This test should remove the NodesWorks of the requests no more in Inputs with the sweep started every NODES_WORKS_SWEEP_INTERVAL blocks.
+#[test]
+fn test_retention_sweeps_stale_nodes_works() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let validator = Public::from_raw([1; 32]);
+        Inputs::<Test>::insert(U256::from(1), AgentRequest::default());
+        NodesWorks::<Test>::insert(validator.clone(), U256::from(1), true);
+        NodesWorks::<Test>::insert(validator.clone(), U256::from(2), true);
+
+        // The sweep does not start before the interval
+        TestingPallet::on_idle(NODES_WORKS_SWEEP_INTERVAL as u64 - 1, Weight::MAX);
+        assert!(NodesWorks::<Test>::contains_key(validator.clone(), U256::from(2)));
+
+        TestingPallet::on_idle(NODES_WORKS_SWEEP_INTERVAL as u64, Weight::MAX);
+        assert!(NodesWorks::<Test>::contains_key(validator.clone(), U256::from(1)));
+        assert!(!NodesWorks::<Test>::contains_key(validator.clone(), U256::from(2)));
+        assert!(NodesWorksCursor::<Test>::get().is_none());
+    });
+}
+
+// AI MODELS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should let only the governance register, replace and deprecate the models of the registry.
+#[test]
+fn test_models_registry_managed_by_governance() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let ai_model_key = AiModelKey::from(1);
+        let model_a: Data = BoundedVec::try_from(b"model-a".to_vec()).unwrap();
+        let model_b: Data = BoundedVec::try_from(b"model-b".to_vec()).unwrap();
+
+        // Only the governance can register a model
+        assert!(TestingPallet::register_model(RuntimeOrigin::signed(Public::from_raw([1; 32])), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::from(5)).is_err());
+        assert_ok!(TestingPallet::register_model(RuntimeOrigin::root(), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::from(5)));
+        assert_eq!(ModelsRegistry::<Test>::get(ai_model_key), Some(AiModel {
+            backend_name: model_a.clone(),
+            modality: AiModelModality::Text,
+            min_consensus: 1,
+            max_consensus: 10,
+            activation_block_number: U256::from(5),
+            deprecated: false,
+        }));
+        System::assert_last_event(RuntimeEvent::TestingPallet(Event::ModelRegistered { ai_model_key, backend_name: model_a.clone() }));
+
+        // The registered models can not be registered again, the model 0 is reserved and the consensus must be valid
+        assert_eq!(
+            TestingPallet::register_model(RuntimeOrigin::root(), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::from(5)),
+            Err(Error::<Test>::ModelAlreadyRegistered.into())
+        );
+        assert_eq!(
+            TestingPallet::register_model(RuntimeOrigin::root(), AiModelKey::zero(), model_a.clone(), AiModelModality::Text, 1, 10, U256::from(5)),
+            Err(Error::<Test>::InvalidModelKey.into())
+        );
+        assert_eq!(
+            TestingPallet::register_model(RuntimeOrigin::root(), AiModelKey::from(100), model_a.clone(), AiModelModality::Image, 2, 1, U256::from(5)),
+            Err(Error::<Test>::InvalidModelConsensus.into())
+        );
+
+        // A model is replaced keeping its key
+        assert_ok!(TestingPallet::replace_model(RuntimeOrigin::root(), ai_model_key, model_b.clone(), AiModelModality::Text, 1, 5, U256::from(10), 10));
+        let model = ModelsRegistry::<Test>::get(ai_model_key).unwrap();
+        assert_eq!(model.backend_name, model_b);
+        assert_eq!(model.max_consensus, 5);
+        assert!(TestingPallet::aimodelscalc_is_allowed_consensus(&ai_model_key, 5));
+        assert!(!TestingPallet::aimodelscalc_is_allowed_consensus(&ai_model_key, 6));
+
+        // A deprecated model can not be used anymore
+        assert_ok!(TestingPallet::deprecate_model(RuntimeOrigin::root(), ai_model_key));
+        assert!(ModelsRegistry::<Test>::get(ai_model_key).unwrap().deprecated);
+        assert!(!TestingPallet::aimodelscalc_is_allowed_consensus(&ai_model_key, 1));
+        System::assert_last_event(RuntimeEvent::TestingPallet(Event::ModelDeprecated { ai_model_key }));
+
+        // Only the registered models can be replaced or deprecated
+        assert_eq!(
+            TestingPallet::replace_model(RuntimeOrigin::root(), AiModelKey::from(2), model_b.clone(), AiModelModality::Text, 1, 5, U256::from(10), 10),
+            Err(Error::<Test>::ModelNotFound.into())
+        );
+        assert_eq!(
+            TestingPallet::deprecate_model(RuntimeOrigin::root(), AiModelKey::from(2)),
+            Err(Error::<Test>::ModelNotFound.into())
+        );
+    });
+}
+
+// This test should switch the AI models only when the registered model is served by the supermajority of the active validators.
+#[test]
+fn test_aimodelscalc_switches_models_served_by_supermajority() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+        let ai_model_key = AiModelKey::from(1);
+        let model_a: Data = BoundedVec::try_from(b"model-a".to_vec()).unwrap();
+        let model_b: Data = BoundedVec::try_from(b"model-b".to_vec()).unwrap();
+        let served_models: BoundedVec<AiModelKey, MaxModelsPerNode> = BoundedVec::try_from(vec![ai_model_key]).unwrap();
+
+        assert_ok!(TestingPallet::register_model(RuntimeOrigin::root(), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::from(5)));
+
+        // The model is not switched before the activation block
+        NodesModels::<Test>::insert(validators[0].clone(), served_models.clone());
+        NodesModels::<Test>::insert(validators[1].clone(), served_models.clone());
+        assert!(TestingPallet::aimodelscalc_run(U256::from(4)).unwrap().is_empty());
+
+        // The model is not switched if it is served by less than the activation threshold of the stake
+        NodesModels::<Test>::remove(validators[1].clone());
+        assert!(TestingPallet::aimodelscalc_run(U256::from(5)).unwrap().is_empty());
+
+        // The model is switched when it is served by the activation threshold of the stake
+        NodesModels::<Test>::insert(validators[1].clone(), served_models.clone());
+        let aimodelscalc_operations = TestingPallet::aimodelscalc_run(U256::from(5)).unwrap();
+        assert_eq!(aimodelscalc_operations.get(&ai_model_key), Some(&(model_a.clone(), Data::default(), U256::from(5 + MAX_BLOCKS_TO_WAIT_NODE_UPDATE))));
+        assert_ok!(TestingPallet::aimodelscalc_store_operations(aimodelscalc_operations));
+
+        // The replacement is rejected if the witness does not cover every node advertising its models
+        assert_noop!(
+            TestingPallet::replace_model(RuntimeOrigin::root(), ai_model_key, model_b.clone(), AiModelModality::Text, 1, 10, U256::from(5), 1),
+            Error::<Test>::InvalidNodesModelsWitness
+        );
+
+        // A replaced model has to be advertised again by the nodes and waits the previous switch to be completed
+        assert_ok!(TestingPallet::replace_model(RuntimeOrigin::root(), ai_model_key, model_b.clone(), AiModelModality::Text, 1, 10, U256::from(5), 2));
+        assert!(NodesModels::<Test>::get(validators[0].clone()).is_empty());
+        NodesModels::<Test>::insert(validators[0].clone(), served_models.clone());
+        NodesModels::<Test>::insert(validators[1].clone(), served_models.clone());
+        assert!(TestingPallet::aimodelscalc_run(U256::from(6)).unwrap().is_empty());
+        let available_from = 5 + MAX_BLOCKS_TO_WAIT_NODE_UPDATE;
+        let aimodelscalc_operations = TestingPallet::aimodelscalc_run(U256::from(available_from)).unwrap();
+        assert_eq!(aimodelscalc_operations.get(&ai_model_key), Some(&(model_b.clone(), model_a.clone(), U256::from(available_from + MAX_BLOCKS_TO_WAIT_NODE_UPDATE))));
+        assert_ok!(TestingPallet::aimodelscalc_store_operations(aimodelscalc_operations));
+
+        // A deprecated model is removed without waiting the nodes
+        assert_ok!(TestingPallet::deprecate_model(RuntimeOrigin::root(), ai_model_key));
+        NodesModels::<Test>::remove(validators[0].clone());
+        NodesModels::<Test>::remove(validators[1].clone());
+        let deprecated_from = available_from + MAX_BLOCKS_TO_WAIT_NODE_UPDATE;
+        let aimodelscalc_operations = TestingPallet::aimodelscalc_run(U256::from(deprecated_from)).unwrap();
+        assert_eq!(aimodelscalc_operations.get(&ai_model_key), Some(&(Data::default(), model_b.clone(), U256::from(deprecated_from + MAX_BLOCKS_TO_WAIT_NODE_UPDATE))));
+        assert_ok!(TestingPallet::aimodelscalc_store_operations(aimodelscalc_operations));
+        assert_eq!(AIModels::<Test>::get(ai_model_key).0, Data::default());
+    });
+}
+
+// This test should weight the validators serving a model by their stake, emit the events of the switch and never activate a model again.
+#[test]
+fn test_aimodelscalc_activates_models_by_stake_without_downgrades() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+        let ai_model_key = AiModelKey::from(1);
+        let model_a: Data = BoundedVec::try_from(b"model-a".to_vec()).unwrap();
+        let model_b: Data = BoundedVec::try_from(b"model-b".to_vec()).unwrap();
+        let served_models: BoundedVec<AiModelKey, MaxModelsPerNode> = BoundedVec::try_from(vec![ai_model_key]).unwrap();
+
+        // The third validator has 3/5 of the total stake
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&validators[2], stake * 3);
+        assert_ok!(Staking::bond_extra(RuntimeOrigin::signed(validators[2].clone()), stake * 2));
+        snapshot_active_validators();
+
+        assert_ok!(TestingPallet::register_model(RuntimeOrigin::root(), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::zero()));
+
+        // Two validators of three are not enough if they have only 2/5 of the stake
+        NodesModels::<Test>::insert(validators[0].clone(), served_models.clone());
+        NodesModels::<Test>::insert(validators[1].clone(), served_models.clone());
+        assert!(TestingPallet::aimodelscalc_run(U256::from(1)).unwrap().is_empty());
+
+        // The switch is scheduled when the validators serving the model have the supermajority of the stake
+        NodesModels::<Test>::remove(validators[1].clone());
+        NodesModels::<Test>::insert(validators[2].clone(), served_models.clone());
+        let available_from = U256::from(1 + MAX_BLOCKS_TO_WAIT_NODE_UPDATE);
+        assert_ok!(TestingPallet::aimodelscalc_store_operations(TestingPallet::aimodelscalc_run(U256::from(1)).unwrap()));
+        System::assert_last_event(RuntimeEvent::TestingPallet(Event::ModelSwitchScheduled {
+            ai_model_key,
+            local_name: model_a.clone(),
+            previous_local_name: Data::default(),
+            available_from_block_number: available_from,
+        }));
+        assert_eq!(ModelsPendingActivations::<Test>::get(ai_model_key), Some(available_from));
+
+        // The switch is activated when the available block is reached
+        TestingPallet::aimodelscalc_activate(available_from - 1);
+        assert_eq!(ModelsPendingActivations::<Test>::get(ai_model_key), Some(available_from));
+        TestingPallet::aimodelscalc_activate(available_from);
+        assert_eq!(ModelsPendingActivations::<Test>::get(ai_model_key), None);
+        assert!(ModelsActivatedNames::<Test>::contains_key(ai_model_key, sp_io::hashing::blake2_256(&model_a)));
+        System::assert_last_event(RuntimeEvent::TestingPallet(Event::ModelActivated { ai_model_key, local_name: model_a.clone() }));
+
+        // The model is upgraded to a new local name
+        assert_ok!(TestingPallet::replace_model(RuntimeOrigin::root(), ai_model_key, model_b.clone(), AiModelModality::Text, 1, 10, U256::zero(), 10));
+        NodesModels::<Test>::insert(validators[0].clone(), served_models.clone());
+        NodesModels::<Test>::insert(validators[2].clone(), served_models.clone());
+        let upgraded_from = available_from + MAX_BLOCKS_TO_WAIT_NODE_UPDATE;
+        assert_ok!(TestingPallet::aimodelscalc_store_operations(TestingPallet::aimodelscalc_run(available_from).unwrap()));
+        TestingPallet::aimodelscalc_activate(upgraded_from);
+        assert_eq!(AIModels::<Test>::get(ai_model_key).0, model_b);
+
+        // The governance can not replace the model with an older local name
+        assert_eq!(
+            TestingPallet::replace_model(RuntimeOrigin::root(), ai_model_key, model_a.clone(), AiModelModality::Text, 1, 10, U256::zero(), 10),
+            Err(Error::<Test>::ModelDowngrade.into())
+        );
+
+        // An older local name is never activated again, even if served by all the validators
+        ModelsRegistry::<Test>::mutate(ai_model_key, |model| model.as_mut().unwrap().backend_name = model_a.clone());
+        for validator in validators.iter() {
+            NodesModels::<Test>::insert(validator.clone(), served_models.clone());
+        }
+        assert!(TestingPallet::aimodelscalc_run(upgraded_from).unwrap().is_empty());
+    });
+}
+
+// MIGRATIONS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should translate the requests in progress and register the AI models stored before the version 1 on the runtime upgrade.
+#[test]
+fn test_migration_v6_migrates_inputs_and_ai_models() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let input_data: Data = BoundedVec::try_from(vec![1, 2, 3]).unwrap();
+        let input_file_cid: Cid = BoundedVec::try_from(vec![4, 5, 6]).unwrap();
+        let text_model: Data = BoundedVec::try_from(b"Qwen/QwQ-32B-AWQ".to_vec()).unwrap();
+        let image_model: Data = BoundedVec::try_from(b"Efficient-Large-Model/Sana_1600M_1024px_BF16_diffusers".to_vec()).unwrap();
+
+        // Store the values with the layout used before the version 1
+        System::set_block_number(7);
+        StorageVersion::new(0).put::<TestingPallet>();
+        unhashed::put(&Inputs::<Test>::hashed_key_for(U256::from(1)), &(
+            U256::from(7), // block_number
+            U256::from(1312), // nft_id
+            U256::from(3), // nft_required_consensus
+            U256::from(25), // nft_execution_max_time
+            Cid::default(), // nft_file_cid
+            input_data.clone(), // input_data
+            input_file_cid.clone(), // input_file_cid
+        ));
+        AIModels::<Test>::insert(AiModelKey::from(2), (text_model.clone(), Data::default(), U256::from(10)));
+        AIModels::<Test>::insert(AiModelKey::from(100), (image_model.clone(), Data::default(), U256::from(10)));
+
+        MigrateToV6::<Test>::on_runtime_upgrade();
+
+        assert_eq!(TestingPallet::on_chain_storage_version(), 6);
+        assert_eq!(Inputs::<Test>::get(U256::from(1)), AgentRequest {
+            block_number: U256::from(7),
+            nft_id: U256::from(1312),
+            nft_required_consensus: U256::from(3),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: Cid::default(),
+            input_data: input_data.clone(),
+            input_file_cid: input_file_cid.clone(),
+        });
+        let model = ModelsRegistry::<Test>::get(AiModelKey::from(2)).unwrap();
+        assert_eq!(model.backend_name, text_model);
+        assert_eq!(model.modality, AiModelModality::Text);
+        assert!(TestingPallet::aimodelscalc_is_allowed_consensus(&AiModelKey::from(2), 5));
+        let model = ModelsRegistry::<Test>::get(AiModelKey::from(100)).unwrap();
+        assert_eq!(model.backend_name, image_model);
+        assert_eq!(model.modality, AiModelModality::Image);
+        assert!(!TestingPallet::aimodelscalc_is_allowed_consensus(&AiModelKey::from(100), 2));
+
+        // The registered models are already served, so the AI models are not changed
+        assert!(TestingPallet::aimodelscalc_run(U256::from(20)).unwrap().is_empty());
+
+        // The history is migrated by on_idle, the retention starts from the block of the upgrade
+        assert_eq!(MigrationsCursor::<Test>::get(), Some(MigrationCursor {
+            block_number: U256::from(7),
+            outputs_completed: false,
+            last_raw_key: None,
+        }));
+        assert_eq!(RetentionCursor::<Test>::get(), U256::from(7));
+
+        // The snapshot read by the OPoC is seeded for the inherent of the block of the upgrade
+        assert_eq!(ActiveValidatorsSessionIndex::<Test>::get(), Some(0));
+        assert!(!OpocRandomSeed::<Test>::get().is_empty());
+
+        // The migration is skipped once the storage version is updated
+        MigrationsCursor::<Test>::kill();
+        MigrateToV6::<Test>::on_runtime_upgrade();
+        assert_eq!(TestingPallet::on_chain_storage_version(), 6);
+        assert_eq!(Inputs::<Test>::iter().count(), 1);
+        assert!(!MigrationsCursor::<Test>::exists());
+    });
+}
+
+// This test should translate the outputs stored with every previous layout and record the closed requests in batches on on_idle.
+#[test]
+fn test_migration_v6_migrates_history_on_idle() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let output_data: Data = BoundedVec::try_from(vec![3, 2, 1]).unwrap();
+        let transaction = AgentTransaction::Evm { target: H160::repeat_byte(0xBB), value: U256::zero(), input: Data::default(), gas_limit: 21_000 };
+        let result = AgentResult { output_data: output_data.clone(), total_executions: 3, total_consensus: 2, ..Default::default() };
+
+        // Store the outputs with the layouts used before the versions 1, 3, 4 and 5 and with the current layout
+        System::set_block_number(7);
+        StorageVersion::new(2).put::<TestingPallet>();
+        unhashed::put(&Outputs::<Test>::hashed_key_for(U256::from(1)), &(output_data.clone(), 3u32, 2u32));
+        unhashed::put(&Outputs::<Test>::hashed_key_for(U256::from(2)), &AgentResultV1 {
+            output_data: output_data.clone(),
+            total_executions: 3,
+            total_consensus: 2,
+        });
+        unhashed::put(&Outputs::<Test>::hashed_key_for(U256::from(3)), &AgentResultV3 {
+            output_data: output_data.clone(),
+            total_executions: 3,
+            total_consensus: 2,
+            output_cid: None,
+            output_cid_expiration_block_number: U256::from(9),
+        });
+        unhashed::put(&Outputs::<Test>::hashed_key_for(U256::from(4)), &AgentResultV4 {
+            output_data: output_data.clone(),
+            total_executions: 3,
+            total_consensus: 2,
+            output_cid: None,
+            output_cid_expiration_block_number: U256::zero(),
+            output_transaction: Some(transaction.clone()),
+        });
+        Outputs::<Test>::insert(U256::from(5), AgentResult { out_of_fuel: true, ..result.clone() });
+        RequestsStatuses::<Test>::insert(U256::from(6), RequestStatus::Cancelled);
+
+        MigrateToV6::<Test>::on_runtime_upgrade();
+        assert_eq!(TestingPallet::on_chain_storage_version(), 6);
+
+        // The remaining weight of the block covers the migration of two outputs
+        let db_weight = <Test as frame_system::Config>::DbWeight::get();
+        let remaining_weight = db_weight.reads_writes(1, 1)
+            .saturating_add(db_weight.reads_writes(2, 4))
+            .set_proof_size(u64::MAX);
+        assert!(TestingPallet::migrations_run(remaining_weight).all_lte(remaining_weight));
+        assert_eq!(FinishedRequests::<Test>::iter_prefix(U256::from(7)).count(), 2);
+        let cursor = MigrationsCursor::<Test>::get().unwrap();
+        assert!(!cursor.outputs_completed);
+        assert!(cursor.last_raw_key.is_some());
+
+        // The migration continues from the cursor on the next block
+        TestingPallet::on_idle(8, Weight::MAX);
+
+        assert!(!MigrationsCursor::<Test>::exists());
+        assert_eq!(Outputs::<Test>::get(U256::from(1)), result.clone());
+        assert_eq!(Outputs::<Test>::get(U256::from(2)), result.clone());
+        assert_eq!(Outputs::<Test>::get(U256::from(3)), AgentResult { output_cid_expiration_block_number: U256::from(9), ..result.clone() });
+        assert_eq!(Outputs::<Test>::get(U256::from(4)), AgentResult { output_transaction: Some(transaction.clone()), ..result.clone() });
+        assert_eq!(Outputs::<Test>::get(U256::from(5)), AgentResult { out_of_fuel: true, ..result.clone() });
+        for request_id in 1..=6u32 {
+            assert!(FinishedRequests::<Test>::contains_key(U256::from(7), U256::from(request_id)));
+        }
+        assert_eq!(RetentionCursor::<Test>::get(), U256::from(7));
+    });
+}
+
+// WEIGHTS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should compute the weight of the calls from the number of operations of the inherent and from the size of the payloads.
+#[test]
+fn test_weights_of_calls() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let validators = create_validators(2, 10_000_000_000_000_000_000);
+
+        // The weight of the inherent grows with the operations computed by the OPoC
+        let mut opoc_operations = OpocOperations::<AccountId>::default();
+        let empty_info = crate::Call::<Test>::set_inherent_data {
+            opoc_operations: opoc_operations.clone(),
+            aimodelscalc_operations: BTreeMap::new(),
+        }.get_dispatch_info();
+        assert_eq!(empty_info.class, DispatchClass::Mandatory);
+        assert_eq!(empty_info.weight, <() as WeightInfo>::set_inherent_data(0, 0, 0, 0, 0, 0));
+
+        opoc_operations.opoc_penalties_operations.insert(validators[0].clone(), vec![OpocOffenceKind::Timeout, OpocOffenceKind::InvalidOutput]);
+        opoc_operations.opoc_assignment_operations.insert((U256::from(1), validators[0].clone()), U256::from(10));
+        opoc_operations.opoc_assignment_operations.insert((U256::from(1), validators[1].clone()), U256::from(10));
+        opoc_operations.nodes_works_operations.entry(validators[1].clone()).or_default().insert(U256::from(1), true);
+        opoc_operations.outputs_operations.insert(U256::from(2), AgentResult::default());
+        opoc_operations.failures_operations.insert(U256::from(3), RequestFailureReason::Expired);
+        let info = crate::Call::<Test>::set_inherent_data {
+            opoc_operations: opoc_operations.clone(),
+            aimodelscalc_operations: BTreeMap::new(),
+        }.get_dispatch_info();
+        assert_eq!(info.weight, <() as WeightInfo>::set_inherent_data(2, 2, 1, 1, 1, 0));
+        assert_eq!(info.weight, TestingPallet::opoc_operations_weight(&opoc_operations, 0));
+        assert!(info.weight.all_gt(empty_info.weight));
+
+        // The weight of the outputs grows with the size of the output
+        let store_nodes_outputs_info = |output_size: usize| crate::Call::<Test>::store_nodes_outputs {
+            payload: crate::payloads::PayloadNodesOutputs {
+                request_id: U256::from(1),
+                output_data: BoundedVec::try_from(vec![1u8; output_size]).unwrap(),
+                output_cid: None,
+                output_transaction: None,
+                out_of_fuel: false,
+                ai_models: BoundedVec::default(),
+                salt: H256::zero(),
+                public: validators[0].clone(),
+            },
+            signature: sp_core::sr25519::Signature::from_raw([0u8; 64]),
+        }.get_dispatch_info();
+        assert_eq!(store_nodes_outputs_info(1024).weight, <() as WeightInfo>::store_nodes_outputs(1024 + 2));
+        assert!(store_nodes_outputs_info(1024).weight.ref_time() > store_nodes_outputs_info(0).weight.ref_time());
+        assert!(store_nodes_outputs_info(0).weight.ref_time() > 0);
+    });
+}
+
+// This test should stop the OPoC when the operations computed fill its share of the block weight.
+// It should check the inputs not managed are managed on the next blocks.
+#[test]
+fn test_opoc_run_is_bounded_by_weight() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        System::set_block_number(1);
+
+        // Every input is expired, so every input managed adds a failure operation
+        let inputs_count = MAX_INPUTS_MANAGED_PER_BLOCK as u32;
+        for request_id in 1..=inputs_count {
+            Inputs::<Test>::insert(U256::from(request_id), AgentRequest {
+                block_number: U256::from(1),
+                nft_required_consensus: U256::from(1),
+                nft_execution_max_time: U256::from(10),
+                ..Default::default()
+            });
+        }
+
+        let current_block = U256::from(2) + U256::from(RequestMaxLifetime::get());
+        let operations = TestingPallet::opoc_run(current_block).unwrap();
+        let failures_count = operations.failures_operations.len() as u32;
+        assert!(failures_count > 0);
+        assert!(failures_count < inputs_count);
+
+        // The OPoC stops only after the operations exceed the limit
+        let max_weight = MAX_OPOC_BLOCK_WEIGHT * <Test as frame_system::Config>::BlockWeights::get().max_block;
+        assert!(TestingPallet::opoc_operations_weight(&operations, 0).any_gt(max_weight));
+        assert!(!<() as WeightInfo>::set_inherent_data(0, 0, 0, 0, failures_count - 1, 0).any_gt(max_weight));
+
+        // The remaining inputs are managed on the next blocks
+        assert_ok!(TestingPallet::opoc_store_operations(operations));
+        assert_eq!(Inputs::<Test>::iter().count() as u32, inputs_count - failures_count);
+        let operations = TestingPallet::opoc_run(current_block + 1).unwrap();
+        assert_eq!(operations.failures_operations.len() as u32, (inputs_count - failures_count).min(failures_count));
+    });
+}
+
+// UNSIGNED TRANSACTIONS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This test should accept only the payloads signed by the active validator embedded in them.
+// It should check the outputs of a validator for the same request provide the same tag.
+#[test]
+fn test_validate_unsigned_verifies_payloads_signatures() {
+    make_logger();
+
+    let mut ext = new_test_ext();
+
+    // Create and register the keystore
+    let keystore = Arc::new(MemoryKeystore::new());
+    let validator = keystore.sr25519_generate_new(crate::crypto::CRYPTO_KEY_TYPE, None).unwrap();
+    let not_validator = keystore.sr25519_generate_new(crate::crypto::CRYPTO_KEY_TYPE, None).unwrap();
+    ext.register_extension(KeystoreExt(keystore.clone()));
+
+    ext.execute_with(|| {
+        System::set_block_number(1);
+
+        // Register the first key as validator
+        let stake = 10_000_000_000_000_000_000;
+        let _ = <Balances as Currency<AccountId>>::make_free_balance_be(&validator, stake);
+        assert_ok!(Staking::bond(
+            RuntimeOrigin::signed(validator.clone()),
+            stake,
+            pallet_staking::RewardDestination::Staked,
+        ));
+        assert_ok!(Staking::validate(
+            RuntimeOrigin::signed(validator.clone()),
+            pallet_staking::ValidatorPrefs {
+                commission: Perbill::from_percent(0),
+                blocked: false,
+            }
+        ));
+
+        let store_nodes_outputs_call = |request_id: u32, output: u8, public: AccountId, signer: AccountId| {
+            let payload = crate::payloads::PayloadNodesOutputs {
+                request_id: U256::from(request_id),
+                output_data: BoundedVec::try_from(vec![output]).unwrap(),
+                output_cid: None,
+                output_transaction: None,
+                out_of_fuel: false,
+                ai_models: BoundedVec::default(),
+                salt: H256::zero(),
+                public,
+            };
+            let signature = keystore.sr25519_sign(crate::crypto::CRYPTO_KEY_TYPE, &signer, &payload.encode()).unwrap().unwrap();
+            crate::Call::<Test>::store_nodes_outputs { payload, signature }
+        };
+        let validate = |call: &crate::Call<Test>| <TestingPallet as ValidateUnsigned>::validate_unsigned(TransactionSource::Local, call);
+        let commit = |request_id: u32, output: u8| {
+            let request_id = U256::from(request_id);
+            let node_output = NodeOutput { output_data: BoundedVec::try_from(vec![output]).unwrap(), ..Default::default() };
+            NodesOutputsCommitments::<Test>::insert(request_id, validator, TestingPallet::opoc_output_hash(&node_output, &H256::zero(), &validator));
+        };
+        commit(1, 1);
+        commit(2, 1);
+
+        // The output signed by the validator is valid
+        let valid = validate(&store_nodes_outputs_call(1, 1, validator, validator)).unwrap();
+        assert_eq!(valid.provides.len(), 1);
+
+        // The output not matching the commitment of the validator is rejected
+        assert_eq!(validate(&store_nodes_outputs_call(1, 2, validator, validator)), InvalidTransaction::BadProof.into());
+        assert_eq!(validate(&store_nodes_outputs_call(3, 1, validator, validator)), InvalidTransaction::BadProof.into());
+
+        // Another output of the validator for the same request provides the same tag, so it is deduplicated by the pool
+        commit(1, 2);
+        let same_request = validate(&store_nodes_outputs_call(1, 2, validator, validator)).unwrap();
+        assert_eq!(same_request.provides, valid.provides);
+        let other_request = validate(&store_nodes_outputs_call(2, 1, validator, validator)).unwrap();
+        assert_ne!(other_request.provides, valid.provides);
+
+        // The output claiming to be of the validator but signed by another key is rejected
+        assert_eq!(validate(&store_nodes_outputs_call(1, 1, validator, not_validator)), InvalidTransaction::BadProof.into());
+
+        // The output signed by a key that is not an active validator is rejected
+        assert_eq!(validate(&store_nodes_outputs_call(1, 1, not_validator, not_validator)), InvalidTransaction::BadSigner.into());
+
+        // The same checks are applied to the other payloads
+        let payload = crate::payloads::PayloadNodesVersions { version: 1, public: validator };
+        let signature = keystore.sr25519_sign(crate::crypto::CRYPTO_KEY_TYPE, &not_validator, &payload.encode()).unwrap().unwrap();
+        assert_eq!(validate(&crate::Call::<Test>::store_nodes_versions { payload: payload.clone(), signature }), InvalidTransaction::BadProof.into());
+        let signature = keystore.sr25519_sign(crate::crypto::CRYPTO_KEY_TYPE, &validator, &payload.encode()).unwrap().unwrap();
+        assert_ok!(validate(&crate::Call::<Test>::store_nodes_versions { payload, signature }));
+
+        let payload = crate::payloads::PayloadNodesOpocL0Inferences {
+            request_id: U256::from(1),
+            inference_index: 0,
+            inference_proof: BoundedVec::try_from(vec![1]).unwrap(),
+            public: not_validator,
+        };
+        let signature = keystore.sr25519_sign(crate::crypto::CRYPTO_KEY_TYPE, &not_validator, &payload.encode()).unwrap().unwrap();
+        assert_eq!(validate(&crate::Call::<Test>::store_nodes_opoc_l0_inferences { payload, signature }), InvalidTransaction::BadSigner.into());
+    });
+}
+
+// COMMIT REVEAL
+//////////////////////////////////////////////////////////////////////////////////
+
+// This function commits the salted hash of an output of a validator with the store of the commitment call.
+fn commit_output(request_id: RequestId, validator: &AccountId, node_output: &NodeOutput, salt: H256) -> frame_support::dispatch::DispatchResult {
+    TestingPallet::commit_nodes_outputs(
+        RuntimeOrigin::none(),
+        crate::payloads::PayloadNodesOutputsCommitments {
+            request_id,
+            output_hash: TestingPallet::opoc_output_hash(node_output, &salt, validator),
+            public: validator.clone(),
+        },
+        sp_core::sr25519::Signature::from_raw([0u8; 64]),
+    )
+}
+
+// This function reveals an output of a validator with the store of the outputs call.
+fn reveal_output(request_id: RequestId, validator: &AccountId, node_output: &NodeOutput, salt: H256) -> frame_support::dispatch::DispatchResult {
+    TestingPallet::store_nodes_outputs(
+        RuntimeOrigin::none(),
+        crate::payloads::PayloadNodesOutputs {
+            request_id,
+            output_data: node_output.output_data.clone(),
+            output_cid: node_output.output_cid.clone(),
+            output_transaction: node_output.output_transaction.clone(),
+            out_of_fuel: node_output.out_of_fuel,
+            ai_models: node_output.ai_models.clone(),
+            salt,
+            public: validator.clone(),
+        },
+        sp_core::sr25519::Signature::from_raw([0u8; 64]),
+    )
+}
+
+// This test should accept the reveal of the outputs only after the commitments of all the validators required by the consensus.
+// It should check the commitments of the validators not assigned and the reveals not committed are rejected.
+#[test]
+fn test_commit_reveal_nodes_outputs() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(3, stake);
+
+        System::set_block_number(3);
+        let current_block_number = U256::from(3);
+
+        // Insert an input requiring the consensus of 2 validators, assigned to the first validator
+        let request_id: RequestId = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(1),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(2),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: BoundedVec::default(),
+            input_file_cid: empty_cid.clone(),
+        });
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(28));
+
+        let node_output = NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() };
+        let salt = H256::repeat_byte(1);
+
+        // Only the validators assigned to the request can commit
+        assert_noop!(commit_output(request_id, &validators[2], &node_output, salt), Error::<Test>::NodeNotAssigned);
+
+        // The first validator commits its output only once
+        assert_ok!(commit_output(request_id, &validators[0], &node_output, salt));
+        System::assert_last_event(RuntimeEvent::TestingPallet(Event::NodeOutputCommitted {
+            request_id,
+            account_id: validators[0].clone(),
+            output_hash: TestingPallet::opoc_output_hash(&node_output, &salt, &validators[0]),
+        }));
+        assert_noop!(commit_output(request_id, &validators[0], &node_output, salt), Error::<Test>::NodeOutputAlreadyCommitted);
+        assert_eq!(TestingPallet::api_request_assignments(request_id)[0].output_committed, true);
+        assert_eq!(TestingPallet::api_request_assignments(request_id)[0].output_received, false);
+
+        // The output can not be revealed before the commitments of the validators of opoc level 1
+        assert!(!TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+        assert_noop!(reveal_output(request_id, &validators[0], &node_output, salt), Error::<Test>::NodeOutputRevealNotOpen);
+
+        // The output not committed can not be revealed
+        OpocAssignment::<Test>::insert(request_id, validators[1].clone(), U256::from(28));
+        assert_noop!(reveal_output(request_id, &validators[1], &node_output, salt), Error::<Test>::NodeOutputNotCommitted);
+
+        // The outputs are revealed when all the validators have committed
+        assert_ok!(commit_output(request_id, &validators[1], &node_output, H256::repeat_byte(2)));
+        assert!(TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+        assert_ok!(reveal_output(request_id, &validators[0], &node_output, salt));
+        assert_ok!(reveal_output(request_id, &validators[1], &node_output, H256::repeat_byte(2)));
+        assert_eq!(NodesOutputs::<Test>::get(request_id, validators[0].clone()), node_output.output_data);
+        assert_eq!(NodesOutputs::<Test>::get(request_id, validators[1].clone()), node_output.output_data);
+        assert!(reveal_output(request_id, &validators[0], &node_output, salt).is_err());
+    });
+}
+
+// This test should reject the reveal of an output not matching the commitment of the validator.
+// It should check the validator that does not reveal the committed output is penalized with a timeout.
+#[test]
+fn test_commit_reveal_not_matching_output_rejected() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(2, stake);
+
+        System::set_block_number(3);
+
+        // Insert an input not requiring a consensus, assigned to the first validator
+        let request_id: RequestId = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(1),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(1),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: BoundedVec::default(),
+            input_file_cid: empty_cid.clone(),
+        });
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(28));
+        NodesWorks::<Test>::insert(validators[0].clone(), request_id, true);
+
+        // The validator commits an output and reveals another one or the same one with another salt
+        let committed_output = NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() };
+        let revealed_output = NodeOutput { output_data: BoundedVec::try_from(vec![3, 2, 1]).unwrap(), ..Default::default() };
+        assert_ok!(commit_output(request_id, &validators[0], &committed_output, H256::repeat_byte(1)));
+        assert_noop!(reveal_output(request_id, &validators[0], &revealed_output, H256::repeat_byte(1)), Error::<Test>::NodeOutputNotMatchingCommitment);
+        assert_noop!(reveal_output(request_id, &validators[0], &committed_output, H256::repeat_byte(2)), Error::<Test>::NodeOutputNotMatchingCommitment);
+        assert!(!NodesOutputs::<Test>::contains_key(request_id, validators[0].clone()));
+
+        // The OPoC deassigns the validator that does not reveal its output after the reveal duration and reassigns the request
+        let reveal_expiration_block_number = U256::from(28 + OPOC_REVEAL_DURATION);
+        let operations = TestingPallet::opoc_run(reveal_expiration_block_number).unwrap();
+        assert!(operations.opoc_penalties_operations.get(&validators[0]).is_none());
+        let operations = TestingPallet::opoc_run(reveal_expiration_block_number + 1).unwrap();
+        assert_eq!(operations.opoc_penalties_operations.get(&validators[0]), Some(&vec![OpocOffenceKind::Timeout]));
+        assert!(operations.outputs_operations.is_empty());
+    });
+}
+
+// This test should assign the request to the validators of opoc level 1 when the validator of opoc level 0 has only committed its output.
+#[test]
+fn test_commit_reveal_level_0_committed_assigns_level_1() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(5, stake);
+
+        System::set_block_number(3);
+        let current_block_number = System::block_number();
+
+        // Insert an input requiring the consensus of 3 validators, assigned to the first validator that has committed its output
+        let request_id: RequestId = U256::from(1);
+        let nft_required_consensus = U256::from(3);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(1),
+            nft_id: U256::zero(),
+            nft_required_consensus,
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: BoundedVec::default(),
+            input_file_cid: empty_cid.clone(),
+        });
+        OpocAssignment::<Test>::insert(request_id, validators[0].clone(), U256::from(28));
+        NodesWorks::<Test>::insert(validators[0].clone(), request_id, true);
+        let node_output = NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() };
+        assert_ok!(commit_output(request_id, &validators[0], &node_output, H256::repeat_byte(1)));
+
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+
+        // The request is assigned to the validators of opoc level 1 and the output of opoc level 0 is still hidden
+        let opoc_assignments = OpocAssignment::<Test>::iter_prefix(request_id).collect::<Vec<_>>();
+        assert_eq!(opoc_assignments.len() as u32, nft_required_consensus.as_u32());
+        assert!(NodesOutputsCommitments::<Test>::contains_key(request_id, validators[0].clone()));
+        assert_eq!(NodesOutputs::<Test>::iter_prefix(request_id).count(), 0);
+        assert!(!TestingPallet::opoc_reveal_open(&request_id, &U256::from(current_block_number)));
+    });
+}
+
+// This test should assign the request to the validators of opoc level 2 when a validator of opoc level 1 does not reveal its output after the reveal of another one.
+// It should check the request is not reassigned at opoc level 1 and is closed as failed when the validators are not enough for opoc level 2.
+#[test]
+fn test_commit_reveal_level_1_fault_after_reveal_not_reassigned() {
+    make_logger();
+
+    for (validators_count, escalated) in [(4, true), (3, false)] {
+        new_test_ext().execute_with(|| {
+            let empty_cid = Cid::default();
+            let stake = 10_000_000_000_000_000_000;
+            let validators = create_validators(validators_count, stake);
+
+            System::set_block_number(3);
+            let current_block_number = U256::from(3);
+
+            // Insert an input requiring the consensus of 2 validators, assigned to the validators of opoc level 0 and 1
+            let request_id: RequestId = U256::from(1);
+            Inputs::<Test>::insert(request_id, AgentRequest {
+                block_number: U256::from(1),
+                nft_id: U256::zero(),
+                nft_required_consensus: U256::from(2),
+                nft_execution_max_time: U256::from(25),
+                nft_file_cid: empty_cid.clone(),
+                input_data: BoundedVec::default(),
+                input_file_cid: empty_cid.clone(),
+            });
+            for validator in validators[0..2].iter() {
+                OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(28));
+                NodesWorks::<Test>::insert(validator.clone(), request_id, true);
+            }
+
+            // Both validators commit, but only the first one reveals its output
+            let output = NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() };
+            assert_ok!(commit_output(request_id, &validators[0], &output, H256::repeat_byte(1)));
+            assert_ok!(commit_output(request_id, &validators[1], &output, H256::repeat_byte(2)));
+            assert!(TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+            assert_ok!(reveal_output(request_id, &validators[0], &output, H256::repeat_byte(1)));
+
+            // The validator that does not reveal its output is in timeout and is deassigned without being replaced at opoc level 1
+            let operations = TestingPallet::opoc_run(U256::from(28 + OPOC_REVEAL_DURATION + 1)).unwrap();
+            assert_eq!(operations.opoc_penalties_operations.get(&validators[1]), Some(&vec![OpocOffenceKind::Timeout]));
+            assert!(operations.opoc_assignment_operations.get(&(request_id, validators[0].clone())).is_none());
+            assert_eq!(operations.opoc_assignment_operations.get(&(request_id, validators[1].clone())), Some(&U256::from(0)));
+            assert!(operations.outputs_operations.is_empty());
+
+            if escalated {
+                // The request is assigned to all the other validators, so it goes to opoc level 2
+                assert!(operations.opoc_assignment_operations.contains_key(&(request_id, validators[2].clone())));
+                assert!(operations.opoc_assignment_operations.contains_key(&(request_id, validators[3].clone())));
+                assert!(operations.failures_operations.is_empty());
+            } else {
+                // A single replacement would keep the request at opoc level 1, so the request is closed as failed
+                assert!(operations.opoc_assignment_operations.get(&(request_id, validators[2].clone())).is_none());
+                assert_eq!(operations.failures_operations.get(&request_id), Some(&RequestFailureReason::NoConsensus));
+            }
+        });
+    }
+}
+
+// This test should publish only the salted hashes bound to the validators before the reveal and compare the outputs after it.
+// It should check the validator of opoc level 2 copying the commitment of another validator is not counted in the consensus.
+#[test]
+fn test_commit_reveal_level_2_copied_output_not_counted() {
+    make_logger();
+
+    new_test_ext().execute_with(|| {
+        let empty_cid = Cid::default();
+        let stake = 10_000_000_000_000_000_000;
+        let validators = create_validators(4, stake);
+
+        System::set_block_number(3);
+        let current_block_number = U256::from(3);
+
+        // Insert an input requiring the consensus of 2 validators, assigned to the validators of opoc level 0 and 1
+        let request_id: RequestId = U256::from(1);
+        Inputs::<Test>::insert(request_id, AgentRequest {
+            block_number: U256::from(1),
+            nft_id: U256::zero(),
+            nft_required_consensus: U256::from(2),
+            nft_execution_max_time: U256::from(25),
+            nft_file_cid: empty_cid.clone(),
+            input_data: BoundedVec::default(),
+            input_file_cid: empty_cid.clone(),
+        });
+        for validator in validators[0..2].iter() {
+            OpocAssignment::<Test>::insert(request_id, validator.clone(), U256::from(28));
+            NodesWorks::<Test>::insert(validator.clone(), request_id, true);
+        }
+
+        // The validators of opoc level 0 and 1 commit different outputs
+        let output = NodeOutput { output_data: BoundedVec::try_from(vec![1, 2, 3]).unwrap(), ..Default::default() };
+        let other_output = NodeOutput { output_data: BoundedVec::try_from(vec![3, 2, 1]).unwrap(), ..Default::default() };
+        assert_ok!(commit_output(request_id, &validators[0], &output, H256::repeat_byte(1)));
+        assert_ok!(commit_output(request_id, &validators[1], &other_output, H256::repeat_byte(2)));
+
+        // The outputs are revealed when both validators have committed and are compared by the OPoC after the reveal
+        assert!(TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+        assert_ok!(reveal_output(request_id, &validators[0], &output, H256::repeat_byte(1)));
+        assert_ok!(reveal_output(request_id, &validators[1], &other_output, H256::repeat_byte(2)));
+
+        let inherent_data = InherentData::new();
+        let inherent_call = TestingPallet::create_inherent(&inherent_data).expect("Should create inherent");
+        let runtime_call: RuntimeCall = inherent_call.into();
+        assert_ok!(runtime_call.dispatch(RuntimeOrigin::none()));
+
+        // The outputs do not match, so the request is assigned to the validators of opoc level 2
+        assert!(OpocAssignment::<Test>::contains_key(request_id, validators[2].clone()));
+        assert!(OpocAssignment::<Test>::contains_key(request_id, validators[3].clone()));
+        assert!(!TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+
+        // A validator of opoc level 2 executes the request, the other one copies the commitment of the validator of opoc level 0
+        assert_ok!(commit_output(request_id, &validators[2], &output, H256::repeat_byte(3)));
+        let output_hash = NodesOutputsCommitments::<Test>::get(request_id, validators[0].clone()).unwrap();
+        assert_ok!(TestingPallet::commit_nodes_outputs(
+            RuntimeOrigin::none(),
+            crate::payloads::PayloadNodesOutputsCommitments { request_id, output_hash, public: validators[3].clone() },
+            sp_core::sr25519::Signature::from_raw([0u8; 64]),
+        ));
+
+        // The commitments of the same output are not comparable, they are salted and bound to the validators
+        assert_ne!(
+            NodesOutputsCommitments::<Test>::get(request_id, validators[0].clone()),
+            NodesOutputsCommitments::<Test>::get(request_id, validators[2].clone())
+        );
+        assert_ne!(
+            TestingPallet::opoc_output_hash(&output, &H256::repeat_byte(1), &validators[0]),
+            TestingPallet::opoc_output_hash(&output, &H256::repeat_byte(1), &validators[2])
+        );
+
+        // The validators of opoc level 2 have committed, so their outputs are revealed
+        assert!(TestingPallet::opoc_reveal_open(&request_id, &current_block_number));
+        assert_ok!(reveal_output(request_id, &validators[2], &output, H256::repeat_byte(3)));
+
+        // The output revealed by the validator of opoc level 0 can not be revealed by the validator that copied its commitment
+        assert_noop!(reveal_output(request_id, &validators[3], &output, H256::repeat_byte(1)), Error::<Test>::NodeOutputNotMatchingCommitment);
+
+        // The OPoC completes the request without counting the output of the validator that copied the commitment
+        let reveal_expiration_block_number = OpocAssignment::<Test>::iter_prefix(request_id)
+            .map(|(_validator, expiration_block_number)| expiration_block_number)
+            .max()
+            .unwrap() + U256::from(OPOC_REVEAL_DURATION);
+        let operations = TestingPallet::opoc_run(reveal_expiration_block_number + 1).unwrap();
+        assert_eq!(operations.opoc_penalties_operations.get(&validators[3]), Some(&vec![OpocOffenceKind::Timeout]));
+        assert_eq!(operations.opoc_penalties_operations.get(&validators[1]), Some(&vec![OpocOffenceKind::InvalidOutput]));
+        let result = operations.outputs_operations.get(&request_id).unwrap();
+        assert_eq!(result.output_data, output.output_data);
+        assert_eq!(result.total_executions, 3);
+        assert_eq!(result.total_consensus, 2);
+    });
+}
+
+// HELPERS
+//////////////////////////////////////////////////////////////////////////////////
+
+// This function initializes the logger for the tests.
+// It makes possible to see the logs from the tested pallet directly in the console.
+// USAGE: Run tests with `cargo test -- --show-output`
+// This function also returns a counter that can be used to check how many logs were generated.
+// Example: `let counter = make_logger();`
+//          `assert_eq!(*counter.lock().unwrap(), 1);`
+fn make_logger() -> Arc<Mutex<u16>> {
   let log_counter = Arc::new(Mutex::new(0_u16));
   let log_counter_ref = Arc::clone(&log_counter);
     
@@ -1550,6 +4288,14 @@ fn make_logger() -> Arc<Mutex<u16>> {
 //   AccountId::from_raw(seed)
 // }
 
+// This function sets the fees used by the run_request function.
+fn set_fees(base_fee: u128, fee_per_byte: u128, fee_per_validator: u128, fee_per_block: u128) {
+    RequestBaseFee::set(base_fee);
+    RequestFeePerByte::set(fee_per_byte);
+    RequestFeePerValidator::set(fee_per_validator);
+    RequestFeePerBlock::set(fee_per_block);
+}
+
 fn create_validators(num_validators: u32, stake: u128) -> Vec<AccountId> {
     let mut validators = Vec::new();
     
@@ -1582,6 +4328,7 @@ fn create_validators(num_validators: u32, stake: u128) -> Vec<AccountId> {
 
         validators.push(account_id);
     }
+    snapshot_active_validators();
 
     validators
 }
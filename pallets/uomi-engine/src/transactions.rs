@@ -0,0 +1,64 @@
+use codec::Encode;
+use frame_support::pallet_prelude::Weight;
+use sp_core::H160;
+use sp_io::hashing::blake2_256;
+use sp_runtime::{DispatchError, DispatchResult};
+
+use crate::{
+    consts::AGENT_ADDRESS_PREFIX,
+    types::{Address, AgentTransaction, NftId, RequestId},
+    Config, Event, Pallet,
+};
+
+// AgentTransactionExecutor is implemented by the runtime to execute the transactions emitted by the agents.
+// The transactions are executed by on_idle, max_weight is the bound checked against the remaining weight of the block before the execution
+// and execute returns the weight actually used by the transaction and the result of the transaction.
+// NOTE: The executor must never use more than max_weight, the weight reported for the transaction is capped to it.
+pub trait AgentTransactionExecutor<T: Config> {
+    fn max_weight(transaction: &AgentTransaction) -> Weight;
+    fn execute(address: Address, transaction: &AgentTransaction) -> (Weight, DispatchResult);
+}
+
+// The default executor rejects every transaction.
+impl<T: Config> AgentTransactionExecutor<T> for () {
+    fn max_weight(_transaction: &AgentTransaction) -> Weight {
+        Weight::zero()
+    }
+
+    fn execute(_address: Address, _transaction: &AgentTransaction) -> (Weight, DispatchResult) {
+        (Weight::zero(), Err(DispatchError::Other("Agent transactions are not supported")))
+    }
+}
+
+impl<T: Config> Pallet<T> {
+    // This function is used to get the address of an agent, derived from its NFT ID.
+    // The account id of the agent is the AddressToAccountId of this address, so the agent can be funded both from the EVM and from substrate.
+    pub fn transactions_agent_address(nft_id: &NftId) -> Address {
+        let hash = blake2_256(&(AGENT_ADDRESS_PREFIX, nft_id).encode());
+        H160::from_slice(&hash[0..20])
+    }
+
+    // This function returns the maximum weight of the execution of a transaction emitted by an agent.
+    pub fn transactions_max_weight(transaction: &AgentTransaction) -> Weight {
+        T::AgentTransactionExecutor::max_weight(transaction)
+    }
+
+    // This function is used to execute the transaction of a completed request from the address of its agent, it returns the weight used.
+    // NOTE: A failed transaction does not fail the completion of the request, the result is recorded on the AgentTransactionExecuted event.
+    pub fn transactions_execute(request_id: &RequestId, nft_id: &NftId, transaction: &AgentTransaction) -> Weight {
+        let address = Self::transactions_agent_address(nft_id);
+        let (used_weight, result) = T::AgentTransactionExecutor::execute(address, transaction);
+        if let Err(error) = result {
+            log::info!("UOMI-ENGINE: Transaction of request {:?} failed: {:?}", request_id, error);
+        }
+
+        Self::deposit_event(Event::AgentTransactionExecuted {
+            request_id: *request_id,
+            nft_id: *nft_id,
+            address,
+            result,
+        });
+
+        used_weight
+    }
+}
@@ -1,6 +1,16 @@
-use sp_core::{U256, H160};
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{pallet_prelude::RuntimeDebug, traits::ConstU32, DefaultNoBound};
+use pallet_ipfs::types::Cid;
+use sp_core::{U256, H160, H256};
 use sp_runtime::BoundedVec;
-use crate::MaxDataSize;
+use sp_std::{collections::btree_map::BTreeMap, vec::Vec};
+use crate::{
+    consts::{
+        EXECUTION_LOGS_STORAGE_PREFIX, FUEL_PER_EXECUTION_BLOCK, MAX_AGENT_MEMORY_SIZE, MAX_AGENT_TABLE_ELEMENTS, MAX_EXECUTION_FUEL,
+        MAX_EXECUTION_LOGS_HOST_CALLS, MAX_EXECUTION_LOGS_SIZE, MAX_EXECUTION_LOG_LINE_SIZE, MAX_STORAGE_KEY_SIZE,
+    },
+    MaxDataSize, MaxModelsPerNode,
+};
 
 pub type Version = u32;
 pub type AiModelKey = U256;
@@ -8,4 +18,325 @@ pub type RequestId = U256;
 pub type NftId = U256;
 pub type BlockNumber = U256;
 pub type Address = H160;
+pub type EraIndex = u32;
+pub type OffenceId = u64;
+pub type ExecutionId = u64;
 pub type Data = BoundedVec<u8, MaxDataSize>;
+
+// AgentRequest is a request received by the run_request function waiting to be completed by the OPoC.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct AgentRequest {
+    pub block_number: BlockNumber, // The block number where the request has been accepted.
+    pub nft_id: NftId, // The NFT ID of the agent.
+    pub nft_required_consensus: U256, // The minimum number of validators required.
+    pub nft_execution_max_time: U256, // The maximum number of blocks for the execution.
+    pub nft_file_cid: Cid, // The CID of the agent wasm.
+    pub input_data: Data, // The input data of the request.
+    pub input_file_cid: Cid, // The CID of the input file.
+}
+
+// AgentResult is the consensus output of a request completed by the OPoC.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct AgentResult {
+    pub output_data: Data, // The output data of the request, empty if the output is offloaded to IPFS.
+    pub total_executions: u32, // The total executions of the request.
+    pub total_consensus: u32, // The total consensus of the request.
+    pub output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS, None if the output is stored on chain.
+    pub output_cid_expiration_block_number: BlockNumber, // The block number when the pin of the output offloaded to IPFS expires.
+    pub output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent, executed from the agent address by on_idle after the completion.
+    pub out_of_fuel: bool, // True if the consensus is that the agent exhausted the fuel of the request, the output is empty.
+}
+
+// RequestStatus is the stage of the lifecycle of a request.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum RequestStatus {
+    Pending, // The request is accepted and waits to be assigned to the validators.
+    Running, // The request is assigned to the validators by the OPoC.
+    Completed, // The request is completed with a consensus output stored on Outputs.
+    Failed(RequestFailureReason), // The request is closed by the OPoC without an output.
+    Cancelled, // The request is cancelled by the requester before its assignment.
+}
+
+// RequestFailureReason is the reason why a request is closed by the OPoC without an output.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum RequestFailureReason {
+    Expired, // The request has not reached the consensus before its maximum lifetime (no validators, repeated timeouts).
+    NoConsensus, // The request has no output at opoc level 2 because every validator is in fault or no validator is available for it.
+}
+
+// AgentTransaction is the on-chain action emitted by an agent with set_output_transaction, SCALE encoded by the agent.
+// The transaction is executed from the address derived from the NFT ID of the agent only when the OPoC reaches the consensus on it.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum AgentTransaction {
+    Evm { target: Address, value: U256, input: Data, gas_limit: u64 }, // A call to an EVM contract or account.
+    Substrate { call: Data }, // A SCALE encoded runtime call dispatched with the signed origin of the agent account.
+}
+
+// PendingExecution is an execution of a completed request queued by the inherent and run by on_idle.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum PendingExecution {
+    AgentTransaction { request_id: RequestId, nft_id: NftId, transaction: AgentTransaction }, // The transaction emitted by the agent of the request.
+    Callback { request_id: RequestId, gas_limit: u64 }, // The callback of the request, executed with the output stored on Outputs.
+}
+
+// OutputCid is the reference of an output larger than MaxDataSize, added by the validator to IPFS.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct OutputCid {
+    pub cid: Cid, // The CID of the output on IPFS.
+    pub hash: H256, // The blake2_256 hash of the output.
+}
+
+// NodeOutput is the output of a request produced by a validator, compared by the OPoC to find the consensus.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, PartialOrd, Ord, Default, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct NodeOutput {
+    pub output_data: Data, // The output data, empty if the output is offloaded to IPFS.
+    pub output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS.
+    pub output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent.
+    pub out_of_fuel: bool, // True if the agent exhausted the fuel of the request, the output is empty.
+    pub ai_models: BoundedVec<AiModelKey, MaxModelsPerNode>, // The AI models called by the agent, used to charge the model fee actually used.
+}
+
+// WasmOutput is the state of the execution of an agent wasm, returned when the execution is completed.
+#[derive(Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct WasmOutput {
+    pub output: Vec<u8>, // The output set by the agent with set_output.
+    pub output_transaction: Option<AgentTransaction>, // The transaction set by the agent with set_output_transaction.
+    pub ai_calls: u32, // The number of calls to call_ai made by the agent.
+    pub ai_models: Vec<AiModelKey>, // The distinct AI models called by the agent, in the order of the first call.
+    pub abi_version: u32, // The version of the host ABI negotiated with the agent.
+    pub fuel_consumed: u64, // The fuel consumed by the agent, deterministic for the same wasm, input and host results.
+    pub out_of_fuel: bool, // True if the agent exhausted the fuel, the output and the transaction are empty.
+    pub logs: ExecutionLogs, // The logs collected during the execution, never part of the consensus.
+}
+
+// OpocOperations are the storage operations computed by the OPoC on every block and applied by the inherent.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, DefaultNoBound, RuntimeDebug, scale_info::TypeInfo)]
+pub struct OpocOperations<AccountId: Ord> {
+    pub opoc_penalties_operations: BTreeMap<AccountId, Vec<OpocOffenceKind>>, // The misbehaviours of the validators to penalize.
+    pub opoc_assignment_operations: BTreeMap<(RequestId, AccountId), BlockNumber>, // The assignments to add, or to remove if the expiration block number is zero.
+    pub nodes_works_operations: BTreeMap<AccountId, BTreeMap<RequestId, bool>>, // The works of the validators to add (true) or to remove (false).
+    pub outputs_operations: BTreeMap<RequestId, AgentResult>, // The results of the completed requests.
+    pub failures_operations: BTreeMap<RequestId, RequestFailureReason>, // The requests closed without an output.
+}
+
+// AiModelModality is the kind of output produced by an AI model.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum AiModelModality {
+    Text, // The model produces text.
+    Image, // The model produces images.
+}
+
+// AiModel is an AI model registered by the governance on the models registry.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct AiModel {
+    pub backend_name: Data, // The name of the model on the AI service of the nodes.
+    pub modality: AiModelModality, // The kind of output produced by the model.
+    pub min_consensus: u32, // The minimum nft_required_consensus of the requests allowed to use the model.
+    pub max_consensus: u32, // The maximum nft_required_consensus of the requests allowed to use the model.
+    pub activation_block_number: BlockNumber, // The block number from which the nodes can switch to the model.
+    pub deprecated: bool, // True if the model has been deprecated by the governance.
+}
+
+// RequestAssignment is the view of a single OPoC assignment of a request returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct RequestAssignment<AccountId> {
+    pub account_id: AccountId, // The account ID of the validator.
+    pub expiration_block_number: BlockNumber, // The block number after which the assignment is in timeout.
+    pub output_committed: bool, // True if the validator has already committed the hash of its output.
+    pub output_received: bool, // True if the validator has already stored its output.
+}
+
+// RequestNodeOutput is the view of an output stored by a validator for a request returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct RequestNodeOutput<AccountId> {
+    pub account_id: AccountId, // The account ID of the validator.
+    pub output_data: Data, // The output data stored by the validator.
+    pub output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS by the validator.
+    pub output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent on the execution of the validator.
+    pub out_of_fuel: bool, // True if the agent exhausted the fuel on the execution of the validator.
+}
+
+// RequestResult is the view of the final result of a request returned by the runtime API.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct RequestResult {
+    pub output_data: Data, // The output data of the request.
+    pub total_executions: u32, // The total executions of the request.
+    pub total_consensus: u32, // The total consensus of the request.
+    pub output_cid: Option<OutputCid>, // The reference of the output offloaded to IPFS.
+    pub output_cid_expiration_block_number: BlockNumber, // The block number when the pin of the output offloaded to IPFS expires.
+    pub output_transaction: Option<AgentTransaction>, // The transaction emitted by the agent.
+    pub out_of_fuel: bool, // True if the agent exhausted the fuel of the request.
+}
+
+// RequestInfo is the full view of the lifecycle of a request returned by the runtime API.
+// NOTE: The inputs fields are empty when the request is closed because Inputs are removed on completion, failure and cancellation.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo)]
+pub struct RequestInfo<AccountId> {
+    pub request_id: RequestId, // The request ID.
+    pub status: RequestStatus, // The stage of the lifecycle of the request.
+    pub block_number: BlockNumber, // The block number where the request has been accepted.
+    pub nft_id: NftId, // The NFT ID of the agent.
+    pub nft_required_consensus: U256, // The minimum number of validators required.
+    pub nft_execution_max_time: U256, // The maximum number of blocks for the execution.
+    pub nft_file_cid: Cid, // The CID of the agent wasm.
+    pub input_data: Data, // The input data of the request.
+    pub input_file_cid: Cid, // The CID of the input file.
+    pub opoc_level: Option<u8>, // The current OPoC level, None if the request is not assigned.
+    pub assignments: Vec<RequestAssignment<AccountId>>, // The current assignments of the request.
+    pub outputs: Vec<RequestNodeOutput<AccountId>>, // The outputs received so far.
+    pub result: Option<RequestResult>, // The final result of the request, None if not completed.
+}
+
+// RequestFee is the fee of a request held in escrow until the request is completed.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct RequestFee<AccountId, Balance> {
+    pub payer: AccountId, // The account ID that paid the fee.
+    pub amount: Balance, // The total amount reserved from the payer.
+    pub execution_fee: Balance, // The amount paid for every execution, up to min_validators executions.
+    pub min_validators: u32, // The number of executions paid by the fee.
+}
+
+// RequestCallback is the EVM call to the requesting contract executed by on_idle after the completion of a request.
+// The gas of the callback is prepaid by the payer of the request, the fee of the gas not used is refunded after the execution.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct RequestCallback<AccountId, Balance> {
+    pub target: Address, // The contract called on completion.
+    pub selector: [u8; 4], // The selector of the function called with (requestId, output, totalExecutions, totalConsensus).
+    pub gas_limit: u64, // The gas limit of the callback.
+    pub payer: AccountId, // The account ID that prepaid the gas of the callback.
+    pub fee: Balance, // The amount reserved from the payer for the gas limit.
+}
+
+// RequestCallbackResult is the result of the callback executed on the completion of a request.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct RequestCallbackResult {
+    pub success: bool, // True if the callback has been executed without errors.
+    pub used_gas: u64, // The gas used by the callback.
+}
+
+// MigrationCursor is the progress of the migration of the history of the closed requests run by on_idle, see migrations_run.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct MigrationCursor {
+    pub block_number: BlockNumber, // The block number of the runtime upgrade, the migrated requests are recorded as closed on it.
+    pub outputs_completed: bool, // True if every value of Outputs has been migrated, the requests in RequestsStatuses are migrated next.
+    pub last_raw_key: Option<BoundedVec<u8, ConstU32<MAX_STORAGE_KEY_SIZE>>>, // The raw key of the last request migrated, None to start from the first one.
+}
+
+// OpocOffenceKind is the kind of misbehaviour of a validator detected by the OPoC.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub enum OpocOffenceKind {
+    InvalidOutput, // The validator stored an output that does not match the consensus.
+    Timeout, // The validator did not store an output before the expiration of its assignment.
+}
+
+// PendingOffence is an offence detected by the OPoC waiting to be reported to the offences pallet.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct PendingOffence<AccountId> {
+    pub account_id: AccountId, // The account ID of the validator.
+    pub kind: OpocOffenceKind, // The kind of the offence.
+    pub report_block_number: BlockNumber, // The block number after which the offence is reported.
+}
+
+// NodeReputation is the penalty accumulated by a validator for its misbehaviours, it decays over time.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, scale_info::TypeInfo, MaxEncodedLen)]
+pub struct NodeReputation {
+    pub penalty: u32, // The penalty of the validator at the block number of the last update.
+    pub updated_at_block_number: BlockNumber, // The block number of the last update of the penalty.
+}
+
+// AiServiceEndpoint is an HTTP endpoint of the AI service called by the offchain worker.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct AiServiceEndpoint {
+    pub url: Vec<u8>, // The base URL of the AI service, e.g. http://127.0.0.1:8888.
+    pub headers: Vec<(Vec<u8>, Vec<u8>)>, // The headers added to every request, e.g. the Authorization header.
+}
+
+// AiServiceConfig is the configuration of the AI service of a node, stored by the node on the offchain local storage.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct AiServiceConfig {
+    pub default_endpoint: AiServiceEndpoint, // The endpoint used for the models without a specific endpoint.
+    pub models_endpoints: Vec<(AiModelKey, AiServiceEndpoint)>, // The specific endpoints of the models.
+}
+
+impl AiServiceConfig {
+    // This function is used to get the endpoint serving an AI model.
+    pub fn endpoint(&self, ai_model_key: &AiModelKey) -> &AiServiceEndpoint {
+        self.models_endpoints.iter()
+            .find(|(key, _)| key == ai_model_key)
+            .map(|(_, endpoint)| endpoint)
+            .unwrap_or(&self.default_endpoint)
+    }
+}
+
+// HostCallTiming is the timing of a call_ai or get_cid_file host call made by an agent during its execution.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct HostCallTiming {
+    pub name: Vec<u8>, // The name of the host function.
+    pub started_at_ms: u64, // The milliseconds passed from the start of the execution when the call started.
+    pub duration_ms: u64, // The duration of the call in milliseconds.
+    pub success: bool, // True if the host function completed without errors.
+}
+
+// ExecutionLogs are the console_log lines and the host call timings collected during the execution of an agent wasm.
+// NOTE: The logs are stored by the node on the offchain local storage to help debugging, they are never part of the consensus.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Default, RuntimeDebug)]
+pub struct ExecutionLogs {
+    pub lines: Vec<Vec<u8>>, // The lines logged by the agent with console_log.
+    pub host_calls: Vec<HostCallTiming>, // The timings of the host calls made by the agent.
+    pub truncated: bool, // True if some lines or host calls have been dropped because of the size bounds.
+    pub size: u32, // The total size in bytes of the logged lines.
+}
+
+impl ExecutionLogs {
+    // This function is used to get the key of the offchain local storage where the logs of a request are stored.
+    pub fn storage_key(request_id: &RequestId) -> Vec<u8> {
+        let mut key = EXECUTION_LOGS_STORAGE_PREFIX.to_vec();
+        key.extend_from_slice(&request_id.encode());
+        key
+    }
+
+    // This function is used to add a line to the logs, the line is truncated or dropped when it exceeds the size bounds.
+    pub fn push_line(&mut self, mut line: Vec<u8>) {
+        if line.len() > MAX_EXECUTION_LOG_LINE_SIZE {
+            line.truncate(MAX_EXECUTION_LOG_LINE_SIZE);
+            self.truncated = true;
+        }
+        if self.size as usize + line.len() > MAX_EXECUTION_LOGS_SIZE {
+            self.truncated = true;
+            return;
+        }
+        self.size += line.len() as u32;
+        self.lines.push(line);
+    }
+
+    // This function is used to add a host call timing to the logs, the timing is dropped when the logs have too many host calls.
+    pub fn push_host_call(&mut self, host_call: HostCallTiming) {
+        if self.host_calls.len() >= MAX_EXECUTION_LOGS_HOST_CALLS {
+            self.truncated = true;
+            return;
+        }
+        self.host_calls.push(host_call);
+    }
+}
+
+// ExecutionBudget are the resources given to an agent for a single execution, equal on every validator for the same request.
+// NOTE: The fuel is consumed deterministically by the wasm instructions, so its exhaustion is part of the consensus, while the
+// wall-clock timeout is only a bound for the host calls and does not depend on the budget.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ExecutionBudget {
+    pub fuel: u64, // The fuel available to the wasm instructions of the agent.
+    pub memory_size: u64, // The maximum size in bytes of the linear memory of the agent.
+    pub table_elements: u32, // The maximum number of elements of a table of the agent.
+}
+
+impl ExecutionBudget {
+    // This function is used to get the budget of a request from its maximum execution time in blocks.
+    pub fn from_execution_max_time(nft_execution_max_time: U256) -> Self {
+        let blocks = u64::try_from(nft_execution_max_time).unwrap_or(u64::MAX);
+        Self {
+            fuel: blocks.saturating_mul(FUEL_PER_EXECUTION_BLOCK).min(MAX_EXECUTION_FUEL),
+            memory_size: MAX_AGENT_MEMORY_SIZE as u64,
+            table_elements: MAX_AGENT_TABLE_ELEMENTS as u32,
+        }
+    }
+}
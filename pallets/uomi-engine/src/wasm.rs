@@ -0,0 +1,586 @@
+use codec::DecodeAll;
+use frame_support::pallet_prelude::DispatchError;
+use pallet_ipfs::types::Cid;
+use sp_core::U256;
+use sp_std::vec::Vec;
+use std::time::Instant;
+
+use crate::{
+    consts::MAX_EXECUTION_LOG_LINE_SIZE,
+    types::{AgentTransaction, AiModelKey, ExecutionBudget, ExecutionLogs, HostCallTiming, WasmOutput},
+    wasm_cache::wasm_cache,
+};
+
+// This is the duration in milliseconds of an epoch of the wasm engine, the timeout of the execution is checked on every epoch.
+// NOTE: The timeout is only a backstop of the node, the fuel of the budget is the only limit of the execution compared by the OPoC.
+const WASM_EPOCH_DURATION_MS: u64 = 100;
+
+// This is the maximum time in milliseconds given to an agent to declare its ABI version when it is validated.
+const WASM_VALIDATION_TIMEOUT_MS: u64 = 1_000;
+
+// ABI VERSIONS
+// The agents declare the version of the host ABI they are built for by exporting a function named WASM_ABI_VERSION_EXPORT,
+// without params and returning the version as i32. The agents without the export are the ones deployed before the versioning
+// and use the ABI v1. Every version imports its host functions from its own module, so all the versions are linked side by side
+// and an agent is rejected if it imports functions of a version different from the declared one or of an unsupported one.
+pub const WASM_ABI_VERSION_EXPORT: &str = "uomi_abi_version";
+pub const WASM_ABI_V1: u32 = 1; // Length prefixed buffers written without bounds, failures return empty data.
+pub const WASM_ABI_V2: u32 = 2; // Bounded buffers, host calls return the length of their result or an error code.
+pub const WASM_ABI_VERSIONS: [u32; 2] = [WASM_ABI_V1, WASM_ABI_V2];
+
+// ABI V2 ERROR CODES
+// The host functions of the ABI v2 return a negative error code on failure, they never trap and never write outside of the
+// buffer given by the agent.
+pub const WASM_ABI_ERROR_MEMORY_ACCESS: i32 = -1; // The buffer is outside of the memory of the agent.
+pub const WASM_ABI_ERROR_BUFFER_TOO_SMALL: i32 = -2; // The buffer is smaller than the result, the result is kept to be read again.
+pub const WASM_ABI_ERROR_INVALID_ARGUMENT: i32 = -3; // An argument is negative or its content is not valid.
+pub const WASM_ABI_ERROR_HOST_CALL_FAILED: i32 = -4; // The host failed to get the file or to run the inference.
+pub const WASM_ABI_ERROR_NO_RESULT: i32 = -5; // There is no result to read.
+
+// This function is used to get the module the agents built for an ABI version import the host functions from.
+pub fn wasm_abi_import_module(version: u32) -> Option<&'static str> {
+    match version {
+        WASM_ABI_V1 => Some("env"),
+        WASM_ABI_V2 => Some("uomi_v2"),
+        _ => None,
+    }
+}
+
+// AgentHost provides to the wasm of an agent the host functions that depend on the environment where the agent is executed.
+// The offchain worker implements it with the IPFS pallet and the AI service of the node, the tools of the node implement it
+// to execute the agents without submitting a request.
+pub trait AgentHost {
+    // This function is used to get the input file of the request, empty if the request has no input file.
+    fn get_input_file(&mut self) -> Vec<u8>;
+    // This function is used to get a file from IPFS by its CID.
+    fn get_cid_file(&mut self, cid: Cid) -> Result<Vec<u8>, DispatchError>;
+    // This function is used to run an inference on an AI model, the counter is the index of the call in the execution starting from 1.
+    fn call_ai(&mut self, model: AiModelKey, input: Vec<u8>, counter: u32) -> Result<Vec<u8>, DispatchError>;
+}
+
+// WasmState is the state of the wasmtime store during the execution of an agent.
+struct WasmState<H: AgentHost> {
+    output: WasmOutput, // The output of the execution, returned when the execution is completed.
+    host: H, // The host providing the functions that depend on the environment.
+    started: Instant, // The instant the execution started, used to time the host calls.
+    result: Option<Vec<u8>>, // The result of the last ABI v2 host call, until it is read by the agent with read_result.
+    limits: wasmtime::StoreLimits, // The memory and table limits of the budget of the execution.
+}
+
+impl<H: AgentHost> WasmState<H> {
+    // This function is used to record the timing of a host call started at call_started.
+    fn record_host_call(&mut self, name: &[u8], call_started: Instant, success: bool) {
+        let timing = HostCallTiming {
+            name: name.to_vec(),
+            started_at_ms: call_started.duration_since(self.started).as_millis() as u64,
+            duration_ms: call_started.elapsed().as_millis() as u64,
+            success,
+        };
+        self.output.logs.push_host_call(timing);
+    }
+
+    // This function is used to record an AI model called by the agent, the model fee is charged only for the models called.
+    fn record_ai_model(&mut self, model: AiModelKey) {
+        if !self.output.ai_models.contains(&model) {
+            self.output.ai_models.push(model);
+        }
+    }
+
+    // This function is used to keep the result of an ABI v2 host call and return its length, or the error code of the call.
+    fn set_result(&mut self, result: Result<Vec<u8>, i32>) -> i32 {
+        match result {
+            Ok(data) if data.len() > i32::MAX as usize => {
+                self.result = None;
+                WASM_ABI_ERROR_HOST_CALL_FAILED
+            },
+            Ok(data) => {
+                let len = data.len() as i32;
+                self.result = Some(data);
+                len
+            },
+            Err(code) => {
+                self.result = None;
+                code
+            },
+        }
+    }
+}
+
+// This function is used to run the wasm of an agent with the host ABI of the engine and the resources of the budget, interrupting it after timeout_ms milliseconds.
+// It returns, together with the result, the logs collected during the execution, also when the execution fails.
+// The module is compiled once for the CID of the agent when the node uses a cache, the wasm without a CID is always compiled.
+// NOTE: The exhaustion of the fuel is a successful execution with out_of_fuel set and an empty output, so it is compared by the OPoC as any other output.
+// The interruption after timeout_ms depends on the speed of the node, so it is an error recognized by wasm_interrupted and its output must not be committed.
+pub fn wasm_run<H: AgentHost + 'static>(wasm: &[u8], cid: Option<&Cid>, input_data: Vec<u8>, timeout_ms: u64, budget: ExecutionBudget, host: H) -> (Result<WasmOutput, wasmtime::Error>, ExecutionLogs) {
+    wasm_run_with_epoch_duration(wasm, cid, input_data, timeout_ms, WASM_EPOCH_DURATION_MS, budget, host)
+}
+
+// This function is used to run the wasm of an agent as wasm_run, checking the timeout every epoch_duration_ms milliseconds.
+pub(crate) fn wasm_run_with_epoch_duration<H: AgentHost + 'static>(
+    wasm: &[u8],
+    cid: Option<&Cid>,
+    input_data: Vec<u8>,
+    timeout_ms: u64,
+    epoch_duration_ms: u64,
+    budget: ExecutionBudget,
+    host: H
+) -> (Result<WasmOutput, wasmtime::Error>, ExecutionLogs) {
+    let engine = match wasm_engine() {
+        Ok(engine) => engine,
+        Err(error) => {
+            log::error!("UOMI-ENGINE: Error creating the wasm engine: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        },
+    };
+    let module = match (cid, wasm_cache()) {
+        (Some(cid), Some(cache)) => cache.load_or_compile(&engine, cid, wasm),
+        _ => wasmtime::Module::new(&engine, wasm),
+    };
+    let module = match module {
+        Ok(module) => module,
+        Err(error) => {
+            log::error!("UOMI-ENGINE: Error loading the wasm module: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        },
+    };
+    let mut store = match wasm_store(&engine, host, &budget) {
+        Ok(store) => store,
+        Err(error) => {
+            log::error!("UOMI-ENGINE: Error creating the wasm store: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        },
+    };
+    store.set_epoch_deadline((timeout_ms / epoch_duration_ms).max(1));
+    store.epoch_deadline_trap();
+    wasm_start_epoch_ticker(&engine, timeout_ms, epoch_duration_ms);
+
+    let linker = match wasm_linker(&engine, input_data) {
+        Ok(linker) => linker,
+        Err(error) => {
+            log::error!("UOMI-ENGINE: Error linking the host functions: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        },
+    };
+    let instance = match linker.instantiate(&mut store, &module) {
+        Ok(instance) => instance,
+        Err(error) => {
+            log::error!("Error instantiating the wasm module: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        }
+    };
+    match wasm_abi_version(&mut store, &module, &instance) {
+        Ok(version) => store.data_mut().output.abi_version = version,
+        Err(error) => {
+            log::error!("UOMI-ENGINE: Error negotiating the ABI version: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        }
+    }
+    let run = match instance.get_typed_func::<(), ()>(&mut store, "run") {
+        Ok(run) => run,
+        Err(error) => {
+            log::error!("Error getting the run function: {:?}", error);
+            return (Err(error), ExecutionLogs::default());
+        }
+    };
+
+    let result = run.call(&mut store, ());
+    let fuel_consumed = budget.fuel.saturating_sub(store.get_fuel().unwrap_or(0));
+    let mut output = store.into_data().output;
+    output.fuel_consumed = fuel_consumed;
+    let logs = output.logs.clone();
+    match result {
+        Ok(_) => (Ok(output), logs),
+        Err(err) if matches!(err.downcast_ref::<wasmtime::Trap>(), Some(wasmtime::Trap::OutOfFuel)) => {
+            log::info!("UOMI-ENGINE: WASM execution out of fuel after {:?} fuel", fuel_consumed);
+            (Ok(WasmOutput { output: Vec::new(), output_transaction: None, out_of_fuel: true, ..output }), logs)
+        },
+        Err(err) if matches!(err.downcast_ref::<wasmtime::Trap>(), Some(wasmtime::Trap::Interrupt)) => {
+            log::warn!("UOMI-ENGINE: WASM execution interrupted by the timeout of the node after {:?} fuel", fuel_consumed);
+            (Err(err), logs)
+        },
+        Err(err) => {
+            log::error!("UOMI-ENGINE: WASM execution error: {:?}", err);
+            (Err(wasmtime::Error::new(std::io::Error::new(std::io::ErrorKind::Other, "WASM execution error"))), logs)
+        }
+    }
+}
+
+// This function is used to check if an execution failed because it was interrupted by the timeout of the node instead of ending within its fuel.
+pub fn wasm_interrupted(error: &wasmtime::Error) -> bool {
+    matches!(error.downcast_ref::<wasmtime::Trap>(), Some(wasmtime::Trap::Interrupt))
+}
+
+// This function is used to validate the wasm of an agent without executing it.
+// The wasm must export the run function and the memory, and it must import only the host functions of the engine with their signatures.
+pub fn wasm_validate(wasm: &[u8]) -> Result<(), wasmtime::Error> {
+    let engine = wasm_engine()?;
+    let module = wasmtime::Module::new(&engine, wasm)?;
+
+    match module.get_export("run") {
+        Some(wasmtime::ExternType::Func(func)) if func.params().len() == 0 && func.results().len() == 0 => {},
+        Some(_) => return Err(wasmtime::Error::msg("The run export must be a function without params and results")),
+        None => return Err(wasmtime::Error::msg("The run function is not exported")),
+    }
+    match module.get_export("memory") {
+        Some(wasmtime::ExternType::Memory(_)) => {},
+        Some(_) => return Err(wasmtime::Error::msg("The memory export must be a memory")),
+        None => return Err(wasmtime::Error::msg("The memory is not exported")),
+    }
+
+    // The imports are resolved with the same linker used to run the agents, so they are checked by name and signature
+    let linker = wasm_linker::<ValidationHost>(&engine, Vec::new())?;
+    let mut store = wasm_store(&engine, ValidationHost, &ExecutionBudget::from_execution_max_time(U256::one()))?;
+    store.set_epoch_deadline(WASM_VALIDATION_TIMEOUT_MS / WASM_EPOCH_DURATION_MS);
+    store.epoch_deadline_trap();
+    wasm_start_epoch_ticker(&engine, WASM_VALIDATION_TIMEOUT_MS, WASM_EPOCH_DURATION_MS);
+    let instance = linker.instantiate(&mut store, &module)?;
+    wasm_abi_version(&mut store, &module, &instance)?;
+
+    Ok(())
+}
+
+// This function is used to create the engine executing the agents, with fuel metering and epoch interruption.
+fn wasm_engine() -> Result<wasmtime::Engine, wasmtime::Error> {
+    let mut config = wasmtime::Config::new();
+    config.epoch_interruption(true);
+    config.consume_fuel(true);
+    wasmtime::Engine::new(&config)
+}
+
+// This function is used to create the store of an execution, with the fuel and the memory and table limits of the budget.
+fn wasm_store<H: AgentHost>(engine: &wasmtime::Engine, host: H, budget: &ExecutionBudget) -> Result<wasmtime::Store<WasmState<H>>, wasmtime::Error> {
+    let limits = wasmtime::StoreLimitsBuilder::new()
+        .memory_size(usize::try_from(budget.memory_size).unwrap_or(usize::MAX))
+        .table_elements(budget.table_elements as _)
+        .build();
+    let mut store = wasmtime::Store::new(engine, WasmState {
+        output: WasmOutput::default(),
+        host,
+        started: Instant::now(),
+        result: None,
+        limits,
+    });
+    store.limiter(|state| &mut state.limits);
+    store.set_fuel(budget.fuel)?;
+
+    Ok(store)
+}
+
+// This function is used to negotiate the ABI version of an instantiated agent.
+// It returns the version declared by the agent, if it is supported by the engine and the agent imports only the host functions of that version.
+fn wasm_abi_version<H: AgentHost>(store: &mut wasmtime::Store<WasmState<H>>, module: &wasmtime::Module, instance: &wasmtime::Instance) -> Result<u32, wasmtime::Error> {
+    let version = match module.get_export(WASM_ABI_VERSION_EXPORT) {
+        Some(_) => {
+            let version = instance.get_typed_func::<(), i32>(&mut *store, WASM_ABI_VERSION_EXPORT)?.call(&mut *store, ())?;
+            u32::try_from(version).map_err(|_| wasmtime::Error::msg(format!("The ABI version {} is not valid", version)))?
+        },
+        None => WASM_ABI_V1,
+    };
+    let import_module = wasm_abi_import_module(version)
+        .ok_or_else(|| wasmtime::Error::msg(format!("The ABI version {} is not supported, the supported versions are {:?}", version, WASM_ABI_VERSIONS)))?;
+    for import in module.imports() {
+        if import.module() != import_module {
+            return Err(wasmtime::Error::msg(format!("The import {}::{} is not part of the ABI version {}", import.module(), import.name(), version)));
+        }
+    }
+
+    Ok(version)
+}
+
+// This function is used to start a thread incrementing the epoch of the engine every epoch_duration_ms milliseconds until timeout_ms milliseconds are passed.
+fn wasm_start_epoch_ticker(engine: &wasmtime::Engine, timeout_ms: u64, epoch_duration_ms: u64) {
+    let mut time_passed_ms = 0;
+    let engine = engine.clone();
+    std::thread::spawn(move || {
+        while time_passed_ms < timeout_ms {
+            std::thread::sleep(std::time::Duration::from_millis(epoch_duration_ms));
+            engine.increment_epoch();
+            time_passed_ms += epoch_duration_ms;
+        }
+    });
+}
+
+// ValidationHost is the host used to build the linker when an agent is validated, it is never called.
+struct ValidationHost;
+
+impl AgentHost for ValidationHost {
+    fn get_input_file(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn get_cid_file(&mut self, _cid: Cid) -> Result<Vec<u8>, DispatchError> {
+        Ok(Vec::new())
+    }
+
+    fn call_ai(&mut self, _model: AiModelKey, _input: Vec<u8>, _counter: u32) -> Result<Vec<u8>, DispatchError> {
+        Ok(Vec::new())
+    }
+}
+
+// This function is used to build the linker exposing the host functions of all the ABI versions to the wasm of an agent.
+fn wasm_linker<H: AgentHost + 'static>(engine: &wasmtime::Engine, input_data: Vec<u8>) -> Result<wasmtime::Linker<WasmState<H>>, wasmtime::Error> {
+    let mut linker = wasmtime::Linker::new(engine);
+    wasm_link_v1(&mut linker, input_data.clone())?;
+    wasm_link_v2(&mut linker, input_data)?;
+
+    Ok(linker)
+}
+
+// This function is used to define the host functions of the ABI v1 on the linker.
+fn wasm_link_v1<H: AgentHost + 'static>(linker: &mut wasmtime::Linker<WasmState<H>>, input_data: Vec<u8>) -> Result<(), wasmtime::Error> {
+    let get_input_data = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, _len: i32| {
+        let data_to_write = wasm_generate_data(input_data.clone());
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        memory.write(caller, ptr as usize, &data_to_write).expect("Failed to write memory");
+    };
+
+    let get_input_file = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, _len: i32| {
+        let file = caller.data_mut().host.get_input_file();
+        let data_to_write = wasm_generate_data(file);
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        memory.write(caller, ptr as usize, &data_to_write).expect("Failed to write memory");
+    };
+
+    let set_output = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| {
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        let mut buffer = vec![0u8; len as usize];
+        memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
+        caller.data_mut().output.output = buffer;
+    };
+
+    // NOTE: The transaction is the SCALE encoding of an AgentTransaction, an invalid encoding is ignored on every node so it does not affect the consensus.
+    let set_output_transaction = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| {
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        let mut buffer = vec![0u8; len as usize];
+        memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
+        caller.data_mut().output.output_transaction = match AgentTransaction::decode_all(&mut &buffer[..]) {
+            Ok(transaction) => Some(transaction),
+            Err(error) => {
+                log::error!("Error decoding the output transaction: {:?}", error);
+                None
+            }
+        };
+    };
+
+    let get_cid_file = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32, output_ptr: i32, _: i32| {
+        let call_started = Instant::now();
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        let mut buffer = vec![0u8; len as usize];
+        memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
+        let cid = match Cid::try_from(buffer) {
+            Ok(cid) => cid,
+            Err(error) => {
+                log::error!("Error converting buffer to CID: {:?}", error);
+                Cid::default()
+            }
+        };
+        let (file, success) = match caller.data_mut().host.get_cid_file(cid) {
+            Ok(file) => (file, true),
+            Err(error) => {
+                log::error!("Error getting the file from the IPFS pallet: {:?}", error);
+                (Vec::new(), false)
+            }
+        };
+        caller.data_mut().record_host_call(b"get_cid_file", call_started, success);
+        let data_to_write = wasm_generate_data(file);
+        memory.write(caller, output_ptr as usize, &data_to_write).expect("Failed to write memory");
+    };
+
+    // NOTE: The lines logged by the agent are collected on the ExecutionLogs of the output, bounded in size, to help wasm debugging.
+    let console_log = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| {
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        let len = (len.max(0) as usize).min(MAX_EXECUTION_LOG_LINE_SIZE + 1);
+        let mut buffer = vec![0u8; len];
+        if let Err(error) = memory.read(&caller, ptr as usize, &mut buffer) {
+            log::error!("Error reading the console log from memory: {:?}", error);
+            return;
+        }
+        caller.data_mut().output.logs.push_line(buffer);
+    };
+
+    // NOTE: The call_ai function is "special". It needs to track the number of calls and count them by incrementing a counter.
+    // This is required to permit us to log the executions and store them on OpocL0Inferences (on Opoc level 0) or read them from OpocL0Inferences (on Opoc level 1/2).
+    let call_ai = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, model: i32, ptr: i32, len: i32, output_ptr: i32, _: i32| {
+        caller.data_mut().output.ai_calls += 1;
+        let counter = caller.data().output.ai_calls;
+        let call_started = Instant::now();
+
+        let memory = caller.get_export("memory").and_then(|x| x.into_memory()).expect("Failed to get memory export");
+        let mut buffer = vec![0u8; len as usize];
+        memory.read(&caller, ptr as usize, &mut buffer).expect("Failed to read memory");
+        let model = AiModelKey::from(model as u32);
+        caller.data_mut().record_ai_model(model);
+        let (output, success) = match caller.data_mut().host.call_ai(model, buffer, counter) {
+            Ok(output) => (output, true),
+            Err(error) => {
+                log::error!("Error calling the AI: {:?}", error);
+                (Vec::new(), false)
+            }
+        };
+        caller.data_mut().record_host_call(b"call_ai", call_started, success);
+        let data_to_write = wasm_generate_data(output);
+        memory.write(caller, output_ptr as usize, &data_to_write).expect("Failed to write memory");
+    };
+
+    let module = wasm_abi_import_module(WASM_ABI_V1).expect("ABI v1 is supported");
+    linker.func_wrap(module, "get_input_file", get_input_file)?;
+    linker.func_wrap(module, "get_input_data", get_input_data)?;
+    linker.func_wrap(module, "set_output", set_output)?;
+    linker.func_wrap(module, "set_output_transaction", set_output_transaction)?;
+    linker.func_wrap(module, "get_cid_file", get_cid_file)?;
+    linker.func_wrap(module, "console_log", console_log)?;
+    linker.func_wrap(module, "call_ai", call_ai)?;
+
+    Ok(())
+}
+
+// This function is used to define the host functions of the ABI v2 on the linker.
+// The functions returning data keep it as the pending result and return its length, the agent copies it on its memory with
+// read_result(ptr, len), that writes it only if it fits in the buffer. All the functions return a WASM_ABI_ERROR_* on failure.
+fn wasm_link_v2<H: AgentHost + 'static>(linker: &mut wasmtime::Linker<WasmState<H>>, input_data: Vec<u8>) -> Result<(), wasmtime::Error> {
+    let get_input_data = move |mut caller: wasmtime::Caller<'_, WasmState<H>>| -> i32 {
+        caller.data_mut().set_result(Ok(input_data.clone()))
+    };
+
+    let get_input_file = move |mut caller: wasmtime::Caller<'_, WasmState<H>>| -> i32 {
+        let file = caller.data_mut().host.get_input_file();
+        caller.data_mut().set_result(Ok(file))
+    };
+
+    let read_result = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| -> i32 {
+        let Some(result) = caller.data_mut().result.take() else {
+            return WASM_ABI_ERROR_NO_RESULT;
+        };
+        if len < 0 || result.len() > len as usize {
+            caller.data_mut().result = Some(result);
+            return WASM_ABI_ERROR_BUFFER_TOO_SMALL;
+        }
+        if let Err(code) = wasm_write(&mut caller, ptr, &result) {
+            caller.data_mut().result = Some(result);
+            return code;
+        }
+        result.len() as i32
+    };
+
+    let set_output = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| -> i32 {
+        match wasm_read(&mut caller, ptr, len) {
+            Ok(buffer) => {
+                caller.data_mut().output.output = buffer;
+                0
+            },
+            Err(code) => code,
+        }
+    };
+
+    let set_output_transaction = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| -> i32 {
+        let buffer = match wasm_read(&mut caller, ptr, len) {
+            Ok(buffer) => buffer,
+            Err(code) => return code,
+        };
+        match AgentTransaction::decode_all(&mut &buffer[..]) {
+            Ok(transaction) => {
+                caller.data_mut().output.output_transaction = Some(transaction);
+                0
+            },
+            Err(_) => WASM_ABI_ERROR_INVALID_ARGUMENT,
+        }
+    };
+
+    let get_cid_file = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| -> i32 {
+        let call_started = Instant::now();
+        let cid = match wasm_read(&mut caller, ptr, len) {
+            Ok(buffer) => match Cid::try_from(buffer) {
+                Ok(cid) => cid,
+                Err(_) => return caller.data_mut().set_result(Err(WASM_ABI_ERROR_INVALID_ARGUMENT)),
+            },
+            Err(code) => return caller.data_mut().set_result(Err(code)),
+        };
+        let result = caller.data_mut().host.get_cid_file(cid).map_err(|error| {
+            log::error!("Error getting the file from the IPFS pallet: {:?}", error);
+            WASM_ABI_ERROR_HOST_CALL_FAILED
+        });
+        caller.data_mut().record_host_call(b"get_cid_file", call_started, result.is_ok());
+        caller.data_mut().set_result(result)
+    };
+
+    let console_log = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32| -> i32 {
+        if len < 0 {
+            return WASM_ABI_ERROR_INVALID_ARGUMENT;
+        }
+        match wasm_read(&mut caller, ptr, len.min(MAX_EXECUTION_LOG_LINE_SIZE as i32 + 1)) {
+            Ok(buffer) => {
+                caller.data_mut().output.logs.push_line(buffer);
+                0
+            },
+            Err(code) => code,
+        }
+    };
+
+    // NOTE: The calls are counted as on the ABI v1, also when the arguments are not valid, so the index of the inferences
+    // stored on OpocL0Inferences does not depend on the ABI version.
+    let call_ai = move |mut caller: wasmtime::Caller<'_, WasmState<H>>, model: i32, ptr: i32, len: i32| -> i32 {
+        caller.data_mut().output.ai_calls += 1;
+        let counter = caller.data().output.ai_calls;
+        let call_started = Instant::now();
+
+        if model < 0 {
+            return caller.data_mut().set_result(Err(WASM_ABI_ERROR_INVALID_ARGUMENT));
+        }
+        let buffer = match wasm_read(&mut caller, ptr, len) {
+            Ok(buffer) => buffer,
+            Err(code) => return caller.data_mut().set_result(Err(code)),
+        };
+        let model = AiModelKey::from(model as u32);
+        caller.data_mut().record_ai_model(model);
+        let result = caller.data_mut().host.call_ai(model, buffer, counter).map_err(|error| {
+            log::error!("Error calling the AI: {:?}", error);
+            WASM_ABI_ERROR_HOST_CALL_FAILED
+        });
+        caller.data_mut().record_host_call(b"call_ai", call_started, result.is_ok());
+        caller.data_mut().set_result(result)
+    };
+
+    let module = wasm_abi_import_module(WASM_ABI_V2).expect("ABI v2 is supported");
+    linker.func_wrap(module, "get_input_data", get_input_data)?;
+    linker.func_wrap(module, "get_input_file", get_input_file)?;
+    linker.func_wrap(module, "read_result", read_result)?;
+    linker.func_wrap(module, "set_output", set_output)?;
+    linker.func_wrap(module, "set_output_transaction", set_output_transaction)?;
+    linker.func_wrap(module, "get_cid_file", get_cid_file)?;
+    linker.func_wrap(module, "console_log", console_log)?;
+    linker.func_wrap(module, "call_ai", call_ai)?;
+
+    Ok(())
+}
+
+// This function is used to read len bytes at ptr from the memory of the agent, checking the bounds of the buffer.
+fn wasm_read<H: AgentHost>(caller: &mut wasmtime::Caller<'_, WasmState<H>>, ptr: i32, len: i32) -> Result<Vec<u8>, i32> {
+    if ptr < 0 || len < 0 {
+        return Err(WASM_ABI_ERROR_INVALID_ARGUMENT);
+    }
+    let memory = caller.get_export("memory").and_then(|x| x.into_memory()).ok_or(WASM_ABI_ERROR_MEMORY_ACCESS)?;
+    let data = memory.data(&*caller);
+    let end = (ptr as usize).checked_add(len as usize).ok_or(WASM_ABI_ERROR_MEMORY_ACCESS)?;
+    data.get(ptr as usize..end).map(|buffer| buffer.to_vec()).ok_or(WASM_ABI_ERROR_MEMORY_ACCESS)
+}
+
+// This function is used to write data at ptr on the memory of the agent, checking the bounds of the buffer.
+fn wasm_write<H: AgentHost>(caller: &mut wasmtime::Caller<'_, WasmState<H>>, ptr: i32, data: &[u8]) -> Result<(), i32> {
+    if ptr < 0 {
+        return Err(WASM_ABI_ERROR_INVALID_ARGUMENT);
+    }
+    let memory = caller.get_export("memory").and_then(|x| x.into_memory()).ok_or(WASM_ABI_ERROR_MEMORY_ACCESS)?;
+    memory.write(&mut *caller, ptr as usize, data).map_err(|_| WASM_ABI_ERROR_MEMORY_ACCESS)
+}
+
+// This function is used to prefix the data written on the memory of the wasm with its length, as 4 bytes little endian.
+fn wasm_generate_data(data: Vec<u8>) -> Vec<u8> {
+    let data_len = data.len();
+    let mut wasm_data = Vec::new();
+
+    // write data_len on first 4 bytes of wasm_data, then write data
+    wasm_data.extend(&(data_len as u32).to_le_bytes());
+    wasm_data.extend(data);
+
+    wasm_data
+}
@@ -0,0 +1,170 @@
+use pallet_ipfs::types::Cid;
+use sp_core::hexdisplay::HexDisplay;
+use sp_io::hashing::blake2_256;
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+// This is the magic prefix of the files of the cache, changed when the layout of the files changes.
+const WASM_CACHE_MAGIC: &[u8; 8] = b"UOMIMOD1";
+
+// This is the size of the header of the files of the cache: the magic, the hash of the wasm and the hash of the compiled module.
+const WASM_CACHE_HEADER_SIZE: usize = WASM_CACHE_MAGIC.len() + 32 + 32;
+
+// This is the extension of the files of the cache.
+const WASM_CACHE_EXTENSION: &str = "cwasm";
+
+// The cache used by the executions of the node, set once by the node on startup.
+static WASM_MODULE_CACHE: OnceLock<WasmModuleCache> = OnceLock::new();
+
+// WasmModuleCache stores on disk the agents compiled by wasmtime, so the agents called many times are compiled only once.
+// The files are keyed by the hash of the wasm of the agent and the configuration of the engine, and every file stores the hash of the wasm
+// it has been compiled from and the hash of the compiled module, so a file corrupted or compiled from another wasm is never loaded.
+pub struct WasmModuleCache {
+    dir: PathBuf, // The directory where the compiled modules are stored.
+    max_size: u64, // The maximum total size in bytes of the compiled modules, the least recently used are evicted over it.
+    lock: Mutex<()>, // Serializes the writes and the evictions of the executions running in parallel.
+}
+
+impl WasmModuleCache {
+    // This function is used to create a cache on dir, creating the directory if it does not exist.
+    pub fn new(dir: PathBuf, max_size: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, max_size, lock: Mutex::new(()) })
+    }
+
+    // This function is used to get the compiled module of the agent with the CID, compiling and storing it if it is not cached yet.
+    // NOTE: The errors of the cache are only logged, the wasm is compiled as if the cache does not exist.
+    pub fn load_or_compile(&self, engine: &wasmtime::Engine, cid: &Cid, wasm: &[u8]) -> Result<wasmtime::Module, wasmtime::Error> {
+        let path = self.entry_path(engine, wasm);
+        let wasm_hash = blake2_256(wasm);
+        if let Some(module) = self.read_entry(engine, &path, &wasm_hash) {
+            log::debug!("UOMI-ENGINE: Compiled module of {:?} loaded from the cache", cid);
+            return Ok(module);
+        }
+
+        let module = wasmtime::Module::new(engine, wasm)?;
+        if let Err(error) = self.write_entry(&path, &wasm_hash, &module) {
+            log::warn!("UOMI-ENGINE: Error storing the compiled module of {:?} on the cache: {:?}", cid, error);
+        }
+        Ok(module)
+    }
+
+    // This function is used to get the path of the file of the wasm compiled by the engine.
+    // The name of the file is the blake2 hash of the wasm and of the compatibility hash of the engine, so it is stable across the builds of the node.
+    pub(crate) fn entry_path(&self, engine: &wasmtime::Engine, wasm: &[u8]) -> PathBuf {
+        let mut hasher = BytesHasher::default();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        let mut key = blake2_256(wasm).to_vec();
+        key.extend_from_slice(&hasher.0);
+        self.dir.join(format!("{}.{}", HexDisplay::from(&blake2_256(&key)), WASM_CACHE_EXTENSION))
+    }
+
+    // This function is used to read the module stored on path, it returns None and removes the file if it does not pass the integrity checks.
+    fn read_entry(&self, engine: &wasmtime::Engine, path: &Path, wasm_hash: &[u8; 32]) -> Option<wasmtime::Module> {
+        let data = fs::read(path).ok()?;
+        let valid = data.len() > WASM_CACHE_HEADER_SIZE &&
+            &data[..WASM_CACHE_MAGIC.len()] == WASM_CACHE_MAGIC &&
+            &data[WASM_CACHE_MAGIC.len()..WASM_CACHE_MAGIC.len() + 32] == wasm_hash &&
+            data[WASM_CACHE_MAGIC.len() + 32..WASM_CACHE_HEADER_SIZE] == blake2_256(&data[WASM_CACHE_HEADER_SIZE..]);
+        // SAFETY: The compiled module has been serialized by this node with an engine with the same configuration, as checked by the
+        // hash of the file name, and its content is checked against the hash stored with it. Wasmtime checks again the compatibility.
+        let module = valid.then(|| unsafe { wasmtime::Module::deserialize(engine, &data[WASM_CACHE_HEADER_SIZE..]) });
+        match module {
+            Some(Ok(module)) => {
+                // The modification time of the file is its last use, the least recently used files are evicted first
+                let _ = fs::File::options().write(true).open(path).and_then(|file| file.set_modified(SystemTime::now()));
+                Some(module)
+            },
+            Some(Err(error)) => {
+                log::warn!("UOMI-ENGINE: Error deserializing the compiled module {:?}: {:?}", path, error);
+                let _ = fs::remove_file(path);
+                None
+            },
+            None => {
+                log::warn!("UOMI-ENGINE: Compiled module {:?} not valid, it is compiled again", path);
+                let _ = fs::remove_file(path);
+                None
+            },
+        }
+    }
+
+    // This function is used to store the module on path, replacing the file atomically, and to evict the least recently used files over the maximum size.
+    fn write_entry(&self, path: &Path, wasm_hash: &[u8; 32], module: &wasmtime::Module) -> Result<(), wasmtime::Error> {
+        let serialized = module.serialize()?;
+        let mut data = Vec::with_capacity(WASM_CACHE_HEADER_SIZE + serialized.len());
+        data.extend_from_slice(WASM_CACHE_MAGIC);
+        data.extend_from_slice(wasm_hash);
+        data.extend_from_slice(&blake2_256(&serialized));
+        data.extend_from_slice(&serialized);
+
+        let _lock = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, &data)?;
+        fs::rename(&temp_path, path)?;
+        self.evict(path)?;
+
+        Ok(())
+    }
+
+    // This function is used to remove the least recently used files until the total size is under the maximum size, the file on keep is never removed.
+    fn evict(&self, keep: &Path) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total_size = 0u64;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some(WASM_CACHE_EXTENSION) {
+                continue;
+            }
+            let metadata = fs::metadata(&path)?;
+            total_size += metadata.len();
+            entries.push((metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len(), path));
+        }
+
+        entries.sort();
+        for (_, size, path) in entries {
+            if total_size <= self.max_size {
+                break;
+            }
+            if path == keep {
+                continue;
+            }
+            fs::remove_file(&path)?;
+            total_size -= size;
+            log::debug!("UOMI-ENGINE: Compiled module {:?} evicted from the cache", path);
+        }
+
+        Ok(())
+    }
+}
+
+// This function is used to set the cache used by the executions of the node, it returns false if the cache is already set.
+pub fn wasm_cache_init(cache: WasmModuleCache) -> bool {
+    WASM_MODULE_CACHE.set(cache).is_ok()
+}
+
+// This function is used to get the cache used by the executions of the node, None if the node does not use a cache.
+pub fn wasm_cache() -> Option<&'static WasmModuleCache> {
+    WASM_MODULE_CACHE.get()
+}
+
+// BytesHasher collects the bytes written by a Hash implementation, so they are hashed with blake2 instead of a hasher
+// of the standard library, whose output is not guaranteed to be the same across the versions of the compiler.
+#[derive(Default)]
+struct BytesHasher(Vec<u8>);
+
+impl Hasher for BytesHasher {
+    fn finish(&self) -> u64 {
+        let hash = blake2_256(&self.0);
+        u64::from_le_bytes([hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7]])
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
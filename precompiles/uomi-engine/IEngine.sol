@@ -21,6 +21,31 @@ interface IEngine {
     function call_agent(
         uint256 requestId, uint256 nftId, address sender, bytes calldata data, bytes calldata inputCid, uint256 minValidators, uint256 minBlocks
     ) external;
+    /**
+     * @notice Calls an agent and registers a callback executed when the request is completed.
+     * @dev The callback is called from this precompile with (uint256 requestId, bytes output, uint256 totalExecutions, uint256 totalConsensus).
+     *      The gas of the callback is prepaid by the sender, the fee of the gas not used is refunded on completion.
+     * @param requestId The unique identifier for the request.
+     * @param nftId The unique identifier for the NFT.
+     * @param sender The address of the sender initiating the call.
+     * @param data The calldata to be passed to the agent.
+     * @param inputCid The content identifier for the input data (0x if none).
+     * @param minValidators The minimum number of validators required.
+     * @param minBlocks The minimum number of blocks required for execution.
+     * @param callbackTarget The contract called on completion.
+     * @param callbackSelector The selector of the function called on completion, as uint32(bytes4).
+     * @param callbackGasLimit The gas limit of the callback.
+     */
+    function call_agent_with_callback(
+        uint256 requestId, uint256 nftId, address sender, bytes calldata data, bytes calldata inputCid, uint256 minValidators, uint256 minBlocks,
+        address callbackTarget, uint32 callbackSelector, uint64 callbackGasLimit
+    ) external;
+    /**
+     * @notice Cancels a request not assigned yet to the validators, the fees of the request are refunded.
+     * @dev Only the address that sent the request can cancel it.
+     * @param requestId The unique identifier for the request.
+     */
+    function cancel_agent(uint256 requestId) external;
     /**
      * @notice Retrieves the output associated with a given request ID.
      * @param requestId The unique identifier for the request.
@@ -30,4 +55,25 @@ interface IEngine {
      *         - A uint256 representing the second additional output value.
      */
     function get_output(uint256 requestId) external view returns (bytes memory, uint256, uint256);
+    /**
+     * @notice Retrieves the IPFS reference of an output too large to be stored on chain.
+     * @param requestId The unique identifier for the request.
+     * @return A tuple containing:
+     *         - A bytes array representing the CID of the output (0x if the output is stored on chain).
+     *         - A bytes32 representing the blake2_256 hash of the output.
+     *         - A uint256 representing the block number when the pin of the output expires.
+     */
+    function get_agent_output_cid(uint256 requestId) external view returns (bytes memory, bytes32, uint256);
+    /**
+     * @notice Retrieves the hash of the output of a completed request, available also after the output is pruned.
+     * @param requestId The unique identifier for the request.
+     * @return The keccak256 of the output data, the blake2_256 hash of the output if it is offloaded to IPFS, or zero if not found.
+     */
+    function get_agent_output_hash(uint256 requestId) external view returns (bytes32);
+    /**
+     * @notice Retrieves the address of an agent, used to execute the transactions emitted by the agent.
+     * @param nftId The unique identifier for the NFT of the agent.
+     * @return The address of the agent.
+     */
+    function get_agent_address(uint256 nftId) external view returns (address);
 }
\ No newline at end of file
@@ -2,11 +2,58 @@
 
 use fp_evm::{PrecompileHandle};
 use precompile_utils::prelude::*;
-use sp_runtime::DispatchResult;
-use frame_support::pallet_prelude::IsType;
+use sp_runtime::{DispatchError, DispatchResult};
+use frame_support::{pallet_prelude::IsType, weights::Weight};
+use pallet_evm::{GasWeightMapping, Runner};
 use sp_std::vec::Vec;
 use core::marker::PhantomData;
-use sp_core::{U256, H160};
+use sp_core::{U256, H160, H256};
+use pallet_uomi_engine::types::{AgentResult, OutputCid};
+
+/// The address of the uomi-engine precompile on every runtime.
+pub const UOMI_ENGINE_PRECOMPILE_ADDRESS: u64 = 1970236777;
+
+/// The executor of the callbacks of the requests, used by every runtime.
+/// The callbacks are executed from the address of the uomi-engine precompile, their gas is prepaid on the request.
+pub struct UomiEngineCallbackExecutor<R>(PhantomData<R>);
+
+impl<R> pallet_uomi_engine::callbacks::RequestCallbackExecutor<R> for UomiEngineCallbackExecutor<R>
+where
+    R: pallet_evm::Config + pallet_uomi_engine::Config,
+{
+    fn gas_to_weight(gas: u64) -> Weight {
+        <R as pallet_evm::Config>::GasWeightMapping::gas_to_weight(gas, true)
+    }
+
+    fn execute(target: H160, input: Vec<u8>, gas_limit: u64) -> (u64, DispatchResult) {
+        match <R as pallet_evm::Config>::Runner::call(
+            H160::from_low_u64_be(UOMI_ENGINE_PRECOMPILE_ADDRESS),
+            target,
+            input,
+            U256::zero(),
+            gas_limit,
+            None,
+            None,
+            None,
+            Vec::new(),
+            false,
+            false,
+            None,
+            None,
+            <R as pallet_evm::Config>::config(),
+        ) {
+            Ok(info) => {
+                let used_gas = info.used_gas.standard.low_u64();
+                if info.exit_reason.is_succeed() {
+                    (used_gas, Ok(()))
+                } else {
+                    (used_gas, Err(DispatchError::Other("Callback reverted")))
+                }
+            },
+            Err(err) => (gas_limit, Err(err.error.into())),
+        }
+    }
+}
 
 /// A precompile that exposes `call_agent` function.
 pub struct UomiEnginePrecompile<T>(PhantomData<T>);
@@ -29,18 +76,17 @@ where
         min_blocks: U256,
     ) -> EvmResult<bool> {
         // Get the caller   
-        let caller = handle.context().caller;
-        
-       
-        // Convert Address to H160 for internal use
-        let sender: H160 = caller.into();
+        let caller: H160 = handle.context().caller.into();
 
-        //check if sender is 0x609a8AEeef8b89BE02C5b59A936A520547252824
+        //check if caller is 0x609a8AEeef8b89BE02C5b59A936A520547252824
         let agent_address = H160::from_slice(&hex::decode("609a8AEeef8b89BE02C5b59A936A520547252824").expect("Invalid hex"));
 
-        if sender != agent_address {
+        if caller != agent_address {
             return Err(revert("Only the agent contract can call this function"));
         }
+
+        // The agent contract pays the fee of the request, the sender passed by it is the owner of the request
+        let sender: H160 = sender.into();
         
         
         //convert data to vec<u8>
@@ -50,6 +96,7 @@ where
         // Prepare the call to the pallet
         let dispatch_result: DispatchResult = pallet_uomi_engine::Pallet::<R>::run_request(
             request_id,
+            caller,
             sender,
             nft_id,
             data_vec,
@@ -68,6 +115,70 @@ where
         }
     }
 
+    #[precompile::public("call_agent_with_callback(uint256,uint256,address,bytes,bytes,uint256,uint256,address,uint32,uint64)")]
+    fn call_agent_with_callback(
+        handle: &mut impl PrecompileHandle,
+        request_id: U256,
+        nft_id: U256,
+        sender: Address,
+        data: UnboundedBytes,
+        data_cid: UnboundedBytes,
+        min_validators: U256,
+        min_blocks: U256,
+        callback_target: Address,
+        callback_selector: u32,
+        callback_gas_limit: u64,
+    ) -> EvmResult<bool> {
+        let caller: H160 = handle.context().caller.into();
+
+        let agent_address = H160::from_slice(&hex::decode("609a8AEeef8b89BE02C5b59A936A520547252824").expect("Invalid hex"));
+        if caller != agent_address {
+            return Err(revert("Only the agent contract can call this function"));
+        }
+
+        // The gas of the callback is prepaid by the agent contract together with the fee of the request, the sender is the owner of the request
+        let dispatch_result: DispatchResult = pallet_uomi_engine::Pallet::<R>::run_request_with_callback(
+            request_id,
+            caller,
+            sender.into(),
+            nft_id,
+            data.into(),
+            data_cid.into(),
+            min_validators,
+            min_blocks,
+            callback_target.into(),
+            callback_selector.to_be_bytes(),
+            callback_gas_limit,
+        );
+
+        match dispatch_result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                log::info!("Error executing call_agent_with_callback: {:?}", e);
+                Err(revert("Error executing call_agent_with_callback"))
+            }
+        }
+    }
+
+    #[precompile::public("cancel_agent(uint256)")]
+    fn cancel_agent(
+        handle: &mut impl PrecompileHandle,
+        request_id: U256,
+    ) -> EvmResult<bool> {
+        // Only the owner of the request (the sender passed to call_agent) can cancel it, before it is assigned to the validators
+        let sender: H160 = handle.context().caller.into();
+
+        let dispatch_result: DispatchResult = pallet_uomi_engine::Pallet::<R>::cancel_request_from_address(request_id, sender);
+
+        match dispatch_result {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                log::info!("Error executing cancel_agent: {:?}", e);
+                Err(revert("Error executing cancel_agent"))
+            }
+        }
+    }
+
     #[precompile::public("get_agent_output(uint256)")]
     #[precompile::view]
     fn get_agent_output(
@@ -75,13 +186,48 @@ where
         request_id: U256,
     ) -> EvmResult<(UnboundedBytes, U256, U256)> {
         // Read the value from the storage - it returns the value directly because of ValueQuery
-        let (data, total_executions, total_consensus) = pallet_uomi_engine::Outputs::<R>::get(request_id);
+        let AgentResult { output_data, total_executions, total_consensus, .. } = pallet_uomi_engine::Outputs::<R>::get(request_id);
         
-        let data_vec_u8: Vec<u8> = data.into_inner().to_vec();
+        let data_vec_u8: Vec<u8> = output_data.into_inner().to_vec();
         Ok((
             data_vec_u8.into(),
             U256::from(total_executions),
             U256::from(total_consensus)
         ))
     }
+
+    #[precompile::public("get_agent_output_cid(uint256)")]
+    #[precompile::view]
+    fn get_agent_output_cid(
+        _: &mut impl PrecompileHandle,
+        request_id: U256,
+    ) -> EvmResult<(UnboundedBytes, H256, U256)> {
+        // The output of the request is offloaded to IPFS when it is larger than the maximum output size
+        let AgentResult { output_cid, output_cid_expiration_block_number, .. } = pallet_uomi_engine::Outputs::<R>::get(request_id);
+
+        match output_cid {
+            Some(OutputCid { cid, hash }) => Ok((cid.into_inner().into(), hash, output_cid_expiration_block_number)),
+            None => Ok((Vec::<u8>::new().into(), H256::zero(), U256::zero())),
+        }
+    }
+
+    #[precompile::public("get_agent_output_hash(uint256)")]
+    #[precompile::view]
+    fn get_agent_output_hash(
+        _: &mut impl PrecompileHandle,
+        request_id: U256,
+    ) -> EvmResult<H256> {
+        // The hash is kept after the output is pruned, so the historical results can still be verified
+        Ok(pallet_uomi_engine::Pallet::<R>::retention_get_output_hash(&request_id).unwrap_or_default())
+    }
+
+    #[precompile::public("get_agent_address(uint256)")]
+    #[precompile::view]
+    fn get_agent_address(
+        _: &mut impl PrecompileHandle,
+        nft_id: U256,
+    ) -> EvmResult<Address> {
+        // The transactions emitted by the agent are executed from this address
+        Ok(pallet_uomi_engine::Pallet::<R>::transactions_agent_address(&nft_id).into())
+    }
 }
\ No newline at end of file
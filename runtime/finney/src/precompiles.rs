@@ -35,7 +35,7 @@ use pallet_evm_precompile_simple::{ECRecover, ECRecoverPublicKey, Identity, Ripe
 use pallet_evm_precompile_sr25519::Sr25519Precompile;
 use pallet_evm_precompile_substrate_ecdsa::SubstrateEcdsaPrecompile;
 use pallet_evm_precompile_unified_accounts::UnifiedAccountsPrecompile;
-use pallet_evm_precompile_uomi_engine::UomiEnginePrecompile;
+use pallet_evm_precompile_uomi_engine::{UomiEnginePrecompile, UOMI_ENGINE_PRECOMPILE_ADDRESS};
 use pallet_evm_precompile_ipfs::IpfsPrecompile;
 use precompile_utils::precompile_set::*;
 use sp_std::fmt::Debug;
@@ -137,7 +137,7 @@ pub type LocalPrecompilesSetAt<R> = (
         (),
     >,
     PrecompileAt<
-    AddressU64<1970236777>,
+    AddressU64<UOMI_ENGINE_PRECOMPILE_ADDRESS>,
     UomiEnginePrecompile<R>,
     (CallableByContract, CallableByPrecompile),
     >,
@@ -154,7 +154,7 @@ pub type LocalPrecompiles<R> = PrecompileSetBuilder<
         // Skip precompiles if out of range.
         PrecompilesInRangeInclusive<
             // We take range as last precompile index, UPDATE this once new precompile is added
-            (AddressU64<1>, AddressU64<1970236777>),
+            (AddressU64<1>, AddressU64<UOMI_ENGINE_PRECOMPILE_ADDRESS>),
             LocalPrecompilesSetAt<R>,
         >,
         // Prefixed precompile sets (XC20)
@@ -66,7 +66,9 @@ use pallet_grandpa::{fg_primitives, AuthorityList as GrandpaAuthorityList};
 use pallet_identity::legacy::IdentityInfo;
 use pallet_im_online::sr25519::AuthorityId as ImOnlineId;
 use pallet_transaction_payment::{CurrencyAdapter, Multiplier, TargetedFeeAdjustment};
-use parity_scale_codec::{Compact, Decode, Encode, MaxEncodedLen};
+use parity_scale_codec::{Compact, Decode, DecodeLimit, Encode, MaxEncodedLen};
+use frame_support::dispatch::GetDispatchInfo;
+use pallet_uomi_engine::types::AgentTransaction;
 use sp_api::impl_runtime_apis;
 use sp_core::{crypto::KeyTypeId, ConstBool, OpaqueMetadata, H160, H256, U256};
 use sp_runtime::{
@@ -1284,6 +1286,96 @@ impl pallet_collective::Config<MainCouncilCollectiveInst> for Runtime {
     type WeightInfo = pallet_collective::weights::SubstrateWeight<Runtime>;
 }
 
+parameter_types! {
+    pub const UomiEngineRequestBaseFee: Balance = 10 * MILLIUOMI;
+    pub const UomiEngineRequestFeePerByte: Balance = 10 * MICROUOMI;
+    pub const UomiEngineRequestFeePerValidator: Balance = 5 * MILLIUOMI;
+    pub const UomiEngineRequestFeePerBlock: Balance = 100 * MICROUOMI;
+    pub const UomiEngineInvalidOutputSlashFraction: Perbill = Perbill::from_percent(10);
+    pub const UomiEngineTimeoutSlashFraction: Perbill = Perbill::from_percent(1);
+    pub const UomiEngineOffenceReportDelay: u32 = 1 * DAYS;
+    pub const UomiEngineReputationDecayWindow: u32 = 7 * DAYS;
+    pub const UomiEngineModelActivationThreshold: Perbill = Perbill::from_percent(80);
+    pub const UomiEngineOutputsPinDuration: u32 = 7 * DAYS;
+    pub const UomiEngineAgentTransactionMaxGas: u64 = 1_000_000;
+    pub UomiEngineAgentCallMaxWeight: Weight = Weight::from_parts(WEIGHT_REF_TIME_PER_SECOND / 10, 1024 * 1024);
+    pub UomiEngineCallbackFeePerGas: Balance = DefaultBaseFeePerGas::get().low_u128();
+    pub const UomiEngineMaxCallbackGas: u64 = 1_000_000;
+    pub const UomiEngineRequestMaxLifetime: u32 = 1 * DAYS;
+    pub const UomiEngineOutputsRetentionPeriod: u32 = 30 * DAYS;
+    pub const UomiEngineArchiveOutputsHashes: bool = true;
+}
+
+pub struct UomiEngineAddressToAccountId;
+
+impl Convert<H160, AccountId> for UomiEngineAddressToAccountId {
+    fn convert(address: H160) -> AccountId {
+        <UnifiedAccounts as pallet_evm::AddressMapping<AccountId>>::into_account_id(address)
+    }
+}
+
+// The transactions emitted by the agents are executed from the agent address, the EVM calls pay the gas with the balance of the agent.
+pub struct UomiEngineAgentTransactionExecutor;
+
+impl pallet_uomi_engine::transactions::AgentTransactionExecutor<Runtime> for UomiEngineAgentTransactionExecutor {
+    fn max_weight(transaction: &AgentTransaction) -> Weight {
+        match transaction {
+            AgentTransaction::Evm { gas_limit, .. } => {
+                <Runtime as pallet_evm::Config>::GasWeightMapping::gas_to_weight((*gas_limit).min(UomiEngineAgentTransactionMaxGas::get()), true)
+            },
+            AgentTransaction::Substrate { .. } => UomiEngineAgentCallMaxWeight::get(),
+        }
+    }
+
+    fn execute(address: H160, transaction: &AgentTransaction) -> (Weight, DispatchResult) {
+        match transaction {
+            AgentTransaction::Evm { target, value, input, gas_limit } => {
+                let (base_fee, _) = <Runtime as pallet_evm::Config>::FeeCalculator::min_gas_price();
+                match <Runtime as pallet_evm::Config>::Runner::call(
+                    address,
+                    *target,
+                    input.to_vec(),
+                    *value,
+                    (*gas_limit).min(UomiEngineAgentTransactionMaxGas::get()),
+                    Some(base_fee),
+                    None,
+                    None,
+                    Vec::new(),
+                    true,
+                    true,
+                    None,
+                    None,
+                    <Runtime as pallet_evm::Config>::config(),
+                ) {
+                    Ok(info) => {
+                        let used_weight = <Runtime as pallet_evm::Config>::GasWeightMapping::gas_to_weight(info.used_gas.standard.low_u64(), true);
+                        if !info.exit_reason.is_succeed() {
+                            return (used_weight, Err(DispatchError::Other("EVM call of the agent failed")));
+                        }
+                        (used_weight, Ok(()))
+                    },
+                    Err(err) => (err.weight, Err(DispatchError::from(err.error))),
+                }
+            },
+            AgentTransaction::Substrate { call } => {
+                let call = match RuntimeCall::decode_all_with_depth_limit(sp_api::MAX_EXTRINSIC_DEPTH, &mut &call[..]) {
+                    Ok(call) => call,
+                    Err(_) => return (Weight::zero(), Err(DispatchError::Other("Invalid call of the agent"))),
+                };
+                let info = call.get_dispatch_info();
+                if !info.weight.all_lte(UomiEngineAgentCallMaxWeight::get()) {
+                    return (Weight::zero(), Err(DispatchError::Other("Call of the agent exceeds the maximum weight")));
+                }
+                let account_id = UomiEngineAddressToAccountId::convert(address);
+                match call.dispatch(RuntimeOrigin::signed(account_id)) {
+                    Ok(post_info) => (post_info.calc_actual_weight(&info), Ok(())),
+                    Err(err) => (err.post_info.calc_actual_weight(&info), Err(err.error)),
+                }
+            },
+        }
+    }
+}
+
 impl pallet_uomi_engine::Config for Runtime {
     type UomiAuthorityId = pallet_uomi_engine::crypto::AuthId;
     type RuntimeEvent = RuntimeEvent;
@@ -1291,6 +1383,29 @@ impl pallet_uomi_engine::Config for Runtime {
     type RandomnessOld = pallet_babe::RandomnessFromOneEpochAgo<Runtime>; // for finney update. remove on turing
     type Randomness = pallet_babe::ParentBlockRandomness<Runtime>;
     type InherentDataType = u16;
+    type Currency = Balances;
+    type AddressToAccountId = UomiEngineAddressToAccountId;
+    type GovernanceOrigin = EnsureRoot<AccountId>;
+    type RequestBaseFee = UomiEngineRequestBaseFee;
+    type RequestFeePerByte = UomiEngineRequestFeePerByte;
+    type RequestFeePerValidator = UomiEngineRequestFeePerValidator;
+    type RequestFeePerBlock = UomiEngineRequestFeePerBlock;
+    type ValidatorSet = Historical;
+    type ReportOffence = Offences;
+    type InvalidOutputSlashFraction = UomiEngineInvalidOutputSlashFraction;
+    type TimeoutSlashFraction = UomiEngineTimeoutSlashFraction;
+    type OffenceReportDelay = UomiEngineOffenceReportDelay;
+    type ReputationDecayWindow = UomiEngineReputationDecayWindow;
+    type ModelActivationThreshold = UomiEngineModelActivationThreshold;
+    type OutputsPinDuration = UomiEngineOutputsPinDuration;
+    type AgentTransactionExecutor = UomiEngineAgentTransactionExecutor;
+    type CallbackExecutor = pallet_evm_precompile_uomi_engine::UomiEngineCallbackExecutor<Runtime>;
+    type CallbackFeePerGas = UomiEngineCallbackFeePerGas;
+    type MaxCallbackGas = UomiEngineMaxCallbackGas;
+    type RequestMaxLifetime = UomiEngineRequestMaxLifetime;
+    type OutputsRetentionPeriod = UomiEngineOutputsRetentionPeriod;
+    type ArchiveOutputsHashes = UomiEngineArchiveOutputsHashes;
+    type WeightInfo = weights::pallet_uomi_engine::SubstrateWeight<Runtime>;
 }
 
 pub struct IpfsWrapper;
@@ -1317,6 +1432,14 @@ impl pallet_uomi_engine::ipfs::IpfsInterface<Runtime> for IpfsWrapper {
     ) -> DispatchResult {
         pallet_ipfs::Pallet::<Runtime>::pin_file(origin, cid, duration)
     }
+
+    fn add_file(data: Vec<u8>) -> Result<Cid, sp_runtime::offchain::http::Error> {
+        pallet_ipfs::Pallet::<Runtime>::add_file(data)
+    }
+
+    fn temporary_pin(cid: &Cid, duration: BlockNumber) -> ExpirationBlockNumber {
+        pallet_ipfs::Pallet::<Runtime>::temporary_pin(cid, duration)
+    }
 }
 
 impl pallet_tss::Config for Runtime {
@@ -1341,6 +1464,7 @@ impl pallet_ipfs::Config for Runtime {
     type BlockNumber = BlockNumber;
     // Nuovo campo per il costo del pinning temporaneo
     type TemporaryPinningCost = IpfsTemporaryPinningCost;
+    type WeightInfo = weights::pallet_ipfs::SubstrateWeight<Runtime>;
 }
 
 impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
@@ -1801,6 +1925,7 @@ mod benches {
         [pallet_balances, Balances]
         [pallet_timestamp, Timestamp]
         [pallet_uomi_engine, UomiEngine]
+        [pallet_ipfs, Ipfs]
     );
 }
 
@@ -2529,6 +2654,32 @@ impl_runtime_apis! {
         }
     }
 
+    impl pallet_uomi_engine::UomiEngineApi<Block, AccountId> for Runtime {
+        fn get_request(request_id: U256) -> Option<pallet_uomi_engine::types::RequestInfo<AccountId>> {
+            UomiEngine::api_get_request(request_id)
+        }
+
+        fn pending_requests() -> Vec<U256> {
+            UomiEngine::api_pending_requests()
+        }
+
+        fn request_assignments(request_id: U256) -> Vec<pallet_uomi_engine::types::RequestAssignment<AccountId>> {
+            UomiEngine::api_request_assignments(request_id)
+        }
+
+        fn agent_file_cid(nft_id: U256) -> Option<pallet_ipfs::types::Cid> {
+            UomiEngine::api_agent_file_cid(nft_id)
+        }
+
+        fn ai_model_local_name(ai_model_key: U256) -> Option<pallet_uomi_engine::types::Data> {
+            UomiEngine::api_ai_model_local_name(ai_model_key)
+        }
+
+        fn opoc_l0_inferences(request_id: U256) -> Vec<(u32, pallet_uomi_engine::types::Data)> {
+            UomiEngine::api_opoc_l0_inferences(request_id)
+        }
+    }
+
     impl pallet_tss::TssApi<Block> for Runtime {
         fn get_dkg_session_threshold(session_id: u64) -> u32 {
             if let Some(session) = pallet_tss::pallet::Pallet::<Runtime>::get_dkg_session(session_id) {
@@ -18,3 +18,5 @@
 
 pub mod pallet_assets;
 pub mod pallet_balances;
+pub mod pallet_ipfs;
+pub mod pallet_uomi_engine;
@@ -0,0 +1,315 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+//! Weights for pallet_uomi_engine
+//!
+//! ESTIMATED WEIGHTS: this file has not been generated by the benchmark CLI. The figures are estimated from the storage
+//! accesses of the benchmarks of pallets/uomi-engine/src/benchmarking.rs and are the same in all the runtimes, they are not measurements.
+//! Replace this file with the output of ./scripts/run-benchmarks.sh on the reference hardware before the release of the
+//! runtime, and every time a benchmarked call changes.
+
+// Command to generate this file:
+// ./target/release/uomi
+// benchmark
+// pallet
+// --chain=dev
+// --steps=50
+// --repeat=20
+// --pallet=pallet_uomi_engine
+// --extrinsic=*
+// --wasm-execution=compiled
+// --heap-pages=4096
+// --output=./runtime/local/src/weights/pallet_uomi_engine.rs
+// --template=./scripts/templates/weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weights for pallet_uomi_engine using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> pallet_uomi_engine::WeightInfo for SubstrateWeight<T> {
+	/// Storage: `UomiEngine::InherentDidUpdate` (r:1 w:1)
+	/// Proof: `UomiEngine::InherentDidUpdate` (`max_values`: Some(1), `max_size`: Some(1), added: 496, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesReputations` (r:100 w:100)
+	/// Proof: `UomiEngine::NodesReputations` (`max_values`: None, `max_size`: Some(84), added: 2559, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NextOffenceId` (r:1 w:1)
+	/// Proof: `UomiEngine::NextOffenceId` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::PendingOffences` (r:0 w:100)
+	/// Proof: `UomiEngine::PendingOffences` (`max_values`: None, `max_size`: Some(89), added: 2564, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::OpocAssignment` (r:1100 w:2000)
+	/// Proof: `UomiEngine::OpocAssignment` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesWorks` (r:0 w:2000)
+	/// Proof: `UomiEngine::NodesWorks` (`max_values`: None, `max_size`: Some(113), added: 2588, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputs` (r:2200 w:1000)
+	/// Proof: `UomiEngine::NodesOutputs` (`max_values`: None, `max_size`: Some(1048692), added: 1051167, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCids` (r:1100 w:0)
+	/// Proof: `UomiEngine::NodesOutputsCids` (`max_values`: None, `max_size`: Some(192), added: 2667, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsTransactions` (r:1100 w:0)
+	/// Proof: `UomiEngine::NodesOutputsTransactions` (`max_values`: None, `max_size`: Some(1048717), added: 1051192, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCommitments` (r:2200 w:2000)
+	/// Proof: `UomiEngine::NodesOutputsCommitments` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsOutOfFuel` (r:1100 w:0)
+	/// Proof: `UomiEngine::NodesOutputsOutOfFuel` (`max_values`: None, `max_size`: Some(113), added: 2588, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsModels` (r:3200 w:2000)
+	/// Proof: `UomiEngine::NodesOutputsModels` (`max_values`: None, `max_size`: Some(2145), added: 4620, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ModelsFees` (r:6400 w:0)
+	/// Proof: `UomiEngine::ModelsFees` (`max_values`: None, `max_size`: Some(64), added: 2539, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOpocL0Inferences` (r:200 w:0)
+	/// Proof: `UomiEngine::NodesOpocL0Inferences` (`max_values`: None, `max_size`: Some(1048696), added: 1051171, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::Outputs` (r:0 w:100)
+	/// Proof: `UomiEngine::Outputs` (`max_values`: None, `max_size`: Some(3145925), added: 3148400, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::FinishedRequests` (r:0 w:200)
+	/// Proof: `UomiEngine::FinishedRequests` (`max_values`: None, `max_size`: Some(96), added: 2571, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsFees` (r:200 w:200)
+	/// Proof: `UomiEngine::RequestsFees` (`max_values`: None, `max_size`: Some(117), added: 2592, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsModelsFees` (r:200 w:200)
+	/// Proof: `UomiEngine::RequestsModelsFees` (`max_values`: None, `max_size`: Some(64), added: 2539, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsCallbacks` (r:200 w:100)
+	/// Proof: `UomiEngine::RequestsCallbacks` (`max_values`: None, `max_size`: Some(121), added: 2596, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NextExecutionId` (r:200 w:200)
+	/// Proof: `UomiEngine::NextExecutionId` (`max_values`: Some(1), `max_size`: Some(8), added: 503, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::PendingExecutions` (r:0 w:200)
+	/// Proof: `UomiEngine::PendingExecutions` (`max_values`: None, `max_size`: Some(1048726), added: 1051201, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1200 w:1200)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RewardsCurrentEra` (r:100 w:0)
+	/// Proof: `UomiEngine::RewardsCurrentEra` (`max_values`: Some(1), `max_size`: Some(4), added: 499, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ErasRewardPoints` (r:1000 w:1000)
+	/// Proof: `UomiEngine::ErasRewardPoints` (`max_values`: None, `max_size`: Some(56), added: 2531, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ErasTotalRewardPoints` (r:100 w:100)
+	/// Proof: `UomiEngine::ErasTotalRewardPoints` (`max_values`: None, `max_size`: Some(24), added: 2499, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::Inputs` (r:0 w:200)
+	/// Proof: `UomiEngine::Inputs` (`max_values`: None, `max_size`: Some(1048887), added: 1051362, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsAddresses` (r:0 w:200)
+	/// Proof: `UomiEngine::RequestsAddresses` (`max_values`: None, `max_size`: Some(68), added: 2543, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsStatuses` (r:0 w:100)
+	/// Proof: `UomiEngine::RequestsStatuses` (`max_values`: None, `max_size`: Some(50), added: 2525, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::AIModels` (r:0 w:100)
+	/// Proof: `UomiEngine::AIModels` (`max_values`: None, `max_size`: Some(2097244), added: 2099719, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ModelsPendingActivations` (r:0 w:100)
+	/// Proof: `UomiEngine::ModelsPendingActivations` (`max_values`: None, `max_size`: Some(80), added: 2555, mode: `MaxEncodedLen`)
+	/// The range of component `p` is `[0, 100]`.
+	/// The range of component `a` is `[0, 1000]`.
+	/// The range of component `w` is `[0, 1000]`.
+	/// The range of component `o` is `[0, 100]`.
+	/// The range of component `f` is `[0, 100]`.
+	/// The range of component `m` is `[0, 100]`.
+	fn set_inherent_data(p: u32, a: u32, w: u32, o: u32, f: u32, m: u32, ) -> Weight {
+		Weight::from_parts(10_284_000, 3493)
+			.saturating_add(Weight::from_parts(14_219_000, 0).saturating_mul(p.into()))
+			.saturating_add(Weight::from_parts(4_873_000, 0).saturating_mul(a.into()))
+			.saturating_add(Weight::from_parts(3_106_000, 0).saturating_mul(w.into()))
+			.saturating_add(Weight::from_parts(197_318_000, 0).saturating_mul(o.into()))
+			.saturating_add(Weight::from_parts(64_041_000, 0).saturating_mul(f.into()))
+			.saturating_add(Weight::from_parts(9_870_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(p.into())))
+			.saturating_add(T::DbWeight::get().reads((179_u64).saturating_mul(o.into())))
+			.saturating_add(T::DbWeight::get().reads((54_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(p.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(a.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(w.into())))
+			.saturating_add(T::DbWeight::get().writes((70_u64).saturating_mul(o.into())))
+			.saturating_add(T::DbWeight::get().writes((48_u64).saturating_mul(f.into())))
+			.saturating_add(T::DbWeight::get().writes((2_u64).saturating_mul(m.into())))
+			.saturating_add(Weight::from_parts(0, 2559).saturating_mul(p.into()))
+			.saturating_add(Weight::from_parts(0, 10692776).saturating_mul(o.into()))
+			.saturating_add(Weight::from_parts(0, 77878).saturating_mul(f.into()))
+	}
+	/// Storage: `Session::Validators` (r:1 w:0)
+	/// Proof: `Session::Validators` (`max_values`: Some(1), `max_size`: None, mode: `Measured`)
+	/// Storage: `UomiEngine::NodesOutputs` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesOutputs` (`max_values`: None, `max_size`: Some(1048692), added: 1051167, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCommitments` (r:2 w:0)
+	/// Proof: `UomiEngine::NodesOutputsCommitments` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::Inputs` (r:1 w:0)
+	/// Proof: `UomiEngine::Inputs` (`max_values`: None, `max_size`: Some(1048887), added: 1051362, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::OpocAssignment` (r:2 w:0)
+	/// Proof: `UomiEngine::OpocAssignment` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsTransactions` (r:0 w:1)
+	/// Proof: `UomiEngine::NodesOutputsTransactions` (`max_values`: None, `max_size`: Some(1048717), added: 1051192, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsModels` (r:0 w:1)
+	/// Proof: `UomiEngine::NodesOutputsModels` (`max_values`: None, `max_size`: Some(2145), added: 4620, mode: `MaxEncodedLen`)
+	/// The range of component `s` is `[0, 1048576]`.
+	fn store_nodes_outputs(s: u32, ) -> Weight {
+		Weight::from_parts(56_912_000, 2106547)
+			.saturating_add(Weight::from_parts(3_204, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(7_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: `Session::Validators` (r:1 w:0)
+	/// Proof: `Session::Validators` (`max_values`: Some(1), `max_size`: None, mode: `Measured`)
+	/// Storage: `UomiEngine::OpocAssignment` (r:1 w:0)
+	/// Proof: `UomiEngine::OpocAssignment` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCommitments` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesOutputsCommitments` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	fn commit_nodes_outputs() -> Weight {
+		Weight::from_parts(24_352_000, 3641)
+			.saturating_add(T::DbWeight::get().reads(3_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Session::Validators` (r:1 w:0)
+	/// Proof: `Session::Validators` (`max_values`: Some(1), `max_size`: None, mode: `Measured`)
+	/// Storage: `UomiEngine::NodesVersions` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesVersions` (`max_values`: None, `max_size`: Some(52), added: 2527, mode: `MaxEncodedLen`)
+	fn store_nodes_versions() -> Weight {
+		Weight::from_parts(19_761_000, 3517)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Session::Validators` (r:1 w:0)
+	/// Proof: `Session::Validators` (`max_values`: Some(1), `max_size`: None, mode: `Measured`)
+	/// Storage: `UomiEngine::NodesOpocL0Inferences` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesOpocL0Inferences` (`max_values`: None, `max_size`: Some(1048696), added: 1051171, mode: `MaxEncodedLen`)
+	/// The range of component `s` is `[0, 1048576]`.
+	fn store_nodes_opoc_l0_inferences(s: u32, ) -> Weight {
+		Weight::from_parts(25_806_000, 1052161)
+			.saturating_add(Weight::from_parts(1_587, 0).saturating_mul(s.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Session::Validators` (r:1 w:0)
+	/// Proof: `Session::Validators` (`max_values`: Some(1), `max_size`: None, mode: `Measured`)
+	/// Storage: `UomiEngine::NodesModels` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesModels` (`max_values`: None, `max_size`: Some(2097), added: 4572, mode: `MaxEncodedLen`)
+	/// The range of component `m` is `[1, 64]`.
+	fn store_nodes_models(m: u32, ) -> Weight {
+		Weight::from_parts(21_108_000, 5562)
+			.saturating_add(Weight::from_parts(196_000, 0).saturating_mul(m.into()))
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	fn temporary_cleanup_inputs() -> Weight {
+		Weight::from_parts(3_187_000, 0)
+	}
+	fn temporary_function() -> Weight {
+		Weight::from_parts(2_960_000, 0)
+	}
+	/// Storage: `UomiEngine::ModelsFees` (r:101 w:1)
+	/// Proof: `UomiEngine::ModelsFees` (`max_values`: None, `max_size`: Some(64), added: 2539, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ModelsMaxFee` (r:1 w:1)
+	/// Proof: `UomiEngine::ModelsMaxFee` (`max_values`: Some(1), `max_size`: Some(16), added: 511, mode: `MaxEncodedLen`)
+	fn set_model_fee() -> Weight {
+		Weight::from_parts(64_914_000, 256950)
+			.saturating_add(T::DbWeight::get().reads(102_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: `UomiEngine::RewardsInflationPerEra` (r:0 w:1)
+	/// Proof: `UomiEngine::RewardsInflationPerEra` (`max_values`: Some(1), `max_size`: Some(16), added: 511, mode: `MaxEncodedLen`)
+	fn set_rewards_inflation() -> Weight {
+		Weight::from_parts(8_960_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `UomiEngine::ClaimableRewards` (r:1 w:1)
+	/// Proof: `UomiEngine::ClaimableRewards` (`max_values`: None, `max_size`: Some(64), added: 2539, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	fn claim_rewards() -> Weight {
+		Weight::from_parts(38_412_000, 3593)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: `UomiEngine::PendingOffences` (r:1 w:1)
+	/// Proof: `UomiEngine::PendingOffences` (`max_values`: None, `max_size`: Some(89), added: 2564, mode: `MaxEncodedLen`)
+	fn cancel_offence() -> Weight {
+		Weight::from_parts(14_873_000, 3554)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `UomiEngine::NodesReputations` (r:1 w:1)
+	/// Proof: `UomiEngine::NodesReputations` (`max_values`: None, `max_size`: Some(84), added: 2559, mode: `MaxEncodedLen`)
+	fn reset_reputation() -> Weight {
+		Weight::from_parts(14_512_000, 3549)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `UomiEngine::ModelsRegistry` (r:1 w:1)
+	/// Proof: `UomiEngine::ModelsRegistry` (`max_values`: None, `max_size`: Some(1048670), added: 1051145, mode: `MaxEncodedLen`)
+	fn register_model() -> Weight {
+		Weight::from_parts(17_226_000, 1052135)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `UomiEngine::ModelsRegistry` (r:1 w:1)
+	/// Proof: `UomiEngine::ModelsRegistry` (`max_values`: None, `max_size`: Some(1048670), added: 1051145, mode: `MaxEncodedLen`)
+	fn deprecate_model() -> Weight {
+		Weight::from_parts(16_841_000, 1052135)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `UomiEngine::ModelsRegistry` (r:1 w:1)
+	/// Proof: `UomiEngine::ModelsRegistry` (`max_values`: None, `max_size`: Some(1048670), added: 1051145, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::AIModels` (r:1 w:0)
+	/// Proof: `UomiEngine::AIModels` (`max_values`: None, `max_size`: Some(2097244), added: 2099719, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::ModelsActivatedNames` (r:1 w:0)
+	/// Proof: `UomiEngine::ModelsActivatedNames` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesModels` (r:1001 w:1000)
+	/// Proof: `UomiEngine::NodesModels` (`max_values`: None, `max_size`: Some(2097), added: 4572, mode: `MaxEncodedLen`)
+	/// The range of component `n` is `[0, 1000]`.
+	fn replace_model(n: u32, ) -> Weight {
+		Weight::from_parts(28_403_000, 3154457)
+			.saturating_add(Weight::from_parts(11_874_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().reads(4_u64))
+			.saturating_add(T::DbWeight::get().reads((2_u64).saturating_mul(n.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(n.into())))
+			.saturating_add(Weight::from_parts(0, 4572).saturating_mul(n.into()))
+	}
+	/// Storage: `UomiEngine::RequestsAddresses` (r:1 w:1)
+	/// Proof: `UomiEngine::RequestsAddresses` (`max_values`: None, `max_size`: Some(68), added: 2543, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::Inputs` (r:1 w:1)
+	/// Proof: `UomiEngine::Inputs` (`max_values`: None, `max_size`: Some(1048887), added: 1051362, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::OpocAssignment` (r:1 w:0)
+	/// Proof: `UomiEngine::OpocAssignment` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsModelsFees` (r:0 w:1)
+	/// Proof: `UomiEngine::RequestsModelsFees` (`max_values`: None, `max_size`: Some(64), added: 2539, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsFees` (r:1 w:1)
+	/// Proof: `UomiEngine::RequestsFees` (`max_values`: None, `max_size`: Some(117), added: 2592, mode: `MaxEncodedLen`)
+	/// Storage: `System::Account` (r:1 w:1)
+	/// Proof: `System::Account` (`max_values`: None, `max_size`: Some(128), added: 2603, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsCallbacks` (r:1 w:1)
+	/// Proof: `UomiEngine::RequestsCallbacks` (`max_values`: None, `max_size`: Some(121), added: 2596, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputs` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputs` (`max_values`: None, `max_size`: Some(1048692), added: 1051167, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCids` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputsCids` (`max_values`: None, `max_size`: Some(192), added: 2667, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsTransactions` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputsTransactions` (`max_values`: None, `max_size`: Some(1048717), added: 1051192, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsOutOfFuel` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputsOutOfFuel` (`max_values`: None, `max_size`: Some(113), added: 2588, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsModels` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputsModels` (`max_values`: None, `max_size`: Some(2145), added: 4620, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOutputsCommitments` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOutputsCommitments` (`max_values`: None, `max_size`: Some(144), added: 2619, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::NodesOpocL0Inferences` (r:1 w:0)
+	/// Proof: `UomiEngine::NodesOpocL0Inferences` (`max_values`: None, `max_size`: Some(1048696), added: 1051171, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::RequestsStatuses` (r:0 w:1)
+	/// Proof: `UomiEngine::RequestsStatuses` (`max_values`: None, `max_size`: Some(50), added: 2525, mode: `MaxEncodedLen`)
+	/// Storage: `UomiEngine::FinishedRequests` (r:0 w:1)
+	/// Proof: `UomiEngine::FinishedRequests` (`max_values`: None, `max_size`: Some(96), added: 2571, mode: `MaxEncodedLen`)
+	fn cancel_request() -> Weight {
+		Weight::from_parts(71_328_000, 4231329)
+			.saturating_add(T::DbWeight::get().reads(13_u64))
+			.saturating_add(T::DbWeight::get().writes(8_u64))
+	}
+}
@@ -0,0 +1,101 @@
+// This file is part of Uomi.
+
+// Copyright (C) Uomi.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Uomi is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Uomi is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Uomi. If not, see <http://www.gnu.org/licenses/>.
+
+//! Weights for pallet_ipfs
+//!
+//! ESTIMATED WEIGHTS: this file has not been generated by the benchmark CLI. The figures are estimated from the storage
+//! accesses of the benchmarks of pallets/ipfs/src/benchmarking.rs and are the same in all the runtimes, they are not measurements.
+//! Replace this file with the output of ./scripts/run-benchmarks.sh on the reference hardware before the release of the
+//! runtime, and every time a benchmarked call changes.
+
+// Command to generate this file:
+// ./target/release/uomi
+// benchmark
+// pallet
+// --chain=uomi
+// --steps=50
+// --repeat=20
+// --pallet=pallet_ipfs
+// --extrinsic=*
+// --wasm-execution=compiled
+// --heap-pages=4096
+// --output=./runtime/uomi/src/weights/pallet_ipfs.rs
+// --template=./scripts/templates/weight-template.hbs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use core::marker::PhantomData;
+
+/// Weights for pallet_ipfs using the Substrate node and recommended hardware.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> pallet_ipfs::WeightInfo for SubstrateWeight<T> {
+	/// Storage: `Ipfs::AgentsPins` (r:1 w:1)
+	/// Proof: `Ipfs::AgentsPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:2)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_agent() -> Weight {
+		Weight::from_parts(28_105_000, 3606)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(3_u64))
+	}
+	/// Storage: `Ipfs::CidsStatus` (r:1 w:1)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	fn pin_file() -> Weight {
+		Weight::from_parts(18_290_000, 3606)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: `Staking::Validators` (r:1 w:0)
+	/// Proof: `Staking::Validators` (`max_values`: None, `max_size`: Some(45), added: 2520, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:0 w:200)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `s` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn submit_processed_pins(s: u32, r: u32, ) -> Weight {
+		Weight::from_parts(14_871_000, 3510)
+			.saturating_add(Weight::from_parts(6_482_000, 0).saturating_mul(s.into()))
+			.saturating_add(Weight::from_parts(6_117_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(s.into())))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(r.into())))
+	}
+	/// Storage: `Ipfs::InherentDidUpdate` (r:1 w:1)
+	/// Proof: `Ipfs::InherentDidUpdate` (`max_values`: Some(1), `max_size`: Some(1), added: 496, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::CidsStatus` (r:100 w:200)
+	/// Proof: `Ipfs::CidsStatus` (`max_values`: None, `max_size`: Some(141), added: 2616, mode: `MaxEncodedLen`)
+	/// Storage: `Ipfs::NodesPins` (r:1000 w:1000)
+	/// Proof: `Ipfs::NodesPins` (`max_values`: None, `max_size`: Some(125), added: 2600, mode: `MaxEncodedLen`)
+	/// The range of component `u` is `[0, 100]`.
+	/// The range of component `r` is `[0, 100]`.
+	fn set_inherent_data(u: u32, r: u32, ) -> Weight {
+		Weight::from_parts(8_412_000, 1486)
+			.saturating_add(Weight::from_parts(8_906_000, 0).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(41_735_000, 0).saturating_mul(r.into()))
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().reads((1_u64).saturating_mul(u.into())))
+			.saturating_add(T::DbWeight::get().reads((10_u64).saturating_mul(r.into())))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(u.into())))
+			.saturating_add(T::DbWeight::get().writes((11_u64).saturating_mul(r.into())))
+			.saturating_add(Weight::from_parts(0, 2616).saturating_mul(u.into()))
+			.saturating_add(Weight::from_parts(0, 26000).saturating_mul(r.into()))
+	}
+}
@@ -0,0 +1,44 @@
+#!/bin/sh
+# Regenerate the weights of the pallets and of the runtimes with the benchmark CLI.
+# Run it on the reference hardware from the root of the repository, e.g. ./scripts/run-benchmarks.sh pallet_uomi_engine pallet_ipfs
+# Run it with --check to list the weights files still estimated and not generated by the benchmark CLI, it fails when there are some.
+set -e
+
+if [ "$1" = "--check" ]; then
+    estimated=$(grep -l "ESTIMATED WEIGHTS" ./pallets/*/src/weights.rs ./runtime/*/src/weights/*.rs || true)
+    if [ -n "$estimated" ]; then
+        echo "The following weights are estimated, regenerate them with ./scripts/run-benchmarks.sh on the reference hardware:"
+        echo "$estimated"
+        exit 1
+    fi
+    exit 0
+fi
+
+PALLETS=${*:-"pallet_uomi_engine pallet_ipfs"}
+BINARY=./target/release/uomi
+
+cargo build --release --features runtime-benchmarks
+
+benchmark() {
+    $BINARY benchmark pallet \
+        --chain="$1" \
+        --steps=50 \
+        --repeat=20 \
+        --pallet="$2" \
+        --extrinsic='*' \
+        --wasm-execution=compiled \
+        --heap-pages=4096 \
+        --output="$3" \
+        --template="$4"
+}
+
+for pallet in $PALLETS; do
+    # The weights of the pallet, used by the tests and by the runtimes without benchmarks
+    pallet_dir=./pallets/$(echo "$pallet" | sed 's/^pallet_//; s/_/-/g')
+    benchmark dev "$pallet" "$pallet_dir/src/weights.rs" ./scripts/templates/pallet-weight-template.hbs
+
+    # The weights of every runtime, benchmarked on the chain of the runtime
+    benchmark dev "$pallet" "./runtime/local/src/weights/$pallet.rs" ./scripts/templates/weight-template.hbs
+    benchmark finney "$pallet" "./runtime/finney/src/weights/$pallet.rs" ./scripts/templates/weight-template.hbs
+    benchmark uomi "$pallet" "./runtime/uomi/src/weights/$pallet.rs" ./scripts/templates/weight-template.hbs
+done